@@ -0,0 +1,330 @@
+use std::{collections::HashMap, env, fs, path::Path, rc::Rc, sync::OnceLock};
+use color_eyre::eyre;
+use crate::{
+    lints::{Diagnostic, Lint},
+    manifest::LintLevel,
+    parser::ast::*,
+    symbols::Span
+};
+
+
+/// The name of the cache file within a project's output directory.
+pub const CACHE_FILE: &str = ".nadra-cache";
+
+
+///
+/// What was known about a module the last time it was built.
+///
+/// # Members
+/// - `source_hash` - The hash of the module's source code.
+/// - `interface_hash` - The hash of the module's interface. See [`interface_hash`].
+/// - `build_key` - The hash of everything the generated Python code depends on, i.e. the
+/// module's own source code and the interfaces of its dependencies.
+/// - `lint_key` - The hash of everything the lints of the module depend on, i.e. its build
+/// key and the level of each lint.
+/// - `diagnostics` - The problems found when the module was last linted.
+/// - `dependencies` - The names of the local modules imported by the module.
+///
+#[derive(Debug, Clone)]
+pub struct CacheEntry {
+    pub source_hash: u64,
+    pub interface_hash: u64,
+    pub build_key: u64,
+    pub lint_key: u64,
+    pub diagnostics: Vec<Diagnostic>,
+    pub dependencies: Vec<String>
+}
+
+
+///
+/// # Overview
+/// A content-hash based record of the previous build of a project, stored in the output
+/// directory. A module only needs to be rebuilt if its build key has changed.
+///
+/// The whole cache is discarded if it was written by another build of the compiler,
+/// or with different build options, since either could change the generated code.
+///
+/// # Members
+/// - `fingerprint` - Identifies the compiler build and build options.
+/// - `entries` - What is known about each module, by module name.
+///
+pub struct BuildCache {
+    fingerprint: String,
+    entries: HashMap<String, CacheEntry>
+}
+
+
+impl BuildCache {
+    ///
+    /// Create an empty cache for builds with the given `options_fingerprint`.
+    ///
+    pub fn empty(options_fingerprint: &str) -> Self {
+        Self {
+            fingerprint: format!("{} {:x} {}", env!("CARGO_PKG_VERSION"), compiler_hash(), options_fingerprint),
+            entries: HashMap::new()
+        }
+    }
+
+
+    ///
+    /// Load the cache stored in `out_dir`. If there is no usable cache, e.g. it was
+    /// written by a different build of the compiler, an empty cache is returned instead.
+    ///
+    pub fn load(out_dir: &Path, options_fingerprint: &str) -> Self {
+        let mut cache = Self::empty(options_fingerprint);
+
+        let Ok(contents) = fs::read_to_string(out_dir.join(CACHE_FILE)) else {
+            return cache;
+        };
+
+        let mut lines = contents.lines();
+        if lines.next() != Some(cache.fingerprint.as_str()) {
+            return cache;
+        }
+
+        let mut last = None;
+        for line in lines {
+            // The problems found in a module follow the line of the module.
+            if let Some(line) = line.strip_prefix('\t') {
+                let entry = last.as_ref().and_then(|name| cache.entries.get_mut(name));
+                match (Self::parse_diagnostic(line), entry) {
+                    (Some(diagnostic), Some(entry)) => entry.diagnostics.push(diagnostic),
+                    _ => return Self::empty(options_fingerprint)
+                }
+                continue;
+            }
+
+            // A malformed cache is treated as no cache at all.
+            let Some((name, entry)) = Self::parse_entry(line) else {
+                return Self::empty(options_fingerprint);
+            };
+            cache.entries.insert(name.clone(), entry);
+            last = Some(name);
+        }
+
+        cache
+    }
+
+
+    ///
+    /// Parse a line of the cache file describing a module, which has the form:
+    /// ```
+    /// <module> <source hash> <interface hash> <build key> <lint key> [<dependency>,]*
+    /// ```
+    ///
+    fn parse_entry(line: &str) -> Option<(String, CacheEntry)> {
+        let mut fields = line.split(' ');
+
+        let name = fields.next()?.to_string();
+        let source_hash = u64::from_str_radix(fields.next()?, 16).ok()?;
+        let interface_hash = u64::from_str_radix(fields.next()?, 16).ok()?;
+        let build_key = u64::from_str_radix(fields.next()?, 16).ok()?;
+        let lint_key = u64::from_str_radix(fields.next()?, 16).ok()?;
+
+        let dependencies = fields
+            .next()?
+            .split(',')
+            .filter(|dependency| !dependency.is_empty())
+            .map(String::from)
+            .collect();
+
+        let entry = CacheEntry {
+            source_hash: source_hash,
+            interface_hash: interface_hash,
+            build_key: build_key,
+            lint_key: lint_key,
+            diagnostics: vec![],
+            dependencies: dependencies
+        };
+
+        Some((name, entry))
+    }
+
+
+    ///
+    /// Parse a line of the cache file describing a problem found in the module before it,
+    /// which has the form:
+    /// ```
+    /// \t<level> <line> <column> <length> <lint or -> <message>
+    /// ```
+    ///
+    fn parse_diagnostic(line: &str) -> Option<Diagnostic> {
+        let mut fields = line.splitn(6, ' ');
+
+        let level = match fields.next()? {
+            "warning" => LintLevel::Warn,
+            "error" => LintLevel::Error,
+            _ => return None
+        };
+
+        let span = Span {
+            line: fields.next()?.parse().ok()?,
+            column: fields.next()?.parse().ok()?,
+            length: fields.next()?.parse().ok()?
+        };
+
+        let lint = match fields.next()? {
+            "-" => None,
+            name => Some(Lint::from_name(name)?)
+        };
+
+        Some(Diagnostic {
+            lint: lint,
+            level: level,
+            message: fields.next()?.to_string(),
+            span: span
+        })
+    }
+
+
+    pub fn get(&self, module: &str) -> Option<&CacheEntry> {
+        self.entries.get(module)
+    }
+
+
+    pub fn insert(&mut self, module: &str, entry: CacheEntry) {
+        self.entries.insert(module.to_string(), entry);
+    }
+
+
+    ///
+    /// Write the cache to `out_dir`.
+    ///
+    pub fn save(&self, out_dir: &Path) -> eyre::Result<()> {
+        let mut names: Vec<&String> = self.entries.keys().collect();
+        names.sort();
+
+        let mut contents = format!("{}\n", self.fingerprint);
+        for name in names {
+            let entry = &self.entries[name];
+            contents.push_str(&format!(
+                "{} {:016x} {:016x} {:016x} {:016x} {}\n",
+                name,
+                entry.source_hash,
+                entry.interface_hash,
+                entry.build_key,
+                entry.lint_key,
+                entry.dependencies.join(",")
+            ));
+
+            for diagnostic in &entry.diagnostics {
+                contents.push_str(&format!(
+                    "\t{} {} {} {} {} {}\n",
+                    diagnostic.level,
+                    diagnostic.span.line,
+                    diagnostic.span.column,
+                    diagnostic.span.length,
+                    diagnostic.lint.map_or("-", |lint| lint.name()),
+                    diagnostic.message
+                ));
+            }
+        }
+
+        fs::create_dir_all(out_dir)?;
+        fs::write(out_dir.join(CACHE_FILE), contents)?;
+        Ok(())
+    }
+}
+
+
+///
+/// Hash the executable of the running compiler. Unlike its version, this changes whenever
+/// the compiler is rebuilt with different code, which may generate different Python code.
+/// If the executable cannot be read, the cache is only tied to the version.
+///
+fn compiler_hash() -> u64 {
+    static HASH: OnceLock<u64> = OnceLock::new();
+
+    *HASH.get_or_init(|| {
+        env::current_exe()
+            .and_then(fs::read)
+            .map(|executable| hash_bytes(&executable))
+            .unwrap_or(0)
+    })
+}
+
+
+///
+/// Hash `bytes` with 64-bit FNV-1a. Unlike `std`'s hashers, the result is stable between
+/// builds of the compiler, so it can be stored on disk.
+///
+pub fn hash_bytes(bytes: &[u8]) -> u64 {
+    const OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+    const PRIME: u64 = 0x100000001b3;
+
+    bytes.iter().fold(OFFSET_BASIS, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(PRIME)
+    })
+}
+
+
+///
+/// Hash the interface of a module, i.e. everything other modules can see: the signatures
+/// of its functions, the fields and methods of its structs, interfaces and enums, the names
+/// it assigns to along with the declared types of its variables and constants, and what it
+/// imports. Changing the body of a function does not change the interface of the module.
+///
+pub fn interface_hash(ast: &[Rc<AstNode>]) -> u64 {
+    let mut interface = String::new();
+
+    for statement in ast {
+        match statement.as_ref() {
+            AstNode::FunctionDefinition(function) => interface.push_str(&function_signature(function)),
+
+            AstNode::StructDefinition(definition) => {
+                interface.push_str(&format!(
+                    "struct {} {:?} {:?} {:?}\n",
+                    definition.name,
+                    definition.type_params,
+                    definition.interfaces,
+                    definition.fields
+                ));
+                interface.push_str(&method_signatures(&definition.methods));
+            }
+
+            AstNode::InterfaceDefinition(definition) => {
+                interface.push_str(&format!("interface {}\n", definition.name));
+                interface.push_str(&method_signatures(&definition.methods));
+            }
+
+            AstNode::EnumDefinition(definition) => interface.push_str(&format!("{}\n", definition)),
+
+            AstNode::BinaryOperation(operation) if matches!(operation.operator, BinaryOperator::Equal) => {
+                interface.push_str(&format!("let {}\n", operation.left_child));
+            }
+
+            AstNode::Declaration(declaration) => {
+                let keyword = if declaration.constant { "const" } else { "let" };
+                interface.push_str(&format!("{} {} {:?}\n", keyword, declaration.name, declaration.annotation));
+            }
+
+            AstNode::UseStatement(statement) => interface.push_str(&format!("{}\n", statement)),
+
+            _ => {}
+        }
+    }
+
+    hash_bytes(interface.as_bytes())
+}
+
+
+fn function_signature(function: &FunctionDefinition) -> String {
+    format!(
+        "def {} {:?} {:?} {}\n",
+        function.name,
+        function.type_params,
+        function.param_list,
+        function.return_type
+    )
+}
+
+
+fn method_signatures(methods: &[Rc<AstNode>]) -> String {
+    methods
+        .iter()
+        .filter_map(|method| match method.as_ref() {
+            AstNode::FunctionDefinition(function) => Some(function_signature(function)),
+            _ => None
+        })
+        .collect()
+}
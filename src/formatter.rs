@@ -0,0 +1,360 @@
+use std::{fs, path::{Path, PathBuf}, rc::Rc};
+use color_eyre::eyre::{self, eyre};
+use crate::{
+    lexer::{self, BlockTracker, PieToken, TokenType, TriviaKind},
+    manifest::FormatSettings,
+    parser::{self, ast::AstNode}
+};
+
+
+///
+/// # Overview
+/// Formats Nadra source code. The formatter is opinionated, so the only settings are
+/// those in the `[format]` table of the manifest. Line breaks are kept where they were
+/// written, but everything else about the layout of the code is normalised:
+/// - Each block (`def ... enddef`, `if ... endif`, etc.) is indented by one level, and
+/// lines continuing an open bracket are indented by one more level.
+/// - Binary operators are surrounded by single spaces, while unary operators, brackets,
+/// `.`, `::` and `..` are not. Commas and colons are followed by a space, except for the
+/// colons of slices. The bounds of type parameters are spaced like annotations, e.g. `[T: int]`.
+/// - As in PEP 8, `=` has no spaces within arguments and unannotated parameters.
+/// - Runs of blank lines are limited to `max_blank_lines`, and the file ends with a
+/// single newline.
+/// - Comments are kept, with trailing comments separated from the code by two spaces.
+///
+/// # Members
+/// - `settings` - The `[format]` settings of the project.
+/// - `output` - The formatted code so far.
+/// - `blocks` - The blocks that are open after the last token written.
+/// - `brackets` - The brackets that are currently open, innermost last, along with whether
+/// a `:` has been seen since the start of the current element, and whether they hold the
+/// type parameters of a definition.
+/// - `previous` - The previous token of the current statement, if any.
+/// - `previous_unary` - Whether `previous` is a unary operator.
+/// - `after_definition_name` - Whether `previous` is the name of a function or struct.
+/// - `line_start` - Whether nothing has been written on the current line yet.
+/// - `last_line` - The source line on which the last token or comment written ends.
+///
+struct Formatter<'a> {
+    settings: &'a FormatSettings,
+    output: String,
+    blocks: BlockTracker,
+    brackets: Vec<(TokenType, bool, bool)>,
+    previous: Option<Rc<PieToken>>,
+    previous_unary: bool,
+    after_definition_name: bool,
+    line_start: bool,
+    last_line: Option<u64>
+}
+
+
+impl<'a> Formatter<'a> {
+    fn new(settings: &'a FormatSettings) -> Self {
+        Self {
+            settings: settings,
+            output: String::new(),
+            blocks: BlockTracker::default(),
+            brackets: vec![],
+            previous: None,
+            previous_unary: false,
+            after_definition_name: false,
+            line_start: true,
+            last_line: None
+        }
+    }
+
+
+    fn format(mut self, tokens: &[Rc<PieToken>]) -> String {
+        for token in tokens {
+            // Comments are the only trivia that is kept.
+            for trivia in &token.leading_trivia {
+                if trivia.kind == TriviaKind::Comment {
+                    self.move_to_line(trivia.line, None);
+                    self.write_comment(&trivia.text);
+                    self.last_line = Some(trivia.line);
+                }
+            }
+
+            if token.type_ == TokenType::Eof {
+                break;
+            }
+
+            self.move_to_line(token.line, Some(&token.type_));
+            self.write_token(token);
+            self.last_line = Some(token.line + token.lexeme.matches('\n').count() as u64);
+        }
+
+        if !self.output.is_empty() {
+            self.output.push('\n');
+        }
+        self.output
+    }
+
+
+    ///
+    /// Start a new line if `line` comes after the last line written, where `first` is the
+    /// type of the token that will begin the line, or `None` for a comment.
+    ///
+    fn move_to_line(&mut self, line: u64, first: Option<&TokenType>) {
+        match self.last_line {
+            Some(last_line) if line <= last_line => {}
+            Some(last_line) => {
+                let blank_lines = (line - last_line - 1) as usize;
+                self.start_line(first, blank_lines.min(self.settings.max_blank_lines));
+            }
+            None => self.start_line(first, 0)
+        }
+    }
+
+
+    fn start_line(&mut self, first: Option<&TokenType>, blank_lines: usize) {
+        if !self.output.is_empty() {
+            self.output.push('\n');
+            for _ in 0..blank_lines {
+                self.output.push('\n');
+            }
+        }
+
+        // A line that closes blocks is outside of them, as is the `else` of an `if`.
+        let mut level = self.blocks.depth();
+        match first {
+            Some(TokenType::Else) => level = level.saturating_sub(1),
+            Some(first) => level -= self.blocks.closes(first),
+            None => {}
+        }
+        if !self.brackets.is_empty() && !first.is_some_and(is_closing_bracket) {
+            level += 1;
+        }
+
+        self.output.push_str(&" ".repeat(level * self.settings.indent_width));
+        self.line_start = true;
+
+        // Outside of brackets, a new line is a new statement.
+        if self.brackets.is_empty() {
+            self.previous = None;
+            self.previous_unary = false;
+        }
+    }
+
+
+    fn write_token(&mut self, token: &Rc<PieToken>) {
+        if let Some(previous) = &self.previous
+            && !self.line_start
+            && self.space_between(previous, token) {
+            self.output.push(' ');
+        }
+
+        self.output.push_str(&token.lexeme);
+        self.line_start = false;
+        self.update_state(token);
+    }
+
+
+    fn write_comment(&mut self, comment: &str) {
+        if !self.line_start {
+            self.output.push_str("  ");
+        }
+
+        self.output.push_str(comment.trim_end());
+        self.line_start = false;
+    }
+
+
+    fn update_state(&mut self, token: &Rc<PieToken>) {
+        let is_operand = self.previous.as_ref().is_some_and(|previous| ends_operand(&previous.type_));
+        self.previous_unary = !is_operand && matches!(token.type_,
+            TokenType::Minus
+            | TokenType::Bang
+            | TokenType::Asterisk
+            | TokenType::AsteriskAsterisk
+        );
+
+        match token.type_ {
+            TokenType::LeftParen | TokenType::LeftBracket | TokenType::LeftBrace => {
+                // E.g. `def first[T]` or `struct Box[T]`.
+                let type_params = token.type_ == TokenType::LeftBracket && self.after_definition_name;
+                self.brackets.push((token.type_.clone(), false, type_params));
+            }
+
+            TokenType::RightParen | TokenType::RightBracket | TokenType::RightBrace => {
+                self.brackets.pop();
+            }
+
+            TokenType::Colon => {
+                if let Some((_, seen_colon, _)) = self.brackets.last_mut() {
+                    *seen_colon = true;
+                }
+            }
+
+            TokenType::Comma => {
+                if let Some((_, seen_colon, _)) = self.brackets.last_mut() {
+                    *seen_colon = false;
+                }
+            }
+
+            _ => {}
+        }
+
+        self.after_definition_name = token.type_ == TokenType::Identifier
+            && self.previous.as_ref().is_some_and(|previous| matches!(previous.type_, TokenType::Def | TokenType::Struct));
+
+        self.blocks.advance(&token.type_);
+        self.previous = Some(token.clone());
+    }
+
+
+    ///
+    /// Whether a space is written between two tokens on the same line.
+    ///
+    fn space_between(&self, previous: &PieToken, next: &PieToken) -> bool {
+        let innermost = self.brackets.last();
+        let in_brackets = |type_: TokenType| innermost.is_some_and(|(bracket, ..)| *bracket == type_);
+        let in_type_params = innermost.is_some_and(|(_, _, type_params)| *type_params);
+        let after_keyword = is_keyword(&previous.type_);
+
+        match (&previous.type_, &next.type_) {
+            _ if self.previous_unary => false,
+
+            (_, TokenType::Comma | TokenType::Colon) => false,
+
+            (_, TokenType::RightParen | TokenType::RightBracket | TokenType::RightBrace) => false,
+
+            (TokenType::LeftParen | TokenType::LeftBracket | TokenType::LeftBrace, _) => false,
+
+            (TokenType::Colon, _) => !in_brackets(TokenType::LeftBracket) || in_type_params,
+
+            (TokenType::ScopeOperator, _) | (_, TokenType::ScopeOperator) => false,
+
+            (TokenType::Dot | TokenType::DotDot, _) => false,
+
+            (_, TokenType::Dot | TokenType::DotDot) => after_keyword,
+
+            // Calls and subscripts
+            (_, TokenType::LeftParen | TokenType::LeftBracket) => !ends_operand(&previous.type_),
+
+            // Keyword arguments and unannotated parameters, e.g. `f(x=1)`
+            (TokenType::Equal, _) | (_, TokenType::Equal) => {
+                let in_arguments = innermost.is_some_and(|(bracket, seen_colon, _)| {
+                    *bracket == TokenType::LeftParen && !seen_colon
+                });
+                !in_arguments
+            }
+
+            _ => true
+        }
+    }
+}
+
+
+fn is_closing_bracket(type_: &TokenType) -> bool {
+    matches!(type_, TokenType::RightParen | TokenType::RightBracket | TokenType::RightBrace)
+}
+
+
+///
+/// Whether a token can end an operand, in which case a following `-` or `*` is a binary
+/// operator rather than a unary one, and a following `(` or `[` is a call or subscript.
+///
+fn ends_operand(type_: &TokenType) -> bool {
+    matches!(type_,
+        TokenType::Identifier
+        | TokenType::Integer
+        | TokenType::Float
+        | TokenType::String
+        | TokenType::FormatString
+        | TokenType::True
+        | TokenType::False
+        | TokenType::RightParen
+        | TokenType::RightBracket
+        | TokenType::RightBrace
+    )
+}
+
+
+fn is_keyword(type_: &TokenType) -> bool {
+    type_.opens_block() || type_.closes_block() || matches!(type_,
+        TokenType::Then
+        | TokenType::Else
+        | TokenType::Do
+        | TokenType::Return
+        | TokenType::Break
+        | TokenType::Continue
+        | TokenType::Use
+        | TokenType::As
+        | TokenType::Implements
+        | TokenType::Let
+        | TokenType::Const
+        | TokenType::TypeAlias
+        | TokenType::Match
+        | TokenType::With
+        | TokenType::Case
+        | TokenType::EndMatch
+    )
+}
+
+
+///
+/// Format `source`. Only code that parses can be formatted.
+///
+/// # Returns
+/// - The formatted code, or the error that prevented `source` from being parsed.
+///
+pub fn format_source(source: &str, settings: &FormatSettings) -> eyre::Result<String> {
+    let mut tokens = lexer::scan_all_tokens(source)?;
+    let tree = parser::parse(&mut tokens)?;
+
+    // Comments are read from the tree, so it must hold every byte of the source.
+    if tree.text() != source {
+        return Err(eyre!("The syntax tree does not match the source code, this is a bug in the parser"));
+    }
+
+    let formatted = Formatter::new(settings).format(&tree.root.tokens());
+
+    // Formatting must never change what the code means.
+    let original = ast_to_string(&tree.ast());
+    let reparsed = lexer::scan_all_tokens(&formatted)
+        .and_then(|mut tokens| parser::generate_ast(&mut tokens));
+
+    match reparsed {
+        Ok(ast) if ast_to_string(&ast) == original => Ok(formatted),
+        _ => Err(eyre!("Formatting would change the meaning of the code, this is a bug in the formatter"))
+    }
+}
+
+
+fn ast_to_string(ast: &[Rc<AstNode>]) -> String {
+    ast.iter().map(|statement| format!("{}\n", statement)).collect()
+}
+
+
+
+///
+/// Find every `.ndr` file within `directory`, skipping hidden directories.
+///
+pub fn find_sources(directory: &Path) -> eyre::Result<Vec<PathBuf>> {
+    let mut sources = vec![];
+    let mut directories = vec![directory.to_path_buf()];
+
+    while let Some(directory) = directories.pop() {
+        for entry in fs::read_dir(&directory)? {
+            let path = entry?.path();
+
+            let is_hidden = path
+                .file_name()
+                .is_some_and(|name| name.to_string_lossy().starts_with('.'));
+
+            if is_hidden {
+                continue;
+            }
+
+            if path.is_dir() {
+                directories.push(path);
+            } else if path.extension().is_some_and(|extension| extension == "ndr") {
+                sources.push(path);
+            }
+        }
+    }
+
+    sources.sort();
+    Ok(sources)
+}
@@ -15,17 +15,25 @@ pub enum TokenType {
     Or, Less, LessEqual, Greater, GreaterEqual,
 
     Dot, DotDot, Comma, LeftParen, RightParen, LeftBracket, RightBracket,
-    LeftBrace, RightBrace, Underscore, Colon, ScopeOperator, RightArrow,
+    LeftBrace, RightBrace, Colon, ScopeOperator, RightArrow,
+
+    // Not produced by the lexer yet.
+    #[allow(dead_code)]
+    Underscore,
 
     TypeAlias,
 
     Float, Integer, True, False,
-    String, FormatString,
+    String,
+    // Not produced by the lexer yet.
+    #[allow(dead_code)]
+    FormatString,
     Identifier,
 
-    Enum, EndEnum, Struct, EndStruct, Def, EndDef,
+    Enum, EndEnum, Struct, EndStruct, Interface, EndInterface, Implements, Def, EndDef,
     If, Then, EndIf, Else, For, Do, Done, While, Return,
-    Break, Continue, Use,
+    Break, Continue, Use, As, Let, Const,
+    Match, With, Case, EndMatch,
 
     Eof
 }
@@ -47,8 +55,7 @@ impl TokenType {
             | TokenType::LessEqual
             | TokenType::BangEqual
             | TokenType::Less
-            | TokenType::Greater
-            | TokenType::Equal => 1,
+            | TokenType::Greater => 1,
 
             // P3 - Addition, Subtraction, Conjunction, Disjunction
             TokenType::Plus
@@ -72,6 +79,131 @@ impl TokenType {
             _ => -1
         }
     }
+
+
+    ///
+    /// Whether the token assigns to its left-hand side, i.e. `=` or one of the
+    /// augmented assignment operators such as `+=`.
+    /// 
+    pub fn is_assignment_operator(&self) -> bool {
+        matches!(self,
+            TokenType::Equal
+            | TokenType::PlusEqual
+            | TokenType::MinusEqual
+            | TokenType::AsteriskEqual
+            | TokenType::SlashEqual
+            | TokenType::ModulusEqual
+        )
+    }
+
+
+    ///
+    /// Whether the keyword starts a block, e.g. `def` or `if`. Each case of a `match` is a
+    /// block within it. See [`BlockTracker`] for when these do not start a block.
+    /// 
+    pub fn opens_block(&self) -> bool {
+        matches!(self,
+            TokenType::Def
+            | TokenType::If
+            | TokenType::While
+            | TokenType::For
+            | TokenType::Struct
+            | TokenType::Interface
+            | TokenType::Enum
+            | TokenType::Match
+            | TokenType::Case
+        )
+    }
+
+
+    ///
+    /// Whether the keyword ends a block, e.g. `enddef` or `done`.
+    /// 
+    pub fn closes_block(&self) -> bool {
+        matches!(self,
+            TokenType::EndDef
+            | TokenType::EndIf
+            | TokenType::Done
+            | TokenType::EndStruct
+            | TokenType::EndInterface
+            | TokenType::EndEnum
+            | TokenType::EndMatch
+        )
+    }
+}
+
+
+///
+/// # Overview
+/// Follows the blocks opened and closed by a sequence of tokens, for the tools which
+/// work with code that may not parse, such as the formatter and the REPL. Every keyword
+/// that starts a block opens one, except:
+/// - `def` within an interface, as the methods of an interface have no body.
+/// - `if` within the header of a case, where it starts the case's guard.
+///
+/// A case ends where the next case starts, or with the end of its match.
+/// 
+/// # Members
+/// - `open` - The keyword that started each block that is still open, innermost last.
+/// - `in_case_header` - Whether the tokens so far end between a `case` and its `then`.
+/// 
+#[derive(Debug, Default)]
+pub struct BlockTracker {
+    open: Vec<TokenType>,
+    in_case_header: bool
+}
+
+
+impl BlockTracker {
+    ///
+    /// The number of blocks that are open.
+    /// 
+    pub fn depth(&self) -> usize {
+        self.open.len()
+    }
+
+
+    ///
+    /// The number of blocks that a token of type `type_` would close if it came next.
+    /// Keywords that close a block which is not open are left for the parser to report.
+    /// 
+    pub fn closes(&self, type_: &TokenType) -> usize {
+        let ends_case = matches!(type_, TokenType::Case | TokenType::EndMatch)
+            && self.open.last() == Some(&TokenType::Case);
+
+        let closes = ends_case as usize + type_.closes_block() as usize;
+        closes.min(self.open.len())
+    }
+
+
+    ///
+    /// Follow a token of type `type_`.
+    /// 
+    /// # Returns
+    /// - The number of blocks the token closes, and whether it opens a block.
+    /// 
+    pub fn advance(&mut self, type_: &TokenType) -> (usize, bool) {
+        let closes = self.closes(type_);
+        self.open.truncate(self.open.len() - closes);
+
+        let opens = match type_ {
+            TokenType::Def => self.open.last() != Some(&TokenType::Interface),
+            TokenType::If => !self.in_case_header,
+            type_ => type_.opens_block()
+        };
+
+        match type_ {
+            TokenType::Case => self.in_case_header = true,
+            TokenType::Then => self.in_case_header = false,
+            _ => {}
+        }
+
+        if opens {
+            self.open.push(type_.clone());
+        }
+
+        (closes, opens)
+    }
 }
 
 
@@ -82,7 +214,11 @@ impl TokenType {
 #[derive(Debug, Clone)]
 pub enum PieValue {
     FloatLiteral(f64), IntegerLiteral(i64),
-    StringLiteral(String), FormatStringLiteral(String)
+    StringLiteral(String),
+
+    // Placeholder until format strings are lexed.
+    #[allow(dead_code)]
+    FormatStringLiteral(String)
 }
 
 
@@ -121,11 +257,19 @@ impl fmt::Display for PieValue {
 /// - `type_: TokenType`            - The type of the token.
 /// - `lexeme: String`              - The actual text representation of the token in the code.
 /// - `value: Option<PieValue>`     - The actual in-memory representation of the token, if applicable.
+/// - `line: u64`                   - The (zero-based) line on which the token begins.
+/// - `column: u32`                 - The (zero-based) column at which the token begins.
+/// - `leading_trivia: Vec<Trivia>` - The whitespace and comments between the previous token
+///                                   and this one. Trailing trivia at the end of the source
+///                                   belongs to the `Eof` token.
 #[derive(Debug)]
 pub struct PieToken {
     pub type_: TokenType,
     pub lexeme: String,
-    pub value: Option<PieValue>
+    pub value: Option<PieValue>,
+    pub line: u64,
+    pub column: u32,
+    pub leading_trivia: Vec<Trivia>
 }
 
 
@@ -144,9 +288,63 @@ impl PieToken {
         PieToken { 
             type_: type_, 
             lexeme: lexeme.to_string(), 
-            value: value
+            value: value,
+            line: 0,
+            column: 0,
+            leading_trivia: vec![]
         }
     }
+
+
+    ///
+    /// Record where in the source code this token begins.
+    /// 
+    pub fn at(mut self, line: u64, column: u32) -> Self {
+        self.line = line;
+        self.column = column;
+        self
+    }
+
+
+    ///
+    /// The source code of this token, including its leading trivia.
+    /// 
+    pub fn full_text(&self) -> String {
+        let mut text: String = self.leading_trivia
+            .iter()
+            .map(|trivia| trivia.text.as_str())
+            .collect();
+        text.push_str(&self.lexeme);
+        text
+    }
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TriviaKind {
+    Whitespace,
+    Newline,
+    Comment
+}
+
+
+///
+/// # Overview
+/// Source code that has no meaning to the parser, i.e. whitespace and comments. It is
+/// kept so that tools such as the formatter can reproduce the original source code.
+/// 
+/// # Members
+/// - `kind` - What kind of trivia this is. Consecutive spaces and tabs are a single
+///   `Whitespace`, while each line break is its own `Newline`.
+/// - `text` - The trivia's source code. For comments, this includes the leading `#`.
+/// - `line`, `column` - Where the trivia begins.
+/// 
+#[derive(Debug, Clone)]
+pub struct Trivia {
+    pub kind: TriviaKind,
+    pub text: String,
+    pub line: u64,
+    pub column: u32
 }
 
 
@@ -217,6 +415,14 @@ impl PieTokenStream {
     pub fn peek(&self) -> Option<Rc<PieToken>> {
         self.tokens.first().cloned()
     }
+
+
+    ///
+    /// The tokens remaining in the stream, without consuming them.
+    /// 
+    pub fn tokens(&self) -> &[Rc<PieToken>] {
+        &self.tokens
+    }
 }
 
 
@@ -239,6 +445,8 @@ impl PieTokenStream {
 /// - `left: u128`       - the left pointer, inclusive.
 /// - `right: u128`      - the right pointer, exclusive.
 /// 
+/// Both pointers are byte offsets, which always lie on a character boundary.
+/// 
 struct Cursor <'a> {
     source: &'a str,
     left: u128,
@@ -263,23 +471,24 @@ impl <'a> Cursor <'a> {
     }
 
 
-    pub fn advance_left(&mut self) {
-        self.left += 1
-    }
-
-
     pub fn advance_left_to_right(&mut self) {
         self.left = self.right
     }
 
 
     pub fn advance_right(&mut self) {
-        self.right += 1
+        let width = self.current().map_or(0, char::len_utf8);
+        self.right += width as u128
     }
 
 
     pub fn current(&self) -> Option<char> {
-        self.source.chars().nth(self.right as usize)
+        self.source[self.right as usize..].chars().next()
+    }
+
+
+    pub fn lookahead(&self) -> Option<char> {
+        self.source[self.right as usize..].chars().nth(1)
     }
 
 
@@ -299,7 +508,7 @@ impl <'a> Cursor <'a> {
 ///
 /// Used to lookup keywords based on lexemes.
 /// 
-struct KeywordLookup {
+pub struct KeywordLookup {
     keyword_tokens: HashMap<String, TokenType>
 }
 
@@ -312,6 +521,9 @@ impl KeywordLookup {
         map.insert(String::from("endenum"), TokenType::EndEnum);
         map.insert(String::from("struct"), TokenType::Struct);
         map.insert(String::from("endstruct"), TokenType::EndStruct);
+        map.insert(String::from("interface"), TokenType::Interface);
+        map.insert(String::from("endinterface"), TokenType::EndInterface);
+        map.insert(String::from("implements"), TokenType::Implements);
         map.insert(String::from("if"), TokenType::If);
         map.insert(String::from("then"), TokenType::Then);
         map.insert(String::from("endif"), TokenType::EndIf);
@@ -329,9 +541,26 @@ impl KeywordLookup {
         map.insert(String::from("continue"), TokenType::Continue);
         map.insert(String::from("type"), TokenType::TypeAlias);
         map.insert(String::from("use"), TokenType::Use);
+        map.insert(String::from("as"), TokenType::As);
+        map.insert(String::from("let"), TokenType::Let);
+        map.insert(String::from("const"), TokenType::Const);
+        map.insert(String::from("match"), TokenType::Match);
+        map.insert(String::from("with"), TokenType::With);
+        map.insert(String::from("case"), TokenType::Case);
+        map.insert(String::from("endmatch"), TokenType::EndMatch);
 
         Self { keyword_tokens: map }
     }
+
+
+    ///
+    /// Every keyword, in alphabetical order.
+    ///
+    pub fn keywords(&self) -> Vec<&str> {
+        let mut keywords: Vec<&str> = self.keyword_tokens.keys().map(String::as_str).collect();
+        keywords.sort();
+        keywords
+    }
 }
 
 
@@ -349,14 +578,21 @@ impl KeywordLookup {
 /// 
 /// - `column` - The current column on the current line.
 /// 
+/// - `token_line`, `token_column` - Where the token currently being scanned begins.
+/// 
 /// - `token_list: Vec<Rc<PieToken>>` - The list of all tokens that have been scanned 
-///                                     thus far.
+///   thus far.
+/// 
+/// - `trivia` - The trivia scanned since the last token, which will lead the next token.
 /// 
 struct Lexer <'a> {
     cursor: Cursor<'a>,
     line: u64,
     column: u32,
-    token_list: Vec<Rc<PieToken>>
+    token_line: u64,
+    token_column: u32,
+    token_list: Vec<Rc<PieToken>>,
+    trivia: Vec<Trivia>
 }
 
 
@@ -366,7 +602,10 @@ impl <'a> Lexer <'a> {
             cursor: Cursor::new(source), 
             line: 0, 
             column: 0,
-            token_list: vec![]
+            token_line: 0,
+            token_column: 0,
+            token_list: vec![],
+            trivia: vec![]
         }
     }
 
@@ -381,6 +620,12 @@ impl <'a> Lexer <'a> {
     }
 
 
+    /// Get the character after the next one without advancing.
+    fn peek_second(&self) -> Option<char> {
+        self.cursor.lookahead()
+    }
+
+
     fn match_peek(&self, to_match: char) -> bool {
         match self.peek() {
             Some(ch) => to_match == ch,
@@ -399,23 +644,49 @@ impl <'a> Lexer <'a> {
 
     fn add_token(&mut self, type_: TokenType, value: Option<PieValue>) {
         let lexeme = self.cursor.capture();
-        let token = PieToken::new(type_, &lexeme, value);
+        let mut token = PieToken::new(type_, &lexeme, value)
+            .at(self.token_line, self.token_column);
+        token.leading_trivia = std::mem::take(&mut self.trivia);
         self.token_list.push(Rc::new(token));
     }
 
 
+    fn add_trivia(&mut self, kind: TriviaKind) {
+        let text = self.cursor.capture();
+
+        // Merge runs of whitespace into one piece of trivia.
+        if let Some(last) = self.trivia.last_mut()
+            && last.kind == TriviaKind::Whitespace
+            && kind == TriviaKind::Whitespace {
+            last.text.push_str(&text);
+            return;
+        }
+
+        self.trivia.push(Trivia {
+            kind: kind,
+            text: text,
+            line: self.token_line,
+            column: self.token_column
+        });
+    }
+
+
     fn scan_token(&mut self) -> eyre::Result<()> {
+        self.token_line = self.line;
+        self.token_column = self.column;
+
         let ch = self.next();
         if ch.is_none() {
             return Err(eyre!("Reached the end of the input file."))
         }
 
         match ch.unwrap() {
-            ' ' | '\r' | '\t' => {}
+            ' ' | '\r' | '\t' => self.add_trivia(TriviaKind::Whitespace),
 
             '\n' => {
+                self.add_trivia(TriviaKind::Newline);
                 self.line += 1;
-                self.column = 1;
+                self.column = 0;
             },
 
             // Digraph operators
@@ -541,11 +812,11 @@ impl <'a> Lexer <'a> {
 
             '_' => self.scan_keyword(),
 
-            '#' => self.skip_comment(),
+            '#' => self.scan_comment(),
 
             alpha if alpha.is_alphabetic() => self.scan_keyword(),
 
-            digit if digit.is_digit(10) => self.scan_numeric(),
+            digit if digit.is_ascii_digit() => self.scan_numeric()?,
 
             '\"' => self.scan_string()?,
 
@@ -556,57 +827,70 @@ impl <'a> Lexer <'a> {
     }
 
 
-    fn skip_comment(&mut self) {
+    ///
+    /// Attach the position of the token being scanned to `error`.
+    /// 
+    fn locate(&self, error: eyre::Report) -> eyre::Report {
+        let length = self.cursor.capture().chars().count() as u32;
+        SyntaxError::wrap(error, self.token_line, self.token_column, length)
+    }
+
+
+    fn scan_comment(&mut self) {
+        // Stop before the newline so that it is still counted as a new line.
         while self.has_next() && self.peek().unwrap() != '\n' {
             self.next();
         }
 
-        if self.has_next() {
-            self.next();
-        }
+        self.add_trivia(TriviaKind::Comment);
     }
 
     fn scan_string(&mut self) -> eyre::Result<()> {
         while self.has_next() && self.peek().unwrap() != '\"' {
-            self.next();
+            if self.next() == Some('\n') {
+                self.line += 1;
+                self.column = 0;
+            }
         }
 
         if !self.has_next() {
-            return Err(eyre!("Unterminated string at: {}:{}", self.line, self.column));
+            return Err(UnterminatedString { line: self.token_line, column: self.token_column }.into());
         }
 
         self.next(); // Skip terminating quote
         let lexeme = self.cursor.capture();
         let value = PieValue::StringLiteral(lexeme[1..lexeme.len() - 1].to_string());
-
-        let token = PieToken::new(TokenType::String, &lexeme, Some(value));
-        self.token_list.push(Rc::new(token));
+        self.add_token(TokenType::String, Some(value));
 
         Ok(())
     }
 
 
-    fn scan_numeric(&mut self) {
-        while self.has_next() && self.peek().unwrap().is_digit(10) {
+    fn scan_numeric(&mut self) -> eyre::Result<()> {
+        while self.has_next() && self.peek().unwrap().is_ascii_digit() {
             self.next();
         }
 
-        if let Some('.') = self.peek() {
+        // A second '.' means this is a range such as `1..3`, not the float `1.`
+        let is_range = self.peek_second() == Some('.');
+
+        if self.match_peek('.') && !is_range {
             self.next();
-            while self.has_next() && self.peek().unwrap().is_digit(10) {
+            while self.has_next() && self.peek().unwrap().is_ascii_digit() {
                 self.next();
             }
             let lexeme = self.cursor.capture();
             let float_value = PieValue::FloatLiteral(lexeme.parse().unwrap());
-            let token = PieToken::new(TokenType::Float, &lexeme, Some(float_value));
-            self.token_list.push(Rc::new(token));
-            return;
+            self.add_token(TokenType::Float, Some(float_value));
+            return Ok(());
         }
 
         let lexeme = self.cursor.capture();
-        let int_value = PieValue::IntegerLiteral(lexeme.parse().unwrap());
-        let token = PieToken::new(TokenType::Integer, &lexeme, Some(int_value));
-        self.token_list.push(Rc::new(token));
+        let integer = lexeme.parse().map_err(|_| {
+            eyre!("Integer literal is too large: {} at {}:{}", lexeme, self.token_line, self.token_column)
+        })?;
+        self.add_token(TokenType::Integer, Some(PieValue::IntegerLiteral(integer)));
+        Ok(())
     }
 
 
@@ -629,19 +913,94 @@ impl <'a> Lexer <'a> {
 
     pub fn tokenize(&mut self) -> eyre::Result<()> {
         while self.peek().is_some() {
-            self.scan_token()?;
+            self.scan_token().map_err(|e| self.locate(e))?;
             self.cursor.advance_left_to_right();
         }
 
-        self.token_list.push(Rc::new(PieToken::new(TokenType::Eof, "", None)));
+        let mut eof = PieToken::new(TokenType::Eof, "", None).at(self.line, self.column);
+        eof.leading_trivia = std::mem::take(&mut self.trivia);
+        self.token_list.push(Rc::new(eof));
         Ok(())
     }
 }
 
 
+///
+/// The error returned when the input ends inside of a string. This is distinguished from
+/// other errors since more input, e.g. the next line in the REPL, could complete the string.
+/// The position is that of the opening quote.
+/// 
+#[derive(Debug)]
+pub struct UnterminatedString {
+    pub line: u64,
+    pub column: u32
+}
+
+
+impl fmt::Display for UnterminatedString {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Unterminated string at: {}:{}", self.line, self.column)
+    }
+}
+
+
+impl std::error::Error for UnterminatedString {}
+
+
+///
+/// # Overview
+/// An error in the syntax of the source code, along with where it was found. Lexer and
+/// parser errors are wrapped in a `SyntaxError` so that tools such as the language server
+/// can point at them. An [`UnterminatedString`] is the only error left unwrapped.
+/// 
+/// # Members
+/// - `message` - What is wrong.
+/// - `line`, `column` - Where the offending token begins, both counted from `0`.
+/// - `length` - The number of characters of the offending token, which is `0` at the end
+/// of the source code.
+/// 
+#[derive(Debug)]
+pub struct SyntaxError {
+    pub message: String,
+    pub line: u64,
+    pub column: u32,
+    pub length: u32
+}
+
+
+impl SyntaxError {
+    ///
+    /// Attach a position to `error`, unless it already has one.
+    /// 
+    pub fn wrap(error: eyre::Report, line: u64, column: u32, length: u32) -> eyre::Report {
+        if error.is::<SyntaxError>() || error.is::<UnterminatedString>() {
+            return error;
+        }
+
+        SyntaxError {
+            message: error.to_string(),
+            line: line,
+            column: column,
+            length: length
+        }.into()
+    }
+}
+
+
+impl fmt::Display for SyntaxError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+
+impl std::error::Error for SyntaxError {}
+
+
 pub fn scan_all_tokens(source: &str) -> eyre::Result<PieTokenStream> {
     let mut lexer = Lexer::new(source);
     lexer.tokenize()?;
     let token_stream = PieTokenStream::from(lexer.token_list);
     Ok(token_stream)
-}
\ No newline at end of file
+}
+
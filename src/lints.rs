@@ -0,0 +1,414 @@
+use core::fmt;
+use std::collections::{HashMap, HashSet};
+use crate::{
+    lexer::TokenType,
+    manifest::LintLevel,
+    parser::{ast::AstNode, cst::{SyntaxElement, SyntaxKind, SyntaxNode, SyntaxTree}},
+    symbols::{OccurrenceKind, ScopeKind, Span, SymbolId, SymbolIndex, SymbolKind, BUILTINS},
+    types
+};
+
+
+///
+/// The checks made on every module once its names have been resolved. Each can be
+/// allowed, or reported as a warning or an error, from the `[lint]` table of `nadra.toml`.
+/// - `UndefinedNames` - A name which is not bound anywhere, e.g. `pritn(x)`.
+/// - `UnusedVariables` - A local variable which is assigned to but never used.
+/// - `UnusedImports` - A name imported with `use` which is never used.
+/// - `ShadowedParameters` - A nested function binding the name of a parameter of the
+/// function it is within, which hides the parameter from it.
+/// - `TypeErrors` - An operation on values of the wrong types, e.g. `"a" + 1`, or a value
+/// which does not match the type it is declared to be.
+/// - `NonExhaustiveMatches` - A `match` over the variants of an enum which does not handle
+/// every variant, and has no case that matches anything.
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Lint {
+    UndefinedNames,
+    UnusedVariables,
+    UnusedImports,
+    ShadowedParameters,
+    TypeErrors,
+    NonExhaustiveMatches
+}
+
+
+impl Lint {
+    pub const ALL: [Lint; 6] = [
+        Lint::UndefinedNames,
+        Lint::UnusedVariables,
+        Lint::UnusedImports,
+        Lint::ShadowedParameters,
+        Lint::TypeErrors,
+        Lint::NonExhaustiveMatches
+    ];
+
+
+    ///
+    /// The name of the lint within `nadra.toml`.
+    ///
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::UndefinedNames => "undefined_names",
+            Self::UnusedVariables => "unused_variables",
+            Self::UnusedImports => "unused_imports",
+            Self::ShadowedParameters => "shadowed_parameters",
+            Self::TypeErrors => "type_errors",
+            Self::NonExhaustiveMatches => "non_exhaustive_matches"
+        }
+    }
+
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|lint| lint.name() == name)
+    }
+
+
+    ///
+    /// The level of the lint within a project whose manifest sets the levels `levels`.
+    /// Every lint is a warning unless set otherwise, other than type checking, which is
+    /// only done when it is asked for.
+    ///
+    pub fn level(&self, levels: &HashMap<String, LintLevel>) -> LintLevel {
+        let default = match self {
+            Self::TypeErrors => LintLevel::Allow,
+            _ => LintLevel::Warn
+        };
+        levels.get(self.name()).copied().unwrap_or(default)
+    }
+}
+
+
+///
+/// # Overview
+/// A problem found by a lint, or a declaration which is always an error, such as a
+/// constant being reassigned.
+///
+/// # Members
+/// - `lint` - The lint which found the problem, if any.
+/// - `level` - How the problem is reported, which is never [`LintLevel::Allow`].
+/// - `message` - A description of the problem.
+/// - `span` - Where the problem is.
+///
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub lint: Option<Lint>,
+    pub level: LintLevel,
+    pub message: String,
+    pub span: Span
+}
+
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}: {}: {}", self.span.line + 1, self.span.column + 1, self.level, self.message)?;
+        match self.lint {
+            Some(lint) => write!(f, " [{}]", lint.name()),
+            None => Ok(())
+        }
+    }
+}
+
+
+///
+/// Run every lint over the module `tree`, whose names are resolved by `index`. Any name
+/// that is declared or assigned to where it may not be is reported as an error, whatever
+/// the levels of the lints.
+///
+/// # Params
+/// - `levels` - The level of each lint, as set by the project's manifest.
+/// - `modules` - The index of the module imported by a `use` statement, if it is known,
+/// i.e. if the module is part of the project. Names which are not bound anywhere are not
+/// reported where a module that is not known is imported with `use module::*`.
+///
+/// # Returns
+/// - The problems found, in source order.
+///
+pub fn lint<'a>(
+    tree: &SyntaxTree,
+    index: &'a SymbolIndex,
+    levels: &HashMap<String, LintLevel>,
+    modules: impl Fn(&AstNode) -> Option<&'a SymbolIndex>
+) -> Vec<Diagnostic> {
+    let mut diagnostics = vec![];
+    let mut report = |lint: Lint, span: Span, message: String| {
+        let level = lint.level(levels);
+        if level != LintLevel::Allow {
+            diagnostics.push(Diagnostic {
+                lint: Some(lint),
+                level: level,
+                message: message,
+                span: span
+            });
+        }
+    };
+
+    // Undefined names, ignoring those which may come from an unknown module.
+    // `use module::*` brings in the public top-level names of the module.
+    let exports: Vec<Vec<Option<HashSet<&String>>>> = index.scopes
+        .iter()
+        .map(|scope| scope.globs.iter().map(|statement| {
+            let module = modules(statement)?;
+            let names = module.scopes[SymbolIndex::MODULE_SCOPE].names.keys();
+            Some(names.filter(|name| !name.starts_with('_')).collect())
+        }).collect())
+        .collect();
+
+    for occurrence in &index.occurrences {
+        let undefined = occurrence.symbol.is_none()
+            && !occurrence.binding
+            && matches!(occurrence.kind, OccurrenceKind::Name)
+            && !BUILTINS.contains(&occurrence.name.as_str());
+
+        if !undefined {
+            continue;
+        }
+
+        let globbed = index
+            .lookup_chain(occurrence.scope)
+            .into_iter()
+            .flat_map(|scope| exports[scope].iter())
+            .any(|names| names.as_ref().is_none_or(|names| names.contains(&occurrence.name)));
+
+        if !globbed {
+            report(Lint::UndefinedNames, occurrence.span, format!("Undefined name '{}'", occurrence.name));
+        }
+    }
+
+    for (id, symbol) in index.symbols.iter().enumerate() {
+        let used = index
+            .occurrences_of(id)
+            .any(|occurrence| !occurrence.binding);
+
+        let scope_kind = index.scopes[symbol.scope].kind;
+        let local = matches!(scope_kind, ScopeKind::Function | ScopeKind::Lambda);
+
+        match symbol.kind {
+            SymbolKind::Variable if local && !used && !symbol.name.starts_with('_') => {
+                report(Lint::UnusedVariables, symbol.span, format!("Variable '{}' is never used", symbol.name));
+            }
+
+            SymbolKind::Import if !used => {
+                report(Lint::UnusedImports, symbol.span, format!("'{}' is imported but never used", symbol.name));
+            }
+
+            SymbolKind::Parameter if local => {
+                // The scopes within the function which bind the parameter's name again.
+                let shadowing = index.scopes.iter().enumerate().filter(|(scope, inner)| {
+                    *scope != symbol.scope
+                        && matches!(inner.kind, ScopeKind::Function | ScopeKind::Lambda)
+                        && index.lookup_chain(*scope).contains(&symbol.scope)
+                        && inner.names.contains_key(&symbol.name)
+                });
+
+                for (inner, _) in shadowing {
+                    // Only the nearest binding of the name outside of `inner` is shadowed.
+                    let nearest = index
+                        .lookup_chain(inner)
+                        .into_iter()
+                        .skip(1)
+                        .find(|&scope| index.scopes[scope].names.contains_key(&symbol.name));
+
+                    if nearest != Some(symbol.scope) {
+                        continue;
+                    }
+
+                    let shadow = &index.symbols[index.scopes[inner].names[&symbol.name]];
+                    report(Lint::ShadowedParameters, shadow.span, format!(
+                        "'{}' shadows the parameter of the same name on line {}",
+                        symbol.name,
+                        symbol.span.line + 1
+                    ));
+                }
+            }
+
+            _ => {}
+        }
+    }
+
+    let mut matches = vec![];
+    non_exhaustive_matches(&tree.root, index, &modules, &mut matches);
+    for (span, message) in matches {
+        report(Lint::NonExhaustiveMatches, span, message);
+    }
+
+    if Lint::TypeErrors.level(levels) != LintLevel::Allow {
+        for (span, message) in types::check(tree, index).errors {
+            report(Lint::TypeErrors, span, message);
+        }
+    }
+
+    for (span, message) in &index.errors {
+        diagnostics.push(Diagnostic {
+            lint: None,
+            level: LintLevel::Error,
+            message: message.clone(),
+            span: *span
+        });
+    }
+
+    diagnostics.sort_by_key(|diagnostic| (diagnostic.span.line, diagnostic.span.column));
+    diagnostics
+}
+
+
+///
+/// Find the match statements within `node` whose cases name the variants of an enum but
+/// do not handle all of them. A variant is handled by a case without a guard whose pattern
+/// is the variant, where any patterns for its fields match anything. Matches with a case
+/// which matches anything are always exhaustive. The enum may be declared in a module
+/// imported with `use`, whose index is given by `modules`.
+///
+fn non_exhaustive_matches<'a>(
+    node: &SyntaxNode,
+    index: &'a SymbolIndex,
+    modules: &impl Fn(&AstNode) -> Option<&'a SymbolIndex>,
+    found: &mut Vec<(Span, String)>
+) {
+    for child in node.child_nodes() {
+        non_exhaustive_matches(child, index, modules, found);
+        if child.kind != SyntaxKind::MatchStatement {
+            continue;
+        }
+
+        // The enum, as the index of the module declaring it and its symbol there.
+        let mut enumeration: Option<(&SymbolIndex, SymbolId)> = None;
+        let mut handled = HashSet::new();
+        let mut exhaustive = false;
+
+        for case in child.child_nodes().filter(|node| node.kind == SyntaxKind::MatchCase) {
+            let Some(pattern) = case.child_nodes().next() else { continue };
+            let guarded = case.children.iter().any(|element| {
+                matches!(element, SyntaxElement::Token(token) if token.type_ == TokenType::If)
+            });
+
+            if guarded {
+                continue;
+            }
+
+            if is_irrefutable(pattern) {
+                exhaustive = true;
+                continue;
+            }
+
+            let Some((module, variant)) = pattern_variant(pattern, index, modules) else { continue };
+            let Some(owner) = module.owner_of(module.symbols[variant].scope) else { continue };
+            let (declared_in, matched) = *enumeration.get_or_insert((module, owner));
+            if !std::ptr::eq(declared_in, module) || matched != owner {
+                continue;
+            }
+
+            let fields_irrefutable = pattern.child_nodes().skip(1).all(|field| match field.kind {
+                SyntaxKind::KeywordPattern => field.child_nodes().next().is_some_and(|pattern| is_irrefutable(pattern)),
+                _ => is_irrefutable(field)
+            });
+
+            if fields_irrefutable {
+                handled.insert(variant);
+            }
+        }
+
+        let Some((module, enumeration)) = enumeration.filter(|_| !exhaustive) else { continue };
+        let definition = &module.symbols[enumeration];
+        let Some(body) = definition.body else { continue };
+
+        // Symbols are numbered in the order they are bound.
+        let mut variants: Vec<SymbolId> = module.scopes[body].names.values().copied().collect();
+        variants.sort();
+
+        let missing: Vec<String> = variants
+            .into_iter()
+            .filter(|variant| module.symbols[*variant].kind == SymbolKind::Variant && !handled.contains(variant))
+            .map(|variant| format!("'{}::{}'", definition.name, module.symbols[variant].name))
+            .collect();
+
+        if let Some(keyword) = child.first_token() && !missing.is_empty() {
+            let message = format!("Match over '{}' does not handle {}", definition.name, missing.join(", "));
+            found.push((Span::of(&keyword), message));
+        }
+    }
+}
+
+
+fn is_irrefutable(pattern: &SyntaxNode) -> bool {
+    matches!(pattern.kind, SyntaxKind::WildcardPattern | SyntaxKind::CapturePattern)
+}
+
+
+///
+/// The variant of an enum that the value or class pattern `pattern` names, as the index of
+/// the module declaring it and its symbol there. The enum is either declared within the
+/// file, or imported from a module whose index is given by `modules`.
+///
+fn pattern_variant<'a>(
+    pattern: &SyntaxNode,
+    index: &'a SymbolIndex,
+    modules: &impl Fn(&AstNode) -> Option<&'a SymbolIndex>
+) -> Option<(&'a SymbolIndex, SymbolId)> {
+    if !matches!(pattern.kind, SyntaxKind::ValuePattern | SyntaxKind::ClassPattern) {
+        return None;
+    }
+
+    let class = pattern.child_nodes().next()?;
+    let name = class.last_token()?;
+    let occurrence = index.occurrence_at(name.line, name.column)?;
+
+    let variant = match occurrence.symbol {
+        Some(symbol) => (index, symbol),
+
+        // `Enum::Variant`, where the enum is imported from another module.
+        None if class.kind == SyntaxKind::ScopeAccess => {
+            let (module, enumeration) = imported_symbol(class.child_nodes().next()?, index, modules)?;
+            let body = module.symbols[enumeration].body?;
+            (module, *module.scopes[body].names.get(&occurrence.name)?)
+        }
+
+        None => return None
+    };
+
+    Some(variant).filter(|(module, symbol)| module.symbols[*symbol].kind == SymbolKind::Variant)
+}
+
+
+///
+/// The top-level symbol of another module that the name or member access `node` refers to,
+/// along with the index of that module, given by `modules`.
+///
+fn imported_symbol<'a>(
+    node: &SyntaxNode,
+    index: &'a SymbolIndex,
+    modules: &impl Fn(&AstNode) -> Option<&'a SymbolIndex>
+) -> Option<(&'a SymbolIndex, SymbolId)> {
+    let token = node.last_token()?;
+    let occurrence = index.occurrence_at(token.line, token.column)?;
+
+    let (module, name) = match (occurrence.symbol, &occurrence.kind) {
+        // An item imported with `use module::name`.
+        (Some(symbol), _) => {
+            let import = index.symbols[symbol].import.as_ref()?;
+            (modules(&import.statement)?, import.item.as_ref()?)
+        }
+
+        // `module.name`, where `module` was imported with `use`.
+        (None, OccurrenceKind::Member { parent: Some(parent) }) => {
+            let import = index.symbols[*parent].import.as_ref().filter(|import| import.item.is_none())?;
+            (modules(&import.statement)?, &occurrence.name)
+        }
+
+        // A name which may have been imported by `use module::*`.
+        (None, OccurrenceKind::Name) => {
+            let module = index
+                .lookup_chain(occurrence.scope)
+                .into_iter()
+                .flat_map(|scope| index.scopes[scope].globs.iter())
+                .filter_map(|statement| modules(statement))
+                .find(|module| module.scopes[SymbolIndex::MODULE_SCOPE].names.contains_key(&occurrence.name))?;
+
+            (module, &occurrence.name)
+        }
+
+        _ => return None
+    };
+
+    Some((module, *module.scopes[SymbolIndex::MODULE_SCOPE].names.get(name)?))
+}
@@ -0,0 +1,161 @@
+use std::collections::BTreeMap;
+use serde_json::{json, Value};
+use crate::{
+    lexer::{KeywordLookup, TokenType},
+    symbols::{ScopeId, SymbolIndex, SymbolKind}
+};
+use super::{hover, workspace::{Target, Workspace}};
+
+
+// The kinds of completion item defined by the protocol.
+const METHOD: u32 = 2;
+const FUNCTION: u32 = 3;
+const FIELD: u32 = 5;
+const VARIABLE: u32 = 6;
+const INTERFACE: u32 = 8;
+const MODULE: u32 = 9;
+const KEYWORD: u32 = 14;
+const ENUM: u32 = 13;
+const ENUM_MEMBER: u32 = 20;
+const CONSTANT: u32 = 21;
+const STRUCT: u32 = 22;
+const TYPE_PARAMETER: u32 = 25;
+
+
+///
+/// The completion candidates at `line`:`column` of the file `uri`. After `.` or `::`, these
+/// are the members of what comes before it, i.e. the fields and methods of a struct or of
+/// `self`, the variants of an enum, or the top-level names of a module within the workspace.
+/// Otherwise, they are the names in scope and the keywords.
+///
+pub fn completion(workspace: &Workspace, uri: &str, line: u64, column: u32) -> Option<Value> {
+    let document = workspace.document(uri)?;
+    let index = document.index()?;
+    let scope = index.scope_at(line, column);
+
+    // The tokens before the position, ignoring the name being typed.
+    let mut before = document
+        .tokens()
+        .iter()
+        .filter(|token| token.type_ != TokenType::Eof && (token.line, token.column) < (line, column))
+        .rev()
+        .peekable();
+
+    let prefix = match before.peek() {
+        Some(token) if token.type_ == TokenType::Identifier && token.line == line
+            && token.column + token.lexeme.chars().count() as u32 >= column => {
+            let length = (column - token.column) as usize;
+            before.next().map(|token| token.lexeme.chars().take(length).collect()).unwrap_or_default()
+        }
+        _ => String::new()
+    };
+
+    let accessor = before.next().filter(|token| matches!(token.type_, TokenType::Dot | TokenType::ScopeOperator));
+    let parent = before.next().filter(|token| token.type_ == TokenType::Identifier);
+
+    let mut candidates = BTreeMap::new();
+    match (accessor, parent) {
+        (Some(_), Some(parent)) => {
+            let Some(target) = workspace.resolve(uri, scope, &parent.lexeme) else {
+                return Some(json!([]));
+            };
+            add_members(workspace, &target, &mut candidates);
+        }
+
+        // Accessing a member of something other than a name, e.g. `f().`, is not supported.
+        (Some(_), None) => return Some(json!([])),
+
+        _ => {
+            for module in workspace.globbed_modules(uri, scope) {
+                if let Some(module_index) = workspace.index(module) {
+                    add_scope(module_index, SymbolIndex::MODULE_SCOPE, &mut candidates);
+                }
+            }
+
+            // Inner scopes come last, so that their names take precedence.
+            for scope in index.lookup_chain(scope).into_iter().rev() {
+                add_scope(index, scope, &mut candidates);
+            }
+
+            for keyword in KeywordLookup::create().keywords() {
+                candidates.insert(keyword.to_string(), json!({ "label": keyword, "kind": KEYWORD }));
+            }
+        }
+    }
+
+    let items: Vec<Value> = candidates
+        .into_iter()
+        .filter(|(name, _)| name.starts_with(&prefix))
+        .map(|(_, item)| item)
+        .collect();
+
+    Some(Value::Array(items))
+}
+
+
+///
+/// Add the members of `target` as candidates.
+///
+fn add_members(workspace: &Workspace, target: &Target, candidates: &mut BTreeMap<String, Value>) {
+    let Some(index) = workspace.index(&target.uri) else { return };
+    let symbol = &index.symbols[target.symbol];
+
+    let body = match symbol.kind {
+        SymbolKind::Struct | SymbolKind::Interface | SymbolKind::Enum => symbol.body,
+        SymbolKind::Parameter => symbol.instance_of.and_then(|owner| index.symbols[owner].body),
+
+        SymbolKind::Import => {
+            let module = symbol.import
+                .as_ref()
+                .filter(|import| import.item.is_none())
+                .and_then(|import| workspace.imported_uri(&target.uri, &import.statement))
+                .and_then(|module| workspace.index(module));
+
+            if let Some(module) = module {
+                add_scope(module, SymbolIndex::MODULE_SCOPE, candidates);
+            }
+            return;
+        }
+
+        _ => None
+    };
+
+    if let Some(body) = body {
+        add_scope(index, body, candidates);
+    }
+}
+
+
+///
+/// Add the names bound within `scope` as candidates, described by their signatures.
+///
+fn add_scope(index: &SymbolIndex, scope: ScopeId, candidates: &mut BTreeMap<String, Value>) {
+    for (name, &symbol) in &index.scopes[scope].names {
+        let symbol = &index.symbols[symbol];
+        let kind = match symbol.kind {
+            SymbolKind::Function => FUNCTION,
+            SymbolKind::Method => METHOD,
+            SymbolKind::Struct => STRUCT,
+            SymbolKind::Interface => INTERFACE,
+            SymbolKind::Enum => ENUM,
+            SymbolKind::Field => FIELD,
+            SymbolKind::Variant => ENUM_MEMBER,
+            SymbolKind::Constant => CONSTANT,
+            SymbolKind::TypeParameter => TYPE_PARAMETER,
+            SymbolKind::Import if symbol.import.as_ref().is_some_and(|import| import.item.is_none()) => MODULE,
+            SymbolKind::Parameter | SymbolKind::Variable | SymbolKind::Import => VARIABLE
+        };
+
+        let mut item = json!({
+            "label": name,
+            "kind": kind,
+            "detail": hover::signature(index, symbol)
+        });
+
+        if let Some(documentation) = hover::documentation(symbol) {
+            item["documentation"] = json!(documentation);
+        }
+
+        candidates.insert(name.clone(), item);
+    }
+}
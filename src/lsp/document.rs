@@ -0,0 +1,497 @@
+use std::rc::Rc;
+use color_eyre::eyre;
+use serde_json::{json, Value};
+use crate::{
+    lexer::{self, BlockTracker, PieToken, PieTokenStream, SyntaxError, TokenType, TriviaKind, UnterminatedString},
+    lints,
+    manifest::LintLevel,
+    parser::{self, cst::{SyntaxElement, SyntaxKind, SyntaxNode, SyntaxTree}},
+    symbols::{Span, SymbolIndex}
+};
+
+
+/// The kinds of semantic token, in the order they are listed in the legend.
+pub const SEMANTIC_TOKEN_TYPES: [&str; 8] = [
+    "keyword", "variable", "function", "type", "string", "number", "operator", "comment"
+];
+
+
+// The kinds of symbol defined by the protocol.
+const FUNCTION: u32 = 12;
+const METHOD: u32 = 6;
+const STRUCT: u32 = 23;
+const INTERFACE: u32 = 11;
+const ENUM: u32 = 10;
+const FIELD: u32 = 8;
+const ENUM_MEMBER: u32 = 22;
+
+
+///
+/// How the client counts the characters of a line. Nadra counts characters as Unicode
+/// scalar values, i.e. UTF-32 code units, but clients count UTF-16 code units unless
+/// they support something else.
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PositionEncoding {
+    Utf16, Utf32
+}
+
+
+impl PositionEncoding {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Utf16 => "utf-16",
+            Self::Utf32 => "utf-32"
+        }
+    }
+}
+
+
+///
+/// # Overview
+/// A document open in the client, along with what was found by analysing it. The tokens
+/// and syntax tree are kept from the last time the document could be lexed or parsed, so
+/// that symbols and folding ranges do not disappear while an edit is in progress.
+///
+/// # Members
+/// - `text` - The contents of the document.
+/// - `line_starts` - The byte offset at which each line of `text` begins.
+/// - `tokens` - The tokens of the document, including the `Eof` token.
+/// - `tree` - The concrete syntax tree of the document.
+/// - `index` - The symbols of the document and where each of them is used.
+/// - `error` - The error that stopped the document from being parsed, if any.
+///
+pub struct Document {
+    text: String,
+    line_starts: Vec<usize>,
+    tokens: Vec<Rc<PieToken>>,
+    tree: Option<SyntaxTree>,
+    index: Option<SymbolIndex>,
+    error: Option<eyre::Report>
+}
+
+
+impl Document {
+    pub fn new(text: String) -> Self {
+        let mut document = Self {
+            text: String::new(),
+            line_starts: vec![],
+            tokens: vec![],
+            tree: None,
+            index: None,
+            error: None
+        };
+        document.update(text);
+        document
+    }
+
+
+    ///
+    /// Replace the contents of the document and analyse it again.
+    ///
+    pub fn update(&mut self, text: String) {
+        self.line_starts = std::iter::once(0)
+            .chain(text.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        self.text = text;
+
+        let result = lexer::scan_all_tokens(&self.text).and_then(|tokens| {
+            self.tokens = tokens.tokens().to_vec();
+            parser::parse(&mut PieTokenStream::from(self.tokens.clone()))
+        });
+
+        match result {
+            Ok(tree) => {
+                self.index = Some(SymbolIndex::build(&tree));
+                self.tree = Some(tree);
+                self.error = None;
+            }
+            Err(e) => self.error = Some(e)
+        }
+    }
+
+
+    ///
+    /// The diagnostics of the document, i.e. the lexer or parser error if there is one,
+    /// or otherwise the problems found by `lints`.
+    ///
+    pub fn diagnostics(&self, encoding: PositionEncoding, lints: &[lints::Diagnostic]) -> Value {
+        let Some(error) = &self.error else {
+            let diagnostics = lints
+                .iter()
+                .map(|diagnostic| {
+                    let mut value = json!({
+                        "range": self.range(encoding, diagnostic.span),
+                        "severity": if diagnostic.level == LintLevel::Error { 1 } else { 2 },
+                        "source": "nadra",
+                        "message": diagnostic.message
+                    });
+                    if let Some(lint) = diagnostic.lint {
+                        value["code"] = json!(lint.name());
+                    }
+                    value
+                })
+                .collect();
+
+            return Value::Array(diagnostics);
+        };
+
+        let (line, column, length) = if let Some(error) = error.downcast_ref::<SyntaxError>() {
+            (error.line, error.column, error.length)
+        } else if let Some(error) = error.downcast_ref::<UnterminatedString>() {
+            (error.line, error.column, 1)
+        } else {
+            (0, 0, 0)
+        };
+
+        json!([{
+            "range": {
+                "start": self.position(encoding, line, column),
+                "end": self.position(encoding, line, column + length)
+            },
+            "severity": 1,
+            "source": "nadra",
+            "message": error.to_string()
+        }])
+    }
+
+
+    ///
+    /// Whether the document was parsed as it is now, rather than being left with the
+    /// analysis of an earlier version.
+    ///
+    pub fn is_parsed(&self) -> bool {
+        self.error.is_none()
+    }
+
+
+    ///
+    /// The functions, structs, interfaces and enums of the document, with the fields and
+    /// methods of each struct and interface and the variants of each enum nested within them.
+    ///
+    pub fn symbols(&self, encoding: PositionEncoding) -> Value {
+        match &self.tree {
+            Some(tree) => Value::Array(self.symbols_within(&tree.root, encoding)),
+            None => json!([])
+        }
+    }
+
+
+    fn symbols_within(&self, node: &SyntaxNode, encoding: PositionEncoding) -> Vec<Value> {
+        let mut symbols = vec![];
+
+        for child in node.child_nodes() {
+            let kind = match child.kind {
+                SyntaxKind::FunctionDefinition
+                    if matches!(node.kind, SyntaxKind::StructDefinition | SyntaxKind::InterfaceDefinition) => METHOD,
+                SyntaxKind::FunctionDefinition => FUNCTION,
+                SyntaxKind::StructDefinition => STRUCT,
+                SyntaxKind::InterfaceDefinition => INTERFACE,
+                SyntaxKind::EnumDefinition => ENUM,
+                SyntaxKind::Field => FIELD,
+                SyntaxKind::EnumVariant => ENUM_MEMBER,
+
+                // Definitions may be made within blocks.
+                SyntaxKind::IfStatement | SyntaxKind::WhileLoop => {
+                    symbols.extend(self.symbols_within(child, encoding));
+                    continue;
+                }
+
+                _ => continue
+            };
+
+            // The name is the first identifier of the definition, e.g. `def name(...)`.
+            let name = child.children.iter().find_map(|element| match element {
+                SyntaxElement::Token(token) if token.type_ == TokenType::Identifier => Some(token),
+                _ => None
+            });
+
+            let (Some(name), Some(first), Some(last)) = (name, child.first_token(), child.last_token()) else {
+                continue;
+            };
+
+            symbols.push(json!({
+                "name": name.lexeme,
+                "kind": kind,
+                "range": {
+                    "start": self.position(encoding, first.line, first.column),
+                    "end": self.end_of(encoding, &last)
+                },
+                "selectionRange": {
+                    "start": self.position(encoding, name.line, name.column),
+                    "end": self.end_of(encoding, name)
+                },
+                "children": self.symbols_within(child, encoding)
+            }));
+        }
+
+        symbols
+    }
+
+
+    ///
+    /// The blocks of the document that can be folded, i.e. `def ... enddef`,
+    /// `if ... endif`, `while ... done`, etc. Each range ends on the line before the
+    /// keyword that closes the block, so that the keyword stays visible. Each case of a
+    /// `match` is folded up to the next case.
+    ///
+    pub fn folding_ranges(&self) -> Value {
+        let mut blocks = BlockTracker::default();
+        let mut start_lines = vec![];
+        let mut ranges = vec![];
+
+        for token in &self.tokens {
+            let (closes, opens) = blocks.advance(&token.type_);
+
+            for _ in 0..closes {
+                let start_line = start_lines.pop().unwrap();
+                if token.line > start_line + 1 {
+                    ranges.push(json!({
+                        "startLine": start_line,
+                        "endLine": token.line - 1
+                    }));
+                }
+            }
+
+            if opens {
+                start_lines.push(token.line);
+            }
+        }
+
+        Value::Array(ranges)
+    }
+
+
+    ///
+    /// The semantic tokens of the whole document, which are derived from the type of each
+    /// token, along with its comments. Names are highlighted as functions or types where
+    /// they are defined.
+    ///
+    pub fn semantic_tokens(&self, encoding: PositionEncoding) -> Value {
+        let mut builder = SemanticTokensBuilder::new(self, encoding);
+        let mut previous_type = None;
+
+        for token in &self.tokens {
+            for trivia in &token.leading_trivia {
+                if trivia.kind == TriviaKind::Comment {
+                    builder.push(trivia.line, trivia.column, &trivia.text, "comment");
+                }
+            }
+
+            let semantic_type = match token.type_ {
+                TokenType::Identifier => match previous_type {
+                    Some(TokenType::Def) => Some("function"),
+                    Some(TokenType::Struct | TokenType::Interface | TokenType::Implements | TokenType::Enum) => Some("type"),
+                    _ => Some("variable")
+                },
+                TokenType::String | TokenType::FormatString => Some("string"),
+                TokenType::Integer | TokenType::Float => Some("number"),
+                ref type_ if is_keyword(type_) => Some("keyword"),
+                ref type_ if type_.precedence_level() > 0 || type_.is_assignment_operator() => Some("operator"),
+                _ => None
+            };
+
+            if let Some(semantic_type) = semantic_type {
+                builder.push(token.line, token.column, &token.lexeme, semantic_type);
+            }
+            previous_type = Some(token.type_.clone());
+        }
+
+        json!({ "data": builder.data })
+    }
+
+
+    pub fn tokens(&self) -> &[Rc<PieToken>] {
+        &self.tokens
+    }
+
+
+    pub fn tree(&self) -> Option<&SyntaxTree> {
+        self.tree.as_ref()
+    }
+
+
+    pub fn index(&self) -> Option<&SymbolIndex> {
+        self.index.as_ref()
+    }
+
+
+    ///
+    /// The range of `span` within the document.
+    ///
+    pub fn range(&self, encoding: PositionEncoding, span: Span) -> Value {
+        json!({
+            "start": self.position(encoding, span.line, span.column),
+            "end": self.position(encoding, span.line, span.column + span.length)
+        })
+    }
+
+
+    ///
+    /// Convert a position in `encoding`, as sent by the client, to a line and a column
+    /// counted in characters.
+    ///
+    pub fn location(&self, encoding: PositionEncoding, position: &Value) -> Option<(u64, u32)> {
+        let line = position["line"].as_u64()?;
+        let character = position["character"].as_u64()? as u32;
+
+        if encoding == PositionEncoding::Utf32 {
+            return Some((line, character));
+        }
+
+        let start = *self.line_starts.get(line as usize)?;
+        let mut units = 0;
+        let mut column = 0;
+
+        for ch in self.text[start..].chars().take_while(|&ch| ch != '\n') {
+            if units >= character {
+                break;
+            }
+            units += ch.len_utf16() as u32;
+            column += 1;
+        }
+
+        Some((line, column))
+    }
+
+
+    ///
+    /// Convert a line and a column counted in characters to a position in `encoding`.
+    ///
+    fn position(&self, encoding: PositionEncoding, line: u64, column: u32) -> Value {
+        json!({ "line": line, "character": self.character(encoding, line, column) })
+    }
+
+
+    ///
+    /// The position just after `token`, which may span several lines.
+    ///
+    fn end_of(&self, encoding: PositionEncoding, token: &PieToken) -> Value {
+        match token.lexeme.rsplit_once('\n') {
+            Some((before, last)) => {
+                let line = token.line + before.matches('\n').count() as u64 + 1;
+                self.position(encoding, line, last.chars().count() as u32)
+            }
+            None => self.position(encoding, token.line, token.column + token.lexeme.chars().count() as u32)
+        }
+    }
+
+
+    fn character(&self, encoding: PositionEncoding, line: u64, column: u32) -> u32 {
+        if encoding == PositionEncoding::Utf32 {
+            return column;
+        }
+
+        let Some(&start) = self.line_starts.get(line as usize) else {
+            return column;
+        };
+
+        self.text[start..]
+            .chars()
+            .take(column as usize)
+            .map(|ch| ch.len_utf16() as u32)
+            .sum()
+    }
+}
+
+
+///
+/// # Overview
+/// Encodes semantic tokens as the protocol expects. Each token is encoded relative to the
+/// previous one, as five numbers: the change in line, the change in start character, the
+/// length, the index of its type within [`SEMANTIC_TOKEN_TYPES`] and its modifiers, of
+/// which there are none.
+///
+/// # Members
+/// - `document` - The document the tokens are in.
+/// - `encoding` - How characters are counted.
+/// - `previous` - The line and start character of the previous token.
+/// - `data` - The encoded tokens.
+///
+struct SemanticTokensBuilder<'a> {
+    document: &'a Document,
+    encoding: PositionEncoding,
+    previous: (u64, u32),
+    data: Vec<u32>
+}
+
+
+impl<'a> SemanticTokensBuilder<'a> {
+    fn new(document: &'a Document, encoding: PositionEncoding) -> Self {
+        Self {
+            document: document,
+            encoding: encoding,
+            previous: (0, 0),
+            data: vec![]
+        }
+    }
+
+
+    ///
+    /// Add a token beginning at `line` and `column`. Tokens which span several lines,
+    /// i.e. strings, are split into one token per line.
+    ///
+    fn push(&mut self, line: u64, column: u32, text: &str, semantic_type: &str) {
+        let type_index = SEMANTIC_TOKEN_TYPES
+            .iter()
+            .position(|name| *name == semantic_type)
+            .unwrap() as u32;
+
+        let mut column = column;
+        for (i, segment) in text.split('\n').enumerate() {
+            let line = line + i as u64;
+            let start = self.document.character(self.encoding, line, column);
+            let length = match self.encoding {
+                PositionEncoding::Utf16 => segment.encode_utf16().count() as u32,
+                PositionEncoding::Utf32 => segment.chars().count() as u32
+            };
+            column = 0;
+
+            if length == 0 {
+                continue;
+            }
+
+            let (previous_line, previous_start) = self.previous;
+            let delta_start = if line == previous_line { start - previous_start } else { start };
+            self.data.extend([(line - previous_line) as u32, delta_start, length, type_index, 0]);
+            self.previous = (line, start);
+        }
+    }
+}
+
+
+fn is_keyword(type_: &TokenType) -> bool {
+    matches!(type_,
+        TokenType::Enum
+        | TokenType::EndEnum
+        | TokenType::Struct
+        | TokenType::EndStruct
+        | TokenType::Interface
+        | TokenType::EndInterface
+        | TokenType::Implements
+        | TokenType::Def
+        | TokenType::EndDef
+        | TokenType::If
+        | TokenType::Then
+        | TokenType::EndIf
+        | TokenType::Else
+        | TokenType::Match
+        | TokenType::With
+        | TokenType::Case
+        | TokenType::EndMatch
+        | TokenType::For
+        | TokenType::Do
+        | TokenType::Done
+        | TokenType::While
+        | TokenType::Return
+        | TokenType::Break
+        | TokenType::Continue
+        | TokenType::Use
+        | TokenType::As
+        | TokenType::Let
+        | TokenType::Const
+        | TokenType::TypeAlias
+        | TokenType::True
+        | TokenType::False
+    )
+}
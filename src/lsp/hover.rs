@@ -0,0 +1,162 @@
+use std::rc::Rc;
+use serde_json::{json, Value};
+use crate::{
+    lexer::{PieToken, TokenType, TriviaKind},
+    parser::cst::{SyntaxElement, SyntaxKind},
+    symbols::{Symbol, SymbolIndex, SymbolKind},
+    types
+};
+use super::{document::PositionEncoding, workspace::Workspace};
+
+
+///
+/// Describe the symbol at `line`:`column` of the file `uri`, following imported names to
+/// where they are defined. Functions are described by their signature, including their
+/// return type, and definitions by the comments written directly above them. A variable
+/// or parameter is described by its type where it is used, as far as it can be inferred.
+///
+pub fn hover(workspace: &Workspace, uri: &str, line: u64, column: u32, encoding: PositionEncoding) -> Option<Value> {
+    let document = workspace.document(uri)?;
+    let occurrence = document.index()?.occurrence_at(line, column)?;
+    let target = workspace.target_at(uri, line, column, true)?;
+
+    let index = workspace.index(&target.uri)?;
+    let symbol = &index.symbols[target.symbol];
+
+    let inferred = match (document.tree(), document.index()) {
+        (Some(tree), Some(index)) => types::check(tree, index).types.remove(&(occurrence.span.line, occurrence.span.column)),
+        _ => None
+    };
+
+    let declaration = match inferred {
+        Some(type_) => format!("{}: {}", symbol.name, type_),
+        None => signature(index, symbol)
+    };
+
+    let mut contents = format!("```nadra\n{}\n```", declaration);
+    if let Some(documentation) = documentation(symbol) {
+        contents.push_str("\n\n---\n\n");
+        contents.push_str(&documentation);
+    }
+
+    Some(json!({
+        "contents": { "kind": "markdown", "value": contents },
+        "range": document.range(encoding, occurrence.span)
+    }))
+}
+
+
+///
+/// How `symbol` is declared, e.g. `def area(w: int, h: int) -> int` for a function or
+/// `Color::Red` for a variant.
+///
+pub fn signature(index: &SymbolIndex, symbol: &Symbol) -> String {
+    let node = &symbol.node;
+
+    match symbol.kind {
+        // Everything before the body, i.e. up to the end of the return type.
+        SymbolKind::Function | SymbolKind::Method => {
+            let mut tokens = vec![];
+            for element in &node.children {
+                match element {
+                    SyntaxElement::Token(token) => tokens.push(token.clone()),
+                    SyntaxElement::Node(child) if matches!(child.kind, SyntaxKind::Parameter | SyntaxKind::TypeParameter) => {
+                        tokens.extend(child.tokens());
+                    }
+                    SyntaxElement::Node(child) => {
+                        tokens.extend(child.tokens());
+                        break;
+                    }
+                }
+            }
+            join_tokens(&tokens)
+        }
+
+        SymbolKind::Struct => format!("struct {}", symbol.name),
+        SymbolKind::Interface => format!("interface {}", symbol.name),
+        SymbolKind::Enum => format!("enum {}", symbol.name),
+
+        SymbolKind::Variant => match index.owner_of(symbol.scope) {
+            Some(owner) => format!("{}::{}", index.symbols[owner].name, symbol.name),
+            None => symbol.name.clone()
+        },
+
+        SymbolKind::Field
+        | SymbolKind::Parameter
+        | SymbolKind::TypeParameter
+        | SymbolKind::Constant
+        | SymbolKind::Import => join_tokens(&node.tokens()),
+
+        SymbolKind::Variable => symbol.name.clone()
+    }
+}
+
+
+///
+/// The doc comment of a definition, i.e. the comments on the lines directly above it,
+/// without their leading `#`.
+///
+pub fn documentation(symbol: &Symbol) -> Option<String> {
+    let definitions = [
+        SymbolKind::Function,
+        SymbolKind::Method,
+        SymbolKind::Struct,
+        SymbolKind::Interface,
+        SymbolKind::Enum,
+        SymbolKind::Field,
+        SymbolKind::Variant
+    ];
+
+    if !definitions.contains(&symbol.kind) {
+        return None;
+    }
+
+    let first = symbol.node.first_token()?;
+    let mut lines = vec![];
+    let mut newlines = 0;
+
+    // A blank line separates the doc comment from any comments before it.
+    for trivia in first.leading_trivia.iter().rev() {
+        match trivia.kind {
+            TriviaKind::Whitespace => {}
+            TriviaKind::Newline if newlines > 0 => break,
+            TriviaKind::Newline => newlines += 1,
+            TriviaKind::Comment => {
+                let text = trivia.text.trim_start_matches('#');
+                lines.push(text.strip_prefix(' ').unwrap_or(text).trim_end().to_string());
+                newlines = 0;
+            }
+        }
+    }
+
+    if lines.is_empty() {
+        return None;
+    }
+
+    lines.reverse();
+    Some(lines.join("\n"))
+}
+
+
+///
+/// Join `tokens` as source code on a single line, keeping a space wherever there was
+/// whitespace between them.
+///
+fn join_tokens(tokens: &[Rc<PieToken>]) -> String {
+    let mut text = String::new();
+    let mut previous: Option<&TokenType> = None;
+
+    for token in tokens {
+        let spaced = !token.leading_trivia.is_empty()
+            && previous.is_some_and(|type_| *type_ != TokenType::LeftParen)
+            && token.type_ != TokenType::RightParen;
+
+        if spaced {
+            text.push(' ');
+        }
+        text.push_str(&token.lexeme);
+        previous = Some(&token.type_);
+    }
+
+    text
+}
@@ -0,0 +1,401 @@
+mod completion;
+mod document;
+mod hover;
+mod transport;
+mod workspace;
+
+use std::{collections::HashMap, io};
+use color_eyre::eyre::{self, eyre};
+use serde_json::{json, Value};
+use crate::{
+    lints,
+    manifest::{LintLevel, Project},
+    symbols::Span
+};
+use document::{Document, PositionEncoding, SEMANTIC_TOKEN_TYPES};
+use workspace::{Files, Target, Workspace};
+
+
+// Error codes defined by JSON-RPC and the protocol.
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const REQUEST_FAILED: i64 = -32803;
+
+
+///
+/// # Overview
+/// A language server for Nadra, which speaks the Language Server Protocol over `stdin` and
+/// `stdout`. Documents are synchronised in full on every change, after which their
+/// diagnostics are published.
+///
+/// # Members
+/// - `documents` - The documents open in the client, by URI.
+/// - `encoding` - How positions are counted, as agreed with the client.
+/// - `files` - The source files of the workspace on disk, within which names are followed
+/// across files.
+/// - `lints` - The level of each lint, as set by the manifest of the workspace's project.
+/// - `shutting_down` - Whether the client has sent a `shutdown` request.
+/// - `outgoing` - Notifications to send once the current message has been handled.
+///
+struct Server {
+    documents: HashMap<String, Document>,
+    encoding: PositionEncoding,
+    files: Files,
+    lints: HashMap<String, LintLevel>,
+    shutting_down: bool,
+    outgoing: Vec<Value>
+}
+
+
+impl Server {
+    fn new() -> Self {
+        Self {
+            documents: HashMap::new(),
+            encoding: PositionEncoding::Utf16,
+            files: Files::default(),
+            lints: HashMap::new(),
+            shutting_down: false,
+            outgoing: vec![]
+        }
+    }
+
+
+    ///
+    /// Handle a request, i.e. a message that must be answered.
+    ///
+    /// # Returns
+    /// - The result of the request, or the code and message of the error.
+    ///
+    fn handle_request(&mut self, method: &str, params: &Value) -> Result<Value, (i64, String)> {
+        if self.shutting_down {
+            return Err((INVALID_REQUEST, String::from("The server is shutting down")));
+        }
+
+        match method {
+            "initialize" => Ok(self.initialize(params)),
+
+            "shutdown" => {
+                self.shutting_down = true;
+                Ok(Value::Null)
+            }
+
+            "textDocument/documentSymbol" => {
+                let document = self.document(params)?;
+                Ok(document.symbols(self.encoding))
+            }
+
+            "textDocument/foldingRange" => Ok(self.document(params)?.folding_ranges()),
+
+            "textDocument/semanticTokens/full" => {
+                let document = self.document(params)?;
+                Ok(document.semantic_tokens(self.encoding))
+            }
+
+            "textDocument/definition" => {
+                let workspace = Workspace::new(&self.documents, &self.files);
+                let Some(target) = self.target(&workspace, params, true)? else {
+                    return Ok(Value::Null);
+                };
+
+                let index = workspace.index(&target.uri).unwrap();
+                Ok(self.location(&workspace, &target.uri, index.symbols[target.symbol].span))
+            }
+
+            "textDocument/references" => {
+                let workspace = Workspace::new(&self.documents, &self.files);
+                let Some(target) = self.target(&workspace, params, true)? else {
+                    return Ok(Value::Null);
+                };
+
+                let include_declaration = params["context"]["includeDeclaration"].as_bool().unwrap_or(true);
+                let definition = workspace.index(&target.uri).unwrap().symbols[target.symbol].span;
+
+                let locations = workspace
+                    .references(&target)
+                    .into_iter()
+                    .filter(|(uri, span)| include_declaration || *uri != target.uri || *span != definition)
+                    .map(|(uri, span)| self.location(&workspace, &uri, span))
+                    .collect();
+
+                Ok(Value::Array(locations))
+            }
+
+            "textDocument/rename" => self.rename(params),
+
+            "textDocument/completion" => {
+                let workspace = Workspace::new(&self.documents, &self.files);
+                let (uri, line, column) = self.position(params)?;
+                Ok(completion::completion(&workspace, uri, line, column).unwrap_or(Value::Null))
+            }
+
+            "textDocument/hover" => {
+                let workspace = Workspace::new(&self.documents, &self.files);
+                let (uri, line, column) = self.position(params)?;
+                Ok(hover::hover(&workspace, uri, line, column, self.encoding).unwrap_or(Value::Null))
+            }
+
+            _ => Err((METHOD_NOT_FOUND, format!("Unknown method {}", method)))
+        }
+    }
+
+
+    ///
+    /// Handle a notification, i.e. a message that is not answered. Unknown notifications
+    /// are ignored, as the protocol requires.
+    ///
+    fn handle_notification(&mut self, method: &str, params: &Value) {
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or_default().to_string();
+
+        match method {
+            "textDocument/didOpen" => {
+                let text = params["textDocument"]["text"].as_str().unwrap_or_default();
+                self.documents.insert(uri.clone(), Document::new(text.to_string()));
+                self.files.refresh(&uri, true);
+                self.publish_diagnostics(&uri);
+            }
+
+            // Only full changes are requested, so the last change holds the whole document.
+            "textDocument/didChange" => {
+                let text = params["contentChanges"]
+                    .as_array()
+                    .and_then(|changes| changes.last())
+                    .and_then(|change| change["text"].as_str());
+
+                if let (Some(text), Some(document)) = (text, self.documents.get_mut(&uri)) {
+                    document.update(text.to_string());
+                    self.publish_diagnostics(&uri);
+                }
+            }
+
+            "textDocument/didSave" => self.files.refresh(&uri, self.documents.contains_key(&uri)),
+
+            "textDocument/didClose" => {
+                self.documents.remove(&uri);
+                self.files.refresh(&uri, false);
+                self.outgoing.push(notification("textDocument/publishDiagnostics", json!({
+                    "uri": uri,
+                    "diagnostics": []
+                })));
+            }
+
+            // Files which are not open may be changed by other programs.
+            "workspace/didChangeWatchedFiles" => {
+                for change in params["changes"].as_array().into_iter().flatten() {
+                    let uri = change["uri"].as_str().unwrap_or_default();
+                    self.files.refresh(uri, self.documents.contains_key(uri));
+                }
+            }
+
+            _ => {}
+        }
+    }
+
+
+    fn initialize(&mut self, params: &Value) -> Value {
+        let supports_utf32 = params["capabilities"]["general"]["positionEncodings"]
+            .as_array()
+            .is_some_and(|encodings| encodings.iter().any(|encoding| encoding == "utf-32"));
+
+        if supports_utf32 {
+            self.encoding = PositionEncoding::Utf32;
+        }
+
+        let root = params["rootUri"]
+            .as_str()
+            .or_else(|| params["workspaceFolders"][0]["uri"].as_str());
+        let root = root.and_then(workspace::uri_to_path);
+        self.files = Files::load(root.as_deref());
+
+        if let Some(project) = root.as_deref().and_then(|root| Project::find(root).ok()) {
+            self.lints = project.manifest.lint;
+        }
+
+        json!({
+            "capabilities": {
+                "positionEncoding": self.encoding.name(),
+                "textDocumentSync": {
+                    "openClose": true,
+                    "change": 1,
+                    "save": true
+                },
+                "documentSymbolProvider": true,
+                "foldingRangeProvider": true,
+                "definitionProvider": true,
+                "referencesProvider": true,
+                "renameProvider": true,
+                "hoverProvider": true,
+                "completionProvider": {
+                    "triggerCharacters": [".", ":"]
+                },
+                "semanticTokensProvider": {
+                    "legend": {
+                        "tokenTypes": SEMANTIC_TOKEN_TYPES,
+                        "tokenModifiers": []
+                    },
+                    "full": true
+                }
+            },
+            "serverInfo": {
+                "name": "nadra",
+                "version": env!("CARGO_PKG_VERSION")
+            }
+        })
+    }
+
+
+    ///
+    /// The document that a request is about.
+    ///
+    fn document(&self, params: &Value) -> Result<&Document, (i64, String)> {
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+        self.documents
+            .get(uri)
+            .ok_or_else(|| (INVALID_PARAMS, format!("Unknown document {}", uri)))
+    }
+
+
+    ///
+    /// The document and position that a request is about, as a line and column.
+    ///
+    fn position<'a>(&self, params: &'a Value) -> Result<(&'a str, u64, u32), (i64, String)> {
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+        let document = self.document(params)?;
+
+        match document.location(self.encoding, &params["position"]) {
+            Some((line, column)) => Ok((uri, line, column)),
+            None => Err((INVALID_PARAMS, String::from("Invalid position")))
+        }
+    }
+
+
+    ///
+    /// The symbol at the position that a request is about, if there is one.
+    ///
+    fn target(&self, workspace: &Workspace, params: &Value, follow_aliases: bool) -> Result<Option<Target>, (i64, String)> {
+        let (uri, line, column) = self.position(params)?;
+        Ok(workspace.target_at(uri, line, column, follow_aliases))
+    }
+
+
+    fn location(&self, workspace: &Workspace, uri: &str, span: Span) -> Value {
+        json!({
+            "uri": uri,
+            "range": workspace.document(uri).unwrap().range(self.encoding, span)
+        })
+    }
+
+
+    ///
+    /// Rename the symbol at the position of the request throughout the workspace. Renaming
+    /// is refused if any use of a name would then refer to something else.
+    ///
+    fn rename(&self, params: &Value) -> Result<Value, (i64, String)> {
+        let workspace = Workspace::new(&self.documents, &self.files);
+        let new_name = params["newName"].as_str().unwrap_or_default();
+
+        let Some(target) = self.target(&workspace, params, false)? else {
+            return Err((REQUEST_FAILED, String::from("There is no symbol to rename here")));
+        };
+
+        workspace
+            .check_rename(&target, new_name)
+            .map_err(|e| (REQUEST_FAILED, e))?;
+
+        let mut changes: HashMap<String, Vec<Value>> = HashMap::new();
+        for (uri, span) in workspace.references(&target) {
+            let range = workspace.document(&uri).unwrap().range(self.encoding, span);
+            changes.entry(uri).or_default().push(json!({ "range": range, "newText": new_name }));
+        }
+
+        Ok(json!({ "changes": changes }))
+    }
+
+
+    fn publish_diagnostics(&mut self, uri: &str) {
+        let workspace = Workspace::new(&self.documents, &self.files);
+        let document = &self.documents[uri];
+
+        // Lints are only run once the document parses, as the names it binds may change.
+        let lints = match (document.tree(), document.index()) {
+            (Some(tree), Some(index)) if document.is_parsed() => lints::lint(tree, index, &self.lints, |statement| {
+                workspace.index(workspace.imported_uri(uri, statement)?)
+            }),
+            _ => vec![]
+        };
+
+        let diagnostics = document.diagnostics(self.encoding, &lints);
+        self.outgoing.push(notification("textDocument/publishDiagnostics", json!({
+            "uri": uri,
+            "diagnostics": diagnostics
+        })));
+    }
+}
+
+
+fn notification(method: &str, params: Value) -> Value {
+    json!({ "jsonrpc": "2.0", "method": method, "params": params })
+}
+
+
+fn error_response(id: Value, code: i64, message: &str) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } })
+}
+
+
+///
+/// Run the language server until the client sends `exit`. Invoked as:
+/// ```sh
+/// nadra lsp
+/// ```
+///
+/// # Returns
+/// - An error if the client exits without first sending `shutdown`, or closes the
+/// connection without sending `exit`.
+///
+pub fn run() -> eyre::Result<()> {
+    let mut input = io::stdin().lock();
+    let mut output = io::stdout().lock();
+    let mut server = Server::new();
+
+    while let Some(message) = transport::read_message(&mut input)? {
+        let message = match message {
+            Ok(message) => message,
+            Err(e) => {
+                transport::write_message(&mut output, &error_response(Value::Null, PARSE_ERROR, &e.to_string()))?;
+                continue;
+            }
+        };
+
+        // Responses from the client are ignored, as the server never sends requests.
+        let Some(method) = message["method"].as_str() else {
+            continue;
+        };
+        let params = &message["params"];
+
+        if method == "exit" {
+            if server.shutting_down {
+                return Ok(());
+            }
+            return Err(eyre!("The client exited without shutting down the server"));
+        }
+
+        match message.get("id") {
+            Some(id) => {
+                let response = match server.handle_request(method, params) {
+                    Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+                    Err((code, message)) => error_response(id.clone(), code, &message)
+                };
+                transport::write_message(&mut output, &response)?;
+            }
+            None => server.handle_notification(method, params)
+        }
+
+        for message in server.outgoing.drain(..) {
+            transport::write_message(&mut output, &message)?;
+        }
+    }
+
+    Err(eyre!("The client closed the connection without exiting"))
+}
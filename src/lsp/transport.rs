@@ -0,0 +1,58 @@
+use std::io::{BufRead, Write};
+use color_eyre::eyre::{self, eyre};
+use serde_json::Value;
+
+
+///
+/// Read the next message sent by the client. Each message is a JSON value preceded by
+/// a `Content-Length` header and a blank line, e.g.
+/// ```text
+/// Content-Length: 52\r\n
+/// \r\n
+/// {"jsonrpc":"2.0","id":1,"method":"shutdown"}
+/// ```
+///
+/// # Returns
+/// - `None` once the client has closed the input.
+/// - An error if the headers are malformed. A body which is not valid JSON is returned
+/// as an error too, but the input is left at the start of the next message.
+///
+pub fn read_message(input: &mut impl BufRead) -> eyre::Result<Option<eyre::Result<Value>>> {
+    let mut length = None;
+
+    loop {
+        let mut header = String::new();
+        if input.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+
+        // Other headers, i.e. `Content-Type`, are ignored.
+        if let Some((name, value)) = header.split_once(':')
+            && name.eq_ignore_ascii_case("Content-Length") {
+            let value = value.trim().parse::<usize>().map_err(|_| eyre!("Invalid Content-Length: {}", value.trim()))?;
+            length = Some(value);
+        }
+    }
+
+    let length = length.ok_or_else(|| eyre!("Expected a Content-Length header"))?;
+    let mut body = vec![0; length];
+    input.read_exact(&mut body)?;
+
+    Ok(Some(serde_json::from_slice(&body).map_err(eyre::Report::from)))
+}
+
+
+///
+/// Send `message` to the client.
+///
+pub fn write_message(output: &mut impl Write, message: &Value) -> eyre::Result<()> {
+    let body = serde_json::to_string(message)?;
+    write!(output, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    output.flush()?;
+    Ok(())
+}
@@ -0,0 +1,499 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    rc::Rc
+};
+use crate::{
+    formatter,
+    manifest::Project,
+    modules,
+    parser::ast::AstNode,
+    symbols::{OccurrenceKind, ScopeId, Span, Symbol, SymbolId, SymbolIndex, SymbolKind}
+};
+use super::document::Document;
+
+
+///
+/// A symbol within a particular file of the workspace.
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct Target {
+    pub uri: String,
+    pub symbol: SymbolId
+}
+
+
+///
+/// # Overview
+/// The source files of the workspace as they are on disk. They are read once when the
+/// server starts, after which each file is only read again when the client reports that
+/// it has changed.
+///
+/// # Members
+/// - `source_dir` - The directory that module names are relative to, i.e. the source
+/// directory of the project containing the root, or the root itself if it is not within
+/// a project.
+/// - `documents` - The files read from disk, by URI.
+/// - `modules` - The dotted name of each file that is within the source directory, by URI.
+///
+#[derive(Default)]
+pub struct Files {
+    source_dir: Option<PathBuf>,
+    documents: HashMap<String, Document>,
+    modules: HashMap<String, String>
+}
+
+
+impl Files {
+    ///
+    /// Read every source file of the workspace at `root`.
+    ///
+    pub fn load(root: Option<&Path>) -> Self {
+        let Some(root) = root.and_then(|root| root.canonicalize().ok()) else {
+            return Self::default();
+        };
+
+        let source_dir = Project::find(&root)
+            .map(|project| project.source_dir())
+            .unwrap_or_else(|_| root.clone());
+
+        let mut files = Self {
+            source_dir: Some(source_dir.clone()),
+            documents: HashMap::new(),
+            modules: HashMap::new()
+        };
+
+        for path in formatter::find_sources(&source_dir).unwrap_or_default() {
+            let Ok(module) = modules::module_name(&source_dir, &path) else { continue };
+            let Ok(text) = fs::read_to_string(&path) else { continue };
+
+            let uri = path_to_uri(&path);
+            files.documents.insert(uri.clone(), Document::new(text));
+            files.modules.insert(uri, module);
+        }
+
+        files
+    }
+
+
+    ///
+    /// Read the file `uri` again, after it has been created, changed or deleted. A file
+    /// which is open in the client still belongs to its module even if it has not been
+    /// saved yet.
+    ///
+    pub fn refresh(&mut self, uri: &str, is_open: bool) {
+        self.documents.remove(uri);
+        self.modules.remove(uri);
+
+        let Some(source_dir) = &self.source_dir else { return };
+
+        let path = uri_to_path(uri)
+            .filter(|path| path.extension().is_some_and(|extension| extension == "ndr"))
+            .and_then(|path| {
+                let directory = path.parent()?.canonicalize().ok()?;
+                Some(directory.join(path.file_name()?))
+            });
+        let Some(path) = path else { return };
+        let Ok(module) = modules::module_name(source_dir, &path) else { return };
+
+        match fs::read_to_string(&path) {
+            Ok(text) => {
+                self.documents.insert(uri.to_string(), Document::new(text));
+            }
+            Err(_) if is_open => {}
+            Err(_) => return
+        }
+        self.modules.insert(uri.to_string(), module);
+    }
+}
+
+
+///
+/// # Overview
+/// Every source file of the workspace, which is needed to follow names across `use`
+/// statements. Open documents are used as they are in the client, and every other file
+/// as it was last read from disk.
+///
+/// # Members
+/// - `open` - The documents open in the client, by URI.
+/// - `files` - The source files of the workspace on disk.
+///
+pub struct Workspace<'a> {
+    open: &'a HashMap<String, Document>,
+    files: &'a Files
+}
+
+
+impl<'a> Workspace<'a> {
+    pub fn new(open: &'a HashMap<String, Document>, files: &'a Files) -> Self {
+        Self {
+            open: open,
+            files: files
+        }
+    }
+
+
+    pub fn document(&self, uri: &str) -> Option<&Document> {
+        self.open.get(uri).or_else(|| self.files.documents.get(uri))
+    }
+
+
+    pub fn index(&self, uri: &str) -> Option<&SymbolIndex> {
+        self.document(uri)?.index()
+    }
+
+
+    ///
+    /// Every file of the workspace, along with the open documents outside of it.
+    ///
+    pub fn uris(&self) -> Vec<String> {
+        let mut uris: Vec<String> = self.files.modules.keys().cloned().collect();
+        uris.extend(self.open.keys().filter(|uri| !self.files.modules.contains_key(*uri)).cloned());
+        uris.sort();
+        uris
+    }
+
+
+    ///
+    /// The file of the module imported by `statement`, a `use` statement within `importer`.
+    ///
+    pub fn imported_uri(&self, importer: &str, statement: &AstNode) -> Option<&str> {
+        let AstNode::UseStatement(statement) = statement else { return None };
+        let module = modules::imported_module(self.files.modules.get(importer)?, statement).ok()?.join(".");
+
+        self.files.modules
+            .iter()
+            .find(|(_, name)| **name == module)
+            .map(|(uri, _)| uri.as_str())
+    }
+
+
+    ///
+    /// The symbol defined at the top level of the file `uri` with the name `name`.
+    ///
+    fn top_level(&self, uri: &str, name: &str) -> Option<Target> {
+        let symbol = *self.index(uri)?.scopes[SymbolIndex::MODULE_SCOPE].names.get(name)?;
+        Some(Target { uri: uri.to_string(), symbol: symbol })
+    }
+
+
+    ///
+    /// The symbol that the name at `line`:`column` of the file `uri` refers to. Imported
+    /// items are followed to where they are defined, unless they are imported under an
+    /// alias and `follow_aliases` is false, in which case the alias is the symbol.
+    ///
+    pub fn target_at(&self, uri: &str, line: u64, column: u32, follow_aliases: bool) -> Option<Target> {
+        let index = self.index(uri)?;
+        let occurrence = index.occurrence_at(line, column)?;
+
+        let target = match (&occurrence.kind, occurrence.symbol) {
+            (_, Some(symbol)) => Target { uri: uri.to_string(), symbol: symbol },
+
+            // `module.name`, where `module` was imported with `use`.
+            (OccurrenceKind::Member { parent: Some(parent) }, None) => {
+                let import = index.symbols[*parent].import.as_ref().filter(|import| import.item.is_none())?;
+                self.top_level(self.imported_uri(uri, &import.statement)?, &occurrence.name)?
+            }
+
+            // The name of an item imported under an alias, e.g. `join` in `use utils::join as j`.
+            (OccurrenceKind::ImportedItem { statement }, None) => {
+                self.top_level(self.imported_uri(uri, statement)?, &occurrence.name)?
+            }
+
+            // A name which may have been imported by `use module::*`.
+            (OccurrenceKind::Name, None) => self.globbed(uri, occurrence.scope, &occurrence.name)?,
+
+            _ => return None
+        };
+
+        Some(self.follow(target, follow_aliases))
+    }
+
+
+    ///
+    /// The symbol that `name` refers to when used within `scope` of the file `uri`,
+    /// followed to where it is defined if it is imported.
+    ///
+    pub fn resolve(&self, uri: &str, scope: ScopeId, name: &str) -> Option<Target> {
+        let target = match self.index(uri)?.lookup(scope, name) {
+            Some(symbol) => Target { uri: uri.to_string(), symbol: symbol },
+            None => self.globbed(uri, scope, name)?
+        };
+
+        Some(self.follow(target, true))
+    }
+
+
+    ///
+    /// The symbol named `name` within a module that is imported into `scope` of the file
+    /// `uri` with `use module::*`.
+    ///
+    fn globbed(&self, uri: &str, scope: ScopeId, name: &str) -> Option<Target> {
+        let index = self.index(uri)?;
+        index
+            .lookup_chain(scope)
+            .into_iter()
+            .flat_map(|scope| index.scopes[scope].globs.iter())
+            .find_map(|statement| self.top_level(self.imported_uri(uri, statement)?, name))
+    }
+
+
+    ///
+    /// The files of the modules imported into `scope` of the file `uri` with `use module::*`.
+    ///
+    pub fn globbed_modules(&self, uri: &str, scope: ScopeId) -> Vec<&str> {
+        let Some(index) = self.index(uri) else {
+            return vec![];
+        };
+
+        index
+            .lookup_chain(scope)
+            .into_iter()
+            .flat_map(|scope| index.scopes[scope].globs.iter())
+            .filter_map(|statement| self.imported_uri(uri, statement))
+            .collect()
+    }
+
+
+    ///
+    /// Follow an imported item to the symbol it refers to within its module, including
+    /// through modules which import it from elsewhere themselves.
+    ///
+    fn follow(&self, target: Target, follow_aliases: bool) -> Target {
+        let mut target = target;
+
+        // Imports may form a cycle, so only follow as many as there are files.
+        for _ in 0..=self.files.modules.len() {
+            let Some(index) = self.index(&target.uri) else { break };
+            let symbol = &index.symbols[target.symbol];
+
+            let Some(import) = &symbol.import else { break };
+            let Some(item) = &import.item else { break };
+            if !follow_aliases && *item != symbol.name {
+                break;
+            }
+
+            let next = self
+                .imported_uri(&target.uri, &import.statement)
+                .and_then(|uri| self.top_level(uri, item));
+
+            match next {
+                Some(next) => target = next,
+                None => break
+            }
+        }
+
+        target
+    }
+
+
+    ///
+    /// Every place where `target` is used throughout the workspace, including where it is
+    /// defined, as the file and span of each.
+    ///
+    pub fn references(&self, target: &Target) -> Vec<(String, Span)> {
+        let Some(index) = self.index(&target.uri) else {
+            return vec![];
+        };
+
+        let mut references: Vec<(String, Span)> = index
+            .occurrences_of(target.symbol)
+            .map(|occurrence| (target.uri.clone(), occurrence.span))
+            .collect();
+
+        // Only names defined at the top level of a module can be imported elsewhere.
+        let symbol = &index.symbols[target.symbol];
+        if symbol.scope != SymbolIndex::MODULE_SCOPE || symbol.kind == SymbolKind::Import {
+            return references;
+        }
+
+        for uri in self.uris() {
+            let Some(importer) = self.index(&uri) else { continue };
+
+            for (import_symbol, importing) in self.importers_of(&uri, target) {
+                let import = importing.import.as_ref().unwrap();
+                let spans = importer.occurrences.iter().filter(|occurrence| match &occurrence.kind {
+                    // The item's own name within the `use` statement.
+                    OccurrenceKind::ImportedItem { statement } => {
+                        Rc::ptr_eq(statement, &import.statement) && occurrence.name == symbol.name
+                    }
+
+                    // `module.name`, where the module is imported as a whole.
+                    OccurrenceKind::Member { parent } => {
+                        import.item.is_none() && *parent == Some(import_symbol) && occurrence.name == symbol.name
+                    }
+
+                    // The imported name, unless it is imported under an alias.
+                    OccurrenceKind::Name => {
+                        import.item.is_some() && importing.name == symbol.name && occurrence.symbol == Some(import_symbol)
+                    }
+                });
+
+                references.extend(spans.map(|occurrence| (uri.clone(), occurrence.span)));
+            }
+
+            // Names brought in by `use module::*`.
+            for occurrence in &importer.occurrences {
+                let globbed = matches!(occurrence.kind, OccurrenceKind::Name)
+                    && occurrence.symbol.is_none()
+                    && occurrence.name == symbol.name
+                    && self.glob_imports(&uri, occurrence.scope, &target.uri);
+
+                if globbed {
+                    references.push((uri.clone(), occurrence.span));
+                }
+            }
+        }
+
+        references.sort_by(|(a_uri, a), (b_uri, b)| (a_uri, a.line, a.column).cmp(&(b_uri, b.line, b.column)));
+        references.dedup();
+        references
+    }
+
+
+    ///
+    /// The imports within the file `uri` which refer to `target`, i.e. imports of the item
+    /// itself and of the module that defines it.
+    ///
+    fn importers_of<'b>(&'b self, uri: &str, target: &Target) -> Vec<(SymbolId, &'b Symbol)> {
+        let Some(index) = self.index(uri) else {
+            return vec![];
+        };
+
+        index.symbols
+            .iter()
+            .enumerate()
+            .filter(|(_, symbol)| {
+                let Some(import) = &symbol.import else { return false };
+                match &import.item {
+                    Some(item) => self.imported_uri(uri, &import.statement).and_then(|module| self.top_level(module, item))
+                        .is_some_and(|imported| self.follow(imported, true) == *target),
+                    None => self.imported_uri(uri, &import.statement) == Some(target.uri.as_str())
+                }
+            })
+            .collect()
+    }
+
+
+    ///
+    /// Whether the names used within `scope` of the file `uri` include everything from the
+    /// file `module`, through `use module::*`.
+    ///
+    fn glob_imports(&self, uri: &str, scope: ScopeId, module: &str) -> bool {
+        let Some(index) = self.index(uri) else {
+            return false;
+        };
+
+        index
+            .lookup_chain(scope)
+            .into_iter()
+            .flat_map(|scope| index.scopes[scope].globs.iter())
+            .any(|statement| self.imported_uri(uri, statement) == Some(module))
+    }
+
+
+    ///
+    /// Check that `target` can be renamed to `new_name` throughout the workspace.
+    ///
+    /// # Returns
+    /// - Why the rename is refused, if it is.
+    ///
+    pub fn check_rename(&self, target: &Target, new_name: &str) -> Result<(), String> {
+        let index = self.index(&target.uri).ok_or("The file is not indexed")?;
+        let symbol = &index.symbols[target.symbol];
+
+        if symbol.import.as_ref().is_some_and(|import| import.item.is_none()) {
+            return Err(String::from("Modules cannot be renamed"));
+        }
+
+        index.check_rename(target.symbol, new_name)?;
+
+        if symbol.scope != SymbolIndex::MODULE_SCOPE {
+            return Ok(());
+        }
+
+        for uri in self.uris() {
+            let Some(importer) = self.index(&uri) else { continue };
+
+            for (import_symbol, importing) in self.importers_of(&uri, target) {
+                let unaliased = importing.import.as_ref().is_some_and(|import| import.item.is_some()) && importing.name == symbol.name;
+                if unaliased {
+                    importer
+                        .check_rename(import_symbol, new_name)
+                        .map_err(|e| format!("{}: {}", uri, e))?;
+                }
+            }
+
+            // Names brought in by `use module::*` must not then refer to something else, and
+            // other names must not then refer to the renamed symbol.
+            let conflict = importer.occurrences.iter().find(|occurrence| {
+                matches!(occurrence.kind, OccurrenceKind::Name)
+                    && occurrence.symbol.is_none()
+                    && self.glob_imports(&uri, occurrence.scope, &target.uri)
+                    && (occurrence.name == symbol.name && importer.lookup(occurrence.scope, new_name).is_some()
+                        || occurrence.name == new_name)
+            });
+
+            if let Some(conflict) = conflict && uri != target.uri {
+                return Err(format!(
+                    "{}: '{}' on line {} would refer to something else once renamed",
+                    uri,
+                    conflict.name,
+                    conflict.span.line + 1
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+
+///
+/// The path of a `file://` URI, decoding any percent-encoded characters.
+///
+pub fn uri_to_path(uri: &str) -> Option<PathBuf> {
+    let path = uri.strip_prefix("file://")?;
+    let bytes = path.as_bytes();
+    let mut decoded = vec![];
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%')
+            .then(|| path.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+
+    String::from_utf8(decoded).ok().map(PathBuf::from)
+}
+
+
+///
+/// The `file://` URI of `path`, percent-encoding any characters which are not allowed
+/// within the path of a URI.
+///
+pub fn path_to_uri(path: &Path) -> String {
+    let mut uri = String::from("file://");
+
+    for byte in path.to_string_lossy().bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'/' | b'-' | b'.' | b'_' | b'~' => uri.push(byte as char),
+            _ => uri.push_str(&format!("%{:02X}", byte))
+        }
+    }
+
+    uri
+}
@@ -1,72 +1,211 @@
-use std::{fs::{self, File}, io::{self, Read, Write}};
+use std::{collections::HashMap, fs::{self, File}, io::Read, path::{Path, PathBuf}};
 use color_eyre::*;
+use transpiler::TranspileOptions;
 
+mod cache;
+mod formatter;
 mod lexer;
+mod lints;
+mod lsp;
+mod manifest;
+mod modules;
 mod parser;
+mod python;
+mod repl;
+mod symbols;
 mod transpiler;
+mod types;
+mod watch;
+
+
+fn run_file(file: &mut File) -> eyre::Result<()> {
+    let mut source = String::new();
+    file.read_to_string(&mut source)?;
+
+    let tree = modules::parse_tree(&source)?;
+    let ast = tree.ast();
+
+    // Modules imported with `use module::*` are not known outside of a project.
+    let index = symbols::SymbolIndex::build(&tree);
+    let diagnostics = lints::lint(&tree, &index, &HashMap::new(), |_| None);
+    for diagnostic in &diagnostics {
+        eprintln!("{}", diagnostic);
+    }
+
+    let errors = diagnostics.iter().filter(|diagnostic| diagnostic.level == manifest::LintLevel::Error).count();
+    if errors > 0 {
+        return Err(eyre::eyre!("Linting failed with {} error(s)", errors));
+    }
+    
+    for translation in transpiler::transpile_program(&ast, &TranspileOptions::default())? {
+        println!("{}", translation);
+    }
+
+    Ok(())
+}
+
 
 ///
-/// Continouously reads lines from the user until the specified exit command
-/// is entered. Then for every line entered, considers that line to be an expression,
-/// and then computes the result_value of that expression.
+/// Parse the arguments shared by `build` and `watch`, i.e. an optional entry module and
+/// `--out <dir>`. Any other `--flags` are returned to the caller.
 /// 
-fn run_repl() {
-    let mut line = String::new();
-    'repl: loop {
-        print!("expr > ");
-        io::stdout().flush().unwrap();
-
-        // Read an expression from the user
-        io::stdin()
-            .read_line(&mut line)
-            .unwrap();
-
-        // If the user entered the quit command, break out of the REPL.
-        if line.trim() == String::from(".quit") {
-            break 'repl;
+fn parse_build_arguments(arguments: &[String]) -> eyre::Result<(modules::BuildTarget, Vec<String>)> {
+    let mut entry = None;
+    let mut out_dir = None;
+    let mut flags = vec![];
+
+    let mut arguments = arguments.iter();
+    while let Some(argument) = arguments.next() {
+        match argument.as_str() {
+            "-o" | "--out" => {
+                let dir = arguments.next().ok_or_else(|| eyre::eyre!("Expected a directory after {}", argument))?;
+                out_dir = Some(PathBuf::from(dir));
+            }
+            flag if flag.starts_with("--") => flags.push(flag.to_string()),
+            _ => entry = Some(PathBuf::from(argument))
         }
+    }
 
-        let lexer_result = lexer::scan_all_tokens(line.trim());
-        if let Err(e) = &lexer_result {
-            eprintln!("{}", e);
+    let target = modules::BuildTarget::new(entry, out_dir)?;
+    Ok((target, flags))
+}
+
+
+///
+/// Transpile a multi-file project, starting from its entry module. Invoked as:
+/// ```sh
+/// nadra build [<entry.ndr>] [--out <dir>]
+/// ```
+/// Every local module reachable from the entry module is written to the output
+/// directory as a Python package layout. Without an entry module, the project's
+/// `nadra.toml` is used to find the entry module and output directory.
+/// 
+fn build_project(arguments: &[String]) -> eyre::Result<()> {
+    let (target, flags) = parse_build_arguments(arguments)?;
+    if let Some(flag) = flags.first() {
+        return Err(eyre::eyre!("Unknown option {}", flag));
+    }
+
+    let report = target.build()?;
+    for warning in &report.warnings {
+        eprintln!("{}", warning);
+    }
+
+    println!(
+        "Built {} module(s), {} up to date, into {}",
+        report.rebuilt.len(),
+        report.module_count - report.rebuilt.len(),
+        target.out_dir.display()
+    );
+    Ok(())
+}
+
+
+///
+/// Rebuild a project whenever its source code changes. Invoked as:
+/// ```sh
+/// nadra watch [<entry.ndr>] [--out <dir>] [--run]
+/// ```
+/// With `--run`, the program is run with `python3` after every successful build.
+/// 
+fn watch_project(arguments: &[String]) -> eyre::Result<()> {
+    let (target, flags) = parse_build_arguments(arguments)?;
+
+    let mut options = watch::WatchOptions::default();
+    for flag in flags {
+        match flag.as_str() {
+            "--run" => options.run = true,
+            _ => return Err(eyre::eyre!("Unknown option {}", flag))
         }
+    }
 
-        let mut token_stream = lexer_result.unwrap();
-        
-        let parser_result = parser::generate_ast(&mut token_stream);
-        if let Err(e) = &parser_result {
-            eprintln!("{}", e);
-            continue 'repl;
+    watch::watch(&target, &options)
+}
+
+
+///
+/// Format Nadra source files in place. Invoked as:
+/// ```sh
+/// nadra fmt [--check] [<file.ndr>]*
+/// ```
+/// Without any files, every `.ndr` file in the current project is formatted. With
+/// `--check`, files are not changed, but the command fails if any are not formatted.
+/// 
+fn format_files(arguments: &[String]) -> eyre::Result<()> {
+    let mut check = false;
+    let mut paths = vec![];
+
+    for argument in arguments {
+        match argument.as_str() {
+            "--check" => check = true,
+            flag if flag.starts_with("--") => return Err(eyre::eyre!("Unknown option {}", flag)),
+            _ => paths.push(PathBuf::from(argument))
         }
+    }
 
-        let ast = parser_result.unwrap();
+    if paths.is_empty() {
+        let project = manifest::Project::find(Path::new("."))?;
+        paths = formatter::find_sources(&project.source_dir())?;
+    }
 
-        for statement in &ast {
-            let output = transpiler::transpile(statement.clone());
-            if let Err(e) = &output {
-                eprintln!("{}", e);
-                continue 'repl;
+    let mut unformatted = 0;
+    let mut failed = 0;
+
+    for path in &paths {
+        // Files outside of a project use the default settings.
+        let settings = path
+            .parent()
+            .and_then(|parent| manifest::Project::find(parent).ok())
+            .map(|project| project.manifest.format)
+            .unwrap_or_default();
+
+        let result = fs::read_to_string(path)
+            .map_err(eyre::Report::from)
+            .and_then(|source| Ok((formatter::format_source(&source, &settings)?, source)));
+
+        let (formatted, source) = match result {
+            Ok(result) => result,
+            Err(e) => {
+                eprintln!("{}: {}", path.display(), e);
+                failed += 1;
+                continue;
             }
-            println!("{}", output.unwrap());
+        };
+
+        if formatted == source {
+            continue;
         }
 
-        line.clear();
+        unformatted += 1;
+        if check {
+            println!("Would reformat {}", path.display());
+        } else {
+            fs::write(path, formatted)?;
+            println!("Formatted {}", path.display());
+        }
     }
-}
 
+    if failed > 0 {
+        return Err(eyre::eyre!("Could not format {} file(s)", failed));
+    }
+    if check && unformatted > 0 {
+        return Err(eyre::eyre!("{} file(s) would be reformatted", unformatted));
+    }
+    Ok(())
+}
 
-fn run_file(file: &mut File) -> eyre::Result<()> {
-    let mut source = String::new();
-    file.read_to_string(&mut source)?;
 
-    let mut tokens = lexer::scan_all_tokens(&source)?;
-    let ast = parser::generate_ast(&mut tokens)?;
-    
-    for file_level_stmt in &ast {
-        let translation = transpiler::transpile(file_level_stmt.clone())?;
-        println!("{}", translation);
-    }
+///
+/// Scaffold a new project. Invoked as:
+/// ```sh
+/// nadra new <name>
+/// ```
+/// 
+fn new_project(arguments: &[String]) -> eyre::Result<()> {
+    let name = arguments.first().ok_or_else(|| eyre::eyre!("Usage: nadra new <name>"))?;
+    let root = manifest::create_project(name)?;
 
+    println!("Created project '{}' in {}", name, root.display());
     Ok(())
 }
 
@@ -79,18 +218,32 @@ fn main() -> eyre::Result<()> {
     let arguments: Vec<String> = std::env::args().collect();
     if arguments.len() < 2 {
         // If no, then run the Nadra repl.
-        run_repl();
+        if let Err(e) = repl::run() {
+            eprintln!("{}", e);
+        }
         return Ok(());
     }
 
-    // If an input file was supplied then compile that file.
-    let source_path = arguments[1].clone();
-    let mut source_file = fs::File::open(source_path)?;
+    let result = match arguments[1].as_str() {
+        "build" => build_project(&arguments[2..]),
+
+        "fmt" => format_files(&arguments[2..]),
+
+        "lsp" => lsp::run(),
+
+        "new" => new_project(&arguments[2..]),
+
+        "watch" => watch_project(&arguments[2..]),
 
-    let result = run_file(&mut source_file);
+        // If an input file was supplied then compile that file.
+        source_path => fs::File::open(source_path)
+            .map_err(eyre::Report::from)
+            .and_then(|mut source_file| run_file(&mut source_file))
+    };
 
     if let Err(e) = &result {
         eprintln!("{}", &e);
+        std::process::exit(1);
     }
     
     Ok(())
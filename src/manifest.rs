@@ -0,0 +1,342 @@
+use core::fmt;
+use std::{collections::HashMap, fs, path::{Path, PathBuf}};
+use color_eyre::eyre::{self, eyre};
+use serde::Deserialize;
+use crate::lints::Lint;
+
+
+/// The file name of a project's manifest.
+pub const MANIFEST_NAME: &str = "nadra.toml";
+
+
+/// 
+/// # Overview
+/// The contents of a project's `nadra.toml`. For example,
+/// ```toml
+/// [project]
+/// name = "hello"
+/// entry = "main"
+/// source = "src"
+/// output = "build"
+/// python = "3.11"
+/// 
+/// [format]
+/// indent_width = 4
+/// 
+/// [lint]
+/// unused_variables = "warn"
+/// ```
+/// 
+/// # Members
+/// - `project` - Where the project's code lives, and what it is compiled for.
+/// - `format` - Settings for the formatter.
+/// - `lint` - The level of each lint, by name.
+/// 
+#[derive(Debug, Deserialize)]
+pub struct Manifest {
+    pub project: ProjectSettings,
+
+    #[serde(default)]
+    pub format: FormatSettings,
+
+    #[serde(default)]
+    pub lint: HashMap<String, LintLevel>
+}
+
+
+/// 
+/// The `[project]` table of a manifest.
+/// 
+/// # Members
+/// - `name` - The name of the project.
+/// - `entry` - The dotted name of the entry module, relative to `source`.
+/// - `source` - The directory containing the project's `.ndr` files.
+/// - `output` - The directory the generated Python code is written to.
+/// - `python` - The version of Python the generated code targets.
+/// - `annotate` - Whether the generated code gives variables the types inferred for them,
+/// e.g. `x: int = 0`.
+/// 
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProjectSettings {
+    pub name: String,
+
+    #[serde(default = "default_entry")]
+    pub entry: String,
+
+    #[serde(default = "default_source")]
+    pub source: PathBuf,
+
+    #[serde(default = "default_output")]
+    pub output: PathBuf,
+
+    #[serde(default)]
+    pub python: PythonVersion,
+
+    #[serde(default)]
+    pub annotate: bool
+}
+
+
+fn default_entry() -> String {
+    String::from("main")
+}
+
+
+fn default_source() -> PathBuf {
+    PathBuf::from("src")
+}
+
+
+fn default_output() -> PathBuf {
+    PathBuf::from("build")
+}
+
+
+/// 
+/// The `[format]` table of a manifest.
+/// 
+/// # Members
+/// - `indent_width` - The number of spaces per level of indentation.
+/// - `max_blank_lines` - The most consecutive blank lines that are kept.
+/// 
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FormatSettings {
+    pub indent_width: usize,
+    pub max_blank_lines: usize
+}
+
+
+impl Default for FormatSettings {
+    fn default() -> Self {
+        Self {
+            indent_width: 4,
+            max_blank_lines: 2
+        }
+    }
+}
+
+
+/// 
+/// How a lint is reported, if at all.
+/// 
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LintLevel {
+    #[serde(alias = "off")]
+    Allow,
+    Warn,
+    Error
+}
+
+
+impl fmt::Display for LintLevel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Allow => write!(f, "allow"),
+            Self::Warn => write!(f, "warning"),
+            Self::Error => write!(f, "error")
+        }
+    }
+}
+
+
+/// 
+/// A version of Python, e.g. `3.11`, which is written as a string in the manifest.
+/// 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+#[serde(try_from = "String")]
+pub struct PythonVersion {
+    pub major: u32,
+    pub minor: u32
+}
+
+
+impl PythonVersion {
+    pub const fn new(major: u32, minor: u32) -> Self {
+        Self { major: major, minor: minor }
+    }
+}
+
+
+impl Default for PythonVersion {
+    fn default() -> Self {
+        Self::new(3, 10)
+    }
+}
+
+
+impl TryFrom<String> for PythonVersion {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        let invalid = || format!("Invalid Python version '{}', expected e.g. \"3.11\"", value);
+
+        let (major, minor) = value.split_once('.').ok_or_else(invalid)?;
+        let major = major.parse().map_err(|_| invalid())?;
+        let minor = minor.parse().map_err(|_| invalid())?;
+
+        if major != 3 {
+            return Err(format!("Only Python 3 is supported, found {}", value));
+        }
+
+        Ok(Self::new(major, minor))
+    }
+}
+
+
+impl fmt::Display for PythonVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}", self.major, self.minor)
+    }
+}
+
+
+/// 
+/// # Overview
+/// A project on disk, i.e. a directory containing a `nadra.toml`.
+/// 
+/// # Members
+/// - `root` - The directory containing the manifest.
+/// - `manifest` - The parsed manifest.
+/// 
+pub struct Project {
+    pub root: PathBuf,
+    pub manifest: Manifest
+}
+
+
+impl Project {
+    /// 
+    /// Find the project containing `directory` by searching it and its ancestors
+    /// for a `nadra.toml`.
+    /// 
+    pub fn find(directory: &Path) -> eyre::Result<Self> {
+        let directory = directory.canonicalize()?;
+
+        let root = directory
+            .ancestors()
+            .find(|ancestor| ancestor.join(MANIFEST_NAME).is_file())
+            .ok_or_else(|| eyre!("Could not find {} in {} or any parent directory", MANIFEST_NAME, directory.display()))?;
+
+        Self::load(root)
+    }
+
+
+    /// 
+    /// Load the project whose manifest is in `root`.
+    /// 
+    pub fn load(root: &Path) -> eyre::Result<Self> {
+        let path = root.join(MANIFEST_NAME);
+        let contents = fs::read_to_string(&path)
+            .map_err(|e| eyre!("{}: {}", path.display(), e))?;
+
+        let manifest: Manifest = toml::from_str(&contents)
+            .map_err(|e| eyre!("{}: {}", path.display(), e))?;
+
+        check_project_name(&manifest.project.name)
+            .map_err(|e| eyre!("{}: {}", path.display(), e))?;
+
+        if let Some(unknown) = manifest.lint.keys().find(|name| Lint::from_name(name).is_none()) {
+            return Err(eyre!("{}: Unknown lint '{}'", path.display(), unknown));
+        }
+
+        Ok(Self {
+            root: root.to_path_buf(),
+            manifest: manifest
+        })
+    }
+
+
+    pub fn source_dir(&self) -> PathBuf {
+        self.root.join(&self.manifest.project.source)
+    }
+
+
+    pub fn output_dir(&self) -> PathBuf {
+        self.root.join(&self.manifest.project.output)
+    }
+
+
+    /// 
+    /// The path of the entry module, e.g. `src/main.ndr`.
+    /// 
+    pub fn entry_path(&self) -> PathBuf {
+        let mut path = self.source_dir();
+        path.extend(self.manifest.project.entry.split('.'));
+        path.set_extension("ndr");
+        path
+    }
+}
+
+
+/// 
+/// Ensure that `name` can be used as the name of a project, i.e. that it is made of letters,
+/// digits, `_` and `-`, so that it is also a valid directory name.
+/// 
+fn check_project_name(name: &str) -> eyre::Result<()> {
+    let is_valid_name = name
+        .chars()
+        .all(|ch| ch.is_alphanumeric() || ch == '_' || ch == '-');
+
+    if name.is_empty() || !is_valid_name {
+        return Err(eyre!("Invalid project name '{}'", name));
+    }
+
+    Ok(())
+}
+
+
+/// 
+/// Scaffold a new project called `name` in a new directory of the same name:
+/// ```
+/// <name>
+/// ├── .gitignore
+/// ├── nadra.toml
+/// └── src
+///     └── main.ndr
+/// ```
+/// 
+pub fn create_project(name: &str) -> eyre::Result<PathBuf> {
+    check_project_name(name)?;
+
+    let root = PathBuf::from(name);
+    if root.exists() {
+        return Err(eyre!("{} already exists", root.display()));
+    }
+
+    fs::create_dir_all(root.join("src"))?;
+
+    let manifest = format!(
+"[project]
+name = \"{}\"
+entry = \"main\"
+source = \"src\"
+output = \"build\"
+python = \"{}\"
+
+[format]
+indent_width = 4
+max_blank_lines = 2
+
+[lint]
+", name, PythonVersion::default());
+
+    let main = "\
+def main() -> None
+    print(\"Hello, World!\")
+enddef
+
+
+if __name__ == \"__main__\" then
+    main()
+endif
+";
+
+    fs::write(root.join(MANIFEST_NAME), manifest)?;
+    fs::write(root.join("src").join("main.ndr"), main)?;
+    fs::write(root.join(".gitignore"), "build/\n")?;
+
+    Ok(root)
+}
@@ -0,0 +1,711 @@
+use std::{collections::HashMap, fs, path::{Path, PathBuf}, rc::Rc};
+use color_eyre::eyre::{self, eyre};
+use crate::{
+    cache::{self, BuildCache, CacheEntry},
+    lexer,
+    lints::{self, Diagnostic, Lint},
+    manifest::{LintLevel, Project, PythonVersion},
+    parser::{self, ast::*, cst::SyntaxTree},
+    symbols::SymbolIndex,
+    transpiler::{self, PayloadVariant, TranspileOptions},
+    types
+};
+
+
+/// 
+/// Options which affect the Python code generated for a project.
+/// 
+/// # Fields
+/// - `python` - The version of Python to generate code for.
+/// - `annotate` - Whether variables are given the type annotations inferred for them.
+/// 
+#[derive(Debug, Clone, Default)]
+pub struct BuildOptions {
+    pub python: PythonVersion,
+    pub annotate: bool
+}
+
+
+impl BuildOptions {
+    /// 
+    /// A string identifying these options, used to invalidate the build cache
+    /// when they change.
+    /// 
+    pub fn fingerprint(&self) -> String {
+        format!("python={};annotate={}", self.python, self.annotate)
+    }
+
+
+    /// 
+    /// The options to transpile each module with, before any annotations are inferred or
+    /// the enums of its dependencies are known.
+    /// 
+    pub fn transpile_options(&self) -> TranspileOptions {
+        TranspileOptions {
+            python: self.python,
+            annotations: HashMap::new(),
+            enums: HashMap::new()
+        }
+    }
+}
+
+
+/// 
+/// What to build and where to, as given on the command line or by a project's manifest.
+/// 
+/// # Fields
+/// - `root` - The directory that absolute module paths are resolved against.
+/// - `entry` - The path to the entry module.
+/// - `out_dir` - Where the generated Python code is written.
+/// - `options` - Options which affect the generated code.
+/// - `lints` - The level of each lint, as set by the project's manifest.
+/// 
+pub struct BuildTarget {
+    pub root: PathBuf,
+    pub entry: PathBuf,
+    pub out_dir: PathBuf,
+    pub options: BuildOptions,
+    pub lints: HashMap<String, LintLevel>
+}
+
+
+/// 
+/// The outcome of a successful build.
+/// 
+/// # Fields
+/// - `rebuilt` - The names of the modules which were rebuilt.
+/// - `module_count` - The number of modules in the project.
+/// - `warnings` - The problems found by lints set to warn, e.g. `main.ndr:3:5: warning: ...`.
+/// 
+pub struct BuildReport {
+    pub rebuilt: Vec<String>,
+    pub module_count: usize,
+    pub warnings: Vec<String>
+}
+
+
+impl BuildTarget {
+    /// 
+    /// Work out what to build. If no `entry` module is given, the `nadra.toml` of the project
+    /// containing the working directory is used. Otherwise the project root is the directory
+    /// containing `entry`, and the output is written to `build` within it by default.
+    /// 
+    pub fn new(entry: Option<PathBuf>, out_dir: Option<PathBuf>) -> eyre::Result<Self> {
+        let target = match entry {
+            Some(entry) => {
+                let entry = entry.canonicalize()?;
+                let root = entry.parent().unwrap_or(Path::new(".")).to_path_buf();
+
+                Self {
+                    out_dir: out_dir.unwrap_or_else(|| root.join("build")),
+                    root: root,
+                    entry: entry,
+                    options: BuildOptions::default(),
+                    lints: HashMap::new()
+                }
+            }
+
+            None => {
+                let project = Project::find(Path::new("."))?;
+
+                Self {
+                    root: project.source_dir(),
+                    entry: project.entry_path(),
+                    out_dir: out_dir.unwrap_or_else(|| project.output_dir()),
+                    options: BuildOptions {
+                        python: project.manifest.project.python,
+                        annotate: project.manifest.project.annotate
+                    },
+                    lints: project.manifest.lint
+                }
+            }
+        };
+
+        Ok(target)
+    }
+
+
+    /// 
+    /// Build the target, only rebuilding the modules which changed since the last build.
+    /// Every module is linted first, and nothing is built if any lint set to `error`
+    /// finds a problem.
+    /// 
+    pub fn build(&self) -> eyre::Result<BuildReport> {
+        let cache = BuildCache::load(&self.out_dir, &self.options.fingerprint());
+        let mut graph = ModuleGraph::load(&self.root, &self.entry, cache)?;
+
+        let mut warnings = vec![];
+        let mut errors = vec![];
+
+        for (path, diagnostic) in graph.lint(&self.lints)? {
+            let message = format!("{}:{}", path.display(), diagnostic);
+            match diagnostic.level {
+                LintLevel::Error => errors.push(message),
+                _ => warnings.push(message)
+            }
+        }
+
+        if !errors.is_empty() {
+            let count = errors.len();
+            errors.extend(warnings);
+            return Err(eyre!("{}\nLinting failed with {} error(s)", errors.join("\n"), count));
+        }
+
+        let rebuilt = graph.build(&self.out_dir, &self.options)?;
+
+        Ok(BuildReport {
+            rebuilt: rebuilt,
+            module_count: graph.order.len(),
+            warnings: warnings
+        })
+    }
+
+
+    /// 
+    /// Where the Python code of the entry module is written.
+    /// 
+    pub fn entry_output(&self) -> eyre::Result<PathBuf> {
+        let name = module_name(&self.root, &self.entry)?;
+        let segments: Vec<&str> = name.split('.').collect();
+        Ok(output_path(&self.out_dir, &segments))
+    }
+}
+
+
+/// 
+/// A single Nadra source file within a project.
+/// 
+/// # Fields
+/// - `name` - The dotted name of the module, e.g. `utils.strings` for `utils/strings.ndr`.
+/// - `source_path` - Where the source code of the module is located.
+/// - `source` - The source code of the module.
+/// - `source_hash` - The hash of `source`.
+/// - `interface_hash` - The hash of the module's interface. See [`cache::interface_hash`].
+/// - `ast` - The top-level statements of the module. This is `None` if the module has not
+/// changed since it was last built, in which case it is only parsed if it must be rebuilt.
+/// - `dependencies` - The names of the local modules imported by this module.
+/// - `lint_key` - The hash of everything the lints of the module depend on, once linted.
+/// - `diagnostics` - The problems found by linting the module.
+/// 
+pub struct Module {
+    pub name: String,
+    pub source_path: PathBuf,
+    pub source: String,
+    pub source_hash: u64,
+    pub interface_hash: u64,
+    pub ast: Option<Vec<Rc<AstNode>>>,
+    pub dependencies: Vec<String>,
+    pub lint_key: u64,
+    pub diagnostics: Vec<Diagnostic>
+}
+
+
+impl Module {
+    /// 
+    /// The segments of the dotted module name, e.g. `["utils", "strings"]`.
+    /// 
+    pub fn segments(&self) -> Vec<&str> {
+        self.name.split('.').collect()
+    }
+
+
+    /// 
+    /// Get the AST of the module, parsing it first if it has not been parsed yet.
+    /// 
+    pub fn parse(&mut self) -> eyre::Result<&[Rc<AstNode>]> {
+        if self.ast.is_none() {
+            self.ast = Some(parse_source(&self.source)?);
+        }
+        Ok(self.ast.as_deref().unwrap())
+    }
+}
+
+
+/// 
+/// The graph of every module reachable from the entry module of a project via
+/// `use` statements. Imports that do not resolve to a `.ndr` file within the project
+/// (e.g. `use os`) are assumed to be Python modules, and are left to Python.
+/// 
+/// # Fields
+/// - `root` - The directory that absolute module paths are resolved against.
+/// - `modules` - Every reachable module, by name.
+/// - `order` - The names of the modules such that each module comes after its dependencies.
+/// - `cache` - What is known about each module from the previous build.
+/// 
+pub struct ModuleGraph {
+    pub root: PathBuf,
+    pub modules: HashMap<String, Module>,
+    pub order: Vec<String>,
+    pub cache: BuildCache
+}
+
+
+/// 
+/// The state of a module during the depth-first search used to load the graph.
+/// 
+#[derive(PartialEq)]
+enum VisitState {
+    InProgress, Done
+}
+
+
+impl ModuleGraph {
+    /// 
+    /// Load the module at `entry` and every local module it (transitively) depends on.
+    /// 
+    /// # Params
+    /// - `root` - The root directory of the project's source code.
+    /// - `entry` - The path to the entry module, which must be within `root`.
+    /// - `cache` - The cache of the previous build. Modules whose source code has not changed
+    /// since are not parsed.
+    /// 
+    /// # Returns
+    /// - An error if any module fails to parse, or if the imports form a cycle.
+    /// 
+    pub fn load(root: &Path, entry: &Path, cache: BuildCache) -> eyre::Result<Self> {
+        let entry_name = module_name(root, entry)?;
+
+        let mut graph = Self {
+            root: root.to_path_buf(),
+            modules: HashMap::new(),
+            order: vec![],
+            cache: cache
+        };
+
+        let mut states = HashMap::new();
+        let mut stack = vec![];
+        graph.visit(&entry_name, &mut states, &mut stack)?;
+
+        Ok(graph)
+    }
+
+
+    /// 
+    /// Load `name` and its dependencies, failing if `name` is already on `stack`, i.e.
+    /// it (indirectly) imports itself.
+    /// 
+    fn visit(&mut self, name: &str, states: &mut HashMap<String, VisitState>, stack: &mut Vec<String>) -> eyre::Result<()> {
+        match states.get(name) {
+            Some(VisitState::Done) => return Ok(()),
+
+            Some(VisitState::InProgress) => {
+                let start = stack.iter().position(|module| module == name).unwrap();
+                let mut cycle = stack[start..].to_vec();
+                cycle.push(name.to_string());
+                return Err(eyre!("Import cycle detected: {}", cycle.join(" -> ")));
+            }
+
+            None => {}
+        }
+
+        states.insert(name.to_string(), VisitState::InProgress);
+        stack.push(name.to_string());
+
+        let module = self.parse_module(name)?;
+        for dependency in &module.dependencies {
+            self.visit(dependency, states, stack)?;
+        }
+
+        stack.pop();
+        states.insert(name.to_string(), VisitState::Done);
+        self.order.push(name.to_string());
+        self.modules.insert(name.to_string(), module);
+
+        Ok(())
+    }
+
+
+    /// 
+    /// Read and parse the module `name`, and resolve its local dependencies. If the source
+    /// code of the module is unchanged since the last build, what is known about it is taken
+    /// from the cache instead.
+    /// 
+    fn parse_module(&self, name: &str) -> eyre::Result<Module> {
+        let source_path = self.source_path(name);
+        let source = fs::read_to_string(&source_path)
+            .map_err(|e| eyre!("{}: {}", source_path.display(), e))?;
+
+        let source_hash = cache::hash_bytes(source.as_bytes());
+
+        if let Some(entry) = self.cache.get(name) && entry.source_hash == source_hash {
+            return Ok(Module {
+                name: name.to_string(),
+                source_path: source_path,
+                source: source,
+                source_hash: source_hash,
+                interface_hash: entry.interface_hash,
+                ast: None,
+                dependencies: entry.dependencies.clone(),
+                lint_key: 0,
+                diagnostics: vec![]
+            });
+        }
+
+        let ast = parse_source(&source)
+            .map_err(|e| eyre!("{}: {}", source_path.display(), e))?;
+
+        let mut dependencies = vec![];
+        for statement in &ast {
+            if let AstNode::UseStatement(use_statement) = statement.as_ref() {
+                let resolved = self.resolve(name, use_statement)
+                    .map_err(|e| eyre!("{}: {}", source_path.display(), e))?;
+
+                for dependency in resolved {
+                    if !dependencies.contains(&dependency) {
+                        dependencies.push(dependency);
+                    }
+                }
+            }
+        }
+
+        Ok(Module {
+            name: name.to_string(),
+            source_path: source_path,
+            source: source,
+            source_hash: source_hash,
+            interface_hash: cache::interface_hash(&ast),
+            ast: Some(ast),
+            dependencies: dependencies,
+            lint_key: 0,
+            diagnostics: vec![]
+        })
+    }
+
+
+    /// 
+    /// Find the local modules imported by `statement`, a `use` statement within `importer`.
+    /// For example, `use utils::{strings, join}` depends on `utils.ndr` if it exists, or
+    /// otherwise on `utils/strings.ndr` (`join` not being a module).
+    /// 
+    fn resolve(&self, importer: &str, statement: &UseStatement) -> eyre::Result<Vec<String>> {
+        let module = imported_module(importer, statement)?;
+
+        if !module.is_empty() && self.exists(&module.join(".")) {
+            return Ok(vec![module.join(".")]);
+        }
+
+        // Items may themselves be modules within a package, e.g. `use pkg::{a, b}`.
+        let mut dependencies = vec![];
+        if let UseImports::Items(items) = &statement.imports {
+            for item in items {
+                let mut submodule = module.clone();
+                submodule.push(item.name.clone());
+
+                if self.exists(&submodule.join(".")) {
+                    dependencies.push(submodule.join("."));
+                }
+            }
+        }
+
+        Ok(dependencies)
+    }
+
+
+    /// 
+    /// Where the source code of the module `name` is expected to be.
+    /// 
+    fn source_path(&self, name: &str) -> PathBuf {
+        let mut path = self.root.clone();
+        path.extend(name.split('.'));
+        path.set_extension("ndr");
+        path
+    }
+
+
+    fn exists(&self, name: &str) -> bool {
+        self.source_path(name).is_file()
+    }
+
+
+    /// 
+    /// Transpile every module to Python, writing each to the matching location within
+    /// `out_dir`. E.g. `utils/strings.ndr` is written to `<out_dir>/utils/strings.py`, and
+    /// `<out_dir>/utils/__init__.py` is created so that `utils` is a Python package.
+    /// 
+    /// Modules whose source code and dependency interfaces are unchanged since the last
+    /// build are skipped, and the updated cache is then saved to `out_dir`.
+    /// 
+    /// # Returns
+    /// - The names of the modules which were rebuilt.
+    /// 
+    pub fn build(&mut self, out_dir: &Path, options: &BuildOptions) -> eyre::Result<Vec<String>> {
+        let mut rebuilt = vec![];
+
+        for name in self.order.clone() {
+            let build_key = self.build_key(&name);
+            let output_path = output_path(out_dir, &self.modules[&name].segments());
+
+            let up_to_date = self.cache
+                .get(&name)
+                .is_some_and(|entry| entry.build_key == build_key);
+
+            let mut transpile_options = None;
+            if !up_to_date || !output_path.is_file() {
+                transpile_options = Some(TranspileOptions {
+                    enums: self.imported_enums(&name)?,
+                    ..options.transpile_options()
+                });
+            }
+
+            let module = self.modules.get_mut(&name).unwrap();
+            if let Some(transpile_options) = transpile_options {
+                let python = match options.annotate {
+                    true => transpile_annotated(&module.source, transpile_options),
+                    false => module.parse().and_then(|ast| transpile_module(ast, &transpile_options))
+                };
+                let python = python.map_err(|e| eyre!("{}: {}", module.source_path.display(), e))?;
+
+                write_module(out_dir, &module.segments(), &python)?;
+                rebuilt.push(name.clone());
+            }
+
+            let entry = CacheEntry {
+                source_hash: module.source_hash,
+                interface_hash: module.interface_hash,
+                build_key: build_key,
+                lint_key: module.lint_key,
+                diagnostics: module.diagnostics.clone(),
+                dependencies: module.dependencies.clone()
+            };
+            self.cache.insert(&name, entry);
+        }
+
+        self.cache.save(out_dir)?;
+        Ok(rebuilt)
+    }
+
+
+    /// 
+    /// The variants of each enum with payloads defined by the modules that the module
+    /// `name` depends on, by the name of the enum.
+    /// 
+    fn imported_enums(&mut self, name: &str) -> eyre::Result<HashMap<String, Vec<PayloadVariant>>> {
+        let mut enums = HashMap::new();
+        for dependency in self.modules[name].dependencies.clone() {
+            let module = self.modules.get_mut(&dependency).unwrap();
+            let source_path = module.source_path.clone();
+            let ast = module.parse().map_err(|e| eyre!("{}: {}", source_path.display(), e))?;
+            enums.extend(transpiler::payload_enums(ast));
+        }
+        Ok(enums)
+    }
+
+
+    /// 
+    /// Lint every module, with the lint levels `levels`. Modules whose lint key is unchanged
+    /// since the last build are not linted again, and the problems found then are reused.
+    /// 
+    /// # Returns
+    /// - The path of the module and the problem, for every problem found.
+    /// 
+    pub fn lint(&mut self, levels: &HashMap<String, LintLevel>) -> eyre::Result<Vec<(PathBuf, Diagnostic)>> {
+        let mut stale = vec![];
+        for name in self.order.clone() {
+            let lint_key = self.lint_key(&name, levels);
+            let cached = self.cache
+                .get(&name)
+                .filter(|entry| entry.lint_key == lint_key)
+                .map(|entry| entry.diagnostics.clone());
+
+            let module = self.modules.get_mut(&name).unwrap();
+            module.lint_key = lint_key;
+            match cached {
+                Some(diagnostics) => module.diagnostics = diagnostics,
+                None => stale.push(name)
+            }
+        }
+
+        // Linting a module needs the names defined by the modules it imports.
+        let mut trees = HashMap::new();
+        let mut indices = HashMap::new();
+        for name in &stale {
+            for name in self.modules[name].dependencies.iter().chain([name]) {
+                if indices.contains_key(name) {
+                    continue;
+                }
+
+                let module = &self.modules[name];
+                let tree = parse_tree(&module.source)
+                    .map_err(|e| eyre!("{}: {}", module.source_path.display(), e))?;
+
+                indices.insert(name.clone(), SymbolIndex::build(&tree));
+                trees.insert(name.clone(), tree);
+            }
+        }
+
+        for name in &stale {
+            // Only the modules within the project are known.
+            let diagnostics = lints::lint(&trees[name], &indices[name], levels, |statement| {
+                let AstNode::UseStatement(statement) = statement else { return None };
+                indices.get(&imported_module(name, statement).ok()?.join("."))
+            });
+            self.modules.get_mut(name).unwrap().diagnostics = diagnostics;
+        }
+
+        let mut diagnostics = vec![];
+        for name in &self.order {
+            let module = &self.modules[name];
+            for diagnostic in &module.diagnostics {
+                diagnostics.push((module.source_path.clone(), diagnostic.clone()));
+            }
+        }
+
+        Ok(diagnostics)
+    }
+
+
+    /// 
+    /// Hash everything the lints of the module `name` depend on, i.e. its build key and the
+    /// level of each lint within `levels`.
+    /// 
+    fn lint_key(&self, name: &str, levels: &HashMap<String, LintLevel>) -> u64 {
+        let mut key = format!("{:016x}", self.build_key(name));
+        for lint in Lint::ALL {
+            key.push_str(&format!(" {}={}", lint.name(), lint.level(levels)));
+        }
+
+        cache::hash_bytes(key.as_bytes())
+    }
+
+
+    /// 
+    /// Hash everything the Python code generated for the module `name` depends on, i.e. its
+    /// own source code and the interfaces of the modules it imports.
+    /// 
+    fn build_key(&self, name: &str) -> u64 {
+        let module = &self.modules[name];
+
+        let mut key = format!("{:016x}", module.source_hash);
+        for dependency in &module.dependencies {
+            key.push_str(&format!(" {}={:016x}", dependency, self.modules[dependency].interface_hash));
+        }
+
+        cache::hash_bytes(key.as_bytes())
+    }
+}
+
+
+/// 
+/// The absolute name of the module imported by `statement`, a `use` statement within the
+/// module `importer`, split into its segments. For example, `use ..utils::join` within
+/// `app.ui.main` imports from `app.utils`.
+/// 
+pub fn imported_module(importer: &str, statement: &UseStatement) -> eyre::Result<Vec<String>> {
+    let mut module: Vec<String> = vec![];
+
+    // Relative imports start from the package containing `importer`.
+    if statement.level > 0 {
+        let package: Vec<&str> = importer.split('.').collect();
+        let depth = package.len() - 1;
+
+        if statement.level - 1 > depth {
+            return Err(eyre!("Relative import '{}' goes beyond the project root", statement.module_name()));
+        }
+
+        module.extend(package[..depth + 1 - statement.level].iter().map(|s| s.to_string()));
+    }
+
+    module.extend(statement.path.iter().cloned());
+    Ok(module)
+}
+
+
+/// 
+/// Compute the dotted module name of the file at `path` relative to `root`.
+/// 
+pub fn module_name(root: &Path, path: &Path) -> eyre::Result<String> {
+    let relative = path
+        .strip_prefix(root)
+        .map_err(|_| eyre!("{} is not within {}", path.display(), root.display()))?;
+
+    let segments = relative
+        .with_extension("")
+        .iter()
+        .map(|segment| segment.to_string_lossy().to_string())
+        .collect::<Vec<String>>();
+
+    Ok(segments.join("."))
+}
+
+
+/// 
+/// Lex and parse a module's source code.
+/// 
+pub fn parse_source(source: &str) -> eyre::Result<Vec<Rc<AstNode>>> {
+    let mut tokens = lexer::scan_all_tokens(source)?;
+    parser::generate_ast(&mut tokens)
+}
+
+
+/// 
+/// Lex and parse a module's source code into its concrete syntax tree.
+/// 
+pub fn parse_tree(source: &str) -> eyre::Result<SyntaxTree> {
+    let mut tokens = lexer::scan_all_tokens(source)?;
+    parser::parse(&mut tokens)
+}
+
+
+/// 
+/// Transpile each top-level statement of a module to Python.
+/// 
+pub fn transpile_module(ast: &[Rc<AstNode>], options: &TranspileOptions) -> eyre::Result<String> {
+    let mut python = String::new();
+    for statement in transpiler::transpile_program(ast, options)? {
+        python.push_str(&statement);
+        python.push('\n');
+    }
+    Ok(python)
+}
+
+
+/// 
+/// Transpile the module with the source code `source` to Python, annotating each
+/// variable where it is first assigned to with the type inferred for it.
+/// 
+fn transpile_annotated(source: &str, options: TranspileOptions) -> eyre::Result<String> {
+    let tree = parse_tree(source)?;
+    let analysis = types::check(&tree, &SymbolIndex::build(&tree));
+
+    let options = TranspileOptions {
+        annotations: analysis.annotations(),
+        ..options
+    };
+    transpile_module(&tree.ast(), &options)
+}
+
+
+/// 
+/// Where the Python code of the module with the name `segments` is written within `out_dir`.
+/// 
+fn output_path(out_dir: &Path, segments: &[&str]) -> PathBuf {
+    let mut path = out_dir.to_path_buf();
+    path.extend(segments);
+    path.set_extension("py");
+    path
+}
+
+
+/// 
+/// Write the Python code of the module with the name `segments` to `out_dir`, creating
+/// any packages it belongs to.
+/// 
+fn write_module(out_dir: &Path, segments: &[&str], python: &str) -> eyre::Result<()> {
+    let (_, packages) = segments.split_last().unwrap();
+
+    let mut directory = out_dir.to_path_buf();
+    fs::create_dir_all(&directory)?;
+
+    for package in packages {
+        directory.push(package);
+        fs::create_dir_all(&directory)?;
+
+        let init = directory.join("__init__.py");
+        if !init.exists() {
+            fs::write(&init, "")?;
+        }
+    }
+
+    fs::write(output_path(out_dir, segments), python)?;
+    Ok(())
+}
@@ -1,5 +1,5 @@
 use core::fmt;
-use std::{fmt::write, rc::Rc};
+use std::rc::Rc;
 
 use crate::lexer::{PieToken, TokenType};
 
@@ -12,15 +12,31 @@ pub enum AstNode {
     IntegerLiteral(i64), FloatLiteral(f64), BooleanLiteral(bool),
     StringLiteral(String), Identifier(String),
 
+    ListLiteral(Vec<Rc<AstNode>>), TupleLiteral(Vec<Rc<AstNode>>),
+    SetLiteral(Vec<Rc<AstNode>>), DictLiteral(Vec<DictEntry>),
+    Unpack(Unpack),
+
     MemberAccess(MemberAccess),
+    ScopeAccess(ScopeAccess),
+    Subscript(Subscript),
+    Slice(Slice),
+
+    KeywordArgument(KeywordArgument),
 
-    LambdaFunction { params: Vec<Rc<AstNode>>, body: Vec<Rc<AstNode>> },
+    // Placeholder until lambdas are parsed.
+    #[allow(dead_code)]
+    LambdaFunction { params: Vec<Parameter>, body: Vec<Rc<AstNode>> },
     FunctionDefinition(FunctionDefinition),
+    StructDefinition(StructDefinition),
+    InterfaceDefinition(InterfaceDefinition),
+    EnumDefinition(EnumDefinition),
 
     IfStatement(IfStatement),
     ReturnStatement(ReturnStatement),
     UseStatement(UseStatement),
-    WhileLoop(WhileLoop)
+    WhileLoop(WhileLoop),
+    MatchStatement(MatchStatement),
+    Declaration(Declaration)
 }
 
 
@@ -37,12 +53,41 @@ impl fmt::Display for AstNode {
 
             Self::FunctionCall(func) => write!(f, "{}", func),
 
+            Self::MemberAccess(access) => write!(f, "{}", access),
+
+            Self::ScopeAccess(access) => write!(f, "{}", access),
+
+            Self::StructDefinition(definition) => write!(f, "{}", definition),
+
+            Self::InterfaceDefinition(definition) => write!(f, "{}", definition),
+
+            Self::EnumDefinition(definition) => write!(f, "{}", definition),
+
+            Self::Subscript(subscript) => write!(f, "{}", subscript),
+
+            Self::Unpack(unpack) => write!(f, "{}", unpack),
+
+            Self::Slice(slice) => write!(f, "{}", slice),
+
+            Self::KeywordArgument(argument) => write!(f, "{}", argument),
+
+            Self::Declaration(declaration) => write!(f, "{}", declaration),
+
+            Self::MatchStatement(statement) => write!(f, "{}", statement),
+
             _ => write!(f, "{:?}", self)
         }
     }
 }
 
 
+///
+/// A structure representing attribute access on the AST, i.e. `parent.child`.
+/// 
+/// # Fields
+/// - `parent` - The expression whose attribute is being accessed.
+/// - `child` - The identifier of the attribute.
+/// 
 #[derive(Debug)]
 pub struct MemberAccess {
     pub parent: Rc<AstNode>,
@@ -57,19 +102,192 @@ impl fmt::Display for MemberAccess {
 }
 
 
+///
+/// A structure representing access to an item within a type on the AST, i.e.
+/// `parent::name`. For example, `RenderModes::Minimal` accesses a variant of an enum.
+/// 
+/// # Fields
+/// - `parent` - The expression whose item is being accessed.
+/// - `name` - The name of the item.
+/// 
+#[derive(Debug)]
+pub struct ScopeAccess {
+    pub parent: Rc<AstNode>,
+    pub name: String
+}
+
+
+impl fmt::Display for ScopeAccess {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "(ScopeAccess {}::{})", self.parent, self.name)
+    }
+}
+
+
+///
+/// A structure representing a subscript on the AST, i.e. `value[index]`.
+/// 
+/// # Fields
+/// - `value` - The expression being subscripted.
+/// - `index` - The index or key within the brackets. This may be a `Slice`, or a
+/// `TupleLiteral` when several comma separated indices are given, e.g. `grid[i, j]`.
+/// 
+#[derive(Debug)]
+pub struct Subscript {
+    pub value: Rc<AstNode>,
+    pub index: Rc<AstNode>
+}
+
+
+impl fmt::Display for Subscript {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "(Subscript {} {})", self.value, self.index)
+    }
+}
+
+
+///
+/// A structure representing a slice within a subscript on the AST. Each bound may be
+/// omitted. For example,
+/// ```nadra
+/// s[1:-1:2]
+/// xs[1..3]    # The same as xs[1:3]
+/// ```
+/// 
+/// # Fields
+/// - `lower` - The first index included in the slice.
+/// - `upper` - The index at which the slice ends (exclusive).
+/// - `step` - The distance between consecutive indices.
+/// 
+#[derive(Debug)]
+pub struct Slice {
+    pub lower: Option<Rc<AstNode>>,
+    pub upper: Option<Rc<AstNode>>,
+    pub step: Option<Rc<AstNode>>
+}
+
+
+impl fmt::Display for Slice {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "(Slice {:?} {:?} {:?})", self.lower, self.upper, self.step)
+    }
+}
+
+
+///
+/// A single entry of a dictionary literal. For example,
+/// ```nadra
+/// { "key": value, **defaults }
+/// ```
+/// contains a `KeyValue` entry followed by an `Unpack` entry.
+/// 
+#[derive(Debug)]
+pub enum DictEntry {
+    KeyValue { key: Rc<AstNode>, value: Rc<AstNode> },
+    Unpack(Rc<AstNode>)
+}
+
+
+///
+/// A structure representing an unpacked value inside of a collection literal,
+/// i.e. `*items` or `**mapping`.
+/// 
+/// # Fields
+/// - `operator` - Whether an iterable (`*`) or a mapping (`**`) is unpacked.
+/// - `value` - The expression being unpacked.
+/// 
+#[derive(Debug)]
+pub struct Unpack {
+    pub operator: UnpackOperator,
+    pub value: Rc<AstNode>
+}
+
+
+impl fmt::Display for Unpack {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "({} {})", self.operator, self.value)
+    }
+}
+
+
+#[derive(Debug, Clone)]
+pub enum UnpackOperator {
+    Iterable, Mapping
+}
+
+
+impl fmt::Display for UnpackOperator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+
+///
+/// A structure representing a `use` statement on the AST, which imports a module
+/// or items from within a module. For example,
+/// ```nadra
+/// use os.path                     # import os.path
+/// use numpy as np                 # import numpy as np
+/// use collections::OrderedDict    # from collections import OrderedDict
+/// use typing::{List, Dict as D}   # from typing import List, Dict as D
+/// use .utils::helper              # from .utils import helper
+/// ```
+/// 
+/// # Fields
+/// - `level` - The number of leading dots of a relative import, or `0` for an absolute import.
+/// - `path` - The dotted path of the module, e.g. `["os", "path"]`.
+/// - `imports` - What is imported from the module.
+/// 
 #[derive(Debug)]
 pub struct UseStatement {
-    pub namespace: String
+    pub level: usize,
+    pub path: Vec<String>,
+    pub imports: UseImports
+}
+
+
+impl UseStatement {
+    ///
+    /// The module as it would be written in Python, e.g. `..utils.strings`.
+    /// 
+    pub fn module_name(&self) -> String {
+        format!("{}{}", ".".repeat(self.level), self.path.join("."))
+    }
 }
 
 
 impl fmt::Display for UseStatement {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "(Use {})", &self.namespace)
+        write!(f, "(Use {} {:?})", self.module_name(), &self.imports)
     }
 }
 
 
+///
+/// What a `use` statement imports from its module.
+/// - `Module` - The module itself, optionally under an alias (`use os.path as p`).
+/// - `Items` - Specific items within the module (`use typing::{List, Dict}`).
+/// - `Glob` - Everything within the module (`use typing::*`).
+/// 
+#[derive(Debug)]
+pub enum UseImports {
+    Module { alias: Option<String> },
+    Items(Vec<ImportItem>),
+    Glob
+}
+
+
+///
+/// A single item imported from within a module, e.g. `Dict as D`.
+/// 
+#[derive(Debug)]
+pub struct ImportItem {
+    pub name: String,
+    pub alias: Option<String>
+}
+
+
 ///
 /// A structure representing the AST equivalent of a while loop.
 /// 
@@ -91,6 +309,35 @@ impl fmt::Display for WhileLoop {
 }
 
 
+///
+/// A variable declaration, e.g. `let count: int = 0`, or a constant, e.g. `const LIMIT = 10`.
+/// 
+/// # Fields
+/// - `name` - The name of the variable or constant.
+/// - `annotation` - The declared type of the variable, if any.
+/// - `value` - The value it is initialised with.
+/// - `constant` - Whether it is declared with `const`, and so may never be reassigned.
+/// 
+#[derive(Debug)]
+pub struct Declaration {
+    pub name: String,
+    pub annotation: Option<Rc<AstNode>>,
+    pub value: Rc<AstNode>,
+    pub constant: bool
+}
+
+
+impl fmt::Display for Declaration {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let keyword = if self.constant { "Const" } else { "Let" };
+        match &self.annotation {
+            Some(annotation) => write!(f, "({} {} {} {})", keyword, self.name, annotation, self.value),
+            None => write!(f, "({} {} {})", keyword, self.name, self.value)
+        }
+    }
+}
+
+
 ///
 /// A structure representing a unary operation on the AST.
 /// A unary operation has the highest precedence of an expression, other
@@ -160,6 +407,109 @@ impl fmt::Display for IfStatement {
 }
 
 
+///
+/// A structure representing a match statement on the AST, which runs the body of the
+/// first case whose pattern matches the subject. For example,
+/// ```nadra
+/// match shape with
+///     case Shape::Circle(radius) if radius > 10.0 then
+///         print("A big circle")
+///     case Shape::Circle(_) then
+///         print("A circle")
+///     case _ then
+///         print("Something else")
+/// endmatch
+/// ```
+/// 
+/// # Fields
+/// - `subject` - The value which is matched against the patterns.
+/// - `cases` - The cases, in the order they are tried.
+/// 
+#[derive(Debug)]
+pub struct MatchStatement {
+    pub subject: Rc<AstNode>,
+    pub cases: Vec<MatchCase>
+}
+
+
+impl fmt::Display for MatchStatement {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "(Match {} ({:?}))", self.subject, self.cases)
+    }
+}
+
+
+///
+/// A single case of a match statement.
+/// 
+/// # Fields
+/// - `pattern` - What the subject must look like for the case to be run.
+/// - `guard` - A condition which must also be true, written after `if`, if any. The
+/// names bound by `pattern` may be used within it.
+/// - `body` - The statements run when the case matches.
+/// 
+#[derive(Debug)]
+pub struct MatchCase {
+    pub pattern: Pattern,
+    pub guard: Option<Rc<AstNode>>,
+    pub body: Vec<Rc<AstNode>>
+}
+
+
+///
+/// A pattern of a match case, which a value either matches or not.
+/// - `Wildcard` - `_`, which matches anything.
+/// - `Capture` - A name, which matches anything and binds the value to it.
+/// - `Literal` - A number, string, boolean or `None`, which matches a value equal to it.
+/// - `Value` - A name within a type or module, e.g. `Color::Red`, which matches a value
+/// equal to it.
+/// - `Class` - A struct or variant with patterns for its fields, e.g. `Point(0, y=y)`,
+/// which matches an instance whose fields match them. The positional patterns are
+/// matched against the fields in the order they are declared.
+/// - `Sequence` - A list of patterns, e.g. `[first, *rest]`, which matches a sequence
+/// whose elements match them.
+/// - `Star` - `*rest` within a sequence, which matches any number of elements and binds
+/// them as a list, unless it is `*_`.
+/// 
+#[derive(Debug)]
+pub enum Pattern {
+    Wildcard,
+    Capture(String),
+    Literal(Rc<AstNode>),
+    Value(Rc<AstNode>),
+    Class { class: Rc<AstNode>, positional: Vec<Pattern>, keywords: Vec<(String, Pattern)> },
+    Sequence(Vec<Pattern>),
+    Star(Option<String>)
+}
+
+
+impl Pattern {
+    ///
+    /// Whether every value matches the pattern.
+    /// 
+    pub fn is_irrefutable(&self) -> bool {
+        matches!(self, Self::Wildcard | Self::Capture(_))
+    }
+
+
+    ///
+    /// The names bound by the pattern, in the order they are written.
+    /// 
+    pub fn bindings(&self) -> Vec<&str> {
+        match self {
+            Self::Capture(name) | Self::Star(Some(name)) => vec![name],
+            Self::Class { positional, keywords, .. } => positional
+                .iter()
+                .chain(keywords.iter().map(|(_, pattern)| pattern))
+                .flat_map(Self::bindings)
+                .collect(),
+            Self::Sequence(elements) => elements.iter().flat_map(Self::bindings).collect(),
+            _ => vec![]
+        }
+    }
+}
+
+
 
 ///
 /// A structure representing a function definition on the AST.
@@ -167,13 +517,15 @@ impl fmt::Display for IfStatement {
 /// # Fields
 /// - `name` - The name or identifier of the function which will be used to identify 
 /// the function.
-/// - `return_type` - The type that the function will return. If the function does not return a value
-/// it should be marked as follows:
+/// - `return_type` - The type annotation of the value the function will return. If the function 
+/// does not return a value it should be marked as follows:
 /// ```nadra
 /// def hello_world() -> None
 ///     print("Hello, World!")
 /// enddef
 /// ```
+/// - `type_params` - The generic type parameters of the function, e.g. `T` in
+/// `def first[T](xs: list[T]) -> T`.
 /// - `param_list` - The parameters of the function.
 /// - `body` - A list of statements that will be executed every time the 
 /// function is called.
@@ -181,15 +533,263 @@ impl fmt::Display for IfStatement {
 #[derive(Debug)]
 pub struct FunctionDefinition {
     pub name: String,
-    pub return_type: TokenType,
-    pub param_list: Vec<Rc<AstNode>>,
+    pub return_type: Rc<AstNode>,
+    pub type_params: Vec<TypeParameter>,
+    pub param_list: Vec<Parameter>,
     pub body: Vec<Rc<AstNode>>
 }
 
 
 impl fmt::Display for FunctionDefinition {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "(Def {} ({:?}) ({:?})", self.name, self.param_list, self.body)
+        write!(f, "(Def {} ({:?}) ({:?}) ({:?})", self.name, self.type_params, self.param_list, self.body)
+    }
+}
+
+
+///
+/// A structure representing a struct definition on the AST. Structs are made of fields
+/// followed by methods, and become dataclasses in Python. For example,
+/// ```nadra
+/// struct Vector
+///     x: int
+///     y: int = 0
+///
+///     def length(self) -> float
+///         return (self.x ** 2 + self.y ** 2) ** 0.5
+///     enddef
+/// endstruct
+/// ```
+/// 
+/// # Fields
+/// - `name` - The name of the struct.
+/// - `type_params` - The generic type parameters of the struct, e.g. `T` in `struct Box[T]`.
+/// - `interfaces` - The interfaces the struct declares that it implements, e.g. `Shape` in
+/// `struct Square implements Shape`.
+/// - `fields` - The fields of the struct, in the order they are declared.
+/// - `methods` - The `FunctionDefinition` of each method.
+/// 
+#[derive(Debug)]
+pub struct StructDefinition {
+    pub name: String,
+    pub type_params: Vec<TypeParameter>,
+    pub interfaces: Vec<Rc<AstNode>>,
+    pub fields: Vec<Field>,
+    pub methods: Vec<Rc<AstNode>>
+}
+
+
+impl fmt::Display for StructDefinition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "(Struct {} ({:?}) ({:?}) ({:?}) ({:?}))",
+            self.name, self.type_params, self.interfaces, self.fields, self.methods
+        )
+    }
+}
+
+
+///
+/// A structure representing an interface definition on the AST. An interface declares the
+/// signatures of the methods that the structs implementing it must have, and becomes a
+/// `typing.Protocol` in Python. For example,
+/// ```nadra
+/// interface Shape
+///     def area(self) -> float
+///     def name(self) -> str
+/// endinterface
+/// ```
+/// 
+/// # Fields
+/// - `name` - The name of the interface.
+/// - `methods` - The `FunctionDefinition` of each method, none of which have a body.
+/// 
+#[derive(Debug)]
+pub struct InterfaceDefinition {
+    pub name: String,
+    pub methods: Vec<Rc<AstNode>>
+}
+
+
+impl fmt::Display for InterfaceDefinition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "(Interface {} ({:?}))", self.name, self.methods)
+    }
+}
+
+
+///
+/// A generic type parameter of a function or struct, e.g. `T`, `N: float` or `T: (int, str)`.
+/// 
+/// # Fields
+/// - `name` - The name of the type parameter.
+/// - `bound` - The type that every type given for the parameter must be, if any. A tuple
+/// of types is a list of constraints instead, one of which must be given for the parameter.
+/// 
+#[derive(Debug)]
+pub struct TypeParameter {
+    pub name: String,
+    pub bound: Option<Rc<AstNode>>
+}
+
+
+///
+/// A single field of a struct, e.g. `y: int = 0`.
+/// 
+/// # Fields
+/// - `name` - The name of the field.
+/// - `annotation` - The declared type of the field, which is required.
+/// - `default` - The value of the field if none is given when creating the struct.
+/// 
+#[derive(Debug)]
+pub struct Field {
+    pub name: String,
+    pub annotation: Rc<AstNode>,
+    pub default: Option<Rc<AstNode>>
+}
+
+
+///
+/// A structure representing an enum definition on the AST. For example,
+/// ```nadra
+/// enum RenderModes
+///     Detailed,
+///     Minimal,
+///     Performance
+/// endenum
+/// ```
+/// The variants of an enum may instead carry fields, in which case the enum is a tagged
+/// union of them, e.g.
+/// ```nadra
+/// enum Shape
+///     Circle(radius: float),
+///     Rect(w: float, h: float),
+///     Empty
+/// endenum
+/// ```
+/// 
+/// # Fields
+/// - `name` - The name of the enum.
+/// - `variants` - The variants of the enum, in the order they are declared.
+/// 
+#[derive(Debug)]
+pub struct EnumDefinition {
+    pub name: String,
+    pub variants: Vec<EnumVariant>
+}
+
+
+impl EnumDefinition {
+    ///
+    /// Whether any variant of the enum carries fields, even if it has none, e.g. `Empty()`.
+    /// If so, the enum is a tagged union, whose variants without fields carry nothing.
+    /// 
+    pub fn has_payloads(&self) -> bool {
+        self.variants.iter().any(|variant| variant.fields.is_some())
+    }
+}
+
+
+impl fmt::Display for EnumDefinition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "(Enum {} ({:?}))", self.name, self.variants)
+    }
+}
+
+
+///
+/// A single variant of an enum.
+/// 
+/// # Fields
+/// - `name` - The name of the variant.
+/// - `fields` - The fields the variant carries, e.g. `radius` in `Circle(radius: float)`,
+/// or `None` if it is a plain variant.
+/// 
+#[derive(Debug)]
+pub struct EnumVariant {
+    pub name: String,
+    pub fields: Option<Vec<Field>>
+}
+
+
+///
+/// A structure representing a single parameter in the parameter list of a function.
+/// 
+/// # Fields
+/// - `name` - The name of the parameter. This is empty for the `/` and `*` markers.
+/// - `kind` - Whether this is an ordinary parameter, a variadic parameter or a marker.
+/// - `annotation` - The declared type of the parameter, e.g. `int` in `count: int`.
+/// - `default` - The value of the parameter if no argument is passed for it.
+/// 
+#[derive(Debug)]
+pub struct Parameter {
+    pub name: String,
+    pub kind: ParameterKind,
+    pub annotation: Option<Rc<AstNode>>,
+    pub default: Option<Rc<AstNode>>
+}
+
+
+impl Parameter {
+    ///
+    /// Create one of the nameless `/` or `*` markers.
+    /// 
+    pub fn marker(kind: ParameterKind) -> Self {
+        Self {
+            name: String::new(),
+            kind: kind,
+            annotation: None,
+            default: None
+        }
+    }
+}
+
+
+impl fmt::Display for Parameter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "(Param {:?} {} {:?} {:?})", self.kind, self.name, self.annotation, self.default)
+    }
+}
+
+
+///
+/// The kinds of parameter which can appear in a parameter list. In Nadra, as in Python:
+/// ```nadra
+/// def f(a, /, b, *args, c, **kwargs) -> None
+/// def g(a, *, key) -> None
+/// ```
+/// - `Normal` - `a`, `b`, `c` and `key`.
+/// - `VarPositional` - `*args`, which collects any extra positional arguments.
+/// - `VarKeyword` - `**kwargs`, which collects any extra keyword arguments.
+/// - `PositionalOnlyMarker` - `/`. Every parameter before it is positional-only.
+/// - `KeywordOnlyMarker` - `*`. Every parameter after it is keyword-only.
+/// 
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParameterKind {
+    Normal, VarPositional, VarKeyword,
+    PositionalOnlyMarker, KeywordOnlyMarker
+}
+
+
+///
+/// A structure representing a keyword argument passed to a function, e.g. `end=""` in
+/// `print(x, end="")`.
+/// 
+/// # Fields
+/// - `name` - The name of the parameter the argument is passed to.
+/// - `value` - The value of the argument.
+/// 
+#[derive(Debug)]
+pub struct KeywordArgument {
+    pub name: String,
+    pub value: Rc<AstNode>
+}
+
+
+impl fmt::Display for KeywordArgument {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "(Keyword {} {})", self.name, self.value)
     }
 }
 
@@ -198,26 +798,29 @@ impl fmt::Display for FunctionDefinition {
 /// A structure representing a function-call on the AST.
 /// 
 /// # Fields
-/// - `function` - The name/identifier of the function to call.
-/// - `args` - Any arguments that will be passed to the function.
+/// - `function` - The expression being called. This is usually an identifier, but may
+/// be any expression, e.g. `obj.method(x)` or `make_adder(1)(2)`.
+/// - `args` - Any arguments that will be passed to the function. Positional arguments come
+/// first, followed by `KeywordArgument`s, and either may be mixed with `Unpack`ed values.
 /// 
 #[derive(Debug)]
 pub struct FunctionCall {
-    pub function: String,
+    pub function: Rc<AstNode>,
     pub args: Vec<Rc<AstNode>>
 }
 
 
 impl fmt::Display for FunctionCall {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "(Call {} ({:?}))", self.function.as_str(), &self.args)
+        write!(f, "(Call {} ({:?}))", self.function, &self.args)
     }
 }
 
 
 #[derive(Debug)]
+#[allow(dead_code)]
 pub struct LambdaFunction {
-    pub params: Vec<Rc<AstNode>>, 
+    pub params: Vec<Parameter>, 
     pub body: Vec<Rc<AstNode>>
 }
 
@@ -286,6 +889,22 @@ impl BinaryOperator {
             _ => None
         }
     }
+
+
+    ///
+    /// Whether the operator assigns to its left operand, i.e. `=` or an augmented
+    /// assignment operator such as `+=`.
+    /// 
+    pub fn is_assignment(&self) -> bool {
+        matches!(self,
+            Self::Equal
+            | Self::PlusEqual
+            | Self::MinusEqual
+            | Self::AsteriskEqual
+            | Self::SlashEqual
+            | Self::ModulusEqual
+        )
+    }
 }
 
 
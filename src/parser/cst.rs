@@ -0,0 +1,389 @@
+use std::rc::Rc;
+use crate::lexer::PieToken;
+use super::ast::*;
+
+
+///
+/// The kind of a node in the concrete syntax tree. Most kinds correspond to a kind of
+/// [`AstNode`], while the others group tokens that have no node of their own on the AST.
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SyntaxKind {
+    SourceFile,
+
+    // Statements
+    FunctionDefinition,
+    StructDefinition,
+    InterfaceDefinition,
+    EnumDefinition,
+    IfStatement,
+    WhileLoop,
+    MatchStatement,
+    ReturnStatement,
+    UseStatement,
+    Assignment,
+    Declaration,
+
+    // Expressions
+    Literal,
+    Name,
+    UnaryOperation,
+    BinaryOperation,
+    FunctionCall,
+    MemberAccess,
+    ScopeAccess,
+    Subscript,
+    Slice,
+    ListLiteral,
+    TupleLiteral,
+    SetLiteral,
+    DictLiteral,
+    Unpack,
+    KeywordArgument,
+    LambdaFunction,
+    Parenthesized,
+
+    // Parts of other nodes
+    Parameter,
+    TypeParameter,
+    ImportItem,
+    Field,
+    EnumVariant,
+    MatchCase,
+
+    // Patterns
+    WildcardPattern,
+    CapturePattern,
+    LiteralPattern,
+    ValuePattern,
+    ClassPattern,
+    KeywordPattern,
+    SequencePattern,
+    StarPattern
+}
+
+
+impl SyntaxKind {
+    ///
+    /// The kind of the node which `node` was parsed from.
+    ///
+    pub fn of(node: &AstNode) -> Self {
+        match node {
+            AstNode::IntegerLiteral(_)
+            | AstNode::FloatLiteral(_)
+            | AstNode::StringLiteral(_)
+            | AstNode::BooleanLiteral(_) => Self::Literal,
+
+            AstNode::Identifier(_) => Self::Name,
+
+            AstNode::BinaryOperation(operation) if operation.operator.is_assignment() => Self::Assignment,
+
+            AstNode::BinaryOperation(_) => Self::BinaryOperation,
+            AstNode::UnaryOperation(_) => Self::UnaryOperation,
+            AstNode::FunctionCall(_) => Self::FunctionCall,
+            AstNode::MemberAccess(_) => Self::MemberAccess,
+            AstNode::ScopeAccess(_) => Self::ScopeAccess,
+            AstNode::Subscript(_) => Self::Subscript,
+            AstNode::Slice(_) => Self::Slice,
+            AstNode::ListLiteral(_) => Self::ListLiteral,
+            AstNode::TupleLiteral(_) => Self::TupleLiteral,
+            AstNode::SetLiteral(_) => Self::SetLiteral,
+            AstNode::DictLiteral(_) => Self::DictLiteral,
+            AstNode::Unpack(_) => Self::Unpack,
+            AstNode::KeywordArgument(_) => Self::KeywordArgument,
+            AstNode::LambdaFunction { .. } => Self::LambdaFunction,
+            AstNode::FunctionDefinition(_) => Self::FunctionDefinition,
+            AstNode::StructDefinition(_) => Self::StructDefinition,
+            AstNode::InterfaceDefinition(_) => Self::InterfaceDefinition,
+            AstNode::EnumDefinition(_) => Self::EnumDefinition,
+            AstNode::IfStatement(_) => Self::IfStatement,
+            AstNode::ReturnStatement(_) => Self::ReturnStatement,
+            AstNode::UseStatement(_) => Self::UseStatement,
+            AstNode::WhileLoop(_) => Self::WhileLoop,
+            AstNode::MatchStatement(_) => Self::MatchStatement,
+            AstNode::Declaration(_) => Self::Declaration
+        }
+    }
+}
+
+
+#[derive(Debug, Clone)]
+pub enum SyntaxElement {
+    Node(Rc<SyntaxNode>),
+    Token(Rc<PieToken>)
+}
+
+
+///
+/// # Overview
+/// A node of the concrete syntax tree (CST). Unlike the AST, the CST contains every token
+/// of the source code, along with its trivia, so the source code can be reproduced exactly
+/// from it.
+///
+/// # Members
+/// - `kind` - What the node is.
+/// - `children` - The nodes and tokens the node is made of, in source order.
+/// - `ast` - The AST node that was built from this node, if any. The AST of a program is
+/// read off the CST, see [`SyntaxTree::ast`].
+///
+#[derive(Debug)]
+pub struct SyntaxNode {
+    pub kind: SyntaxKind,
+    pub children: Vec<SyntaxElement>,
+    pub ast: Option<Rc<AstNode>>
+}
+
+
+impl SyntaxNode {
+    ///
+    /// The source code of the node, including all trivia within it.
+    ///
+    pub fn text(&self) -> String {
+        self.tokens()
+            .iter()
+            .map(|token| token.full_text())
+            .collect()
+    }
+
+
+    ///
+    /// Every token within the node, in source order.
+    ///
+    pub fn tokens(&self) -> Vec<Rc<PieToken>> {
+        let mut tokens = vec![];
+        self.collect_tokens(&mut tokens);
+        tokens
+    }
+
+
+    fn collect_tokens(&self, tokens: &mut Vec<Rc<PieToken>>) {
+        for child in &self.children {
+            match child {
+                SyntaxElement::Node(node) => node.collect_tokens(tokens),
+                SyntaxElement::Token(token) => tokens.push(token.clone())
+            }
+        }
+    }
+
+
+    ///
+    /// The source code of the node without the trivia before its first token, e.g. for
+    /// quoting the node in an error message.
+    ///
+    pub fn code(&self) -> String {
+        self.tokens()
+            .iter()
+            .enumerate()
+            .map(|(i, token)| if i == 0 { token.lexeme.clone() } else { token.full_text() })
+            .collect()
+    }
+
+
+    ///
+    /// The node within this one, or this node itself, that `ast` was built from.
+    ///
+    pub fn find(self: &Rc<Self>, ast: &Rc<AstNode>) -> Option<Rc<SyntaxNode>> {
+        if self.ast.as_ref().is_some_and(|existing| Rc::ptr_eq(existing, ast)) {
+            return Some(self.clone());
+        }
+
+        self.child_nodes().find_map(|node| node.find(ast))
+    }
+
+
+    pub fn first_token(&self) -> Option<Rc<PieToken>> {
+        self.children.iter().find_map(|child| match child {
+            SyntaxElement::Node(node) => node.first_token(),
+            SyntaxElement::Token(token) => Some(token.clone())
+        })
+    }
+
+
+    pub fn last_token(&self) -> Option<Rc<PieToken>> {
+        self.children.iter().rev().find_map(|child| match child {
+            SyntaxElement::Node(node) => node.last_token(),
+            SyntaxElement::Token(token) => Some(token.clone())
+        })
+    }
+
+
+    pub fn child_nodes(&self) -> impl Iterator<Item = &Rc<SyntaxNode>> {
+        self.children.iter().filter_map(|child| match child {
+            SyntaxElement::Node(node) => Some(node),
+            SyntaxElement::Token(_) => None
+        })
+    }
+}
+
+
+///
+/// # Overview
+/// The lossless concrete syntax tree of a whole source file.
+///
+/// # Members
+/// - `root` - A `SourceFile` node, whose child nodes are the top-level statements of the
+/// file. Its last child is the `Eof` token, which holds any trailing trivia.
+///
+#[derive(Debug)]
+pub struct SyntaxTree {
+    pub root: Rc<SyntaxNode>
+}
+
+
+impl SyntaxTree {
+    ///
+    /// The AST of the file, i.e. the AST node of each top-level statement.
+    ///
+    pub fn ast(&self) -> Vec<Rc<AstNode>> {
+        self.root
+            .child_nodes()
+            .filter_map(|statement| statement.ast.clone())
+            .collect()
+    }
+
+
+    ///
+    /// The source code of the file, which is exactly what it was parsed from.
+    ///
+    pub fn text(&self) -> String {
+        self.root.text()
+    }
+}
+
+
+///
+/// # Overview
+/// Builds a [`SyntaxTree`] while parsing. Tokens are added as they are consumed, then
+/// grouped into nodes once the parser knows what they form. Since that is often only known
+/// after parsing the first part of the node, e.g. the left operand of a binary operation,
+/// nodes are started from a checkpoint taken beforehand.
+///
+/// # Members
+/// - `children` - The nodes and tokens that have not been grouped into a node yet.
+///
+#[derive(Default)]
+pub struct SyntaxTreeBuilder {
+    children: Vec<SyntaxElement>
+}
+
+
+impl SyntaxTreeBuilder {
+    pub fn token(&mut self, token: Rc<PieToken>) {
+        self.children.push(SyntaxElement::Token(token));
+    }
+
+
+    pub fn checkpoint(&self) -> usize {
+        self.children.len()
+    }
+
+
+    ///
+    /// Group everything added since `checkpoint` into a node.
+    ///
+    pub fn finish_node(&mut self, checkpoint: usize, kind: SyntaxKind, ast: Option<Rc<AstNode>>) {
+        let children = self.children.split_off(checkpoint);
+        let node = SyntaxNode {
+            kind: kind,
+            children: children,
+            ast: ast
+        };
+        self.children.push(SyntaxElement::Node(Rc::new(node)));
+    }
+
+
+    ///
+    /// Group everything added since `checkpoint` into a node for `ast`. Nothing is done if
+    /// that would only wrap the node already built for `ast`, e.g. when an expression
+    /// statement is just an expression.
+    ///
+    pub fn finish_ast(&mut self, checkpoint: usize, ast: &Rc<AstNode>) {
+        if let [SyntaxElement::Node(node)] = &self.children[checkpoint..]
+            && node.ast.as_ref().is_some_and(|existing| Rc::ptr_eq(existing, ast)) {
+            return;
+        }
+
+        self.finish_node(checkpoint, SyntaxKind::of(ast), Some(ast.clone()));
+    }
+
+
+    ///
+    /// The source code of the node built for `ast`, if it has been built yet. See
+    /// [`SyntaxNode::code`].
+    ///
+    pub fn code_of(&self, ast: &Rc<AstNode>) -> Option<String> {
+        self.children.iter().rev().find_map(|child| match child {
+            SyntaxElement::Node(node) => node.find(ast).map(|node| node.code()),
+            SyntaxElement::Token(_) => None
+        })
+    }
+
+
+    pub fn finish(self) -> SyntaxTree {
+        let root = SyntaxNode {
+            kind: SyntaxKind::SourceFile,
+            children: self.children,
+            ast: None
+        };
+        SyntaxTree { root: Rc::new(root) }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::{lexer, parser};
+    use super::*;
+
+
+    fn parse(source: &str) -> SyntaxTree {
+        let mut tokens = lexer::scan_all_tokens(source).unwrap();
+        parser::parse(&mut tokens).unwrap()
+    }
+
+
+    fn assert_round_trip(source: &str) {
+        assert_eq!(parse(source).text(), source);
+    }
+
+
+    #[test]
+    fn reproduces_source_files() {
+        assert_round_trip(include_str!("../../scripts/document.ndr"));
+        assert_round_trip(include_str!("../../test/inputs/test1.ndr"));
+    }
+
+
+    #[test]
+    fn reproduces_crlf_line_endings() {
+        let source = include_str!("../../scripts/document.ndr").replace('\n', "\r\n");
+        assert_round_trip(&source);
+        assert_round_trip("x = 1\r\n\r\n# done\r\n");
+    }
+
+
+    #[test]
+    fn reproduces_tabs_and_trailing_whitespace() {
+        assert_round_trip("def f(x) -> int\n\treturn x \t\nenddef\t\n");
+        assert_round_trip("xs = [\n\t1,\t2 ,\n]   \n\n\t\n");
+    }
+
+
+    #[test]
+    fn reproduces_comments_and_empty_files() {
+        assert_round_trip("");
+        assert_round_trip("\n\n");
+        assert_round_trip("# only a comment");
+        assert_round_trip("# one\n  # two\n\n");
+        assert_round_trip("x = 1  # trailing\n# last, without a newline");
+    }
+
+
+    #[test]
+    fn reproduces_each_node() {
+        let source = "def f(x) -> int\n    # comment\n    return x + 1\nenddef\n\ny = f(2)\n";
+        let tree = parse(source);
+        let statements: Vec<String> = tree.root.child_nodes().map(|node| node.text()).collect();
+        assert_eq!(statements, ["def f(x) -> int\n    # comment\n    return x + 1\nenddef", "\n\ny = f(2)"]);
+    }
+}
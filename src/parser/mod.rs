@@ -1,9 +1,11 @@
 pub mod ast;
+pub mod cst;
 
-use std::rc::Rc;
+use std::{collections::HashSet, rc::Rc};
 use color_eyre::eyre::{self, eyre, OptionExt};
-use crate::lexer::{CanBeEof, PieToken, PieTokenStream, PieValue, TokenType};
+use crate::lexer::{CanBeEof, PieToken, PieTokenStream, PieValue, SyntaxError, TokenType};
 use ast::*;
+use cst::{SyntaxKind, SyntaxTree, SyntaxTreeBuilder};
 
 
 type ParseResult = eyre::Result<Rc<AstNode>>;
@@ -16,6 +18,12 @@ type ParseResult = eyre::Result<Rc<AstNode>>;
 /// 
 /// # Fields
 /// `token_stream` - The stream of tokens from which to construct the AST.
+/// `previous_line` - The line of the most recently consumed token. Used to tell
+/// `f(x)` apart from `f` followed by a parenthesized expression on the next line.
+/// `cst` - Builds the concrete syntax tree from the consumed tokens. Each production that
+/// builds an AST node also groups its tokens into a CST node.
+/// `in_slice_bound` - Whether the lower bound of a slice is being parsed, in which case
+/// `::` ends the bound rather than accessing an item, e.g. `xs[i::step]`.
 /// 
 /// # Examples
 /// ```rust
@@ -26,6 +34,9 @@ type ParseResult = eyre::Result<Rc<AstNode>>;
 /// 
 struct Parser <'a> {
     token_stream: &'a mut PieTokenStream,
+    previous_line: u64,
+    cst: SyntaxTreeBuilder,
+    in_slice_bound: bool
 }
 
 
@@ -33,6 +44,9 @@ impl <'a> Parser <'a> {
     pub fn new(token_stream: &'a mut PieTokenStream) -> Self {
         Self {
             token_stream: token_stream,
+            previous_line: 0,
+            cst: SyntaxTreeBuilder::default(),
+            in_slice_bound: false
         }
     }
 
@@ -44,8 +58,30 @@ impl <'a> Parser <'a> {
 
     /// Get the next token and skip ahead in the stream.
     fn next_token(&mut self) -> Rc<PieToken> {
-        let token = self.token_stream.next_token();
-        token.unwrap()
+        let token = self.token_stream.next_token().unwrap();
+        self.previous_line = token.line;
+        self.cst.token(token.clone());
+        token
+    }
+
+
+    ///
+    /// Run `parse`, grouping the tokens it consumes into a CST node of the given `kind`.
+    /// This is for parts of the syntax that have no AST node of their own.
+    /// 
+    fn group<T>(&mut self, kind: SyntaxKind, parse: impl FnOnce(&mut Self) -> eyre::Result<T>) -> eyre::Result<T> {
+        let checkpoint = self.cst.checkpoint();
+        let result = parse(self)?;
+        self.cst.finish_node(checkpoint, kind, None);
+        Ok(result)
+    }
+
+
+    ///
+    /// Check whether the next token begins on the same line as the previous one.
+    /// 
+    fn next_on_same_line(&self) -> bool {
+        self.peek().line == self.previous_line
     }
 
 
@@ -55,13 +91,29 @@ impl <'a> Parser <'a> {
     }
 
 
-    /// Parse an AST from a program.
-    fn parse_program(&mut self) -> eyre::Result<Vec<Rc<AstNode>>> {
-        let mut statements = vec![];
+    /// Get the token after the next one, if there is one.
+    fn peek_second(&self) -> Option<Rc<PieToken>> {
+        self.token_stream.tokens().get(1).cloned()
+    }
+
+
+    /// Get the source code `node` was parsed from, to quote it in an error message.
+    fn code_of(&self, node: &Rc<AstNode>) -> String {
+        self.cst.code_of(node).unwrap_or_else(|| node.to_string())
+    }
+
+
+    /// Parse a program, whose statements are recorded in the CST.
+    fn parse_program(&mut self) -> eyre::Result<()> {
         while self.has_next() {
-            statements.push(self.parse_statement()?);
+            self.parse_statement()?;
+        }
+
+        // The `Eof` token holds the trivia at the end of the program.
+        if let Some(eof) = self.token_stream.next_token() {
+            self.cst.token(eof);
         }
-        Ok(statements)
+        Ok(())
     }
 
 
@@ -71,55 +123,499 @@ impl <'a> Parser <'a> {
     /// 
     fn parse_statement(&mut self) -> eyre::Result<Rc<AstNode>> {
         let next = self.peek();
+        let checkpoint = self.cst.checkpoint();
+
+        let statement = match next.type_.clone() {
+            TokenType::Def => self.parse_function_definition(),
+            TokenType::If => self.parse_if_statement(),
+            TokenType::Use => self.parse_use_statement(),
+            TokenType::While => self.parse_while_loop(),
+            TokenType::Match => self.parse_match_statement(),
+            TokenType::Struct => self.parse_struct_definition(),
+            TokenType::Interface => self.parse_interface_definition(),
+            TokenType::Enum => self.parse_enum_definition(),
+            TokenType::Let | TokenType::Const => self.parse_declaration(),
+            _ => self.parse_assignment()
+        }?;
+
+        self.cst.finish_ast(checkpoint, &statement);
+        Ok(statement)
+    }
+
+
+    ///
+    /// Parse a variable declaration, e.g. `let count: int = 0`, or a constant, e.g.
+    /// `const LIMIT = 10`. Either must be given a value, while the type is optional.
+    /// 
+    fn parse_declaration(&mut self) -> ParseResult {
+        let constant = self.next_token().type_ == TokenType::Const;
 
-        match next.type_.clone() {
-            TokenType::Def => return self.parse_function_definition(),
-            TokenType::TypeAlias => {},
-            TokenType::If => return self.parse_if_statement(),
-            TokenType::Use => return self.parse_use_statement(),
-            TokenType::While => return self.parse_while_loop(),
-            // TokenType::Struct => return self.parse_struct_definition(),
-            _ => {}
+        self.expect_next(TokenType::Identifier)?;
+        let name = self.next_token().lexeme.to_string();
+
+        let mut annotation = None;
+        if self.peek().type_ == TokenType::Colon {
+            self.next_token();
+            annotation = Some(self.parse_type_annotation()?);
         }
 
-        self.parse_equality()
+        self.expect_next(TokenType::Equal)
+            .map_err(|_| eyre!("Expected a value for '{}'", name))?;
+        self.next_token();
+        let value = self.parse_equality()?;
+
+        let declaration = Declaration {
+            name: name,
+            annotation: annotation,
+            value: value,
+            constant: constant
+        };
+
+        Ok(Rc::new(AstNode::Declaration(declaration)))
     }
 
 
-    // fn parse_struct_definition(&mut self) -> ParseResult {
-    //     self.expect_next(TokenType::Struct)?;
-    //     self.next_token();
-    // }
+    ///
+    /// Parse an assignment, or just an expression if no assignment operator follows it.
+    /// Plain assignments may be chained, e.g. `a = b = 0`, while augmented assignments
+    /// such as `total += x` may not.
+    /// 
+    fn parse_assignment(&mut self) -> ParseResult {
+        let target = self.parse_equality()?;
+
+        if !self.has_next() || !self.peek().type_.is_assignment_operator() {
+            return Ok(target);
+        }
+
+        let operator_token = self.next_token();
+        let operator = BinaryOperator
+            ::from(&operator_token)
+            .ok_or_eyre(format!("Unexpected {}", operator_token.lexeme.as_str()))?;
 
+        let augmented = operator_token.type_ != TokenType::Equal;
+        let mut targets = vec![target];
+        let mut value = self.parse_equality()?;
 
+        while !augmented && self.has_next() && self.peek().type_ == TokenType::Equal {
+            self.next_token();
+            targets.push(value);
+            value = self.parse_equality()?;
+        }
+
+        // Build `a = (b = value)` from right to left.
+        for target in targets.into_iter().rev() {
+            self.check_assignment_target(&target, augmented)?;
+
+            let assignment = BinaryOperation {
+                operator: operator.clone(),
+                left_child: target,
+                right_child: value,
+            };
+            value = Rc::new(AstNode::BinaryOperation(assignment));
+        }
+
+        Ok(value)
+    }
+
+
+    ///
+    /// Ensure that `target` may be assigned to. Variables, attributes and subscripts are
+    /// always valid targets, while tuples and lists of targets may only be used with `=`.
+    /// 
+    fn check_assignment_target(&self, target: &Rc<AstNode>, augmented: bool) -> eyre::Result<()> {
+        match target.as_ref() {
+            AstNode::Identifier(_)
+            | AstNode::MemberAccess(_)
+            | AstNode::Subscript(_) => Ok(()),
+
+            AstNode::TupleLiteral(elements)
+            | AstNode::ListLiteral(elements) if !augmented => {
+                for element in elements {
+                    match element.as_ref() {
+                        AstNode::Unpack(unpack) => self.check_assignment_target(&unpack.value, false)?,
+                        _ => self.check_assignment_target(element, false)?
+                    }
+                }
+                Ok(())
+            }
+
+            _ => Err(eyre!("Cannot assign to '{}'", self.code_of(target)))
+        }
+    }
+
+
+    ///
+    /// Parse a `use` statement. `use` statements take one of the following forms:
+    /// ```nadra
+    /// use <module>[.<submodule>]* [as <alias>]?
+    /// use <module>::<item> [as <alias>]?
+    /// use <module>::{<item> [as <alias>]?, ...}
+    /// use <module>::*
+    /// ```
+    /// Where `<module>` may be prefixed with dots to import relative to the current package.
+    /// 
     fn parse_use_statement(&mut self) -> eyre::Result<Rc<AstNode>> {
+        use TokenType::{Asterisk, Dot, DotDot, Identifier, LeftBrace, ScopeOperator};
+
         self.expect_next(TokenType::Use)?;
         self.next_token();
 
-        self.expect_next(TokenType::Identifier)?;
-        let namespace = self.next_token().lexeme.clone();
+        // Count the leading dots of a relative import, e.g. `use ..utils`.
+        let mut level = 0;
+        loop {
+            match self.peek().type_ {
+                Dot => level += 1,
+                DotDot => level += 2,
+                _ => break
+            }
+            self.next_token();
+        }
+
+        // Parse the dotted path of the module.
+        let mut path = vec![];
+        if level == 0 || self.peek().type_ == Identifier {
+            self.expect_next(Identifier)?;
+            path.push(self.next_token().lexeme.to_string());
+
+            while self.peek().type_ == Dot {
+                self.next_token();
+                self.expect_next(Identifier)?;
+                path.push(self.next_token().lexeme.to_string());
+            }
+        }
+
+        let imports = if self.peek().type_ == ScopeOperator {
+            self.next_token();
+
+            match self.peek().type_ {
+                Asterisk => {
+                    self.next_token();
+                    UseImports::Glob
+                }
+
+                LeftBrace => UseImports::Items(self.parse_import_items()?),
+
+                _ => UseImports::Items(vec![self.group(SyntaxKind::ImportItem, Self::parse_import_item)?])
+            }
+        } else {
+            if path.is_empty() {
+                return Err(eyre!("Expected a module name after '{}'", ".".repeat(level)));
+            }
+            UseImports::Module { alias: self.parse_import_alias()? }
+        };
 
         let node = UseStatement {
-            namespace: namespace.to_string(),
+            level: level,
+            path: path,
+            imports: imports
         };
 
         Ok(Rc::new(AstNode::UseStatement(node)))
     }
 
 
-    // fn parse_struct_definition(&mut self) -> eyre::Result<Rc<AstNode>> {
+    ///
+    /// Parse a group of imported items, e.g. `{List, Dict as D}`.
+    /// 
+    fn parse_import_items(&mut self) -> eyre::Result<Vec<ImportItem>> {
+        self.expect_next(TokenType::LeftBrace)?;
+        self.next_token();
 
-    // }
+        let mut items = vec![];
+        while self.has_next() && self.peek().type_ != TokenType::RightBrace {
+            items.push(self.group(SyntaxKind::ImportItem, Self::parse_import_item)?);
 
+            if self.peek().type_ != TokenType::Comma {
+                break;
+            }
+            self.next_token();
+        }
 
-    // fn parse_enum_definition(&mut self) -> eyre::Result<AstNode> {
+        self.expect_next(TokenType::RightBrace)?;
+        self.next_token();
 
-    // }
+        if items.is_empty() {
+            return Err(eyre!("Expected at least one item between '{{' and '}}'"));
+        }
 
+        Ok(items)
+    }
 
-    // fn parse_struct_member(&mut self) -> eyre::Result<AstNode> {
 
-    // }
+    ///
+    /// Parse a single imported item, which may be aliased, e.g. `OrderedDict as OD`.
+    /// 
+    fn parse_import_item(&mut self) -> eyre::Result<ImportItem> {
+        self.expect_next(TokenType::Identifier)?;
+        let name = self.next_token().lexeme.to_string();
+
+        Ok(ImportItem {
+            name: name,
+            alias: self.parse_import_alias()?
+        })
+    }
+
+
+    ///
+    /// Parse `as <alias>` if it follows an import.
+    /// 
+    fn parse_import_alias(&mut self) -> eyre::Result<Option<String>> {
+        if self.peek().type_ != TokenType::As {
+            return Ok(None);
+        }
+        self.next_token();
+
+        self.expect_next(TokenType::Identifier)?;
+        Ok(Some(self.next_token().lexeme.to_string()))
+    }
+
+
+    ///
+    /// Parse a struct definition. Structs declare their fields first, followed by any
+    /// methods:
+    /// ```nadra
+    /// struct <name> [implements <interface>, ...]?
+    ///     <field>: <type> [= <default>]?
+    ///     ...
+    ///     <method>*
+    /// endstruct
+    /// ```
+    /// 
+    fn parse_struct_definition(&mut self) -> ParseResult {
+        self.expect_next(TokenType::Struct)?;
+        self.next_token();
+
+        self.expect_next(TokenType::Identifier)?;
+        let name = self.next_token().lexeme.to_string();
+        let type_params = self.parse_type_params()?;
+
+        let mut interfaces = vec![];
+        if self.peek().type_ == TokenType::Implements {
+            self.next_token();
+
+            loop {
+                self.expect_next(TokenType::Identifier)?;
+                interfaces.push(self.parse_type_annotation()?);
+
+                if self.peek().type_ != TokenType::Comma {
+                    break;
+                }
+                self.next_token();
+            }
+        }
+
+        let mut fields = vec![];
+        while self.has_next() && self.peek().type_ == TokenType::Identifier {
+            fields.push(self.group(SyntaxKind::Field, Self::parse_struct_field)?);
+        }
+
+        let mut methods = vec![];
+        while self.has_next() && self.peek().type_ == TokenType::Def {
+            methods.push(self.parse_statement()?);
+        }
+
+        if self.has_next() && self.peek().type_ == TokenType::Identifier {
+            return Err(eyre!("Fields of '{}' must be declared before its methods", name));
+        }
+
+        self.expect_next(TokenType::EndStruct)?;
+        self.next_token();
+
+        Self::check_struct_fields(&name, &fields)?;
+
+        let definition = StructDefinition {
+            name: name,
+            type_params: type_params,
+            interfaces: interfaces,
+            fields: fields,
+            methods: methods
+        };
+
+        Ok(Rc::new(AstNode::StructDefinition(definition)))
+    }
+
+
+    ///
+    /// Parse an interface definition, which declares the signatures of its methods without
+    /// their bodies:
+    /// ```nadra
+    /// interface <name>
+    ///     def <method>(self, <params>) -> <type>
+    ///     ...
+    /// endinterface
+    /// ```
+    /// 
+    fn parse_interface_definition(&mut self) -> ParseResult {
+        self.expect_next(TokenType::Interface)?;
+        self.next_token();
+
+        self.expect_next(TokenType::Identifier)?;
+        let name = self.next_token().lexeme.to_string();
+
+        let mut methods = vec![];
+        let mut names = HashSet::new();
+
+        while self.has_next() && self.peek().type_ == TokenType::Def {
+            let checkpoint = self.cst.checkpoint();
+            let method = Rc::new(AstNode::FunctionDefinition(self.parse_function_signature()?));
+            self.cst.finish_ast(checkpoint, &method);
+
+            if let AstNode::FunctionDefinition(signature) = method.as_ref() {
+                if !names.insert(signature.name.clone()) {
+                    return Err(eyre!("Duplicate method '{}' in interface '{}'", signature.name, name));
+                }
+                if signature.param_list.is_empty() {
+                    return Err(eyre!("Method '{}' of interface '{}' must take 'self'", signature.name, name));
+                }
+            }
+            methods.push(method);
+        }
+
+        if self.has_next() && self.peek().type_ == TokenType::EndDef {
+            return Err(eyre!("The methods of interface '{}' cannot have a body", name));
+        }
+
+        self.expect_next(TokenType::EndInterface)?;
+        self.next_token();
+
+        let definition = InterfaceDefinition {
+            name: name,
+            methods: methods
+        };
+
+        Ok(Rc::new(AstNode::InterfaceDefinition(definition)))
+    }
+
+
+    ///
+    /// Parse a single field of a struct, e.g. `y: int = 0`.
+    /// 
+    fn parse_struct_field(&mut self) -> eyre::Result<Field> {
+        self.expect_next(TokenType::Identifier)?;
+        let name = self.next_token().lexeme.to_string();
+
+        self.expect_next(TokenType::Colon)
+            .map_err(|_| eyre!("Expected a type annotation for field '{}'", name))?;
+        self.next_token();
+        let annotation = self.parse_type_annotation()?;
+
+        let mut default = None;
+        if self.peek().type_ == TokenType::Equal {
+            self.next_token();
+            default = Some(self.parse_equality()?);
+        }
+
+        Ok(Field {
+            name: name,
+            annotation: annotation,
+            default: default
+        })
+    }
+
+
+    ///
+    /// Ensure that the fields of a struct have unique names, and that fields without a
+    /// default do not follow ones with a default, as the generated dataclass requires.
+    /// 
+    fn check_struct_fields(name: &str, fields: &[Field]) -> eyre::Result<()> {
+        let mut names = HashSet::new();
+        let mut seen_default = false;
+
+        for field in fields {
+            if !names.insert(field.name.as_str()) {
+                return Err(eyre!("Duplicate field '{}' in struct '{}'", field.name, name));
+            }
+
+            if field.default.is_some() {
+                seen_default = true;
+            } else if seen_default {
+                return Err(eyre!("Field '{}' without a default follows a field with a default", field.name));
+            }
+        }
+
+        Ok(())
+    }
+
+
+    ///
+    /// Parse an enum definition. The variants of an enum are separated by commas, and a
+    /// trailing comma is permitted. Either every variant is followed by the fields it
+    /// carries, or none are:
+    /// ```nadra
+    /// enum <name>
+    ///     <variant>[(<field>: <type> [= <default>]?, ...)]?, ...
+    /// endenum
+    /// ```
+    /// 
+    fn parse_enum_definition(&mut self) -> ParseResult {
+        self.expect_next(TokenType::Enum)?;
+        self.next_token();
+
+        self.expect_next(TokenType::Identifier)?;
+        let name = self.next_token().lexeme.to_string();
+
+        let mut variants = vec![];
+        let mut names = HashSet::new();
+
+        while self.has_next() && self.peek().type_ != TokenType::EndEnum {
+            let variant = self.group(SyntaxKind::EnumVariant, Self::parse_enum_variant)?;
+            if !names.insert(variant.name.clone()) {
+                return Err(eyre!("Duplicate variant '{}' in enum '{}'", variant.name, name));
+            }
+            variants.push(variant);
+
+            if self.peek().type_ != TokenType::Comma {
+                break;
+            }
+            self.next_token();
+        }
+
+        self.expect_next(TokenType::EndEnum)?;
+        self.next_token();
+
+        if variants.is_empty() {
+            return Err(eyre!("Enum '{}' must have at least one variant", name));
+        }
+
+        let definition = EnumDefinition {
+            name: name,
+            variants: variants
+        };
+
+        Ok(Rc::new(AstNode::EnumDefinition(definition)))
+    }
+
+
+    fn parse_enum_variant(&mut self) -> eyre::Result<EnumVariant> {
+        self.expect_next(TokenType::Identifier)?;
+        let name = self.next_token().lexeme.to_string();
+
+        let mut fields = None;
+        if self.peek().type_ == TokenType::LeftParen {
+            self.next_token();
+
+            let mut parsed = vec![];
+            while self.has_next() && self.peek().type_ != TokenType::RightParen {
+                parsed.push(self.group(SyntaxKind::Field, Self::parse_struct_field)?);
+
+                if self.peek().type_ != TokenType::Comma {
+                    break;
+                }
+                self.next_token();
+            }
+
+            self.expect_next(TokenType::RightParen)?;
+            self.next_token();
+
+            Self::check_struct_fields(&name, &parsed)?;
+            fields = Some(parsed);
+        }
+
+        Ok(EnumVariant {
+            name: name,
+            fields: fields
+        })
+    }
 
 
     // fn parse_expression(&mut self) -> eyre::Result<AstNode> {
@@ -191,34 +687,230 @@ impl <'a> Parser <'a> {
 
 
     ///
-    /// Parse a list of parameters, separated by commas.
+    /// Parse a list of parameters, separated by commas. Each parameter may have a type
+    /// annotation and a default value, and Python's special parameters are supported:
+    /// ```nadra
+    /// def f(a, /, b: int, *args, c = 1, **kwargs) -> None
+    /// def g(a, *, key: str = "") -> None
+    /// ```
     /// 
-    fn parse_param_list(&mut self) -> eyre::Result<Vec<Rc<AstNode>>> {
-        let mut args = vec![];
+    fn parse_param_list(&mut self) -> eyre::Result<Vec<Parameter>> {
+        let mut params = vec![];
 
-        // If no parameters are required then do not attempt to parse them.
-        if self.has_next() && self.peek().type_ == TokenType::RightParen {
-            return Ok(vec![]);
+        while self.has_next() && self.peek().type_ != TokenType::RightParen {
+            params.push(self.group(SyntaxKind::Parameter, Self::parse_parameter)?);
+
+            if self.peek().type_ != TokenType::Comma {
+                break;
+            }
+            self.next_token();
+        }
+
+        Self::check_param_list(&params)?;
+        Ok(params)
+    }
+
+
+    ///
+    /// Parse the generic type parameters of a function or struct, if it has any, e.g.
+    /// `[T, N: float]`. Each may be given a bound after a colon.
+    /// 
+    fn parse_type_params(&mut self) -> eyre::Result<Vec<TypeParameter>> {
+        let mut params = vec![];
+        if self.peek().type_ != TokenType::LeftBracket {
+            return Ok(params);
+        }
+        self.next_token();
+
+        while self.has_next() && self.peek().type_ != TokenType::RightBracket {
+            params.push(self.group(SyntaxKind::TypeParameter, Self::parse_type_param)?);
+
+            if self.peek().type_ != TokenType::Comma {
+                break;
+            }
+            self.next_token();
+        }
+
+        self.expect_next(TokenType::RightBracket)?;
+        self.next_token();
+
+        let mut names = HashSet::new();
+        if let Some(duplicate) = params.iter().find(|param| !names.insert(param.name.as_str())) {
+            return Err(eyre!("Duplicate type parameter '{}'", duplicate.name));
         }
 
-        // Parse the first parameter.
-        args.push(self.parse_unary()?);
+        if params.is_empty() {
+            return Err(eyre!("Expected at least one type parameter between '[' and ']'"));
+        }
+        Ok(params)
+    }
+
 
-        // Parse the parameters until reaching the end of the param list.
-        while self.has_next() && self.peek().type_ == TokenType::Comma {
+    fn parse_type_param(&mut self) -> eyre::Result<TypeParameter> {
+        self.expect_next(TokenType::Identifier)?;
+        let name = self.next_token().lexeme.to_string();
+
+        let mut bound = None;
+        if self.peek().type_ == TokenType::Colon {
             self.next_token();
+            let annotation = self.parse_type_annotation()?;
+
+            // Constraints, e.g. `T: (int, float)`, of which Python requires at least two.
+            if let AstNode::TupleLiteral(constraints) = annotation.as_ref() && constraints.len() < 2 {
+                return Err(eyre!("Type parameter '{}' needs at least two constraints", name));
+            }
+            bound = Some(annotation);
+        }
+
+        Ok(TypeParameter {
+            name: name,
+            bound: bound
+        })
+    }
+
+
+    ///
+    /// Parse a single parameter, i.e. one of `name: type = default`, `*args`, `**kwargs`,
+    /// `/` or `*`.
+    /// 
+    fn parse_parameter(&mut self) -> eyre::Result<Parameter> {
+        use TokenType::{Asterisk, AsteriskAsterisk, Colon, Equal, Identifier, Slash};
+
+        let kind = match self.peek().type_ {
+            Slash => {
+                self.next_token();
+                return Ok(Parameter::marker(ParameterKind::PositionalOnlyMarker));
+            }
+
+            Asterisk => {
+                self.next_token();
+                if self.peek().type_ != Identifier {
+                    return Ok(Parameter::marker(ParameterKind::KeywordOnlyMarker));
+                }
+                ParameterKind::VarPositional
+            }
+
+            AsteriskAsterisk => {
+                self.next_token();
+                ParameterKind::VarKeyword
+            }
+
+            _ => ParameterKind::Normal
+        };
+
+        if self.peek().type_ != Identifier {
+            return Err(eyre!("Expected a parameter name, found {}", self.peek().lexeme));
+        }
+        let name = self.next_token().lexeme.to_string();
+
+        let mut annotation = None;
+        if self.peek().type_ == Colon {
+            self.next_token();
+            annotation = Some(self.parse_type_annotation()?);
+        }
+
+        let mut default = None;
+        if self.peek().type_ == Equal {
+            if kind != ParameterKind::Normal {
+                return Err(eyre!("Variadic parameter '{}' cannot have a default value", name));
+            }
+
+            self.next_token();
+            default = Some(self.parse_equality()?);
+        }
+
+        Ok(Parameter {
+            name: name,
+            kind: kind,
+            annotation: annotation,
+            default: default
+        })
+    }
+
+
+    ///
+    /// Ensure that the parameters of a function are in an order Python accepts. That is,
+    /// `/` comes before `*` or `*args`, `**kwargs` comes last, names are unique, and
+    /// positional parameters without defaults do not follow ones with defaults.
+    /// 
+    fn check_param_list(params: &[Parameter]) -> eyre::Result<()> {
+        use ParameterKind::*;
+
+        let mut names = HashSet::new();
+        let mut seen_positional_only = false;
+        let mut seen_star = false;
+        let mut seen_default = false;
+
+        for (i, param) in params.iter().enumerate() {
+            if i > 0 && params[i - 1].kind == VarKeyword {
+                return Err(eyre!("'**{}' must be the last parameter", params[i - 1].name));
+            }
+
+            match param.kind {
+                PositionalOnlyMarker => {
+                    if i == 0 {
+                        return Err(eyre!("'/' must follow at least one parameter"));
+                    }
+                    if seen_positional_only {
+                        return Err(eyre!("'/' may only appear once"));
+                    }
+                    if seen_star {
+                        return Err(eyre!("'/' must come before '*'"));
+                    }
+                    seen_positional_only = true;
+                }
+
+                KeywordOnlyMarker | VarPositional => {
+                    if seen_star {
+                        return Err(eyre!("'*' may only appear once"));
+                    }
+                    seen_star = true;
+
+                    let followed_by_name = params
+                        .get(i + 1)
+                        .is_some_and(|next| next.kind == Normal);
+
+                    if param.kind == KeywordOnlyMarker && !followed_by_name {
+                        return Err(eyre!("Named parameters must follow a bare '*'"));
+                    }
+                }
+
+                VarKeyword => {}
+
+                Normal if !seen_star => {
+                    if param.default.is_some() {
+                        seen_default = true;
+                    } else if seen_default {
+                        return Err(eyre!("Parameter '{}' without a default follows a parameter with a default", param.name));
+                    }
+                }
 
-            if self.has_next() && self.peek().type_ != TokenType::Identifier {
-                return Err(eyre!("Expected an identifier."));
+                // Keyword-only parameters may appear in any order.
+                Normal => {}
             }
 
-            args.push(self.parse_unary()?);
+            if !param.name.is_empty() && !names.insert(param.name.as_str()) {
+                return Err(eyre!("Duplicate parameter '{}'", param.name));
+            }
         }
 
-        Ok(args)
+        Ok(())
+    }
+
+
+    ///
+    /// Parse a type annotation. As in Python, annotations are expressions, but they are
+    /// limited to names, attributes and subscripts. For example,
+    /// ```nadra
+    /// dict[str, list[int]]
+    /// ```
+    /// 
+    fn parse_type_annotation(&mut self) -> ParseResult {
+        self.parse_postfix()
     }
 
 
+    #[allow(dead_code)]
     fn parse_lambda(&mut self) -> eyre::Result<Rc<AstNode>> {
         self.expect_next(TokenType::LeftParen)?;
         self.next_token();
@@ -239,6 +931,7 @@ impl <'a> Parser <'a> {
     /// then the left operand will be the result of the equality.
     /// 
     fn parse_equality(&mut self) -> eyre::Result<Rc<AstNode>> {
+        let checkpoint = self.cst.checkpoint();
         let mut left = self.parse_arithmetic()?;
 
         let equal_precedence = TokenType::EqualEqual.precedence_level();
@@ -259,6 +952,7 @@ impl <'a> Parser <'a> {
             };
 
             left = Rc::new(AstNode::BinaryOperation(operation));
+            self.cst.finish_ast(checkpoint, &left);
         }
 
         Ok(left)
@@ -278,6 +972,7 @@ impl <'a> Parser <'a> {
     /// Parse an expression.
     /// 
     fn parse_arithmetic(&mut self) -> eyre::Result<Rc<AstNode>> {
+        let checkpoint = self.cst.checkpoint();
         let mut left = self.parse_term()?;
         let precedence_level = TokenType::Plus.precedence_level();
 
@@ -297,6 +992,7 @@ impl <'a> Parser <'a> {
             };
 
             left = Rc::new(AstNode::BinaryOperation(operation));
+            self.cst.finish_ast(checkpoint, &left);
         }
 
         Ok(left)
@@ -307,6 +1003,7 @@ impl <'a> Parser <'a> {
     /// Parse a term. Terms are separated by `+`, `-`, etc.
     /// 
     fn parse_term(&mut self) -> eyre::Result<Rc<AstNode>> {
+        let checkpoint = self.cst.checkpoint();
         let mut left = self.parse_exponent()?;
         let precedence_level = TokenType::Asterisk.precedence_level();
 
@@ -328,6 +1025,7 @@ impl <'a> Parser <'a> {
             };
 
             left = Rc::new(AstNode::BinaryOperation(operation));
+            self.cst.finish_ast(checkpoint, &left);
         }
 
         Ok(left)
@@ -342,6 +1040,7 @@ impl <'a> Parser <'a> {
     /// Which is the same as saying *x^n* in math.
     /// 
     fn parse_exponent(&mut self) -> eyre::Result<Rc<AstNode>> {
+        let checkpoint = self.cst.checkpoint();
         let mut left = self.parse_unary()?;
         let precedence_level = TokenType::AsteriskAsterisk.precedence_level();
 
@@ -361,6 +1060,7 @@ impl <'a> Parser <'a> {
             };
 
             left = Rc::new(AstNode::BinaryOperation(operation));
+            self.cst.finish_ast(checkpoint, &left);
         }
 
         Ok(left)
@@ -404,23 +1104,117 @@ impl <'a> Parser <'a> {
 
 
     ///
-    /// Parse the list of arguments being passed to a function call.
+    /// Parse the list of arguments being passed to a function call. For example,
+    /// ```nadra
+    /// open(path, mode="w")
+    /// print(*lines, **options)
+    /// ```
     /// 
     fn parse_argument_list(&mut self) -> eyre::Result<Vec<Rc<AstNode>>> {
         let mut args = vec![];
 
-        if self.has_next() && self.peek().type_ == TokenType::RightParen {
-            return Ok(vec![]);
+        while self.has_next() && self.peek().type_ != TokenType::RightParen {
+            args.push(self.parse_argument()?);
+
+            if self.peek().type_ != TokenType::Comma {
+                break;
+            }
+            self.next_token();
         }
 
-        args.push(self.parse_equality()?);
+        self.check_argument_list(&args)?;
+        Ok(args)
+    }
+
+
+    ///
+    /// Parse a single argument of a function call, which is either an expression,
+    /// an unpacked value (`*args` or `**kwargs`), or a keyword argument (`name=value`).
+    /// 
+    fn parse_argument(&mut self) -> ParseResult {
+        use TokenType::{Asterisk, AsteriskAsterisk, Equal};
+
+        let checkpoint = self.cst.checkpoint();
+
+        let operator = match self.peek().type_ {
+            Asterisk => Some(UnpackOperator::Iterable),
+            AsteriskAsterisk => Some(UnpackOperator::Mapping),
+            _ => None
+        };
 
-        while self.has_next() && self.peek().type_ == TokenType::Comma {
+        if let Some(operator) = operator {
             self.next_token();
-            args.push(self.parse_equality()?);
+
+            let unpack = Unpack {
+                operator: operator,
+                value: self.parse_arithmetic()?
+            };
+
+            let node = Rc::new(AstNode::Unpack(unpack));
+            self.cst.finish_ast(checkpoint, &node);
+            return Ok(node);
         }
 
-        Ok(args)
+        let value = self.parse_equality()?;
+        if !self.has_next() || self.peek().type_ != Equal {
+            return Ok(value);
+        }
+
+        let AstNode::Identifier(name) = value.as_ref() else {
+            return Err(eyre!("Expected a parameter name before '=', found {}", value));
+        };
+
+        self.next_token();
+
+        let argument = KeywordArgument {
+            name: name.to_string(),
+            value: self.parse_equality()?
+        };
+
+        let node = Rc::new(AstNode::KeywordArgument(argument));
+        self.cst.finish_ast(checkpoint, &node);
+        Ok(node)
+    }
+
+
+    ///
+    /// Ensure that the arguments of a function call are in an order Python accepts. That is,
+    /// positional arguments do not follow keyword arguments or `**` unpacking, and
+    /// no keyword is passed twice.
+    /// 
+    fn check_argument_list(&self, args: &[Rc<AstNode>]) -> eyre::Result<()> {
+        let mut keywords = HashSet::new();
+        let mut seen_keyword = false;
+        let mut seen_mapping = false;
+
+        for arg in args {
+            match arg.as_ref() {
+                AstNode::KeywordArgument(argument) => {
+                    if !keywords.insert(argument.name.as_str()) {
+                        return Err(eyre!("Keyword argument '{}' is repeated", argument.name));
+                    }
+                    seen_keyword = true;
+                }
+
+                AstNode::Unpack(unpack) => match unpack.operator {
+                    UnpackOperator::Mapping => seen_mapping = true,
+
+                    UnpackOperator::Iterable if seen_mapping => {
+                        return Err(eyre!("Iterable unpacking cannot follow '**' unpacking"));
+                    }
+
+                    UnpackOperator::Iterable => {}
+                }
+
+                _ if seen_keyword || seen_mapping => {
+                    return Err(eyre!("Positional argument '{}' follows a keyword argument", self.code_of(arg)));
+                }
+
+                _ => {}
+            }
+        }
+
+        Ok(())
     }
 
 
@@ -448,8 +1242,119 @@ impl <'a> Parser <'a> {
         }
 
         let token = self.peek();
+        let checkpoint = self.cst.checkpoint();
+
+        let node = match token.as_ref().type_ {
+            Return => {
+                self.next_token();
+                if !self.has_next() {
+                    return Err(eyre!("Expected an expression"));
+                }
+
+                let expr = ReturnStatement { 
+                    body: self.parse_equality()?
+                };
+
+                Ok(Rc::new(AstNode::ReturnStatement(expr)))
+            }
+
+            Minus => {
+                self.next_token();
+                if !self.has_next() {
+                    return Err(eyre!("Expected an expression"));
+                }
+
+                let expr = self.parse_unary()?;
+
+                let operation = UnaryOperation {
+                    operator: UnaryOperator::Minus,
+                    operand: expr,
+                };
+
+                let node = Rc::new(AstNode::UnaryOperation(operation));
+                Ok(node)
+            },
+
+            Bang => {
+                self.next_token();
+                if !self.has_next() {
+                    return Err(eyre!("Expected an expression"));
+                }
+
+                let expr = self.parse_unary()?;
+
+                let operation = UnaryOperation {
+                    operator: UnaryOperator::LogicalNot,
+                    operand: expr,
+                };
+
+                let node = Rc::new(AstNode::UnaryOperation(operation));
+                Ok(node)
+            }
+
+            _ => self.parse_postfix()
+        }?;
+
+        self.cst.finish_ast(checkpoint, &node);
+        Ok(node)
+    }
+
+
+    ///
+    /// Parse a primary expression followed by any number of calls, attribute accesses
+    /// and subscripts. For example,
+    /// ```nadra
+    /// get_list()[0].name
+    /// ```
+    /// Which roughly equates to
+    /// ```lisp
+    /// (MemberAccess
+    ///     (Subscript (Call get_list ()) 0)
+    ///     name
+    /// )
+    /// ```
+    /// 
+    /// Calls and subscripts must open on the same line as the expression they apply to,
+    /// otherwise the `(` or `[` is considered to begin a new statement.
+    /// 
+    fn parse_postfix(&mut self) -> ParseResult {
+        use TokenType::{Dot, Identifier, LeftParen, LeftBracket, ScopeOperator};
+
+        let checkpoint = self.cst.checkpoint();
+        let mut expr = self.parse_primary()?;
+
+        loop {
+            let names_item = self.peek_second().is_some_and(|token| token.type_ == Identifier);
+
+            expr = match self.peek().type_ {
+                Dot => self.parse_member_access(expr)?,
+
+                ScopeOperator if names_item && !self.in_slice_bound => self.parse_scope_access(expr)?,
+
+                LeftParen if self.next_on_same_line() => self.parse_call(expr)?,
+
+                LeftBracket if self.next_on_same_line() => self.parse_subscript(expr)?,
+
+                _ => break
+            };
+            self.cst.finish_ast(checkpoint, &expr);
+        }
+
+        Ok(expr)
+    }
+
+
+    ///
+    /// Parse a primary expression. That is, a literal, an identifier, or an
+    /// expression between parentheses.
+    /// 
+    fn parse_primary(&mut self) -> ParseResult {
+        use TokenType::*;
+
+        let token = self.peek();
+        let checkpoint = self.cst.checkpoint();
 
-        match token.as_ref().type_ {
+        let node = match token.type_ {
             Integer | Float | TokenType::String
                  => self.parse_literal(),
 
@@ -465,140 +1370,419 @@ impl <'a> Parser <'a> {
                 Ok(node)
             }
 
-            Return => {
+            Identifier => {
+                let name = self.next_token().lexeme.to_string();
+                Ok(Rc::new(AstNode::Identifier(name)))
+            }
+
+            LeftParen => self.parse_parentheses(),
+
+            LeftBracket => self.parse_list_literal(),
+
+            LeftBrace => self.parse_brace_literal(),
+
+            _ => Err(eyre!("Unexpected token: {}", token.lexeme))
+        }?;
+
+        self.cst.finish_ast(checkpoint, &node);
+        Ok(node)
+    }
+
+
+    ///
+    /// Parse `.child` following `parent`, where `child` is an identifier. For example,
+    /// ```nadra
+    /// my_obj.field
+    /// ```
+    /// 
+    fn parse_member_access(&mut self, parent: Rc<AstNode>) -> ParseResult {
+        self.expect_next(TokenType::Dot)?;
+        self.next_token();
+
+        self.expect_next(TokenType::Identifier)?;
+        let name = self.next_token().lexeme.to_string();
+
+        let node = MemberAccess {
+            parent: parent,
+            child: Rc::new(AstNode::Identifier(name)),
+        };
+
+        Ok(Rc::new(AstNode::MemberAccess(node)))
+    }
+
+
+    ///
+    /// Parse `::name` following `parent`. For example,
+    /// ```nadra
+    /// RenderModes::Minimal
+    /// ```
+    /// 
+    fn parse_scope_access(&mut self, parent: Rc<AstNode>) -> ParseResult {
+        self.expect_next(TokenType::ScopeOperator)?;
+        self.next_token();
+
+        self.expect_next(TokenType::Identifier)?;
+        let name = self.next_token().lexeme.to_string();
+
+        let node = ScopeAccess {
+            parent: parent,
+            name: name
+        };
+
+        Ok(Rc::new(AstNode::ScopeAccess(node)))
+    }
+
+
+    ///
+    /// Parse the argument list of a call to `function`. For example,
+    /// ```nadra
+    /// obj.method(x, y)
+    /// ```
+    /// 
+    fn parse_call(&mut self, function: Rc<AstNode>) -> ParseResult {
+        self.expect_next(TokenType::LeftParen)?;
+        self.next_token();
+
+        // Parse the arguments to the function
+        let args = self.parse_argument_list()?;
+
+        // Skip the trailing ')'
+        self.expect_next(TokenType::RightParen)?;
+        self.next_token();
+
+        let call = FunctionCall {
+            function: function,
+            args: args
+        };
+
+        Ok(Rc::new(AstNode::FunctionCall(call)))
+    }
+
+
+    ///
+    /// Parse the subscript following `value`. For example,
+    /// ```nadra
+    /// rows[i]
+    /// ```
+    /// 
+    fn parse_subscript(&mut self, value: Rc<AstNode>) -> ParseResult {
+        use TokenType::{Comma, LeftBracket, RightBracket};
+
+        self.expect_next(LeftBracket)?;
+        self.next_token();
+
+        let mut index = self.parse_slice()?;
+
+        // Several comma separated indices form a tuple, e.g. `grid[i, j]`.
+        if self.has_next() && self.peek().type_ == Comma {
+            let mut indices = vec![index];
+            while self.has_next() && self.peek().type_ == Comma {
                 self.next_token();
-                if !self.has_next() {
-                    return Err(eyre!("Expected an expression"));
+                if self.peek().type_ == RightBracket {
+                    break;
+                }
+                indices.push(self.parse_slice()?);
+            }
+            index = Rc::new(AstNode::TupleLiteral(indices));
+        }
+
+        self.expect_next(RightBracket)?;
+        self.next_token();
+
+        let subscript = Subscript {
+            value: value,
+            index: index
+        };
+
+        Ok(Rc::new(AstNode::Subscript(subscript)))
+    }
+
+
+    ///
+    /// Parse a single index within a subscript, which is either an expression or a slice.
+    /// Slices use Python's `lower:upper:step` syntax, or Nadra's range syntax:
+    /// ```nadra
+    /// s[1:-1:2]
+    /// s[::2]
+    /// xs[1..3]
+    /// ```
+    /// 
+    fn parse_slice(&mut self) -> ParseResult {
+        use TokenType::{Colon, Comma, DotDot, RightBracket, ScopeOperator};
+
+        let checkpoint = self.cst.checkpoint();
+        let lower = match self.peek().type_ {
+            Colon | DotDot | ScopeOperator => None,
+            _ => {
+                let in_slice_bound = std::mem::replace(&mut self.in_slice_bound, true);
+                let lower = self.parse_equality();
+                self.in_slice_bound = in_slice_bound;
+                Some(lower?)
+            }
+        };
+
+        let slice = match self.peek().type_ {
+            DotDot => {
+                self.next_token();
+                Slice {
+                    lower: lower,
+                    upper: self.parse_slice_bound(&[Comma, RightBracket])?,
+                    step: None
+                }
+            }
+
+            Colon => {
+                self.next_token();
+                let upper = self.parse_slice_bound(&[Colon, Comma, RightBracket])?;
+
+                let mut step = None;
+                if self.peek().type_ == Colon {
+                    self.next_token();
+                    step = self.parse_slice_bound(&[Comma, RightBracket])?;
                 }
 
-                let expr = ReturnStatement { 
-                    body: self.parse_equality()?
-                };
+                Slice {
+                    lower: lower,
+                    upper: upper,
+                    step: step
+                }
+            }
+
+            // `::` is scanned as a single token, and leaves out the upper bound.
+            ScopeOperator => {
+                self.next_token();
+                Slice {
+                    lower: lower,
+                    upper: None,
+                    step: self.parse_slice_bound(&[Comma, RightBracket])?
+                }
+            }
+
+            // Not a slice, just an ordinary index.
+            _ => return lower.ok_or_eyre("Expected an index")
+        };
+
+        let node = Rc::new(AstNode::Slice(slice));
+        self.cst.finish_ast(checkpoint, &node);
+        Ok(node)
+    }
+
+
+    ///
+    /// Parse an optional bound of a slice, which is omitted if the next token is one
+    /// of `terminators`.
+    /// 
+    fn parse_slice_bound(&mut self, terminators: &[TokenType]) -> eyre::Result<Option<Rc<AstNode>>> {
+        if !self.has_next() || terminators.contains(&self.peek().type_) {
+            return Ok(None);
+        }
+        Ok(Some(self.parse_equality()?))
+    }
+
+
+    ///
+    /// Parse an expression between parentheses, or a tuple literal. A tuple is
+    /// distinguished from a parenthesized expression by its commas:
+    /// ```nadra
+    /// ()          # An empty tuple
+    /// (x)         # Just `x`
+    /// (x,)        # A tuple containing `x`
+    /// (x, *rest)  # A tuple containing `x` and everything in `rest`
+    /// ```
+    /// 
+    fn parse_parentheses(&mut self) -> eyre::Result<Rc<AstNode>> {
+        let checkpoint = self.cst.checkpoint();
+        self.expect_next(TokenType::LeftParen)?;
+        self.next_token();
+
+        if self.has_next() && self.peek().type_ == TokenType::RightParen {
+            self.next_token();
+            return Ok(Rc::new(AstNode::TupleLiteral(vec![])));
+        }
 
-                Ok(Rc::new(AstNode::ReturnStatement(expr)))
-            }
+        let first = self.parse_collection_element()?;
 
-            Identifier => self.parse_identifier(),
+        if !self.has_next() {
+            return Err(eyre!("Expected ')'"));
+        }
 
-            LeftParen => self.parse_parentheses(),
+        // Without a comma this is just an expression in parentheses.
+        if self.peek().type_ != TokenType::Comma {
+            if let AstNode::Unpack(_) = first.as_ref() {
+                return Err(eyre!("Cannot unpack a value outside of a collection"));
+            }
 
-            Minus => {
-                self.next_token();
-                if !self.has_next() {
-                    return Err(eyre!("Expected an expression"));
-                }
+            self.expect_next(TokenType::RightParen).map_err(|_| eyre!("Expected ')'"))?;
+            self.next_token();
+            self.cst.finish_node(checkpoint, SyntaxKind::Parenthesized, Some(first.clone()));
+            return Ok(first);
+        }
 
-                let expr = self.parse_unary()?;
+        self.next_token();
+        let mut elements = vec![first];
+        elements.extend(self.parse_collection_elements(TokenType::RightParen)?);
 
-                let operation = UnaryOperation {
-                    operator: UnaryOperator::Minus,
-                    operand: expr,
-                };
+        self.expect_next(TokenType::RightParen)?;
+        self.next_token();
 
-                let node = Rc::new(AstNode::UnaryOperation(operation));
-                Ok(node)
-            },
+        Ok(Rc::new(AstNode::TupleLiteral(elements)))
+    }
 
-            Bang => {
-                self.next_token();
-                if !self.has_next() {
-                    return Err(eyre!("Expected an expression"));
-                }
 
-                let expr = self.parse_unary()?;
+    ///
+    /// Parse a list literal. For example,
+    /// ```nadra
+    /// [1, 2, *others]
+    /// ```
+    /// 
+    fn parse_list_literal(&mut self) -> ParseResult {
+        self.expect_next(TokenType::LeftBracket)?;
+        self.next_token();
 
-                let operation = UnaryOperation {
-                    operator: UnaryOperator::LogicalNot,
-                    operand: expr,
-                };
+        let elements = self.parse_collection_elements(TokenType::RightBracket)?;
 
-                let node = Rc::new(AstNode::UnaryOperation(operation));
-                Ok(node)
-            }
+        self.expect_next(TokenType::RightBracket)?;
+        self.next_token();
 
-            _ => Err(eyre!("Unexpected token: {}", token.lexeme))
-        }
+        Ok(Rc::new(AstNode::ListLiteral(elements)))
     }
 
 
     ///
-    /// Parse `parent.child` where `parent` and `child` are identifiers. For example,
+    /// Parse a literal between braces, which is either a dictionary or a set. As in
+    /// Python, `{}` is an empty dictionary.
     /// ```nadra
-    /// my_obj.field
+    /// {"a": 1, **defaults}    # A dictionary
+    /// {1, 2, *others}         # A set
     /// ```
     /// 
-    fn parse_member_access(&mut self, parent: Rc<AstNode>) -> ParseResult {
-        self.expect_next(TokenType::Dot)?;
+    fn parse_brace_literal(&mut self) -> ParseResult {
+        use TokenType::{Asterisk, AsteriskAsterisk, Colon, Comma, LeftBrace, RightBrace};
+
+        self.expect_next(LeftBrace)?;
         self.next_token();
 
-        self.expect_next(TokenType::Identifier)?;
-        let child = self.parse_identifier()?;
+        let literal = match self.peek().type_ {
+            RightBrace => AstNode::DictLiteral(vec![]),
 
-        let node = MemberAccess {
-            parent: parent,
-            child: child,
+            AsteriskAsterisk => AstNode::DictLiteral(self.parse_dict_entries(None)?),
+
+            Asterisk => AstNode::SetLiteral(self.parse_collection_elements(RightBrace)?),
+
+            _ => {
+                let first = self.parse_equality()?;
+
+                if self.has_next() && self.peek().type_ == Colon {
+                    AstNode::DictLiteral(self.parse_dict_entries(Some(first))?)
+                } else {
+                    let mut elements = vec![first];
+                    if self.has_next() && self.peek().type_ == Comma {
+                        self.next_token();
+                        elements.extend(self.parse_collection_elements(RightBrace)?);
+                    }
+                    AstNode::SetLiteral(elements)
+                }
+            }
         };
 
-        Ok(Rc::new(AstNode::MemberAccess(node)))
+        self.expect_next(RightBrace)?;
+        self.next_token();
+
+        Ok(Rc::new(literal))
     }
 
 
-    fn parse_identifier(&mut self) -> ParseResult {
-        use TokenType::{Dot, LeftParen, RightParen, Identifier};
+    ///
+    /// Parse comma separated elements of a list, tuple or set up to (but not including)
+    /// `closing`. A trailing comma is permitted.
+    /// 
+    fn parse_collection_elements(&mut self, closing: TokenType) -> eyre::Result<Vec<Rc<AstNode>>> {
+        let mut elements = vec![];
 
-        let token = self.next_token();
-        let value = token.as_ref().lexeme.to_string();
+        while self.has_next() && self.peek().type_ != closing {
+            elements.push(self.parse_collection_element()?);
 
-        if self.peek().type_ == Dot {
-            let node = Rc::new(AstNode::Identifier(value));
-            return self.parse_member_access(node);
+            if self.peek().type_ != TokenType::Comma {
+                break;
+            }
+            self.next_token();
         }
 
-        if self.peek().type_ == LeftParen {
-            self.next_token();
-            
-            // Parse the arguments to the function
-            let args = self.parse_argument_list()?;
+        Ok(elements)
+    }
 
-            // Skip the trailing ')'
-            self.expect_next(RightParen)?;
+
+    ///
+    /// Parse a single element of a list, tuple or set, which may be unpacked with `*`.
+    /// 
+    fn parse_collection_element(&mut self) -> ParseResult {
+        if self.has_next() && self.peek().type_ == TokenType::Asterisk {
+            let checkpoint = self.cst.checkpoint();
             self.next_token();
 
-            let call = FunctionCall {
-                function: value,
-                args: args
+            let unpack = Unpack {
+                operator: UnpackOperator::Iterable,
+                value: self.parse_arithmetic()?
             };
 
-            let node = Rc::new(AstNode::FunctionCall(call));
+            let node = Rc::new(AstNode::Unpack(unpack));
+            self.cst.finish_ast(checkpoint, &node);
             return Ok(node);
         }
-        
 
-        let node = Rc::new(AstNode::Identifier(value));
-        Ok(node)
+        self.parse_equality()
     }
 
 
     ///
-    /// Parse an expression between parentheses.
+    /// Parse the entries of a dictionary literal up to (but not including) the closing
+    /// brace. If the key of the first entry has already been parsed, it is given
+    /// by `first_key`.
     /// 
-    fn parse_parentheses(&mut self) -> eyre::Result<Rc<AstNode>> {
-        self.next_token();
-        let expr = self.parse_equality()?;
+    fn parse_dict_entries(&mut self, first_key: Option<Rc<AstNode>>) -> eyre::Result<Vec<DictEntry>> {
+        let mut entries = vec![];
+        let mut first_key = first_key;
+
+        while self.has_next() && self.peek().type_ != TokenType::RightBrace {
+            let entry = match first_key.take() {
+                Some(key) => self.parse_dict_value(key)?,
+                None => self.parse_dict_entry()?
+            };
+            entries.push(entry);
 
-        if !self.has_next() {
-            return Err(eyre!("Expected ')'"));
+            if self.peek().type_ != TokenType::Comma {
+                break;
+            }
+            self.next_token();
         }
 
-        let right_paren = self.peek();
+        Ok(entries)
+    }
+
 
-        return match right_paren.type_ {
-            TokenType::RightParen => {
-                self.next_token();
-                Ok(expr)
-            },
-            _ => Err(eyre!("Expected ')'"))
-        };
+    ///
+    /// Parse either `key: value` or `**mapping` inside of a dictionary literal.
+    /// 
+    fn parse_dict_entry(&mut self) -> eyre::Result<DictEntry> {
+        if self.has_next() && self.peek().type_ == TokenType::AsteriskAsterisk {
+            self.next_token();
+            return Ok(DictEntry::Unpack(self.parse_arithmetic()?));
+        }
+
+        let key = self.parse_equality()?;
+        self.parse_dict_value(key)
+    }
+
+
+    ///
+    /// Parse the `: value` following `key` inside of a dictionary literal.
+    /// 
+    fn parse_dict_value(&mut self, key: Rc<AstNode>) -> eyre::Result<DictEntry> {
+        self.expect_next(TokenType::Colon)?;
+        self.next_token();
+
+        let value = self.parse_equality()?;
+        Ok(DictEntry::KeyValue { key: key, value: value })
     }
 
 
@@ -621,6 +1805,28 @@ impl <'a> Parser <'a> {
     /// ```
     /// 
     fn parse_function_definition(&mut self) -> eyre::Result<Rc<AstNode>> {
+        let mut function = self.parse_function_signature()?;
+
+        // Parse the body of the function.
+        while self.has_next() && self.expect_next(TokenType::EndDef).is_err() {
+            let statement = self.parse_statement()?;
+            function.body.push(statement);
+        }
+
+        // Ensure the function ends with `enddef`.
+        self.expect_next(TokenType::EndDef)?;
+        self.next_token();
+
+        let node = Rc::new(AstNode::FunctionDefinition(function));
+        Ok(node)
+    }
+
+
+    ///
+    /// Parse the signature of a function, i.e. everything up to its body, which is left
+    /// empty. The methods of an interface are only a signature.
+    /// 
+    fn parse_function_signature(&mut self) -> eyre::Result<FunctionDefinition> {
         // Ensure the first token is `def`
         self.expect_next(TokenType::Def)?;
         self.next_token();
@@ -629,6 +1835,8 @@ impl <'a> Parser <'a> {
         self.expect_next(TokenType::Identifier)?;
         let identifier = self.next_token().lexeme.to_string();
 
+        let type_params = self.parse_type_params()?;
+
         // Ensure the next token is the beginning of the parameter list.
         self.expect_next(TokenType::LeftParen)?;
         self.next_token();
@@ -646,28 +1854,15 @@ impl <'a> Parser <'a> {
 
         // Ensure a return type was specified.
         self.expect_next(TokenType::Identifier)?;
-        let return_type = self.next_token().type_.clone();
-
-        // Parse the body of the function.
-        let mut body = vec![];
-        while self.has_next() && self.expect_next(TokenType::EndDef).is_err() {
-            let statement = self.parse_statement()?;
-            body.push(statement);
-        }
-
-        // Ensure the function ends with `enddef`.
-        self.expect_next(TokenType::EndDef)?;
-        self.next_token();
+        let return_type = self.parse_type_annotation()?;
 
-        let function = FunctionDefinition {
+        Ok(FunctionDefinition {
             name: identifier,
             return_type: return_type,
+            type_params: type_params,
             param_list: params,
-            body: body,
-        };
-
-        let node = Rc::new(AstNode::FunctionDefinition(function));
-        Ok(node)
+            body: vec![],
+        })
     }
 
 
@@ -717,6 +1912,256 @@ impl <'a> Parser <'a> {
     }
 
 
+    ///
+    /// Parse a match statement. A match statement takes the following form:
+    /// ```nadra
+    /// match <subject> with
+    ///     case <pattern> [if <guard>]? then
+    ///         <body>
+    ///     ...
+    /// endmatch
+    /// ```
+    /// 
+    fn parse_match_statement(&mut self) -> ParseResult {
+        self.expect_next(TokenType::Match)?;
+        self.next_token();
+
+        let subject = self.parse_equality()?;
+
+        self.expect_next(TokenType::With)?;
+        self.next_token();
+
+        let mut cases: Vec<MatchCase> = vec![];
+        while self.has_next() && self.peek().type_ == TokenType::Case {
+            // Python rejects cases which can never be reached, and so does Nadra.
+            if let Some(previous) = cases.last()
+                && previous.guard.is_none()
+                && previous.pattern.is_irrefutable() {
+                let name = match &previous.pattern {
+                    Pattern::Capture(name) => name.as_str(),
+                    _ => "_"
+                };
+                return Err(eyre!("'{}' matches anything, so the cases after it are unreachable", name));
+            }
+
+            cases.push(self.group(SyntaxKind::MatchCase, Self::parse_match_case)?);
+        }
+
+        self.expect_next(TokenType::EndMatch)?;
+        self.next_token();
+
+        if cases.is_empty() {
+            return Err(eyre!("A match statement must have at least one case"));
+        }
+
+        let statement = MatchStatement {
+            subject: subject,
+            cases: cases
+        };
+
+        Ok(Rc::new(AstNode::MatchStatement(statement)))
+    }
+
+
+    fn parse_match_case(&mut self) -> eyre::Result<MatchCase> {
+        self.expect_next(TokenType::Case)?;
+        self.next_token();
+
+        let pattern = self.parse_pattern()?;
+
+        let mut names = HashSet::new();
+        if let Some(name) = pattern.bindings().into_iter().find(|name| !names.insert(*name)) {
+            return Err(eyre!("'{}' is bound more than once by the same pattern", name));
+        }
+
+        let mut guard = None;
+        if self.peek().type_ == TokenType::If {
+            self.next_token();
+            guard = Some(self.parse_equality()?);
+        }
+
+        self.expect_next(TokenType::Then)?;
+        self.next_token();
+
+        let mut body = vec![];
+        while self.has_next() && !matches!(self.peek().type_, TokenType::Case | TokenType::EndMatch) {
+            body.push(self.parse_statement()?);
+        }
+
+        Ok(MatchCase {
+            pattern: pattern,
+            guard: guard,
+            body: body
+        })
+    }
+
+
+    ///
+    /// Parse the pattern of a match case. A bare name captures the value, unless it is `_`
+    /// or one of the constants `None`, `True` and `False`, while a name within a type or
+    /// module, e.g. `Color::Red`, is compared against it.
+    /// ```nadra
+    /// case 0 then
+    /// case Color::Red then
+    /// case Point(0, y=height) then
+    /// case Shape::Circle(radius) then
+    /// case [first, *rest] then
+    /// ```
+    /// 
+    fn parse_pattern(&mut self) -> eyre::Result<Pattern> {
+        use TokenType::{Dot, False, Float, Identifier, Integer, LeftBracket, LeftParen, Minus, ScopeOperator, True};
+
+        let token = self.peek();
+        let qualified = self.peek_second().is_some_and(|next| matches!(next.type_, Dot | ScopeOperator | LeftParen));
+
+        match token.type_ {
+            Identifier if token.lexeme == "_" => self.group(SyntaxKind::WildcardPattern, |parser| {
+                parser.next_token();
+                Ok(Pattern::Wildcard)
+            }),
+
+            Identifier if matches!(token.lexeme.as_str(), "None" | "True" | "False") => self.group(SyntaxKind::LiteralPattern, |parser| {
+                Ok(Pattern::Literal(parser.parse_primary()?))
+            }),
+
+            Identifier if !qualified => self.group(SyntaxKind::CapturePattern, |parser| {
+                Ok(Pattern::Capture(parser.next_token().lexeme.to_string()))
+            }),
+
+            Identifier => self.parse_class_pattern(),
+
+            Integer | Float | TokenType::String | True | False | Minus => {
+                self.group(SyntaxKind::LiteralPattern, Self::parse_literal_pattern)
+            }
+
+            LeftBracket => self.group(SyntaxKind::SequencePattern, Self::parse_sequence_pattern),
+
+            _ => Err(eyre!("Expected a pattern, found {}", token.lexeme))
+        }
+    }
+
+
+    fn parse_literal_pattern(&mut self) -> eyre::Result<Pattern> {
+        if self.peek().type_ != TokenType::Minus {
+            return Ok(Pattern::Literal(self.parse_primary()?));
+        }
+
+        let checkpoint = self.cst.checkpoint();
+        self.next_token();
+
+        if !matches!(self.peek().type_, TokenType::Integer | TokenType::Float) {
+            return Err(eyre!("Expected a number after '-' in a pattern"));
+        }
+
+        let operation = UnaryOperation {
+            operator: UnaryOperator::Minus,
+            operand: self.parse_primary()?
+        };
+
+        let node = Rc::new(AstNode::UnaryOperation(operation));
+        self.cst.finish_ast(checkpoint, &node);
+        Ok(Pattern::Literal(node))
+    }
+
+
+    ///
+    /// Parse a value pattern, e.g. `Color::Red`, or a class pattern, e.g. `Point(x, y=0)`.
+    /// Which of the two it is is only known once the name has been parsed.
+    /// 
+    fn parse_class_pattern(&mut self) -> eyre::Result<Pattern> {
+        let checkpoint = self.cst.checkpoint();
+
+        let name_checkpoint = self.cst.checkpoint();
+        let mut class = self.parse_primary()?;
+        loop {
+            class = match self.peek().type_ {
+                TokenType::Dot => self.parse_member_access(class)?,
+                TokenType::ScopeOperator => self.parse_scope_access(class)?,
+                _ => break
+            };
+            self.cst.finish_ast(name_checkpoint, &class);
+        }
+
+        if self.peek().type_ != TokenType::LeftParen {
+            self.cst.finish_node(checkpoint, SyntaxKind::ValuePattern, None);
+            return Ok(Pattern::Value(class));
+        }
+        self.next_token();
+
+        let mut positional = vec![];
+        let mut keywords: Vec<(String, Pattern)> = vec![];
+
+        while self.has_next() && self.peek().type_ != TokenType::RightParen {
+            let is_keyword = self.peek().type_ == TokenType::Identifier
+                && self.peek_second().is_some_and(|next| next.type_ == TokenType::Equal);
+
+            if is_keyword {
+                let (name, pattern) = self.group(SyntaxKind::KeywordPattern, |parser| {
+                    let name = parser.next_token().lexeme.to_string();
+                    parser.next_token();
+                    Ok((name, parser.parse_pattern()?))
+                })?;
+
+                if keywords.iter().any(|(existing, _)| *existing == name) {
+                    return Err(eyre!("Duplicate keyword pattern '{}'", name));
+                }
+                keywords.push((name, pattern));
+            } else if keywords.is_empty() {
+                positional.push(self.parse_pattern()?);
+            } else {
+                return Err(eyre!("Positional patterns must come before keyword patterns"));
+            }
+
+            if self.peek().type_ != TokenType::Comma {
+                break;
+            }
+            self.next_token();
+        }
+
+        self.expect_next(TokenType::RightParen)?;
+        self.next_token();
+
+        self.cst.finish_node(checkpoint, SyntaxKind::ClassPattern, None);
+        Ok(Pattern::Class {
+            class: class,
+            positional: positional,
+            keywords: keywords
+        })
+    }
+
+
+    fn parse_sequence_pattern(&mut self) -> eyre::Result<Pattern> {
+        self.expect_next(TokenType::LeftBracket)?;
+        self.next_token();
+
+        let mut elements = vec![];
+        while self.has_next() && self.peek().type_ != TokenType::RightBracket {
+            if self.peek().type_ != TokenType::Asterisk {
+                elements.push(self.parse_pattern()?);
+            } else if elements.iter().any(|element| matches!(element, Pattern::Star(_))) {
+                return Err(eyre!("A sequence pattern may only have one starred name"));
+            } else {
+                elements.push(self.group(SyntaxKind::StarPattern, |parser| {
+                    parser.next_token();
+                    parser.expect_next(TokenType::Identifier)?;
+                    let name = parser.next_token().lexeme.to_string();
+                    Ok(Pattern::Star(Some(name).filter(|name| name != "_")))
+                })?);
+            }
+
+            if self.peek().type_ != TokenType::Comma {
+                break;
+            }
+            self.next_token();
+        }
+
+        self.expect_next(TokenType::RightBracket)?;
+        self.next_token();
+
+        Ok(Pattern::Sequence(elements))
+    }
+
+
     // fn parse_for_in_loop(&mut self) -> eyre::Result<AstNode> {
 
     // }
@@ -753,6 +2198,24 @@ impl <'a> Parser <'a> {
 /// of every statement in the source code.
 /// 
 pub fn generate_ast(token_stream: &mut PieTokenStream) -> eyre::Result<Vec<Rc<AstNode>>> {
+    Ok(parse(token_stream)?.ast())
+}
+
+
+///
+/// Given a stream of tokens, `token_stream`, construct the lossless concrete syntax tree
+/// of the source code. The AST is derived from it by [`SyntaxTree::ast`].
+/// 
+pub fn parse(token_stream: &mut PieTokenStream) -> eyre::Result<SyntaxTree> {
     let mut parser = Parser::new(token_stream);
-    parser.parse_program()
+
+    // Errors are reported at the token the parser stopped at.
+    if let Err(e) = parser.parse_program() {
+        return Err(match parser.token_stream.peek() {
+            Some(token) => SyntaxError::wrap(e, token.line, token.column, token.lexeme.chars().count() as u32),
+            None => e
+        });
+    }
+
+    Ok(parser.cst.finish())
 }
\ No newline at end of file
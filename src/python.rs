@@ -0,0 +1,144 @@
+use std::{
+    io::{self, BufRead, BufReader, Write},
+    process::{Child, ChildStdin, ChildStdout, Command, Stdio}
+};
+use color_eyre::eyre::{self, eyre};
+
+
+/// Written by the driver after each snippet has finished running. Must match `DRIVER`.
+const SENTINEL: &str = "\x1enadra-done\x1e";
+
+
+///
+/// The program run by the Python process. It reads snippets of code, each preceded by a
+/// line containing its length, and runs them in a single namespace. Snippets are compiled
+/// in `'single'` mode so that the values of expressions are printed just like in Python's
+/// own REPL, and `from __future__` imports apply to every later snippet. Since the
+/// protocol uses `stdin`, the program's `stdin` is replaced with an empty file, so
+/// `input()` raises `EOFError`.
+///
+const DRIVER: &str = r#"
+import codeop, os, sys, traceback
+
+protocol = sys.stdin
+sys.stdin = open(os.devnull)
+namespace = {"__name__": "__main__"}
+compiler = codeop.Compile()
+
+while True:
+    header = protocol.readline()
+    if not header:
+        break
+    source = protocol.read(int(header))
+    try:
+        exec(compiler(source, "<nadra>", "single"), namespace)
+    except SystemExit:
+        raise
+    except BaseException as e:
+        traceback.print_exception(type(e), e, e.__traceback__.tb_next)
+    sys.stderr.flush()
+    sys.stdout.write("\x1enadra-done\x1e\n")
+    sys.stdout.flush()
+"#;
+
+
+///
+/// # Overview
+/// A long-lived `python3` process which runs snippets of transpiled code, keeping any
+/// definitions between them. The process is killed when this is dropped.
+///
+/// # Members
+/// - `child` - The `python3` process.
+/// - `stdin` - Where snippets are sent.
+/// - `stdout` - Where the output of snippets is read from. The process' `stderr` is
+/// inherited, so errors are printed directly.
+///
+pub struct PythonProcess {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>
+}
+
+
+impl PythonProcess {
+    pub fn spawn() -> eyre::Result<Self> {
+        let mut child = Command::new("python3")
+            .arg("-u")
+            .arg("-c")
+            .arg(DRIVER)
+            .env("PYTHONIOENCODING", "utf-8")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .map_err(|e| eyre!("Could not start python3: {}", e))?;
+
+        // Both are always present since they were piped above.
+        let stdin = child.stdin.take().unwrap();
+        let stdout = BufReader::new(child.stdout.take().unwrap());
+
+        Ok(Self {
+            child: child,
+            stdin: stdin,
+            stdout: stdout
+        })
+    }
+
+
+    ///
+    /// Run `source`, which must be a single Python statement, printing its output as it
+    /// is produced. Exceptions raised by `source` are printed by the process itself.
+    ///
+    /// # Returns
+    /// - An error if the process has exited, e.g. because `source` called `exit()`.
+    ///
+    pub fn execute(&mut self, source: &str) -> eyre::Result<()> {
+        let source = format!("{}\n", source);
+        let sent = write!(self.stdin, "{}\n{}", source.chars().count(), source)
+            .and_then(|_| self.stdin.flush());
+
+        if sent.is_err() {
+            return Err(self.exited());
+        }
+
+        let mut stdout = io::stdout();
+        let mut line = Vec::new();
+
+        loop {
+            line.clear();
+            if self.stdout.read_until(b'\n', &mut line)? == 0 {
+                return Err(self.exited());
+            }
+
+            let text = String::from_utf8_lossy(&line);
+
+            // Output that does not end with a newline shares its line with the sentinel.
+            if let Some(rest) = text.strip_suffix('\n').and_then(|text| text.strip_suffix(SENTINEL)) {
+                if !rest.is_empty() {
+                    writeln!(stdout, "{}", rest)?;
+                }
+                stdout.flush()?;
+                return Ok(());
+            }
+
+            stdout.write_all(&line)?;
+            stdout.flush()?;
+        }
+    }
+
+
+    fn exited(&mut self) -> eyre::Report {
+        match self.child.wait() {
+            Ok(status) => eyre!("The Python process exited ({})", status),
+            Err(e) => eyre!("The Python process exited: {}", e)
+        }
+    }
+}
+
+
+impl Drop for PythonProcess {
+    fn drop(&mut self) {
+        // Killing fails if the process already exited, which is fine.
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
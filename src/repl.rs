@@ -0,0 +1,289 @@
+use std::panic::{self, AssertUnwindSafe};
+use color_eyre::eyre::{self, eyre};
+use rustyline::{DefaultEditor, error::ReadlineError};
+use crate::{lexer::{self, BlockTracker, TokenType, UnterminatedString}, parser, python::PythonProcess, transpiler::{self, TranspileOptions}};
+
+
+/// The prompt shown at the start of each new input.
+const PROMPT: &str = "expr > ";
+
+/// The prompt shown while an input is incomplete, e.g. within a function definition.
+const CONTINUATION_PROMPT: &str = "  ... ";
+
+
+/// The meta-commands understood by the REPL, and what they do.
+const HELP: &str = "\
+.py      Toggle showing the generated Python code
+.tokens  Toggle showing the tokens of each input
+.ast     Toggle showing the AST of each input
+.reset   Restart the Python process, forgetting all definitions
+.help    Show this message
+.quit    Exit the REPL";
+
+
+///
+/// What is shown for each input, other than its result. Each can be toggled with a
+/// meta-command.
+///
+#[derive(Default)]
+struct ShowOptions {
+    python: bool,
+    tokens: bool,
+    ast: bool
+}
+
+
+///
+/// What happened to a line fed to a [`ReplSession`].
+///
+pub enum Step {
+    /// The input is incomplete, so the next line continues it.
+    Incomplete,
+
+    /// The input was complete and has been handled. Contains the whole input, for the
+    /// history.
+    Complete(String),
+
+    /// The user asked to exit.
+    Quit
+}
+
+
+///
+/// # Overview
+/// The state of a REPL, which is fed one line of input at a time. Complete inputs are
+/// evaluated in a long-lived Python process, so definitions are kept between inputs.
+///
+/// No input can end the session. Errors are printed, after which the input is discarded,
+/// and a panic within the compiler is reported as an error. If the Python process exits,
+/// e.g. because of `exit()`, a new one is started for the next input.
+///
+/// # Members
+/// - `input` - The lines of the current input, while it is incomplete.
+/// - `show` - What is shown for each input, other than its result.
+/// - `python` - The Python process, which is started when it is first needed.
+/// - `options` - What code is generated, which knows the enums with payloads defined by
+/// earlier inputs.
+///
+#[derive(Default)]
+pub struct ReplSession {
+    input: String,
+    show: ShowOptions,
+    python: Option<PythonProcess>,
+    options: TranspileOptions
+}
+
+
+impl ReplSession {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+
+    pub fn prompt(&self) -> &'static str {
+        if self.input.is_empty() { PROMPT } else { CONTINUATION_PROMPT }
+    }
+
+
+    ///
+    /// Discard the current input, e.g. when Ctrl-C is pressed.
+    ///
+    pub fn cancel(&mut self) {
+        self.input.clear();
+    }
+
+
+    ///
+    /// Add a line to the current input, evaluating the input once it is complete.
+    /// Meta-commands such as `.py` are only recognised at the start of an input.
+    ///
+    pub fn feed(&mut self, line: &str) -> Step {
+        if self.input.is_empty() && line.trim().starts_with('.') {
+            return self.run_command(line.trim());
+        }
+
+        self.input.push_str(line);
+        self.input.push('\n');
+
+        if is_incomplete(&self.input) {
+            return Step::Incomplete;
+        }
+
+        // The input is taken before evaluating it, so a failure cannot leave it behind.
+        let input = std::mem::take(&mut self.input);
+        if !input.trim().is_empty()
+            && let Err(e) = self.evaluate_isolated(&input) {
+            eprintln!("{}", e);
+        }
+
+        Step::Complete(input.trim_end().to_string())
+    }
+
+
+    fn run_command(&mut self, command: &str) -> Step {
+        match command {
+            ".quit" => return Step::Quit,
+            ".py" => toggle("generated Python code", &mut self.show.python),
+            ".tokens" => toggle("tokens", &mut self.show.tokens),
+            ".ast" => toggle("AST", &mut self.show.ast),
+            ".reset" => {
+                self.python = None;
+                self.options = TranspileOptions::default();
+                println!("Reset the Python process");
+            }
+            ".help" => println!("{}", HELP),
+            _ => eprintln!("Unknown command {}, enter .help for a list of commands", command)
+        }
+
+        Step::Complete(command.to_string())
+    }
+
+
+    ///
+    /// Evaluate `input`, turning a panic into an error so that a bug in the compiler
+    /// cannot end the session.
+    ///
+    fn evaluate_isolated(&mut self, input: &str) -> eyre::Result<()> {
+        // Silence the panic hook, since the panic is reported as an error instead.
+        let hook = panic::take_hook();
+        panic::set_hook(Box::new(|_| {}));
+        let result = panic::catch_unwind(AssertUnwindSafe(|| self.evaluate(input)));
+        panic::set_hook(hook);
+
+        result.unwrap_or_else(|payload| {
+            let message = payload
+                .downcast_ref::<&str>()
+                .map(|message| message.to_string())
+                .or_else(|| payload.downcast_ref::<String>().cloned())
+                .unwrap_or_default();
+
+            // The Python process may be part way through a snippet.
+            self.python = None;
+            Err(eyre!("Internal compiler error: {}", message))
+        })
+    }
+
+
+    ///
+    /// Transpile `input` and run each of its statements in the Python process, starting
+    /// the process if it is not running. Anything enabled in `show` is printed along the way.
+    ///
+    /// # Returns
+    /// - An error if `input` could not be transpiled, or the Python process could not be
+    /// started or exited. Exceptions raised by the code itself are printed by Python.
+    ///
+    fn evaluate(&mut self, input: &str) -> eyre::Result<()> {
+        let mut tokens = lexer::scan_all_tokens(input)?;
+        if self.show.tokens {
+            for token in tokens.tokens() {
+                println!("{}", token);
+            }
+        }
+
+        let ast = parser::generate_ast(&mut tokens)?;
+        if self.show.ast {
+            for statement in &ast {
+                println!("{}", statement);
+            }
+        }
+
+        let statements = transpiler::transpile_program(&ast, &self.options)?;
+        self.options.enums.extend(transpiler::payload_enums(&ast));
+
+        if self.show.python {
+            for statement in &statements {
+                println!("{}", statement);
+            }
+        }
+
+        for statement in &statements {
+            let python = match &mut self.python {
+                Some(python) => python,
+                None => self.python.insert(PythonProcess::spawn()?)
+            };
+
+            if let Err(e) = python.execute(statement) {
+                // Start a new process for the next input.
+                self.python = None;
+                return Err(e);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+
+///
+/// Continuously reads lines from the user and feeds them to a [`ReplSession`], until
+/// `.quit` is entered or the input is closed. Line editing and history (arrow keys,
+/// Ctrl-R) are provided by `rustyline`. Pressing Ctrl-C discards the current input.
+///
+pub fn run() -> eyre::Result<()> {
+    let mut editor = DefaultEditor::new()?;
+    let mut session = ReplSession::new();
+
+    loop {
+        let line = match editor.readline(session.prompt()) {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted) => {
+                session.cancel();
+                continue;
+            }
+            Err(ReadlineError::Eof) => break,
+            Err(e) => return Err(e.into())
+        };
+
+        match session.feed(&line) {
+            Step::Incomplete => {}
+            Step::Complete(entry) => {
+                if !entry.is_empty() {
+                    editor.add_history_entry(entry)?;
+                }
+            }
+            Step::Quit => break
+        }
+    }
+
+    Ok(())
+}
+
+
+fn toggle(name: &str, option: &mut bool) {
+    *option = !*option;
+    println!("{} showing the {}", if *option { "Started" } else { "Stopped" }, name);
+}
+
+
+///
+/// Whether more lines are needed to complete `input`. This is the case when a block such
+/// as `def` or `if` has not been closed yet, a bracket has not been closed, or the input
+/// ends inside of a string. Any other error is left for the parser to report.
+///
+pub fn is_incomplete(input: &str) -> bool {
+    let tokens = match lexer::scan_all_tokens(input) {
+        Ok(tokens) => tokens,
+        Err(e) => return e.downcast_ref::<UnterminatedString>().is_some()
+    };
+
+    let mut blocks = BlockTracker::default();
+    let mut brackets = 0;
+
+    for token in tokens.tokens() {
+        blocks.advance(&token.type_);
+
+        match token.type_ {
+            TokenType::LeftParen
+            | TokenType::LeftBracket
+            | TokenType::LeftBrace => brackets += 1,
+
+            TokenType::RightParen
+            | TokenType::RightBracket
+            | TokenType::RightBrace => brackets -= 1,
+
+            _ => {}
+        }
+    }
+
+    blocks.depth() > 0 || brackets > 0
+}
@@ -0,0 +1,1128 @@
+use std::{collections::HashMap, rc::Rc};
+use crate::{
+    lexer::{self, PieToken, TokenType},
+    parser::{
+        ast::{AstNode, UseImports},
+        cst::{SyntaxElement, SyntaxKind, SyntaxNode, SyntaxTree}
+    }
+};
+
+
+pub type ScopeId = usize;
+pub type SymbolId = usize;
+
+
+/// The names which Python binds in every module, i.e. its builtins and module attributes.
+pub const BUILTINS: &[&str] = &[
+    // Constants
+    "None", "True", "False", "NotImplemented", "Ellipsis", "__debug__",
+
+    // Module attributes
+    "__name__", "__file__", "__doc__", "__package__", "__spec__", "__loader__", "__builtins__",
+
+    // Functions and types
+    "abs", "aiter", "all", "anext", "any", "ascii", "bin", "bool", "breakpoint", "bytearray",
+    "bytes", "callable", "chr", "classmethod", "compile", "complex", "delattr", "dict", "dir",
+    "divmod", "enumerate", "eval", "exec", "exit", "filter", "float", "format", "frozenset",
+    "getattr", "globals", "hasattr", "hash", "help", "hex", "id", "input", "int", "isinstance",
+    "issubclass", "iter", "len", "list", "locals", "map", "max", "memoryview", "min", "next",
+    "object", "oct", "open", "ord", "pow", "print", "property", "quit", "range", "repr",
+    "reversed", "round", "set", "setattr", "slice", "sorted", "staticmethod", "str", "sum",
+    "super", "tuple", "type", "vars", "zip", "__import__",
+
+    // Exceptions
+    "BaseException", "BaseExceptionGroup", "Exception", "ExceptionGroup", "ArithmeticError",
+    "AssertionError", "AttributeError", "BlockingIOError", "BrokenPipeError", "BufferError",
+    "ChildProcessError", "ConnectionAbortedError", "ConnectionError", "ConnectionRefusedError",
+    "ConnectionResetError", "EOFError", "EnvironmentError", "FileExistsError",
+    "FileNotFoundError", "FloatingPointError", "GeneratorExit", "IOError", "ImportError",
+    "IndentationError", "IndexError", "InterruptedError", "IsADirectoryError", "KeyError",
+    "KeyboardInterrupt", "LookupError", "MemoryError", "ModuleNotFoundError", "NameError",
+    "NotADirectoryError", "NotImplementedError", "OSError", "OverflowError",
+    "PermissionError", "ProcessLookupError", "RecursionError", "ReferenceError",
+    "RuntimeError", "StopAsyncIteration", "StopIteration", "SyntaxError", "SystemError",
+    "SystemExit", "TabError", "TimeoutError", "TypeError", "UnboundLocalError",
+    "UnicodeDecodeError", "UnicodeEncodeError", "UnicodeError", "UnicodeTranslateError",
+    "ValueError", "ZeroDivisionError",
+
+    // Warnings
+    "Warning", "BytesWarning", "DeprecationWarning", "EncodingWarning", "FutureWarning",
+    "ImportWarning", "PendingDeprecationWarning", "ResourceWarning", "RuntimeWarning",
+    "SyntaxWarning", "UnicodeWarning", "UserWarning"
+];
+
+
+///
+/// Where a name appears in the source code. Names never span several lines.
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Span {
+    pub line: u64,
+    pub column: u32,
+    pub length: u32
+}
+
+
+impl Span {
+    pub fn of(token: &PieToken) -> Self {
+        Self {
+            line: token.line,
+            column: token.column,
+            length: token.lexeme.chars().count() as u32
+        }
+    }
+
+
+    ///
+    /// Whether the position `line`:`column` is within the span, or just after it.
+    ///
+    pub fn contains(&self, line: u64, column: u32) -> bool {
+        self.line == line && self.column <= column && column <= self.column + self.length
+    }
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SymbolKind {
+    Function, Method, Struct, Interface, Enum, Field, Variant, Parameter, TypeParameter, Variable, Constant, Import
+}
+
+
+///
+/// The kinds of scope. As in Python, the bodies of `if` statements and loops are part of
+/// the scope containing them, so a variable assigned within a loop is still bound after it,
+/// and the names bound within the body of a struct, interface or enum are not visible from
+/// within its methods. The type parameters of a generic function or struct are bound in a scope
+/// of their own, between the definition's body and the scope containing it, so they are
+/// visible from within its methods.
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ScopeKind {
+    Module, Function, Lambda, Struct, Interface, Enum, TypeParameters
+}
+
+
+///
+/// # Overview
+/// A region of the source code in which names are bound.
+///
+/// # Members
+/// - `kind` - What the scope is the body of.
+/// - `parent` - The scope containing this one, or `None` for the module scope.
+/// - `extent` - The positions of the first and last tokens of the definition whose body
+/// the scope is, or `None` for the module scope.
+/// - `names` - The symbol bound to each name within the scope.
+/// - `globs` - The `use` statements which import everything from a module into the scope.
+///
+#[derive(Debug)]
+pub struct Scope {
+    pub kind: ScopeKind,
+    pub parent: Option<ScopeId>,
+    pub extent: Option<((u64, u32), (u64, u32))>,
+    pub names: HashMap<String, SymbolId>,
+    pub globs: Vec<Rc<AstNode>>
+}
+
+
+///
+/// Where an imported symbol comes from.
+///
+/// # Members
+/// - `statement` - The `use` statement that imports the symbol.
+/// - `item` - The name of the item within the module, or `None` if the symbol is the
+/// module itself.
+///
+#[derive(Debug, Clone)]
+pub struct Import {
+    pub statement: Rc<AstNode>,
+    pub item: Option<String>
+}
+
+
+///
+/// # Overview
+/// Something a name is bound to, such as a function or a variable.
+///
+/// # Members
+/// - `name` - The name of the symbol.
+/// - `kind` - What the symbol is.
+/// - `span` - Where the symbol is first bound, e.g. the name of a function definition or
+/// the target of the first assignment to a variable.
+/// - `scope` - The scope the symbol is bound in.
+/// - `node` - The node which binds the symbol, e.g. a `FunctionDefinition` or an `Assignment`.
+/// - `body` - The scope of the body of a function, struct, interface or enum, or of the
+/// fields of a variant with a payload.
+/// - `instance_of` - The struct or interface that the first parameter of a method, i.e.
+/// `self`, is an instance of.
+/// - `import` - Where the symbol is imported from, if it is an import.
+///
+#[derive(Debug)]
+pub struct Symbol {
+    pub name: String,
+    pub kind: SymbolKind,
+    pub span: Span,
+    pub scope: ScopeId,
+    pub node: Rc<SyntaxNode>,
+    pub body: Option<ScopeId>,
+    pub instance_of: Option<SymbolId>,
+    pub import: Option<Import>
+}
+
+
+///
+/// How a name is used where it occurs.
+/// - `Name` - On its own, e.g. `x` or the name of a definition.
+/// - `Member` - After `.` or `::`, where `parent` is the symbol that the expression
+/// before it refers to, if it is known. E.g. `self.x` or `utils.join`.
+/// - `ImportedItem` - As an item of the `use` statement `statement`, e.g. `join` in
+/// `use utils::join as j`.
+///
+#[derive(Debug, Clone)]
+pub enum OccurrenceKind {
+    Name,
+    Member { parent: Option<SymbolId> },
+    ImportedItem { statement: Rc<AstNode> }
+}
+
+
+///
+/// # Overview
+/// A place where a name occurs in the source code.
+///
+/// # Members
+/// - `name` - The name.
+/// - `span` - Where the name occurs.
+/// - `scope` - The scope the name occurs in.
+/// - `symbol` - The symbol the name refers to, or `None` if it could not be resolved, e.g.
+/// because it is a builtin or the attribute of a value of an unknown type.
+/// - `kind` - How the name is used.
+/// - `binding` - Whether the name is bound here, e.g. by an assignment or a definition,
+/// rather than its value being used.
+///
+#[derive(Debug, Clone)]
+pub struct Occurrence {
+    pub name: String,
+    pub span: Span,
+    pub scope: ScopeId,
+    pub symbol: Option<SymbolId>,
+    pub kind: OccurrenceKind,
+    pub binding: bool
+}
+
+
+///
+/// # Overview
+/// The symbols of a source file and every place each of them is referred to. The index
+/// is built from the concrete syntax tree in two passes: the first binds the names of each
+/// scope, and the second resolves every name, so a name may be used before the statement
+/// that binds it, as in Python.
+///
+/// # Members
+/// - `scopes` - Every scope of the file, the first being the module scope.
+/// - `symbols` - Every symbol of the file.
+/// - `occurrences` - Every occurrence of a name in the file, in source order, including
+/// those which bind a symbol.
+/// - `errors` - Where each name is declared or assigned to when it may not be, e.g. a
+/// constant being reassigned, and a description of the problem.
+/// - `node_scopes` - The scope of the body of each node that has one, by address.
+///
+#[derive(Debug)]
+pub struct SymbolIndex {
+    pub scopes: Vec<Scope>,
+    pub symbols: Vec<Symbol>,
+    pub occurrences: Vec<Occurrence>,
+    pub errors: Vec<(Span, String)>,
+    node_scopes: HashMap<*const SyntaxNode, ScopeId>
+}
+
+
+impl SymbolIndex {
+    pub const MODULE_SCOPE: ScopeId = 0;
+
+
+    pub fn build(tree: &SyntaxTree) -> Self {
+        let mut index = Self {
+            scopes: vec![],
+            symbols: vec![],
+            occurrences: vec![],
+            errors: vec![],
+            node_scopes: HashMap::new()
+        };
+
+        let module = index.add_scope(ScopeKind::Module, None, &tree.root);
+        index.bind_within(&tree.root, module);
+        index.resolve_within(&tree.root, module);
+        index.check_implementations();
+        index
+    }
+
+
+    fn add_scope(&mut self, kind: ScopeKind, parent: Option<ScopeId>, node: &SyntaxNode) -> ScopeId {
+        let extent = match (parent, node.first_token(), node.last_token()) {
+            (Some(_), Some(first), Some(last)) => Some(((first.line, first.column), (last.line, last.column))),
+            _ => None
+        };
+
+        self.scopes.push(Scope {
+            kind: kind,
+            parent: parent,
+            extent: extent,
+            names: HashMap::new(),
+            globs: vec![]
+        });
+        self.scopes.len() - 1
+    }
+
+
+    ///
+    /// Bind `token` within `scope`, unless its name is already bound there, in which case
+    /// this is just another assignment to the existing symbol, which must not be a constant.
+    ///
+    fn bind(&mut self, scope: ScopeId, token: &PieToken, kind: SymbolKind, node: &Rc<SyntaxNode>) -> SymbolId {
+        if let Some(&symbol) = self.scopes[scope].names.get(&token.lexeme) {
+            if self.symbols[symbol].kind == SymbolKind::Constant {
+                self.errors.push((Span::of(token), format!("Cannot assign to constant '{}'", token.lexeme)));
+            }
+            return symbol;
+        }
+
+        self.symbols.push(Symbol {
+            name: token.lexeme.clone(),
+            kind: kind,
+            span: Span::of(token),
+            scope: scope,
+            node: node.clone(),
+            body: None,
+            instance_of: None,
+            import: None
+        });
+
+        let symbol = self.symbols.len() - 1;
+        self.scopes[scope].names.insert(token.lexeme.clone(), symbol);
+        symbol
+    }
+
+
+    ///
+    /// Bind the name declared by the `let` or `const` declaration `node` within `scope`,
+    /// where it must not already be bound.
+    ///
+    fn declare(&mut self, scope: ScopeId, node: &Rc<SyntaxNode>) {
+        let Some(name) = name_token(node) else { return };
+
+        if let Some(&existing) = self.scopes[scope].names.get(&name.lexeme) {
+            let line = self.symbols[existing].span.line + 1;
+            self.errors.push((Span::of(&name), format!("'{}' is already declared on line {}", name.lexeme, line)));
+            return;
+        }
+
+        let kind = match node.ast.as_deref() {
+            Some(AstNode::Declaration(declaration)) if declaration.constant => SymbolKind::Constant,
+            _ => SymbolKind::Variable
+        };
+        self.bind(scope, &name, kind, node);
+    }
+
+
+    ///
+    /// The first pass, which binds the names of the statements within `node` to `scope`,
+    /// creating a new scope for the body of each definition.
+    ///
+    fn bind_within(&mut self, node: &SyntaxNode, scope: ScopeId) {
+        for child in node.child_nodes() {
+            match child.kind {
+                SyntaxKind::FunctionDefinition => {
+                    let Some(name) = name_token(child) else { continue };
+                    let owner = self.owner_of(scope)
+                        .filter(|&owner| matches!(self.symbols[owner].kind, SymbolKind::Struct | SymbolKind::Interface));
+                    let kind = if owner.is_some() { SymbolKind::Method } else { SymbolKind::Function };
+                    let symbol = self.bind(scope, &name, kind, child);
+
+                    let outer = self.bind_type_params(scope, child);
+                    let body = self.add_scope(ScopeKind::Function, Some(outer), child);
+                    self.symbols[symbol].body = Some(body);
+                    self.node_scopes.insert(Rc::as_ptr(child), body);
+
+                    for (i, parameter) in child.child_nodes().filter(|node| node.kind == SyntaxKind::Parameter).enumerate() {
+                        let Some(name) = name_token(parameter) else { continue };
+                        let parameter_symbol = self.bind(body, &name, SymbolKind::Parameter, parameter);
+                        if i == 0 {
+                            self.symbols[parameter_symbol].instance_of = owner;
+                        }
+                    }
+
+                    self.bind_within(child, body);
+                }
+
+                SyntaxKind::StructDefinition | SyntaxKind::InterfaceDefinition | SyntaxKind::EnumDefinition => {
+                    let Some(name) = name_token(child) else { continue };
+                    let (kind, scope_kind) = match child.kind {
+                        SyntaxKind::StructDefinition => (SymbolKind::Struct, ScopeKind::Struct),
+                        SyntaxKind::InterfaceDefinition => (SymbolKind::Interface, ScopeKind::Interface),
+                        _ => (SymbolKind::Enum, ScopeKind::Enum)
+                    };
+                    let symbol = self.bind(scope, &name, kind, child);
+
+                    let outer = self.bind_type_params(scope, child);
+                    let body = self.add_scope(scope_kind, Some(outer), child);
+                    self.symbols[symbol].body = Some(body);
+                    self.node_scopes.insert(Rc::as_ptr(child), body);
+                    self.bind_within(child, body);
+                }
+
+                SyntaxKind::Field => {
+                    if let Some(name) = name_token(child) {
+                        self.bind(scope, &name, SymbolKind::Field, child);
+                    }
+                }
+
+                // The fields of a variant with a payload are bound within a body of its own.
+                SyntaxKind::EnumVariant => {
+                    let Some(name) = name_token(child) else { continue };
+                    let symbol = self.bind(scope, &name, SymbolKind::Variant, child);
+
+                    if !direct_tokens(child, TokenType::LeftParen).is_empty() {
+                        let body = self.add_scope(ScopeKind::Struct, Some(scope), child);
+                        self.symbols[symbol].body = Some(body);
+                        self.node_scopes.insert(Rc::as_ptr(child), body);
+                        self.bind_within(child, body);
+                    }
+                }
+
+                SyntaxKind::Assignment => {
+                    for target in assignment_targets(child) {
+                        self.bind_target(scope, target, child);
+                    }
+                    self.bind_within(child, scope);
+                }
+
+                SyntaxKind::Declaration => {
+                    self.declare(scope, child);
+                    self.bind_within(child, scope);
+                }
+
+                SyntaxKind::UseStatement => self.bind_imports(scope, child),
+
+                SyntaxKind::LambdaFunction => {
+                    let body = self.add_scope(ScopeKind::Lambda, Some(scope), child);
+                    self.node_scopes.insert(Rc::as_ptr(child), body);
+
+                    for parameter in child.child_nodes().filter(|node| node.kind == SyntaxKind::Parameter) {
+                        if let Some(name) = name_token(parameter) {
+                            self.bind(body, &name, SymbolKind::Parameter, parameter);
+                        }
+                    }
+                }
+
+                SyntaxKind::IfStatement | SyntaxKind::WhileLoop => self.bind_within(child, scope),
+
+                // As in Python, the names bound by a pattern are variables of the enclosing scope.
+                SyntaxKind::CapturePattern | SyntaxKind::StarPattern => {
+                    if let Some(name) = name_token(child).filter(|name| name.lexeme != "_") {
+                        self.bind(scope, &name, SymbolKind::Variable, child);
+                    }
+                }
+
+                // Lambdas may be within any expression.
+                _ => self.bind_within(child, scope)
+            }
+        }
+    }
+
+
+    ///
+    /// Bind the type parameters of the definition `node`, which is within `scope`, to a
+    /// scope of their own.
+    ///
+    /// # Returns
+    /// - The scope which contains the body of the definition, which is `scope` itself if
+    /// the definition is not generic.
+    ///
+    fn bind_type_params(&mut self, scope: ScopeId, node: &Rc<SyntaxNode>) -> ScopeId {
+        let params: Vec<&Rc<SyntaxNode>> = node.child_nodes().filter(|child| child.kind == SyntaxKind::TypeParameter).collect();
+        if params.is_empty() {
+            return scope;
+        }
+
+        let generic = self.add_scope(ScopeKind::TypeParameters, Some(scope), node);
+        for param in params {
+            if let Some(name) = name_token(param) {
+                self.bind(generic, &name, SymbolKind::TypeParameter, param);
+            }
+        }
+        generic
+    }
+
+
+    ///
+    /// Bind the names assigned to by `target`, which may be a tuple or list of targets.
+    ///
+    fn bind_target(&mut self, scope: ScopeId, target: &Rc<SyntaxNode>, assignment: &Rc<SyntaxNode>) {
+        match target.kind {
+            SyntaxKind::Name => {
+                if let Some(token) = target.first_token() {
+                    self.bind(scope, &token, SymbolKind::Variable, assignment);
+                }
+            }
+
+            SyntaxKind::TupleLiteral
+            | SyntaxKind::ListLiteral
+            | SyntaxKind::Parenthesized
+            | SyntaxKind::Unpack => {
+                for element in target.child_nodes() {
+                    self.bind_target(scope, element, assignment);
+                }
+            }
+
+            _ => {}
+        }
+    }
+
+
+    ///
+    /// Bind the names imported by the `use` statement `node`.
+    ///
+    fn bind_imports(&mut self, scope: ScopeId, node: &Rc<SyntaxNode>) {
+        let Some(statement) = node.ast.clone() else { return };
+        let AstNode::UseStatement(use_statement) = statement.as_ref() else { return };
+
+        let import = |item: Option<String>| Some(Import { statement: statement.clone(), item: item });
+
+        match &use_statement.imports {
+            UseImports::Module { alias } => {
+                // `use os.path` binds `os`, while `use .utils` and aliases bind the last name.
+                let names = direct_tokens(node, TokenType::Identifier);
+                let bound = match (alias, use_statement.level) {
+                    (None, 0) => names.first(),
+                    _ => names.last()
+                };
+
+                if let Some(token) = bound {
+                    let symbol = self.bind(scope, token, SymbolKind::Import, node);
+                    self.symbols[symbol].import = import(None);
+                }
+            }
+
+            UseImports::Items(_) => {
+                for item in node.child_nodes().filter(|node| node.kind == SyntaxKind::ImportItem) {
+                    let names = direct_tokens(item, TokenType::Identifier);
+                    let (Some(name), Some(bound)) = (names.first(), names.last()) else { continue };
+
+                    let symbol = self.bind(scope, bound, SymbolKind::Import, item);
+                    self.symbols[symbol].import = import(Some(name.lexeme.clone()));
+                }
+            }
+
+            UseImports::Glob => self.scopes[scope].globs.push(statement.clone())
+        }
+    }
+
+
+    ///
+    /// The function, struct, interface or enum whose body is `scope`.
+    ///
+    pub fn owner_of(&self, scope: ScopeId) -> Option<SymbolId> {
+        self.symbols.iter().position(|symbol| symbol.body == Some(scope))
+    }
+
+
+    ///
+    /// The second pass, which resolves every name within `node`, where `scope` is the scope
+    /// that `node` is in.
+    ///
+    fn resolve_within(&mut self, node: &SyntaxNode, scope: ScopeId) {
+        for child in node.child_nodes() {
+            match child.kind {
+                SyntaxKind::Name => {
+                    if let Some(token) = child.first_token() {
+                        let symbol = self.lookup(scope, &token.lexeme);
+                        self.occur(&token, scope, symbol, OccurrenceKind::Name, false);
+                    }
+                }
+
+                SyntaxKind::FunctionDefinition => self.resolve_function(child, scope),
+
+                SyntaxKind::StructDefinition | SyntaxKind::InterfaceDefinition | SyntaxKind::EnumDefinition => {
+                    self.resolve_definition_name(child, scope);
+                    let body = self.node_scopes[&Rc::as_ptr(child)];
+
+                    // The interfaces a struct implements are named outside of its body.
+                    let outer = self.scopes[body].parent.unwrap_or(scope);
+                    for part in child.child_nodes() {
+                        let within = if implemented(child).any(|interface| Rc::ptr_eq(interface, part)) { outer } else { body };
+                        self.resolve_within(&wrap(part), within);
+                    }
+                }
+
+                SyntaxKind::TypeParameter => {
+                    if let Some(name) = name_token(child) {
+                        let symbol = self.lookup(scope, &name.lexeme);
+                        self.occur(&name, scope, symbol, OccurrenceKind::Name, true);
+                    }
+                    self.resolve_within(child, scope);
+                }
+
+                SyntaxKind::Field | SyntaxKind::Declaration => {
+                    self.resolve_definition_name(child, scope);
+                    self.resolve_within(child, scope);
+                }
+
+                SyntaxKind::EnumVariant => {
+                    self.resolve_definition_name(child, scope);
+                    let body = self.node_scopes.get(&Rc::as_ptr(child)).copied().unwrap_or(scope);
+                    self.resolve_within(child, body);
+                }
+
+                SyntaxKind::MemberAccess | SyntaxKind::ScopeAccess => {
+                    self.resolve_within(child, scope);
+
+                    let parent = child.child_nodes().next().and_then(|parent| self.symbol_of(parent));
+                    let member = direct_tokens(child, TokenType::Identifier).pop();
+
+                    if let Some(member) = member {
+                        let symbol = parent.and_then(|parent| self.member(parent, &member.lexeme));
+                        self.occur(&member, scope, symbol, OccurrenceKind::Member { parent: parent }, false);
+                    }
+                }
+
+                // The name of a keyword argument is not a reference to a variable, but to a
+                // parameter of the function being called, if it is known.
+                SyntaxKind::FunctionCall => {
+                    self.resolve_within(child, scope);
+                    let callee = child.child_nodes().next().and_then(|callee| self.symbol_of(callee));
+
+                    for argument in child.child_nodes().filter(|node| node.kind == SyntaxKind::KeywordArgument) {
+                        let Some(name) = argument.first_token() else { continue };
+                        let symbol = callee.and_then(|callee| self.parameter(callee, &name.lexeme));
+                        self.occur(&name, scope, symbol, OccurrenceKind::Member { parent: callee }, false);
+                    }
+                }
+
+                SyntaxKind::KeywordArgument => {
+                    for value in child.child_nodes().skip(1) {
+                        self.resolve_within(value, scope);
+                    }
+                }
+
+                SyntaxKind::CapturePattern | SyntaxKind::StarPattern => {
+                    if let Some(name) = name_token(child).filter(|name| name.lexeme != "_") {
+                        let symbol = self.lookup(scope, &name.lexeme);
+                        self.occur(&name, scope, symbol, OccurrenceKind::Name, true);
+                    }
+                }
+
+                // The name of a keyword pattern refers to a field of the struct or variant.
+                SyntaxKind::ClassPattern => {
+                    self.resolve_within(child, scope);
+                    let class = child.child_nodes().next().and_then(|class| self.symbol_of(class));
+
+                    for keyword in child.child_nodes().filter(|node| node.kind == SyntaxKind::KeywordPattern) {
+                        let Some(name) = keyword.first_token() else { continue };
+                        let symbol = class.and_then(|class| self.parameter(class, &name.lexeme));
+                        self.occur(&name, scope, symbol, OccurrenceKind::Member { parent: class }, false);
+                    }
+                }
+
+                SyntaxKind::UseStatement => self.resolve_imports(child, scope),
+
+                // The targets of `=` are bound, while those of augmented assignments such as
+                // `+=` are used as well.
+                SyntaxKind::Assignment if !direct_tokens(child, TokenType::Equal).is_empty() => {
+                    let targets = assignment_targets(child);
+                    for operand in child.child_nodes() {
+                        if targets.iter().any(|target| Rc::ptr_eq(target, operand)) {
+                            self.resolve_target(operand, scope);
+                        } else {
+                            self.resolve_within(&wrap(operand), scope);
+                        }
+                    }
+                }
+
+                SyntaxKind::LambdaFunction => {
+                    let body = self.node_scopes[&Rc::as_ptr(child)];
+                    for parameter in child.child_nodes() {
+                        if parameter.kind == SyntaxKind::Parameter && let Some(name) = name_token(parameter) {
+                            let symbol = self.scopes[body].names.get(&name.lexeme).copied();
+                            self.occur(&name, body, symbol, OccurrenceKind::Name, true);
+                            self.resolve_within(parameter, scope);
+                        } else {
+                            self.resolve_within(&wrap(parameter), body);
+                        }
+                    }
+                }
+
+                _ => self.resolve_within(child, scope)
+            }
+        }
+    }
+
+
+    ///
+    /// Resolve the target of an assignment, where any names are bound rather than used.
+    ///
+    fn resolve_target(&mut self, target: &Rc<SyntaxNode>, scope: ScopeId) {
+        match target.kind {
+            SyntaxKind::Name => {
+                if let Some(token) = target.first_token() {
+                    let symbol = self.lookup(scope, &token.lexeme);
+                    self.occur(&token, scope, symbol, OccurrenceKind::Name, true);
+                }
+            }
+
+            SyntaxKind::TupleLiteral
+            | SyntaxKind::ListLiteral
+            | SyntaxKind::Parenthesized
+            | SyntaxKind::Unpack => {
+                for element in target.child_nodes() {
+                    self.resolve_target(element, scope);
+                }
+            }
+
+            // E.g. `point.x = 1` or `items[0] = 1`, which use `point` and `items`.
+            _ => self.resolve_within(&wrap(target), scope)
+        }
+    }
+
+
+    ///
+    /// Resolve the names within a function definition. As in Python, annotations and
+    /// default values are evaluated in the scope containing the function, while the
+    /// body has a scope of its own.
+    ///
+    fn resolve_function(&mut self, node: &Rc<SyntaxNode>, scope: ScopeId) {
+        self.resolve_definition_name(node, scope);
+        let body = self.node_scopes[&Rc::as_ptr(node)];
+        let mut seen_return_type = false;
+
+        // The annotations may refer to the function's type parameters.
+        let outer = self.scopes[body].parent.unwrap_or(scope);
+
+        for element in &node.children {
+            let SyntaxElement::Node(child) = element else { continue };
+
+            if child.kind == SyntaxKind::TypeParameter {
+                self.resolve_within(&wrap(child), outer);
+            } else if child.kind == SyntaxKind::Parameter {
+                if let Some(name) = name_token(child) {
+                    let symbol = self.scopes[body].names.get(&name.lexeme).copied();
+                    self.occur(&name, body, symbol, OccurrenceKind::Name, true);
+                }
+                self.resolve_within(child, outer);
+            } else if !seen_return_type {
+                seen_return_type = true;
+                self.resolve_within(&wrap(child), outer);
+            } else {
+                self.resolve_within(&wrap(child), body);
+            }
+        }
+    }
+
+
+    ///
+    /// Record the occurrence of the name of a definition, which is bound in `scope`.
+    ///
+    fn resolve_definition_name(&mut self, node: &SyntaxNode, scope: ScopeId) {
+        if let Some(name) = name_token(node) {
+            let symbol = self.scopes[scope].names.get(&name.lexeme).copied();
+            self.occur(&name, scope, symbol, OccurrenceKind::Name, true);
+        }
+    }
+
+
+    fn resolve_imports(&mut self, node: &Rc<SyntaxNode>, scope: ScopeId) {
+        let Some(statement) = node.ast.clone() else { return };
+
+        let record = |index: &mut Self, names: Vec<Rc<PieToken>>, item: bool| {
+            let Some(bound) = names.last() else { return };
+            let symbol = index.scopes[scope].names.get(&bound.lexeme).copied();
+
+            // The item's own name refers to the item within the imported module.
+            if item {
+                let kind = OccurrenceKind::ImportedItem { statement: statement.clone() };
+                let item_symbol = if names.len() == 1 { symbol } else { None };
+                index.occur(&names[0], scope, item_symbol, kind, names.len() == 1);
+            }
+
+            if !item || names.len() > 1 {
+                index.occur(bound, scope, symbol, OccurrenceKind::Name, true);
+            }
+        };
+
+        let items: Vec<_> = node.child_nodes().filter(|node| node.kind == SyntaxKind::ImportItem).cloned().collect();
+        if items.is_empty() {
+            // Only the bound name of a module import is an occurrence of its symbol.
+            let names = direct_tokens(node, TokenType::Identifier);
+            let bound = names
+                .iter()
+                .find(|token| self.scopes[scope].names.get(&token.lexeme).is_some_and(|&symbol| {
+                    self.symbols[symbol].span == Span::of(token)
+                }));
+
+            if let Some(bound) = bound.cloned() {
+                record(self, vec![bound], false);
+            }
+        }
+
+        for item in items {
+            record(self, direct_tokens(&item, TokenType::Identifier), true);
+        }
+    }
+
+
+    fn occur(&mut self, token: &PieToken, scope: ScopeId, symbol: Option<SymbolId>, kind: OccurrenceKind, binding: bool) {
+        self.occurrences.push(Occurrence {
+            name: token.lexeme.clone(),
+            span: Span::of(token),
+            scope: scope,
+            symbol: symbol,
+            kind: kind,
+            binding: binding
+        });
+    }
+
+
+    ///
+    /// The symbol that the expression `node` refers to, if it is a name or a member that
+    /// has already been resolved.
+    ///
+    fn symbol_of(&self, node: &SyntaxNode) -> Option<SymbolId> {
+        let token = match node.kind {
+            SyntaxKind::Name => node.first_token()?,
+            SyntaxKind::MemberAccess | SyntaxKind::ScopeAccess => direct_tokens(node, TokenType::Identifier).pop()?,
+            _ => return None
+        };
+
+        self.occurrences
+            .iter()
+            .rev()
+            .find(|occurrence| occurrence.span == Span::of(&token))
+            .and_then(|occurrence| occurrence.symbol)
+    }
+
+
+    ///
+    /// The interfaces within the file that the struct `structure` declares it implements.
+    ///
+    pub fn interfaces(&self, structure: SymbolId) -> Vec<SymbolId> {
+        implemented(&self.symbols[structure].node)
+            .filter_map(|interface| self.symbol_of(interface))
+            .filter(|&interface| self.symbols[interface].kind == SymbolKind::Interface)
+            .collect()
+    }
+
+
+    ///
+    /// Check that every struct has each method of the interfaces it declares that it
+    /// implements, taking the same parameters, with the same types, and returning the
+    /// same type. Interfaces which are imported from other modules are not checked.
+    ///
+    fn check_implementations(&mut self) {
+        for structure in 0..self.symbols.len() {
+            if self.symbols[structure].kind != SymbolKind::Struct {
+                continue;
+            }
+
+            let node = self.symbols[structure].node.clone();
+            for interface_node in implemented(&node) {
+                let Some(interface) = self.symbol_of(interface_node) else { continue };
+                let span = interface_node.last_token().map(|token| Span::of(&token)).unwrap_or(self.symbols[structure].span);
+
+                if self.symbols[interface].kind != SymbolKind::Interface {
+                    self.errors.push((span, format!("'{}' is not an interface", self.symbols[interface].name)));
+                    continue;
+                }
+
+                let Some(required) = self.symbols[interface].body else { continue };
+                let mut methods: Vec<SymbolId> = self.scopes[required].names.values().copied().collect();
+                methods.sort();
+
+                for method in methods {
+                    let name = &self.symbols[method].name;
+                    let found = self.symbols[structure].body
+                        .and_then(|body| self.scopes[body].names.get(name).copied())
+                        .filter(|&found| self.symbols[found].kind == SymbolKind::Method);
+
+                    let error = match found {
+                        None => (span, format!(
+                            "'{}' does not implement '{}' of '{}'",
+                            self.symbols[structure].name,
+                            name,
+                            self.symbols[interface].name
+                        )),
+
+                        Some(found) if !same_signature(&self.symbols[method].node, &self.symbols[found].node) => {
+                            (self.symbols[found].span, format!(
+                                "'{}' of '{}' does not match its signature in '{}'",
+                                name,
+                                self.symbols[structure].name,
+                                self.symbols[interface].name
+                            ))
+                        }
+
+                        Some(_) => continue
+                    };
+                    self.errors.push(error);
+                }
+            }
+        }
+    }
+
+
+    ///
+    /// The symbol named `name` within `parent`, e.g. a field of a struct, a variant of an
+    /// enum, a field of a variant, or a field or method of `self`.
+    ///
+    fn member(&self, parent: SymbolId, name: &str) -> Option<SymbolId> {
+        let parent = &self.symbols[parent];
+        let owner = match parent.kind {
+            SymbolKind::Struct | SymbolKind::Interface | SymbolKind::Enum | SymbolKind::Variant => parent,
+            SymbolKind::Parameter => &self.symbols[parent.instance_of?],
+            _ => return None
+        };
+
+        self.scopes[owner.body?].names.get(name).copied()
+    }
+
+
+    ///
+    /// The parameter named `name` of the function `callee`, or the field of that name if
+    /// `callee` is a struct or a variant with a payload, whose fields are the parameters
+    /// of its constructor.
+    ///
+    fn parameter(&self, callee: SymbolId, name: &str) -> Option<SymbolId> {
+        let callee = &self.symbols[callee];
+        let kind = match callee.kind {
+            SymbolKind::Function | SymbolKind::Method => SymbolKind::Parameter,
+            SymbolKind::Struct | SymbolKind::Variant => SymbolKind::Field,
+            _ => return None
+        };
+
+        self.scopes[callee.body?]
+            .names
+            .get(name)
+            .copied()
+            .filter(|&symbol| self.symbols[symbol].kind == kind)
+    }
+
+
+    ///
+    /// The scopes searched for a name used within `scope`, innermost first. The bodies
+    /// of structs, interfaces and enums are skipped, other than `scope` itself.
+    ///
+    pub fn lookup_chain(&self, scope: ScopeId) -> Vec<ScopeId> {
+        let mut chain = vec![scope];
+        let mut current = self.scopes[scope].parent;
+
+        while let Some(parent) = current {
+            if !matches!(self.scopes[parent].kind, ScopeKind::Struct | ScopeKind::Interface | ScopeKind::Enum) {
+                chain.push(parent);
+            }
+            current = self.scopes[parent].parent;
+        }
+
+        chain
+    }
+
+
+    ///
+    /// The symbol that `name` refers to when used within `scope`, if it is bound in the file.
+    ///
+    pub fn lookup(&self, scope: ScopeId, name: &str) -> Option<SymbolId> {
+        self.lookup_chain(scope)
+            .into_iter()
+            .find_map(|scope| self.scopes[scope].names.get(name).copied())
+    }
+
+
+    ///
+    /// The innermost scope containing the position `line`:`column`.
+    ///
+    pub fn scope_at(&self, line: u64, column: u32) -> ScopeId {
+        // Scopes are created in source order, so inner scopes follow those containing them.
+        self.scopes
+            .iter()
+            .rposition(|scope| scope.extent.is_none_or(|(start, end)| start <= (line, column) && (line, column) <= end))
+            .unwrap_or(Self::MODULE_SCOPE)
+    }
+
+
+    ///
+    /// The occurrence of a name at the position `line`:`column`, if any.
+    ///
+    pub fn occurrence_at(&self, line: u64, column: u32) -> Option<&Occurrence> {
+        self.occurrences
+            .iter()
+            .find(|occurrence| occurrence.span.contains(line, column))
+    }
+
+
+    ///
+    /// Where `symbol` occurs within the file, including where it is bound.
+    ///
+    pub fn occurrences_of(&self, symbol: SymbolId) -> impl Iterator<Item = &Occurrence> {
+        self.occurrences
+            .iter()
+            .filter(move |occurrence| occurrence.symbol == Some(symbol))
+    }
+
+
+    ///
+    /// Check that renaming `symbol` to `new_name` keeps every name referring to what it
+    /// did before. Renaming is refused if `new_name` is already bound in the same scope,
+    /// if another binding of `new_name` would shadow `symbol` where it is used, or if
+    /// `new_name` is used somewhere that it would then refer to `symbol` instead.
+    ///
+    pub fn check_rename(&self, symbol: SymbolId, new_name: &str) -> Result<(), String> {
+        let renamed = &self.symbols[symbol];
+        if !is_identifier(new_name) {
+            return Err(format!("'{}' is not a valid name", new_name));
+        }
+        if renamed.name == new_name {
+            return Ok(());
+        }
+
+        if let Some(&existing) = self.scopes[renamed.scope].names.get(new_name) {
+            return Err(format!(
+                "'{}' is already defined on line {}",
+                new_name,
+                self.symbols[existing].span.line + 1
+            ));
+        }
+
+        for occurrence in self.occurrences_of(symbol) {
+            if !matches!(occurrence.kind, OccurrenceKind::Name) {
+                continue;
+            }
+
+            for scope in self.lookup_chain(occurrence.scope) {
+                if scope == renamed.scope {
+                    break;
+                }
+                if let Some(&shadowing) = self.scopes[scope].names.get(new_name) {
+                    return Err(format!(
+                        "'{}' on line {} would shadow '{}' on line {}",
+                        new_name,
+                        self.symbols[shadowing].span.line + 1,
+                        renamed.name,
+                        occurrence.span.line + 1
+                    ));
+                }
+            }
+        }
+
+        let captured = self.occurrences.iter().find(|occurrence| {
+            occurrence.name == new_name
+                && matches!(occurrence.kind, OccurrenceKind::Name)
+                && self.lookup_chain(occurrence.scope)
+                    .into_iter()
+                    .find(|&scope| scope == renamed.scope || self.scopes[scope].names.contains_key(new_name))
+                    == Some(renamed.scope)
+        });
+
+        if let Some(captured) = captured {
+            return Err(format!(
+                "'{}' on line {} would refer to the renamed '{}' instead",
+                new_name,
+                captured.span.line + 1,
+                renamed.name
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+
+///
+/// Whether `name` can be used as a name, i.e. it is a single identifier and not a keyword.
+///
+pub fn is_identifier(name: &str) -> bool {
+    match lexer::scan_all_tokens(name) {
+        Ok(tokens) => matches!(tokens.tokens(), [token, _] if token.type_ == TokenType::Identifier && token.lexeme == name),
+        Err(_) => false
+    }
+}
+
+
+///
+/// The name of a definition, i.e. the first identifier that is directly within `node`.
+///
+fn name_token(node: &SyntaxNode) -> Option<Rc<PieToken>> {
+    direct_tokens(node, TokenType::Identifier).into_iter().next()
+}
+
+
+fn direct_tokens(node: &SyntaxNode, type_: TokenType) -> Vec<Rc<PieToken>> {
+    node.children
+        .iter()
+        .filter_map(|element| match element {
+            SyntaxElement::Token(token) if token.type_ == type_ => Some(token.clone()),
+            _ => None
+        })
+        .collect()
+}
+
+
+///
+/// The targets of an assignment, i.e. every operand but the last for `a = b = value`, or
+/// the first operand of an augmented assignment such as `total += x`.
+///
+fn assignment_targets(node: &SyntaxNode) -> Vec<&Rc<SyntaxNode>> {
+    let operands: Vec<_> = node.child_nodes().collect();
+    let augmented = direct_tokens(node, TokenType::Equal).is_empty();
+
+    match operands.split_last() {
+        Some((_, targets)) if augmented => targets.iter().take(1).copied().collect(),
+        Some((_, targets)) => targets.to_vec(),
+        None => vec![]
+    }
+}
+
+
+///
+/// The names of the interfaces that the struct definition `node` implements, i.e. its
+/// parts other than its type parameters, fields and methods.
+///
+fn implemented(node: &SyntaxNode) -> impl Iterator<Item = &Rc<SyntaxNode>> {
+    node.child_nodes().filter(|child| !matches!(
+        child.kind,
+        SyntaxKind::TypeParameter | SyntaxKind::Field | SyntaxKind::FunctionDefinition
+    ))
+}
+
+
+///
+/// Whether the method `method` has the signature `required` declares, i.e. the same
+/// parameters, other than the name of `self`, with the same annotations, and the same
+/// return type. A parameter with a default in `required` must have one in `method` too.
+///
+fn same_signature(required: &SyntaxNode, method: &SyntaxNode) -> bool {
+    let (Some(AstNode::FunctionDefinition(required)), Some(AstNode::FunctionDefinition(method))) =
+        (required.ast.as_deref(), method.ast.as_deref()) else {
+        return false;
+    };
+
+    let text = |annotation: &Option<Rc<AstNode>>| annotation.as_ref().map(|annotation| annotation.to_string());
+    let same_params = required.param_list.len() == method.param_list.len()
+        && required.param_list.iter().zip(&method.param_list).enumerate().all(|(i, (expected, actual))| {
+            (i == 0 || expected.name == actual.name)
+                && expected.kind == actual.kind
+                && text(&expected.annotation) == text(&actual.annotation)
+                && (expected.default.is_none() || actual.default.is_some())
+        });
+
+    same_params && required.return_type.to_string() == method.return_type.to_string()
+}
+
+
+///
+/// Wrap `node` in a node of its own, so that it is resolved by [`SymbolIndex::resolve_within`]
+/// like any other child.
+///
+fn wrap(node: &Rc<SyntaxNode>) -> SyntaxNode {
+    SyntaxNode {
+        kind: SyntaxKind::SourceFile,
+        children: vec![SyntaxElement::Node(node.clone())],
+        ast: None
+    }
+}
@@ -1,9 +1,45 @@
-use std::{fmt::format, rc::Rc};
+use std::{collections::{BTreeSet, HashMap, HashSet}, rc::Rc};
 use color_eyre::eyre::{self, Ok};
-use crate::parser::ast::*;
+use crate::{manifest::PythonVersion, parser::ast::*};
 
 
 
+///
+/// # Overview
+/// Options which affect the Python code generated for a program.
+/// 
+/// # Fields
+/// - `python` - The version of Python to generate code for.
+/// - `annotations` - The type annotation to give the target of each assignment that has
+/// one, keyed by the assignment's node, e.g. `int` to turn `x = 0` into `x: int = 0`.
+/// - `enums` - The variants of each enum with payloads that is defined outside of the
+/// program but may be used within it, by the name of the enum. E.g. those of the modules
+/// it imports, or of earlier inputs to the REPL.
+/// 
+#[derive(Default)]
+pub struct TranspileOptions {
+    pub python: PythonVersion,
+    pub annotations: HashMap<*const AstNode, String>,
+    pub enums: HashMap<String, Vec<PayloadVariant>>
+}
+
+
+///
+/// A variant of an enum with payloads, as known to the code that uses it.
+///
+/// # Fields
+/// - `name` - The name of the variant.
+/// - `has_fields` - Whether the variant is declared with fields in brackets. One without
+/// them, e.g. `Empty` in `Circle(radius: float), Empty`, is used without being called, so
+/// it is written as an instance of its class.
+///
+#[derive(Debug, Clone)]
+pub struct PayloadVariant {
+    pub name: String,
+    pub has_fields: bool
+}
+
+
 ///
 /// A structure that holds the state of the transpiler whilst
 /// converting an AST to Python.
@@ -11,16 +47,39 @@ use crate::parser::ast::*;
 /// # Fields
 /// - `indent` - The current indentation of the code. Crucial for generating Python code
 /// as it relies of indentation for scope.
+/// - `imports` - The imports the generated code needs, e.g. `import dataclasses` for structs.
+/// - `options` - What code to generate.
+/// - `enums` - The variants of each enum with payloads that may be used within the program,
+/// by the name that refers to the enum. Their variants are classes of their own, e.g.
+/// `Shape_Circle`, so are matched by class patterns.
+/// - `substitutions` - The Python code to write in place of each name, if any. Used for
+/// the names bound by a pattern within its guard, before they are assigned.
+/// - `subjects` - The number of subjects of match statements stored in a variable so far,
+/// which is used to name the next one.
+/// - `type_vars` - The declarations of the `TypeVar` at the top level of the module, for
+/// versions of Python without PEP 695.
 /// 
-struct PythonTranspiler {
-    indent: u16
+struct PythonTranspiler<'a> {
+    indent: u16,
+    imports: BTreeSet<&'static str>,
+    options: &'a TranspileOptions,
+    enums: HashMap<String, Vec<PayloadVariant>>,
+    substitutions: HashMap<String, String>,
+    subjects: usize,
+    type_vars: HashSet<String>
 }
 
 
-impl PythonTranspiler {
-    pub fn new() -> Self {
+impl<'a> PythonTranspiler<'a> {
+    pub fn new(options: &'a TranspileOptions) -> Self {
         Self {
-            indent: 0
+            indent: 0,
+            imports: BTreeSet::new(),
+            options: options,
+            enums: HashMap::new(),
+            substitutions: HashMap::new(),
+            subjects: 0,
+            type_vars: HashSet::new()
         }
     }
 
@@ -49,8 +108,161 @@ impl PythonTranspiler {
     }
 
 
+    ///
+    /// Transpile an assignment whose target is given the type `annotation`, e.g. `x: int = 0`.
+    /// The annotation is never evaluated, so it may name a struct defined further down.
+    /// 
+    fn transpile_annotated_assignment(&mut self, operation: &BinaryOperation, annotation: &str) -> eyre::Result<String> {
+        self.imports.insert("from __future__ import annotations");
+        let target = self.generate_python(operation.left_child.clone())?;
+        let value = self.generate_python(operation.right_child.clone())?;
+        return Ok(format!("{}: {} = {}", target, annotation, value));
+    }
+
+
+    ///
+    /// Transpile a declaration to an assignment, annotated with its declared type, or else
+    /// with the type `inferred` for it. Constants are annotated with `Final` so that Python's
+    /// type checkers reject assignments to them too, e.g. `LIMIT: Final = 10`.
+    /// 
+    fn transpile_declaration(&mut self, declaration: &Declaration, inferred: Option<&String>) -> eyre::Result<String> {
+        let value = self.generate_python(declaration.value.clone())?;
+        let annotation = match &declaration.annotation {
+            Some(annotation) => Some(self.generate_python(annotation.clone())?),
+            None => inferred.cloned()
+        };
+
+        let annotation = match (declaration.constant, annotation) {
+            (true, Some(annotation)) => Some(format!("Final[{}]", annotation)),
+            (true, None) => Some(String::from("Final")),
+            (false, annotation) => annotation
+        };
+
+        if declaration.constant {
+            self.imports.insert("from typing import Final");
+        }
+
+        let Some(annotation) = annotation else {
+            return Ok(format!("{} = {}", declaration.name, value));
+        };
+
+        self.imports.insert("from __future__ import annotations");
+        return Ok(format!("{}: {} = {}", declaration.name, annotation, value));
+    }
+
+
+    ///
+    /// Transpile the expression on which a call, attribute access or subscript is
+    /// applied, wrapping it in parentheses if it would otherwise bind incorrectly.
+    /// E.g. `(a + b).real` must not become `a + b.real`.
+    /// 
+    fn transpile_postfix_operand(&mut self, operand: Rc<AstNode>) -> eyre::Result<String> {
+        let needs_parens = matches!(
+            operand.as_ref(),
+            AstNode::UnaryOperation(_) | AstNode::BinaryOperation(_)
+        );
+
+        let code = self.generate_python(operand)?;
+        if needs_parens {
+            return Ok(format!("({})", code));
+        }
+        Ok(code)
+    }
+
+
+    ///
+    /// Transpile each of `elements` and join them with commas.
+    /// 
+    fn transpile_elements(&mut self, elements: &[Rc<AstNode>]) -> eyre::Result<String> {
+        let elements = elements
+                .iter()
+                .map(|element| self.generate_python(element.clone()))
+                .collect::<eyre::Result<Vec<String>>>()?
+                .join(", ");
+
+        Ok(elements)
+    }
+
+
+    fn transpile_tuple_literal(&mut self, elements: &[Rc<AstNode>]) -> eyre::Result<String> {
+        let code = self.transpile_elements(elements)?;
+
+        // A tuple with a single element needs a trailing comma in Python.
+        if elements.len() == 1 {
+            return Ok(format!("({},)", code));
+        }
+        Ok(format!("({})", code))
+    }
+
+
+    fn transpile_dict_literal(&mut self, entries: &[DictEntry]) -> eyre::Result<String> {
+        let entries = entries
+                .iter()
+                .map(|entry| match entry {
+                    DictEntry::KeyValue { key, value } => {
+                        let key = self.generate_python(key.clone())?;
+                        let value = self.generate_python(value.clone())?;
+                        Ok(format!("{}: {}", key, value))
+                    }
+                    DictEntry::Unpack(mapping) => {
+                        let mapping = self.transpile_postfix_operand(mapping.clone())?;
+                        Ok(format!("**{}", mapping))
+                    }
+                })
+                .collect::<eyre::Result<Vec<String>>>()?
+                .join(", ");
+
+        Ok(format!("{{{}}}", entries))
+    }
+
+
+    fn transpile_unpack(&mut self, unpack: &Unpack) -> eyre::Result<String> {
+        let value = self.transpile_postfix_operand(unpack.value.clone())?;
+        let operator = match unpack.operator {
+            UnpackOperator::Iterable => "*",
+            UnpackOperator::Mapping => "**",
+        };
+        Ok(format!("{}{}", operator, value))
+    }
+
+
+    fn transpile_subscript(&mut self, subscript: &Subscript) -> eyre::Result<String> {
+        let value = self.transpile_postfix_operand(subscript.value.clone())?;
+
+        // Several indices are written without parentheses, as slices are not
+        // permitted inside of a parenthesized tuple in Python.
+        let index = match subscript.index.as_ref() {
+            AstNode::TupleLiteral(indices) if indices.len() == 1 
+                => format!("{},", self.transpile_elements(indices)?),
+            AstNode::TupleLiteral(indices) if !indices.is_empty()
+                => self.transpile_elements(indices)?,
+            _ => self.generate_python(subscript.index.clone())?
+        };
+
+        Ok(format!("{}[{}]", value, index))
+    }
+
+
+    fn transpile_slice(&mut self, slice: &Slice) -> eyre::Result<String> {
+        let mut bound = |bound: &Option<Rc<AstNode>>| match bound {
+            Some(expr) => self.generate_python(expr.clone()),
+            None => Ok(String::new())
+        };
+
+        let lower = bound(&slice.lower)?;
+        let upper = bound(&slice.upper)?;
+
+        if slice.step.is_none() {
+            return Ok(format!("{}:{}", lower, upper));
+        }
+
+        let step = bound(&slice.step)?;
+        Ok(format!("{}:{}:{}", lower, upper, step))
+    }
+
+
     fn transpile_function_call(&mut self, call: &FunctionCall) -> eyre::Result<String> {
-        let name = &call.function;
+        let name = self.transpile_postfix_operand(call.function.clone())?;
 
         let args = call.args
                 .iter()
@@ -68,7 +280,7 @@ impl PythonTranspiler {
     /// # Params
     /// - `block` - The statements in the 'block' which will each be indented.
     /// 
-    fn transpile_block(&mut self, block: &Vec<Rc<AstNode>>) -> eyre::Result<String> {
+    fn transpile_block(&mut self, block: &[Rc<AstNode>]) -> eyre::Result<String> {
         block.iter()
         .map(|stmt| {
             let line = self.generate_python(stmt.clone())?;
@@ -78,20 +290,332 @@ impl PythonTranspiler {
     }
 
 
+    ///
+    /// Whether type parameters can be written as in PEP 695, e.g. `def first[T](...)`,
+    /// which Python supports from 3.12 onwards.
+    /// 
+    fn supports_type_params(&self) -> bool {
+        self.options.python >= PythonVersion::new(3, 12)
+    }
+
+
+    ///
+    /// Transpile the type parameters of a generic function or struct to the list written
+    /// after its name, e.g. `[T, N: float]`.
+    /// 
+    fn transpile_type_params(&mut self, params: &[TypeParameter]) -> eyre::Result<String> {
+        let params = params
+                .iter()
+                .map(|param| match &param.bound {
+                    Some(bound) => Ok(format!("{}: {}", param.name, self.generate_python(bound.clone())?)),
+                    None => Ok(param.name.clone())
+                })
+                .collect::<eyre::Result<Vec<String>>>()?
+                .join(", ");
+
+        return Ok(format!("[{}]", params));
+    }
+
+
+    ///
+    /// Declare each of `params` as a `TypeVar`, for versions of Python without PEP 695.
+    /// The declarations are followed by the start of the line of the definition they are for.
+    /// E.g. `N_float = typing.TypeVar("N_float", bound="float")`. Bounds and constraints are
+    /// given as forward references, as they may name structs defined further down.
+    /// 
+    /// A parameter with a bound or constraints is declared under a name of its own, e.g.
+    /// `T_Num`, which the caller writes in place of the parameter's name within the
+    /// definition. That way, `def largest[T: Num]` and `def first[T]` do not overwrite each
+    /// other's `T`. A `TypeVar` already declared at the top level of the module is not
+    /// declared again.
+    /// 
+    fn declare_type_vars(&mut self, params: &[TypeParameter]) -> eyre::Result<String> {
+        self.imports.insert("import typing");
+
+        let mut code = String::new();
+        for param in params {
+            let (name, arguments) = match &param.bound {
+                None => (param.name.clone(), String::new()),
+
+                // Constraints, e.g. `T: (int, float)`.
+                Some(bound) if let AstNode::TupleLiteral(constraints) = bound.as_ref() => {
+                    let constraints = constraints
+                        .iter()
+                        .map(|constraint| self.generate_python(constraint.clone()))
+                        .collect::<eyre::Result<Vec<String>>>()?;
+
+                    let name = type_var_name(&param.name, &constraints.join("_"));
+                    let references: Vec<String> = constraints
+                        .iter()
+                        .map(|constraint| format!("\"{}\"", constraint.replace('"', "'")))
+                        .collect();
+                    (name, format!(", {}", references.join(", ")))
+                }
+
+                Some(bound) => {
+                    let bound = self.generate_python(bound.clone())?.replace('"', "'");
+                    (type_var_name(&param.name, &bound), format!(", bound=\"{}\"", bound))
+                }
+            };
+
+            if name != param.name {
+                self.substitutions.insert(param.name.clone(), name.clone());
+            }
+
+            let declaration = format!("{} = typing.TypeVar(\"{}\"{})", name, name, arguments);
+            if self.type_vars.contains(&declaration) {
+                continue;
+            }
+
+            // Declarations within a class or block are not visible to the rest of the module.
+            if self.indent == 0 {
+                self.type_vars.insert(declaration.clone());
+            }
+
+            code.push_str(&declaration);
+            code.push('\n');
+            code.push_str(&self.indent_code());
+        }
+
+        return Ok(code);
+    }
+
+
     fn transpile_function_definition(&mut self, function: &FunctionDefinition) -> eyre::Result<String> {
-        let name = function.name.clone();
+        let mut name = function.name.clone();
+        let mut declarations = String::new();
+
+        // The names of the `TypeVar` declared for the type parameters are only used within
+        // the definition.
+        let outer = self.substitutions.clone();
+
+        if !function.type_params.is_empty() {
+            if self.supports_type_params() {
+                name.push_str(&self.transpile_type_params(&function.type_params)?);
+            } else {
+                declarations = self.declare_type_vars(&function.type_params)?;
+            }
+        }
 
         let params = function.param_list
                 .iter()
-                .map(|param| self.generate_python(param.clone()))
+                .map(|param| self.transpile_parameter(param))
                 .collect::<eyre::Result<Vec<String>>>()?
                 .join(", ");
 
+        let return_type = self.generate_python(function.return_type.clone())?;
+
+        // The methods of interfaces have no body.
+        let code = if function.body.is_empty() {
+            format!("{}def {}({}) -> {}: ...\n", declarations, name, params, return_type)
+        } else {
+            self.increase_indent();
+            let body = self.transpile_block(&function.body)?;
+            self.decrease_indent();
+            format!("{}def {}({}) -> {}:\n{}", declarations, name, params, return_type, body)
+        };
+
+        self.substitutions = outer;
+        return Ok(code);
+    }
+
+
+    ///
+    /// Transpile a struct to a dataclass. Methods are separated from the fields and each
+    /// other by a blank line. The interfaces that the struct implements are its base
+    /// classes, so that Python's type checkers check it against them too.
+    /// 
+    fn transpile_struct_definition(&mut self, definition: &StructDefinition) -> eyre::Result<String> {
+        self.imports.insert("import dataclasses");
+
+        // Lets the annotations of fields and methods refer to the struct itself.
+        self.imports.insert("from __future__ import annotations");
+
+        let mut code = String::new();
+        let mut name = definition.name.clone();
+        let outer = self.substitutions.clone();
+        let mut bases = definition.interfaces
+            .iter()
+            .map(|interface| self.generate_python(interface.clone()))
+            .collect::<eyre::Result<Vec<String>>>()?;
+
+        if !definition.type_params.is_empty() {
+            if self.supports_type_params() {
+                name.push_str(&self.transpile_type_params(&definition.type_params)?);
+            } else {
+                code = self.declare_type_vars(&definition.type_params)?;
+                let params: Vec<&str> = definition.type_params
+                    .iter()
+                    .map(|param| self.substitutions.get(&param.name).unwrap_or(&param.name).as_str())
+                    .collect();
+                bases.push(format!("typing.Generic[{}]", params.join(", ")));
+            }
+        }
+
+        if !bases.is_empty() {
+            name.push_str(&format!("({})", bases.join(", ")));
+        }
+
+        code.push_str(&format!("@dataclasses.dataclass\n{}class {}:\n", self.indent_code(), name));
+
         self.increase_indent();
-        let body = self.transpile_block(&function.body)?;
+
+        for field in &definition.fields {
+            let annotation = self.generate_python(field.annotation.clone())?;
+            code.push_str(&format!("{}{}: {}", self.indent_code(), field.name, annotation));
+
+            if let Some(default) = &field.default {
+                let default = self.generate_python(default.clone())?;
+                code.push_str(&format!(" = {}", default));
+            }
+            code.push('\n');
+        }
+
+        for (i, method) in definition.methods.iter().enumerate() {
+            if i > 0 || !definition.fields.is_empty() {
+                code.push('\n');
+            }
+            let method = self.generate_python(method.clone())?;
+            code.push_str(&format!("{}{}", self.indent_code(), method));
+        }
+
+        if definition.fields.is_empty() && definition.methods.is_empty() {
+            code.push_str(&format!("{}pass\n", self.indent_code()));
+        }
+
+        self.decrease_indent();
+        self.substitutions = outer;
+        Ok(code)
+    }
+
+
+    ///
+    /// Transpile an interface to a `typing.Protocol`, whose methods have no body.
+    /// 
+    fn transpile_interface_definition(&mut self, definition: &InterfaceDefinition) -> eyre::Result<String> {
+        self.imports.insert("import typing");
+        self.imports.insert("from __future__ import annotations");
+
+        let mut code = format!("class {}(typing.Protocol):\n", definition.name);
+
+        self.increase_indent();
+        for method in &definition.methods {
+            let method = self.generate_python(method.clone())?;
+            code.push_str(&format!("{}{}", self.indent_code(), method));
+        }
+
+        if definition.methods.is_empty() {
+            code.push_str(&format!("{}pass\n", self.indent_code()));
+        }
+        self.decrease_indent();
+
+        Ok(code)
+    }
+
+
+    ///
+    /// Transpile an enum to a subclass of `enum.Enum`, whose variants are numbered
+    /// automatically. The variants of an enum with payloads are each a frozen dataclass
+    /// instead, which subclasses a class for the enum.
+    /// 
+    fn transpile_enum_definition(&mut self, definition: &EnumDefinition) -> eyre::Result<String> {
+        if definition.has_payloads() {
+            let statements = self.transpile_tagged_union(definition)?;
+            return Ok(statements.join(&format!("\n{}", self.indent_code())));
+        }
+
+        self.imports.insert("import enum");
+        let mut code = format!("class {}(enum.Enum):\n", definition.name);
+
+        self.increase_indent();
+        for variant in &definition.variants {
+            code.push_str(&format!("{}{} = enum.auto()\n", self.indent_code(), variant.name));
+        }
         self.decrease_indent();
 
-        return Ok(format!("def {}({}):\n{}", name, params, body));
+        Ok(code)
+    }
+
+
+    ///
+    /// Transpile an enum with payloads, e.g.
+    /// ```python
+    /// class Shape:
+    ///     pass
+    ///
+    /// @dataclasses.dataclass(frozen=True)
+    /// class Shape_Circle(Shape):
+    ///     radius: float
+    /// ```
+    /// Each variant subclasses the enum's class, so it is accepted wherever the enum is,
+    /// both by `isinstance` and by Python's type checkers. Variants are named after their
+    /// enum, so variants of different enums do not clash. Each class is a separate
+    /// statement, so that the REPL can run them.
+    /// 
+    fn transpile_tagged_union(&mut self, definition: &EnumDefinition) -> eyre::Result<Vec<String>> {
+        self.imports.insert("import dataclasses");
+        self.imports.insert("from __future__ import annotations");
+
+        self.increase_indent();
+        let mut statements = vec![format!("class {}:\n{}pass\n", definition.name, self.indent_code())];
+        self.decrease_indent();
+
+        for variant in &definition.variants {
+            let mut code = format!("@dataclasses.dataclass(frozen=True)\n{}", self.indent_code());
+            code.push_str(&format!("class {}_{}({}):\n", definition.name, variant.name, definition.name));
+            self.increase_indent();
+
+            let fields = variant.fields.as_deref().unwrap_or_default();
+            for field in fields {
+                let annotation = self.generate_python(field.annotation.clone())?;
+                code.push_str(&format!("{}{}: {}", self.indent_code(), field.name, annotation));
+
+                if let Some(default) = &field.default {
+                    let default = self.generate_python(default.clone())?;
+                    code.push_str(&format!(" = {}", default));
+                }
+                code.push('\n');
+            }
+
+            if fields.is_empty() {
+                code.push_str(&format!("{}pass\n", self.indent_code()));
+            }
+
+            self.decrease_indent();
+            statements.push(code);
+        }
+
+        Ok(statements)
+    }
+
+
+    ///
+    /// Transpile a single parameter of a function definition. Spacing follows PEP 8,
+    /// e.g. `x=0` but `x: int = 0`.
+    /// 
+    fn transpile_parameter(&mut self, param: &Parameter) -> eyre::Result<String> {
+        let prefix = match param.kind {
+            ParameterKind::PositionalOnlyMarker => return Ok(String::from("/")),
+            ParameterKind::KeywordOnlyMarker => return Ok(String::from("*")),
+            ParameterKind::VarPositional => "*",
+            ParameterKind::VarKeyword => "**",
+            ParameterKind::Normal => "",
+        };
+
+        let mut code = format!("{}{}", prefix, param.name);
+
+        if let Some(annotation) = &param.annotation {
+            let annotation = self.generate_python(annotation.clone())?;
+            code = format!("{}: {}", code, annotation);
+        }
+
+        if let Some(default) = &param.default {
+            let default = self.generate_python(default.clone())?;
+            let separator = if param.annotation.is_some() { " = " } else { "=" };
+            code = format!("{}{}{}", code, separator, default);
+        }
+
+        Ok(code)
     }
 
 
@@ -106,23 +630,354 @@ impl PythonTranspiler {
     }
 
 
+    ///
+    /// Whether Python has a `match` statement of its own, which it does from 3.10 onwards.
+    /// 
+    fn supports_match(&self) -> bool {
+        self.options.python >= PythonVersion::new(3, 10)
+    }
+
+
+    ///
+    /// Transpile a match statement to Python's own, or to a chain of `if` and `elif`
+    /// statements for versions of Python without one.
+    /// 
+    fn transpile_match_statement(&mut self, statement: &MatchStatement) -> eyre::Result<String> {
+        if !self.supports_match() {
+            return self.transpile_match_chain(statement);
+        }
+
+        let subject = self.generate_python(statement.subject.clone())?;
+        let mut code = format!("match {}:\n", subject);
+
+        self.increase_indent();
+        for case in &statement.cases {
+            let pattern = self.transpile_pattern(&case.pattern)?;
+            let guard = match &case.guard {
+                Some(guard) => format!(" if {}", self.generate_python(guard.clone())?),
+                None => String::new()
+            };
+            code.push_str(&format!("{}case {}{}:\n", self.indent_code(), pattern, guard));
+
+            self.increase_indent();
+            code.push_str(&self.transpile_case_body(&[], &case.body)?);
+            self.decrease_indent();
+        }
+        self.decrease_indent();
+
+        Ok(code)
+    }
+
+
+    ///
+    /// Transpile a pattern to the equivalent pattern of Python's `match` statement.
+    /// 
+    fn transpile_pattern(&mut self, pattern: &Pattern) -> eyre::Result<String> {
+        let code = match pattern {
+            Pattern::Wildcard => String::from("_"),
+
+            Pattern::Capture(name) => name.clone(),
+
+            Pattern::Literal(value) => self.generate_python(value.clone())?,
+
+            // A variant with a payload is a class, so is matched by a class pattern.
+            Pattern::Value(value) if self.payload_variant(value).is_some() => format!("{}()", self.transpile_class(value)?),
+
+            Pattern::Value(value) => self.generate_python(value.clone())?,
+
+            Pattern::Class { class, positional, keywords } => {
+                let class = self.transpile_class(class)?;
+
+                let mut arguments = vec![];
+                for pattern in positional {
+                    arguments.push(self.transpile_pattern(pattern)?);
+                }
+                for (name, pattern) in keywords {
+                    arguments.push(format!("{}={}", name, self.transpile_pattern(pattern)?));
+                }
+
+                format!("{}({})", class, arguments.join(", "))
+            }
+
+            Pattern::Sequence(elements) => {
+                let elements = elements
+                    .iter()
+                    .map(|element| self.transpile_pattern(element))
+                    .collect::<eyre::Result<Vec<String>>>()?;
+                format!("[{}]", elements.join(", "))
+            }
+
+            Pattern::Star(name) => format!("*{}", name.as_deref().unwrap_or("_")),
+        };
+
+        Ok(code)
+    }
+
+
+    ///
+    /// Transpile a match statement to a chain of `if` and `elif` statements, for versions
+    /// of Python before 3.10. E.g.
+    /// ```python
+    /// _subject0 = get_shape()
+    /// if isinstance(_subject0, Circle) and (_subject0.radius > 1):
+    ///     radius = _subject0.radius
+    ///     ...
+    /// ```
+    /// The subject is only stored in a variable if it is not a name already.
+    /// 
+    fn transpile_match_chain(&mut self, statement: &MatchStatement) -> eyre::Result<String> {
+        let mut code = String::new();
+        let subject = self.generate_python(statement.subject.clone())?;
+
+        let subject = match statement.subject.as_ref() {
+            AstNode::Identifier(_) => subject,
+            _ => {
+                let name = format!("_subject{}", self.subjects);
+                self.subjects += 1;
+                code.push_str(&format!("{} = {}\n{}", name, subject, self.indent_code()));
+                name
+            }
+        };
+
+        for (i, case) in statement.cases.iter().enumerate() {
+            let mut conditions = vec![];
+            let mut bindings = vec![];
+            self.lower_pattern(&case.pattern, &subject, &mut conditions, &mut bindings)?;
+
+            // The names bound by the pattern are only assigned once the case is chosen.
+            if let Some(guard) = &case.guard {
+                let mut substitutions = self.substitutions.clone();
+                substitutions.extend(bindings.iter().cloned());
+                let outer = std::mem::replace(&mut self.substitutions, substitutions);
+                let guard = self.generate_python(guard.clone());
+                self.substitutions = outer;
+                conditions.push(format!("({})", guard?));
+            }
+
+            if i > 0 {
+                code.push_str(&self.indent_code());
+            }
+
+            let header = match (i, conditions.is_empty()) {
+                (0, true) => String::from("if True:"),
+                (_, true) => String::from("else:"),
+                (0, false) => format!("if {}:", conditions.join(" and ")),
+                (_, false) => format!("elif {}:", conditions.join(" and "))
+            };
+            code.push_str(&format!("{}\n", header));
+
+            self.increase_indent();
+            code.push_str(&self.transpile_case_body(&bindings, &case.body)?);
+            self.decrease_indent();
+        }
+
+        Ok(code)
+    }
+
+
+    ///
+    /// Work out the conditions under which `subject`, which is Python code, matches
+    /// `pattern`, along with the code of the value to assign to each name it binds.
+    /// 
+    fn lower_pattern(
+        &mut self,
+        pattern: &Pattern,
+        subject: &str,
+        conditions: &mut Vec<String>,
+        bindings: &mut Vec<(String, String)>
+    ) -> eyre::Result<()> {
+        match pattern {
+            Pattern::Wildcard | Pattern::Star(None) => {}
+
+            Pattern::Capture(name) | Pattern::Star(Some(name)) => bindings.push((name.clone(), subject.to_string())),
+
+            Pattern::Literal(value) => {
+                let literal = self.generate_python(value.clone())?;
+                let operator = match value.as_ref() {
+                    AstNode::BooleanLiteral(_) | AstNode::Identifier(_) => "is",
+                    _ => "=="
+                };
+                conditions.push(format!("{} {} {}", subject, operator, literal));
+            }
+
+            Pattern::Value(value) if self.payload_variant(value).is_some() => {
+                let class = self.transpile_class(value)?;
+                conditions.push(format!("isinstance({}, {})", subject, class));
+            }
+
+            Pattern::Value(value) => {
+                let value = self.generate_python(value.clone())?;
+                conditions.push(format!("{} == {}", subject, value));
+            }
+
+            // Structs and variants are dataclasses, whose fields are in the order they are declared.
+            Pattern::Class { class, positional, keywords } => {
+                let class = self.transpile_class(class)?;
+                conditions.push(format!("isinstance({}, {})", subject, class));
+
+                if !positional.is_empty() {
+                    self.imports.insert("import dataclasses");
+                }
+
+                for (i, pattern) in positional.iter().enumerate() {
+                    let field = format!("getattr({}, dataclasses.fields({})[{}].name)", subject, subject, i);
+                    self.lower_pattern(pattern, &field, conditions, bindings)?;
+                }
+
+                for (name, pattern) in keywords {
+                    self.lower_pattern(pattern, &format!("{}.{}", subject, name), conditions, bindings)?;
+                }
+            }
+
+            // As in Python, strings are not matched as sequences.
+            Pattern::Sequence(elements) => {
+                self.imports.insert("import collections.abc");
+                conditions.push(format!("isinstance({}, collections.abc.Sequence)", subject));
+                conditions.push(format!("not isinstance({}, (str, bytes, bytearray))", subject));
+
+                let star = elements.iter().position(|element| matches!(element, Pattern::Star(_)));
+                conditions.push(match star {
+                    Some(_) => format!("len({}) >= {}", subject, elements.len() - 1),
+                    None => format!("len({}) == {}", subject, elements.len())
+                });
+
+                for (i, element) in elements.iter().enumerate() {
+                    let element_subject = match star {
+                        // The elements after the starred name are counted from the end.
+                        Some(star) if i > star => format!("{}[-{}]", subject, elements.len() - i),
+                        Some(star) if i == star && i + 1 == elements.len() => format!("list({}[{}:])", subject, i),
+                        Some(star) if i == star => format!("list({}[{}:-{}])", subject, i, elements.len() - i - 1),
+                        _ => format!("{}[{}]", subject, i)
+                    };
+                    self.lower_pattern(element, &element_subject, conditions, bindings)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+
+    ///
+    /// Transpile the body of a match case, which begins by assigning the names bound by
+    /// its pattern, if they are not assigned by the pattern itself.
+    /// 
+    fn transpile_case_body(&mut self, bindings: &[(String, String)], body: &[Rc<AstNode>]) -> eyre::Result<String> {
+        let mut code = String::new();
+        for (name, value) in bindings {
+            code.push_str(&format!("{}{} = {}\n", self.indent_code(), name, value));
+        }
+
+        code.push_str(&self.transpile_block(body)?);
+
+        if code.is_empty() {
+            code = format!("{}pass\n", self.indent_code());
+        }
+        Ok(code)
+    }
+
+
+    ///
+    /// The variant of an enum with payloads that `value` names, e.g. `Shape::Circle`, which
+    /// is a class of its own.
+    /// 
+    fn payload_variant(&self, value: &AstNode) -> Option<&PayloadVariant> {
+        let AstNode::ScopeAccess(access) = value else { return None };
+
+        // The enum may be named directly, or within a module, e.g. `shapes::Shape` or `shapes.Shape`.
+        let enumeration = match access.parent.as_ref() {
+            AstNode::Identifier(name) => name,
+            AstNode::ScopeAccess(enumeration) => &enumeration.name,
+            AstNode::MemberAccess(member) => match member.child.as_ref() {
+                AstNode::Identifier(name) => name,
+                _ => return None
+            },
+            _ => return None
+        };
+
+        self.enums
+            .get(enumeration)?
+            .iter()
+            .find(|variant| variant.name == access.name)
+    }
+
+
+    ///
+    /// Transpile `value` where it names a class, e.g. in a class pattern. The class of a
+    /// variant of an enum with payloads is named after the enum, e.g. `Shape_Circle` for
+    /// `Shape::Circle`, or `shapes.Shape_Circle` for `shapes::Shape::Circle`.
+    /// 
+    fn transpile_class(&mut self, value: &Rc<AstNode>) -> eyre::Result<String> {
+        match value.as_ref() {
+            AstNode::ScopeAccess(access) if self.payload_variant(value).is_some() => {
+                let enumeration = self.transpile_postfix_operand(access.parent.clone())?;
+                Ok(format!("{}_{}", enumeration, access.name))
+            }
+            _ => self.generate_python(value.clone())
+        }
+    }
+
+
     fn transpile_return_statement(&mut self, statement: &ReturnStatement) -> eyre::Result<String> {
         let value = self.generate_python(statement.body.clone())?;
         return Ok(format!("return {}", value));
     }
 
 
+    ///
+    /// Transpile a `use` statement to the equivalent Python import. Absolute module
+    /// imports become `import X`, while everything else becomes `from X import Y`.
+    /// 
+    fn transpile_use_statement(&self, statement: &UseStatement) -> String {
+        let with_alias = |name: &str, alias: &Option<String>| match alias {
+            Some(alias) => format!("{} as {}", name, alias),
+            None => name.to_string()
+        };
+
+        match &statement.imports {
+            UseImports::Module { alias } if statement.level == 0 => {
+                format!("import {}", with_alias(&statement.path.join("."), alias))
+            }
+
+            // Relative modules can only be imported from their parent package.
+            UseImports::Module { alias } => {
+                let (name, parent) = statement.path.split_last().unwrap();
+                let package = format!("{}{}", ".".repeat(statement.level), parent.join("."));
+                format!("from {} import {}", package, with_alias(name, alias))
+            }
+
+            // The variants of an enum with payloads are imported along with it, under the
+            // name the enum is imported as.
+            UseImports::Items(items) => {
+                let items = items
+                    .iter()
+                    .flat_map(|item| {
+                        let bound = item.alias.as_ref().unwrap_or(&item.name);
+                        let variants = self.options.enums.get(&item.name).cloned().unwrap_or_default();
+                        let variants = variants.into_iter().map(move |variant| {
+                            let class = format!("{}_{}", item.name, variant.name);
+                            match bound == &item.name {
+                                true => class,
+                                false => format!("{} as {}_{}", class, bound, variant.name)
+                            }
+                        });
+                        std::iter::once(with_alias(&item.name, &item.alias)).chain(variants)
+                    })
+                    .collect::<Vec<String>>()
+                    .join(", ");
+                format!("from {} import {}", statement.module_name(), items)
+            }
+
+            UseImports::Glob => format!("from {} import *", statement.module_name())
+        }
+    }
+
+
     fn transpile_while_loop(&mut self, while_loop: &WhileLoop) -> eyre::Result<String> {
         let condition = self.generate_python(while_loop.condition.clone())?;
 
         self.increase_indent();
-
-        let body = while_loop.body
-                .iter()
-                .map(|statement| self.generate_python(statement.clone()))
-                .collect::<eyre::Result<Vec<String>>>()?
-                .join(&format!("\n"));
-
+        let body = self.transpile_block(&while_loop.body)?;
         self.decrease_indent();
 
         return Ok(format!("while {}:\n{}", condition, body));
@@ -143,7 +998,7 @@ impl PythonTranspiler {
     /// the indentation level is `0`, in which case nothing will happen.
     /// 
     fn decrease_indent(&mut self) -> u16 {
-        self.indent = 0.max(self.indent - 1);
+        self.indent = self.indent.saturating_sub(1);
         self.indent
     }
 
@@ -159,27 +1014,58 @@ impl PythonTranspiler {
             AstNode::UnaryOperation(operation) 
                         => self.transpile_unary_operation(operation)?,
 
-            AstNode::BinaryOperation(operation) 
-                        => self.transpile_binary_operation(operation)?,
+            AstNode::BinaryOperation(operation) => match self.options.annotations.get(&Rc::as_ptr(&program)) {
+                Some(annotation) => self.transpile_annotated_assignment(operation, annotation)?,
+                None => self.transpile_binary_operation(operation)?
+            },
 
             AstNode::FunctionCall(call) 
                         => self.transpile_function_call(call)?,
 
             AstNode::IntegerLiteral(x) => x.to_string(),
 
-            AstNode::FloatLiteral(x) => x.to_string(),
+            // Floats always have a decimal point or exponent, so they stay floats in Python.
+            AstNode::FloatLiteral(x) => format!("{:?}", x),
 
             AstNode::BooleanLiteral(x) => String::from(if *x { "True" } else { "False" }),
 
-            AstNode::StringLiteral(x) => format!("\"{}\"", x),
+            // Strings may span several lines in Nadra, but not in Python.
+            AstNode::StringLiteral(x) => format!("\"{}\"", x.replace('\r', "\\r").replace('\n', "\\n")),
+
+            AstNode::Identifier(x) => match self.substitutions.get(x) {
+                Some(substitute) => substitute.clone(),
+                None => x.to_string()
+            },
+
+            AstNode::ListLiteral(elements) => format!("[{}]", self.transpile_elements(elements)?),
+
+            AstNode::TupleLiteral(elements) => self.transpile_tuple_literal(elements)?,
 
-            AstNode::Identifier(x) => x.to_string(),
+            AstNode::SetLiteral(elements) => format!("{{{}}}", self.transpile_elements(elements)?),
 
-            AstNode::LambdaFunction { params, body } => todo!(),
+            AstNode::DictLiteral(entries) => self.transpile_dict_literal(entries)?,
+
+            AstNode::Unpack(unpack) => self.transpile_unpack(unpack)?,
+
+            AstNode::KeywordArgument(argument) => {
+                let value = self.generate_python(argument.value.clone())?;
+                format!("{}={}", argument.name, value)
+            }
+
+            AstNode::LambdaFunction { .. } => todo!(),
 
             AstNode::FunctionDefinition(function)
                         => self.transpile_function_definition(function)?,
                         
+            AstNode::StructDefinition(definition)
+                        => self.transpile_struct_definition(definition)?,
+
+            AstNode::InterfaceDefinition(definition)
+                        => self.transpile_interface_definition(definition)?,
+
+            AstNode::EnumDefinition(definition)
+                        => self.transpile_enum_definition(definition)?,
+
             AstNode::IfStatement(statement) 
                         => self.transpile_if_statement(statement)?,
 
@@ -189,14 +1075,38 @@ impl PythonTranspiler {
             AstNode::WhileLoop(while_loop) 
                         => self.transpile_while_loop(while_loop)?,
 
+            AstNode::MatchStatement(statement)
+                        => self.transpile_match_statement(statement)?,
+
+            AstNode::Declaration(declaration) => {
+                let inferred = self.options.annotations.get(&Rc::as_ptr(&program));
+                self.transpile_declaration(declaration, inferred)?
+            }
+
             AstNode::UseStatement(use_statement) 
-                => format!("import {}", &use_statement.namespace),
+                        => self.transpile_use_statement(use_statement),
 
             AstNode::MemberAccess(member_access) => {
-                let parent = self.generate_python(member_access.parent.clone())?;
+                let parent = self.transpile_postfix_operand(member_access.parent.clone())?;
                 let child = self.generate_python(member_access.child.clone())?;
                 format!("{}.{}", parent, child)
             }
+
+            // Items of Nadra's types are attributes of the Python classes.
+            // The variants of enums with payloads are classes of their own, and those without
+            // fields are used without being called.
+            AstNode::ScopeAccess(scope_access) => match self.payload_variant(&program).map(|variant| variant.has_fields) {
+                Some(true) => self.transpile_class(&program)?,
+                Some(false) => format!("{}()", self.transpile_class(&program)?),
+                None => {
+                    let parent = self.transpile_postfix_operand(scope_access.parent.clone())?;
+                    format!("{}.{}", parent, scope_access.name)
+                }
+            },
+
+            AstNode::Subscript(subscript) => self.transpile_subscript(subscript)?,
+
+            AstNode::Slice(slice) => self.transpile_slice(slice)?,
         };
 
         Ok(python_code)
@@ -239,7 +1149,7 @@ impl PythonTranspiler {
 
 
 ///
-/// Convert an Abstract Syntax Tree (AST) to Python code. For example, the AST denoted by
+/// Convert the Abstract Syntax Tree (AST) of a program to Python code. For example, the AST denoted by
 /// ```
 ///  (EqEq 
 ///     (Mult 2 (Call fact 5)) 
@@ -252,11 +1162,84 @@ impl PythonTranspiler {
 /// ```
 /// 
 /// # Params
-/// - `program` - The root node of the AST to transpile. Note that a Nadra program may
-/// contain several ASTs, one for each top-level statement.
+/// - `program` - The AST of each top-level statement of the program.
+/// - `options` - What code to generate, e.g. for which version of Python.
+/// 
+/// # Returns
+/// - The Python code of each top-level statement, preceded by any imports the generated
+/// code needs.
 /// 
-pub fn transpile(program: Rc<AstNode>) -> eyre::Result<String> {
-    // Transpile the Nadra AST to Python
-    PythonTranspiler::new()
-        .generate_python(program)
-}
\ No newline at end of file
+pub fn transpile_program(program: &[Rc<AstNode>], options: &TranspileOptions) -> eyre::Result<Vec<String>> {
+    let mut transpiler = PythonTranspiler::new(options);
+
+    // Enums with payloads may be used before they are defined, or come from elsewhere,
+    // possibly under an alias.
+    transpiler.enums.extend(payload_enums(program));
+    transpiler.enums.extend(options.enums.clone());
+
+    for statement in program {
+        if let AstNode::UseStatement(use_statement) = statement.as_ref()
+            && let UseImports::Items(items) = &use_statement.imports {
+            let aliases = items
+                .iter()
+                .filter_map(|item| Some((item.alias.clone()?, options.enums.get(&item.name)?.clone())));
+            transpiler.enums.extend(aliases);
+        }
+    }
+
+    let mut statements = vec![];
+    for statement in program {
+        match statement.as_ref() {
+            AstNode::EnumDefinition(definition) if definition.has_payloads() => {
+                statements.extend(transpiler.transpile_tagged_union(definition)?);
+            }
+            _ => statements.push(transpiler.generate_python(statement.clone())?)
+        }
+    }
+
+    // `from __future__` imports sort first, which Python requires.
+    let imports = transpiler.imports
+        .iter()
+        .map(|import| import.to_string());
+
+    Ok(imports.chain(statements).collect())
+}
+
+
+///
+/// The variants of each enum with payloads defined by `program`, by the name of the enum.
+///
+pub fn payload_enums(program: &[Rc<AstNode>]) -> HashMap<String, Vec<PayloadVariant>> {
+    program
+        .iter()
+        .filter_map(|statement| match statement.as_ref() {
+            AstNode::EnumDefinition(definition) if definition.has_payloads() => Some((
+                definition.name.clone(),
+                definition.variants
+                    .iter()
+                    .map(|variant| PayloadVariant {
+                        name: variant.name.clone(),
+                        has_fields: variant.fields.is_some()
+                    })
+                    .collect()
+            )),
+            _ => None
+        })
+        .collect()
+}
+
+
+///
+/// The name of the `TypeVar` declared for the type parameter `name` with the bound or
+/// constraints `bound`, e.g. `T_Num` for `T: Num`. Anything in `bound` that cannot be
+/// written in a name, such as brackets, becomes an underscore.
+///
+fn type_var_name(name: &str, bound: &str) -> String {
+    let bound: String = bound
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { '_' })
+        .collect();
+
+    let words: Vec<&str> = bound.split('_').filter(|word| !word.is_empty()).collect();
+    format!("{}_{}", name, words.join("_"))
+}
@@ -0,0 +1,2267 @@
+use core::fmt;
+use std::{collections::{HashMap, HashSet}, rc::Rc};
+use crate::{
+    lexer::{PieToken, TokenType},
+    parser::{
+        ast::{AstNode, BinaryOperator, DictEntry, Field, Parameter, ParameterKind, UnaryOperator},
+        cst::{SyntaxElement, SyntaxKind, SyntaxNode, SyntaxTree}
+    },
+    symbols::{ScopeId, ScopeKind, Span, Symbol, SymbolId, SymbolIndex, SymbolKind, BUILTINS}
+};
+
+
+///
+/// The static type of a value, as far as it is known before the program is run.
+/// - `Unknown` - Any type at all, e.g. that of an unannotated parameter. Anything may be
+/// done with a value of an unknown type, which is what lets unannotated code pass unchecked.
+/// - `Struct` and `Enum` - An instance of the struct or enum `symbol`. The `arguments` of
+/// a generic struct are the types given for its type parameters, if they are known.
+/// - `Variant` - An instance of the variant with a payload `symbol` of the enum `owner`.
+/// - `Interface` - A value of any of the `implementors` of the interface `symbol`, i.e. the
+/// structs which declare that they implement it.
+/// - `Parameter` - The type parameter `symbol` of a generic function or struct, within its
+/// definition. Any type accepted by `bound` may be given for it.
+/// - `Function` - The function or method `symbol`, where `bound` is whether it was
+/// accessed through an instance, which then fills its first parameter, and `arguments`
+/// are the type arguments of that instance.
+/// - `Class` - The struct or enum `symbol` itself, e.g. `Point` in `Point(1, 2)`.
+/// - `Builtin` - One of Python's builtin functions or types, e.g. `len` or `int`.
+///
+#[derive(Debug, Clone, PartialEq)]
+pub enum Type {
+    Unknown,
+    None,
+    Bool,
+    Int,
+    Float,
+    Str,
+    List(Box<Type>),
+    Set(Box<Type>),
+    Dict(Box<Type>, Box<Type>),
+    Tuple(Vec<Type>),
+    Union(Vec<Type>),
+    Struct { name: String, symbol: SymbolId, arguments: Vec<Type> },
+    Enum { name: String, symbol: SymbolId },
+    Variant { name: String, symbol: SymbolId, owner: SymbolId },
+    Interface { name: String, symbol: SymbolId, implementors: Vec<SymbolId> },
+    Parameter { name: String, symbol: SymbolId, bound: Box<Type> },
+    Function { symbol: SymbolId, bound: bool, arguments: Vec<Type> },
+    Class { name: String, symbol: SymbolId },
+    Builtin(String)
+}
+
+
+impl Type {
+    ///
+    /// The type of a value that is of one of `types`. Types which are already accepted
+    /// by another of them are left out, so the union of `int` and `float` is just `float`.
+    ///
+    pub fn union(types: Vec<Type>) -> Self {
+        let mut members: Vec<Type> = vec![];
+        for type_ in types {
+            let flattened = match type_ {
+                Self::Union(inner) => inner,
+                Self::Unknown => return Self::Unknown,
+                type_ => vec![type_]
+            };
+
+            for member in flattened {
+                if !members.contains(&member) {
+                    members.push(member);
+                }
+            }
+        }
+
+        let kept: Vec<Type> = members
+            .iter()
+            .filter(|member| !members.iter().any(|other| other != *member && accepts(other, member)))
+            .cloned()
+            .collect();
+
+        match kept.len() {
+            0 => Self::Unknown,
+            1 => kept.into_iter().next().unwrap(),
+            _ => Self::Union(kept)
+        }
+    }
+
+
+    fn is_numeric(&self) -> bool {
+        matches!(self, Self::Bool | Self::Int | Self::Float)
+    }
+
+
+    ///
+    /// The members of this union for which `keep` holds, or `None` if there are none.
+    /// Any other type is treated as a union of itself alone.
+    ///
+    fn filter(&self, keep: impl Fn(&Type) -> bool) -> Option<Self> {
+        let members = match self {
+            Self::Union(members) => members.clone(),
+            type_ => vec![type_.clone()]
+        };
+
+        let kept: Vec<Type> = members.into_iter().filter(|member| keep(member)).collect();
+        match kept.is_empty() {
+            true => None,
+            false => Some(Self::union(kept))
+        }
+    }
+
+
+    ///
+    /// Whether this type can be written down as a Python annotation which refers to
+    /// nothing but builtins and the module's own structs and enums.
+    ///
+    fn is_annotatable(&self) -> bool {
+        match self {
+            Self::Unknown | Self::Function { .. } | Self::Class { .. } | Self::Builtin(_) => false,
+            Self::List(element) | Self::Set(element) => element.is_annotatable(),
+            Self::Dict(key, value) => key.is_annotatable() && value.is_annotatable(),
+            Self::Tuple(members) | Self::Union(members) | Self::Struct { arguments: members, .. } => {
+                members.iter().all(Self::is_annotatable)
+            }
+
+            // Variants are only named through their enum, which Python does not do.
+            Self::Variant { .. } => false,
+            _ => true
+        }
+    }
+
+
+    ///
+    /// The type of the elements produced by iterating over a value of this type.
+    ///
+    pub fn element(&self) -> Self {
+        match self {
+            Self::List(element) | Self::Set(element) => element.as_ref().clone(),
+            Self::Dict(key, _) => key.as_ref().clone(),
+            Self::Str => Self::Str,
+            Self::Tuple(elements) => Self::union(elements.clone()),
+            _ => Self::Unknown
+        }
+    }
+
+
+    ///
+    /// This type with each type parameter in `bindings` replaced by the type given for it.
+    ///
+    fn substitute(&self, bindings: &HashMap<SymbolId, Type>) -> Self {
+        let all = |types: &[Type]| types.iter().map(|type_| type_.substitute(bindings)).collect::<Vec<Type>>();
+
+        match self {
+            Self::Parameter { symbol, .. } => bindings.get(symbol).cloned().unwrap_or_else(|| self.clone()),
+            Self::List(element) => Self::List(Box::new(element.substitute(bindings))),
+            Self::Set(element) => Self::Set(Box::new(element.substitute(bindings))),
+            Self::Dict(key, value) => Self::Dict(Box::new(key.substitute(bindings)), Box::new(value.substitute(bindings))),
+            Self::Tuple(elements) => Self::Tuple(all(elements)),
+            Self::Union(members) => Self::union(all(members)),
+            Self::Struct { name, symbol, arguments } => Self::Struct {
+                name: name.clone(),
+                symbol: *symbol,
+                arguments: all(arguments)
+            },
+            _ => self.clone()
+        }
+    }
+}
+
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let join = |types: &[Type], separator: &str| {
+            types.iter().map(|type_| type_.to_string()).collect::<Vec<_>>().join(separator)
+        };
+
+        match self {
+            Self::Unknown => write!(f, "Any"),
+            Self::None => write!(f, "None"),
+            Self::Bool => write!(f, "bool"),
+            Self::Int => write!(f, "int"),
+            Self::Float => write!(f, "float"),
+            Self::Str => write!(f, "str"),
+            Self::List(element) => write!(f, "list[{}]", element),
+            Self::Set(element) => write!(f, "set[{}]", element),
+            Self::Dict(key, value) => write!(f, "dict[{}, {}]", key, value),
+            Self::Tuple(elements) if elements.is_empty() => write!(f, "tuple[()]"),
+            Self::Tuple(elements) => write!(f, "tuple[{}]", join(elements, ", ")),
+            Self::Union(members) => write!(f, "{}", join(members, " | ")),
+            Self::Struct { name, arguments, .. } if !arguments.is_empty() => write!(f, "{}[{}]", name, join(arguments, ", ")),
+            Self::Struct { name, .. }
+            | Self::Enum { name, .. }
+            | Self::Interface { name, .. }
+            | Self::Parameter { name, .. } => write!(f, "{}", name),
+            Self::Variant { name, .. } => write!(f, "{}", name),
+            Self::Class { name, .. } => write!(f, "type[{}]", name),
+            Self::Builtin(name) if is_builtin_type(name) => write!(f, "type[{}]", name),
+            Self::Function { .. } | Self::Builtin(_) => write!(f, "Callable[..., Any]")
+        }
+    }
+}
+
+
+///
+/// Whether a value of type `actual` may be used where a value of type `expected` is.
+/// As in Python, an `int` is accepted as a `float`, and a `bool` as an `int`.
+///
+pub fn accepts(expected: &Type, actual: &Type) -> bool {
+    use Type::*;
+
+    match (expected, actual) {
+        (Unknown, _) | (_, Unknown) => true,
+        (_, Union(actuals)) => actuals.iter().all(|actual| accepts(expected, actual)),
+        (Union(expecteds), _) => expecteds.iter().any(|expected| accepts(expected, actual)),
+        (Float, Int | Bool) | (Int, Bool) => true,
+        (List(expected), List(actual)) | (Set(expected), Set(actual)) => accepts(expected, actual),
+        (Dict(expected_key, expected_value), Dict(actual_key, actual_value)) => {
+            accepts(expected_key, actual_key) && accepts(expected_value, actual_value)
+        }
+        (Tuple(expecteds), Tuple(actuals)) => {
+            expecteds.len() == actuals.len()
+                && expecteds.iter().zip(actuals).all(|(expected, actual)| accepts(expected, actual))
+        }
+
+        (Enum { symbol, .. }, Variant { owner, .. }) => symbol == owner,
+
+        (Interface { symbol: expected, .. }, Interface { symbol: actual, .. }) => expected == actual,
+        (Interface { implementors, .. }, Struct { symbol, .. }) => implementors.contains(symbol),
+
+        // A value of a type parameter's type may be used as its bound.
+        (Parameter { symbol: expected, .. }, Parameter { symbol: actual, .. }) if expected == actual => true,
+        (_, Parameter { bound, .. }) => accepts(expected, bound),
+
+        // The type arguments of a struct are only checked if both are known.
+        (Struct { symbol: expected, arguments: expecteds, .. }, Struct { symbol: actual, arguments: actuals, .. }) => {
+            expected == actual && (expecteds.is_empty() || actuals.is_empty() || expecteds.len() == actuals.len()
+                && expecteds.iter().zip(actuals).all(|(expected, actual)| accepts(expected, actual)))
+        }
+        _ => expected == actual
+    }
+}
+
+
+///
+/// Record the type given for each type parameter within `expected`, by matching it against
+/// `actual`, e.g. `int` for `T` when `list[T]` is given a `list[int]`.
+///
+fn unify(expected: &Type, actual: &Type, found: &mut HashMap<SymbolId, Vec<Type>>) {
+    match (expected, actual) {
+        (_, Type::Unknown) => {}
+        (Type::Parameter { symbol, .. }, _) => found.entry(*symbol).or_default().push(actual.clone()),
+        (Type::List(expected), Type::List(actual)) | (Type::Set(expected), Type::Set(actual)) => unify(expected, actual, found),
+        (Type::Dict(expected_key, expected_value), Type::Dict(actual_key, actual_value)) => {
+            unify(expected_key, actual_key, found);
+            unify(expected_value, actual_value, found);
+        }
+        (Type::Tuple(expecteds), Type::Tuple(actuals))
+        | (Type::Struct { arguments: expecteds, .. }, Type::Struct { arguments: actuals, .. }) => {
+            for (expected, actual) in expecteds.iter().zip(actuals) {
+                unify(expected, actual, found);
+            }
+        }
+        _ => {}
+    }
+}
+
+
+///
+/// The type of the result of `left <operator> right`, or `None` if Python would raise a
+/// `TypeError` for operands of these types.
+///
+pub fn binary(operator: &BinaryOperator, left: &Type, right: &Type) -> Option<Type> {
+    use BinaryOperator::*;
+
+    match operator {
+        EqualEqual | BangEqual => return Some(Type::Bool),
+        And | Or => return Some(Type::union(vec![left.clone(), right.clone()])),
+        _ => {}
+    }
+
+    let comparison = matches!(operator, Less | Greater | LessEqual | GreaterEqual);
+
+    match (left, right) {
+        (Type::Unknown, _) | (_, Type::Unknown) => return Some(if comparison { Type::Bool } else { Type::Unknown }),
+
+        (Type::Parameter { bound, .. }, _) => return binary(operator, bound, right),
+        (_, Type::Parameter { bound, .. }) => return binary(operator, left, bound),
+
+        (Type::Union(members), _) => {
+            let results: Option<Vec<Type>> = members.iter().map(|member| binary(operator, member, right)).collect();
+            return results.map(Type::union);
+        }
+
+        (_, Type::Union(members)) => {
+            let results: Option<Vec<Type>> = members.iter().map(|member| binary(operator, left, member)).collect();
+            return results.map(Type::union);
+        }
+
+        _ => {}
+    }
+
+    if comparison {
+        let comparable = (left.is_numeric() && right.is_numeric()) || matches!(
+            (left, right),
+            (Type::Str, Type::Str) | (Type::List(_), Type::List(_)) | (Type::Tuple(_), Type::Tuple(_)) | (Type::Set(_), Type::Set(_))
+        );
+        return comparable.then_some(Type::Bool);
+    }
+
+    if left.is_numeric() && right.is_numeric() {
+        return match operator {
+            Slash | SlashEqual => Some(Type::Float),
+            _ if *left == Type::Float || *right == Type::Float => Some(Type::Float),
+            _ => Some(Type::Int)
+        };
+    }
+
+    match (operator, left, right) {
+        (Plus | PlusEqual, Type::Str, Type::Str) => Some(Type::Str),
+        (Plus | PlusEqual, Type::List(left), Type::List(right)) => {
+            Some(Type::List(Box::new(Type::union(vec![*left.clone(), *right.clone()]))))
+        }
+        (Plus | PlusEqual, Type::Tuple(left), Type::Tuple(right)) => Some(Type::Tuple([left.clone(), right.clone()].concat())),
+
+        (Asterisk | AsteriskEqual, Type::Str, Type::Int | Type::Bool) | (Asterisk, Type::Int | Type::Bool, Type::Str) => Some(Type::Str),
+        (Asterisk | AsteriskEqual, Type::List(element), Type::Int | Type::Bool)
+        | (Asterisk, Type::Int | Type::Bool, Type::List(element)) => {
+            Some(Type::List(element.clone()))
+        }
+
+        (Modulus | ModulusEqual, Type::Str, _) => Some(Type::Str),
+        (Minus | MinusEqual, Type::Set(element), Type::Set(_)) => Some(Type::Set(element.clone())),
+
+        _ => None
+    }
+}
+
+
+///
+/// # Overview
+/// What the type checker found out about a module.
+///
+/// # Members
+/// - `errors` - Where each type error is, and a description of it, in source order.
+/// - `types` - The type of the variable or parameter named at each position, where it is
+/// known, e.g. for hovering in an editor. A parameter or constant is only included where
+/// its type differs from the declared one, e.g. after `if x != None then`.
+/// - `annotations` - The type of each variable, keyed by the assignment which first
+/// assigns to it.
+///
+pub struct Analysis {
+    pub errors: Vec<(Span, String)>,
+    pub types: HashMap<(u64, u32), Type>,
+    annotations: HashMap<*const AstNode, Type>
+}
+
+
+impl Analysis {
+    ///
+    /// The annotation to emit for each assignment that first assigns to a variable, in
+    /// Python syntax. Variables whose type cannot be written down are left out.
+    ///
+    pub fn annotations(&self) -> HashMap<*const AstNode, String> {
+        self.annotations
+            .iter()
+            .filter(|(_, type_)| type_.is_annotatable())
+            .map(|(assignment, type_)| (*assignment, type_.to_string()))
+            .collect()
+    }
+}
+
+
+///
+/// Check the types of a module whose names are resolved by `index`.
+///
+pub fn check(tree: &SyntaxTree, index: &SymbolIndex) -> Analysis {
+    let occurrences = index.occurrences
+        .iter()
+        .filter_map(|occurrence| Some(((occurrence.span.line, occurrence.span.column), occurrence.symbol?)))
+        .collect();
+
+    let mut checker = Checker {
+        index: index,
+        occurrences: occurrences,
+        flow: Flow::default(),
+        function: None,
+        errors: vec![],
+        types: HashMap::new(),
+        assignment: None,
+        first_assignments: HashMap::new(),
+        assigned: HashMap::new()
+    };
+
+    for statement in tree.root.child_nodes() {
+        checker.statement(statement);
+    }
+
+    checker.errors.sort_by_key(|(span, _)| (span.line, span.column));
+
+    let annotations = checker.first_assignments
+        .iter()
+        .filter_map(|(symbol, assignment)| {
+            let types = checker.assigned.get(symbol)?.clone();
+            Some(((*assignment)?, Type::union(types)))
+        })
+        .collect();
+
+    Analysis {
+        errors: checker.errors,
+        types: checker.types,
+        annotations: annotations
+    }
+}
+
+
+///
+/// # Overview
+/// What is known at some point of a function, or module, body.
+///
+/// # Members
+/// - `locals` - The types of the variables assigned so far, and of the parameters which
+/// have been assigned to or narrowed.
+/// - `reachable` - Whether the point can be reached at all, i.e. not after a `return`.
+///
+#[derive(Clone, PartialEq)]
+struct Flow {
+    locals: HashMap<SymbolId, Type>,
+    reachable: bool
+}
+
+
+impl Default for Flow {
+    fn default() -> Self {
+        Flow { locals: HashMap::new(), reachable: true }
+    }
+}
+
+
+///
+/// # Overview
+/// The declared parameters and return type of something that can be called.
+///
+/// # Members
+/// - `name` - The name of the function or struct.
+/// - `params` - The name, kind, declared type and whether there is a default value, of each
+/// parameter. The parameters of a struct are its fields.
+/// - `returns` - The type of the value returned by a call.
+///
+struct Signature {
+    name: String,
+    params: Vec<(String, ParameterKind, Type, bool)>,
+    returns: Type
+}
+
+
+///
+/// An argument of a call: its name if it is passed by keyword, its type, the node of its
+/// value, and whether it is unpacked with `*` or `**`.
+///
+struct Argument<'a> {
+    name: Option<Rc<PieToken>>,
+    type_: Type,
+    node: &'a Rc<SyntaxNode>,
+    unpacked: bool
+}
+
+
+///
+/// # Overview
+/// Walks the concrete syntax tree of a module, working out the type of every expression
+/// and recording any operations that Python would reject.
+///
+/// # Members
+/// - `index` - The symbols of the module.
+/// - `occurrences` - The symbol that the name at each position refers to.
+/// - `flow` - What is known at the statement being checked.
+/// - `function` - The function whose body is being checked, if any.
+/// - `errors` - The type errors found so far.
+/// - `types` - The type of the variable or parameter named at each position so far.
+/// - `assignment` - The assignment being checked, if it assigns to a single name.
+/// - `first_assignments` - The assignment which first assigns to each variable, if it
+/// assigns to that variable alone.
+/// - `assigned` - The types of the values assigned to each variable.
+///
+struct Checker<'a> {
+    index: &'a SymbolIndex,
+    occurrences: HashMap<(u64, u32), SymbolId>,
+    flow: Flow,
+    function: Option<SymbolId>,
+    errors: Vec<(Span, String)>,
+    types: HashMap<(u64, u32), Type>,
+    assignment: Option<*const AstNode>,
+    first_assignments: HashMap<SymbolId, Option<*const AstNode>>,
+    assigned: HashMap<SymbolId, Vec<Type>>
+}
+
+
+impl<'a> Checker<'a> {
+    fn error(&mut self, span: Span, message: String) {
+        self.errors.push((span, message));
+    }
+
+
+    ///
+    /// Report an error at `node` unless a value of type `actual` may be used as `what`,
+    /// which is of type `expected`.
+    ///
+    fn expect(&mut self, expected: &Type, actual: &Type, node: &SyntaxNode, what: impl FnOnce() -> String) {
+        if !accepts(expected, actual) {
+            let message = format!("Expected '{}' for {}, found '{}'", expected, what(), actual);
+            self.error(span_of(node), message);
+        }
+    }
+
+
+    fn symbol_at(&self, token: &PieToken) -> Option<SymbolId> {
+        self.occurrences.get(&(token.line, token.column)).copied()
+    }
+
+
+    fn statement(&mut self, node: &Rc<SyntaxNode>) {
+        match node.kind {
+            SyntaxKind::FunctionDefinition => self.function(node),
+
+            SyntaxKind::StructDefinition => {
+                for child in node.child_nodes() {
+                    match child.kind {
+                        SyntaxKind::Field => self.field(child),
+                        _ => self.statement(child)
+                    }
+                }
+            }
+
+            SyntaxKind::InterfaceDefinition | SyntaxKind::EnumDefinition | SyntaxKind::UseStatement => {}
+
+            SyntaxKind::IfStatement => {
+                let mut children = node.child_nodes();
+                let Some(condition) = children.next() else { return };
+                self.type_of(condition);
+
+                let (then, otherwise) = self.narrow(condition, &self.flow.clone());
+                self.flow = then;
+                for statement in children {
+                    self.statement(statement);
+                }
+
+                // The body may not run, in which case the condition was false.
+                let after = std::mem::take(&mut self.flow);
+                self.flow = self.join(otherwise, after);
+            }
+
+            SyntaxKind::WhileLoop => self.loop_(node),
+
+            SyntaxKind::MatchStatement => self.match_(node),
+
+            SyntaxKind::ReturnStatement => {
+                let Some(value) = node.child_nodes().next() else { return };
+                let actual = self.type_of(value);
+
+                if let Some(function) = self.function
+                    && let Some(signature) = self.signature(function)
+                    && !accepts(&signature.returns, &actual) {
+                    let message = format!(
+                        "Expected '{}' to return '{}', found '{}'",
+                        signature.name,
+                        signature.returns,
+                        actual
+                    );
+                    self.error(span_of(value), message);
+                }
+
+                self.flow.reachable = false;
+            }
+
+            SyntaxKind::Assignment => self.assignment(node),
+
+            SyntaxKind::Declaration => {
+                let Some(name) = node.children.iter().find_map(|element| match element {
+                    SyntaxElement::Token(token) if token.type_ == TokenType::Identifier => Some(token.clone()),
+                    _ => None
+                }) else { return };
+
+                // The last node is the value, and any other the annotation.
+                let Some(value) = node.child_nodes().last() else { return };
+                let type_ = self.type_of(value);
+
+                if let Some(AstNode::Declaration(declaration)) = node.ast.as_deref()
+                    && declaration.annotation.is_none()
+                    && !declaration.constant {
+                    self.assignment = node.ast.as_ref().map(Rc::as_ptr);
+                }
+
+                self.assign_name(&name, &type_, value);
+                self.assignment = None;
+            }
+
+            _ => {
+                self.type_of(node);
+            }
+        }
+    }
+
+
+    ///
+    /// Check the default values of a function's parameters, then its body, within which
+    /// only its own variables are known.
+    ///
+    fn function(&mut self, node: &Rc<SyntaxNode>) {
+        let Some(symbol) = self.definition(node) else { return };
+        let Some(AstNode::FunctionDefinition(definition)) = node.ast.as_deref() else { return };
+
+        let parameters = node.child_nodes().filter(|child| child.kind == SyntaxKind::Parameter);
+        for (parameter_node, parameter) in parameters.zip(&definition.param_list) {
+            if parameter.default.is_none() {
+                continue;
+            }
+
+            if let Some(default) = parameter_node.child_nodes().last() {
+                let expected = self.parameter_type(symbol, parameter);
+                let actual = self.type_of(default);
+                self.expect(&expected, &actual, default, || format!("parameter '{}'", parameter.name));
+            }
+        }
+
+        let flow = std::mem::take(&mut self.flow);
+        let function = self.function.replace(symbol);
+
+        // The first node other than a parameter is the return type.
+        let body = node.child_nodes().filter(|child| !matches!(child.kind, SyntaxKind::Parameter | SyntaxKind::TypeParameter));
+        for statement in body.skip(1) {
+            self.statement(statement);
+        }
+
+        self.flow = flow;
+        self.function = function;
+    }
+
+
+    fn field(&mut self, node: &Rc<SyntaxNode>) {
+        let Some(name) = node.first_token() else { return };
+        let Some(symbol) = self.symbol_at(&name) else { return };
+
+        // The first node is the annotation, and the second the default value.
+        if let Some(default) = node.child_nodes().nth(1) {
+            let expected = self.declared(symbol);
+            let actual = self.type_of(default);
+            let owner = self.owner_name(symbol);
+            self.expect(&expected, &actual, default, || format!("field '{}' of '{}'", name.lexeme, owner));
+        }
+    }
+
+
+    fn assignment(&mut self, node: &Rc<SyntaxNode>) {
+        let Some(AstNode::BinaryOperation(operation)) = node.ast.as_deref() else { return };
+        let operands: Vec<&Rc<SyntaxNode>> = node.child_nodes().collect();
+        let Some((value, targets)) = operands.split_last() else { return };
+
+        if matches!(operation.operator, BinaryOperator::Equal) {
+            let type_ = self.type_of(value);
+            if let [target] = targets && target.kind == SyntaxKind::Name {
+                self.assignment = node.ast.as_ref().map(Rc::as_ptr);
+            }
+
+            for target in targets {
+                self.assign(target, &type_, value);
+            }
+
+            self.assignment = None;
+            return;
+        }
+
+        // An augmented assignment, e.g. `total += x`.
+        let Some(target) = targets.first() else { return };
+        let current = self.type_of(target);
+        let operand = self.type_of(value);
+        let result = self.operation(node, &operation.operator, &current, &operand);
+
+        if target.kind == SyntaxKind::Name && let Some(token) = target.first_token() {
+            self.assign_name(&token, &result, value);
+        }
+    }
+
+
+    ///
+    /// Assign a value of type `type_`, which is the value of `source`, to `target`.
+    ///
+    fn assign(&mut self, target: &Rc<SyntaxNode>, type_: &Type, source: &SyntaxNode) {
+        match target.kind {
+            SyntaxKind::Name => {
+                if let Some(token) = target.first_token() {
+                    self.assign_name(&token, type_, source);
+                }
+            }
+
+            SyntaxKind::Parenthesized => {
+                if let Some(inner) = target.child_nodes().next() {
+                    self.assign(inner, type_, source);
+                }
+            }
+
+            SyntaxKind::TupleLiteral | SyntaxKind::ListLiteral => {
+                let elements: Vec<&Rc<SyntaxNode>> = target.child_nodes().collect();
+                let unpacked = elements.iter().any(|element| element.kind == SyntaxKind::Unpack);
+
+                for (i, element) in elements.iter().enumerate() {
+                    let element_type = match type_ {
+                        Type::Tuple(types) if !unpacked && types.len() == elements.len() => types[i].clone(),
+                        _ if element.kind == SyntaxKind::Unpack => Type::Unknown,
+                        _ => type_.element()
+                    };
+                    self.assign(element, &element_type, source);
+                }
+            }
+
+            SyntaxKind::Unpack => {
+                if let Some(inner) = target.child_nodes().next() {
+                    self.assign(inner, &Type::Unknown, source);
+                }
+            }
+
+            SyntaxKind::MemberAccess => {
+                let expected = self.type_of(target);
+                let member = target.last_token().map(|token| token.lexeme.clone()).unwrap_or_default();
+                self.expect(&expected, type_, source, || format!("attribute '{}'", member));
+            }
+
+            SyntaxKind::Subscript => {
+                let (element, container) = self.subscript(target);
+                self.expect(&element, type_, source, || format!("an element of '{}'", container));
+            }
+
+            _ => {
+                self.type_of(target);
+            }
+        }
+    }
+
+
+    fn assign_name(&mut self, token: &PieToken, type_: &Type, source: &SyntaxNode) {
+        let Some(symbol) = self.symbol_at(token) else { return };
+
+        let kind = self.index.symbols[symbol].kind;
+        let declared = self.declared(symbol);
+
+        match kind {
+            SymbolKind::Variable if declared == Type::Unknown => {
+                self.first_assignments.entry(symbol).or_insert(self.assignment);
+                self.assigned.entry(symbol).or_default().push(type_.clone());
+                self.flow.locals.insert(symbol, type_.clone());
+            }
+
+            // An annotated variable or parameter may only be assigned a value of its declared type.
+            SymbolKind::Variable | SymbolKind::Constant | SymbolKind::Parameter => {
+                if accepts(&declared, type_) {
+                    self.flow.locals.insert(symbol, type_.clone());
+                } else {
+                    let what = match kind {
+                        SymbolKind::Parameter => "parameter",
+                        SymbolKind::Constant => "constant",
+                        _ => "variable"
+                    };
+                    self.expect(&declared, type_, source, || format!("{} '{}'", what, token.lexeme));
+                    self.flow.locals.remove(&symbol);
+                }
+            }
+
+            _ => return
+        }
+
+        self.record(token, symbol);
+    }
+
+
+    ///
+    /// The type of the expression `node`, reporting any type errors within it.
+    ///
+    fn type_of(&mut self, node: &Rc<SyntaxNode>) -> Type {
+        match node.kind {
+            SyntaxKind::Literal => match node.ast.as_deref() {
+                Some(AstNode::IntegerLiteral(_)) => Type::Int,
+                Some(AstNode::FloatLiteral(_)) => Type::Float,
+                Some(AstNode::BooleanLiteral(_)) => Type::Bool,
+                Some(AstNode::StringLiteral(_)) => Type::Str,
+                _ => Type::Unknown
+            },
+
+            SyntaxKind::Name => {
+                let Some(token) = node.first_token() else { return Type::Unknown };
+                match self.symbol_at(&token) {
+                    Some(symbol) => {
+                        self.record(&token, symbol);
+                        self.symbol_type(symbol)
+                    }
+                    None if token.lexeme == "None" => Type::None,
+                    None if matches!(token.lexeme.as_str(), "True" | "False") => Type::Bool,
+                    None if BUILTINS.contains(&token.lexeme.as_str()) => Type::Builtin(token.lexeme.clone()),
+                    None => Type::Unknown
+                }
+            }
+
+            SyntaxKind::Parenthesized => match node.child_nodes().next() {
+                Some(inner) => self.type_of(inner),
+                None => Type::Unknown
+            },
+
+            SyntaxKind::UnaryOperation => {
+                let Some(AstNode::UnaryOperation(operation)) = node.ast.as_deref() else { return Type::Unknown };
+                let Some(operand) = node.child_nodes().next() else { return Type::Unknown };
+                let operand = self.type_of(operand);
+
+                match operation.operator {
+                    UnaryOperator::LogicalNot => Type::Bool,
+                    UnaryOperator::Minus => match negate(&operand) {
+                        Some(result) => result,
+                        None => {
+                            let message = format!("Unsupported operand type for -: '{}'", operand);
+                            self.error(span_of(node), message);
+                            Type::Unknown
+                        }
+                    }
+                }
+            }
+
+            SyntaxKind::BinaryOperation => {
+                let Some(AstNode::BinaryOperation(operation)) = node.ast.as_deref() else { return Type::Unknown };
+                let operands: Vec<&Rc<SyntaxNode>> = node.child_nodes().collect();
+                let [left, right] = operands[..] else { return Type::Unknown };
+
+                let left = self.type_of(left);
+                let right = self.type_of(right);
+                self.operation(node, &operation.operator, &left, &right)
+            }
+
+            SyntaxKind::Assignment => {
+                self.assignment(node);
+                Type::Unknown
+            }
+
+            SyntaxKind::FunctionCall => self.call(node),
+
+            SyntaxKind::MemberAccess => {
+                let Some(parent) = node.child_nodes().next() else { return Type::Unknown };
+                let parent = self.type_of(parent);
+                match node.last_token() {
+                    Some(member) => self.member(&parent, &member),
+                    None => Type::Unknown
+                }
+            }
+
+            SyntaxKind::ScopeAccess => {
+                let Some(parent) = node.child_nodes().next() else { return Type::Unknown };
+                let parent = self.type_of(parent);
+                match node.last_token() {
+                    Some(member) => self.variant(&parent, &member),
+                    None => Type::Unknown
+                }
+            }
+
+            SyntaxKind::Subscript => self.subscript(node).0,
+
+            SyntaxKind::ListLiteral => Type::List(Box::new(self.elements(node))),
+            SyntaxKind::SetLiteral => Type::Set(Box::new(self.elements(node))),
+
+            SyntaxKind::TupleLiteral => {
+                let elements: Vec<Type> = node
+                    .child_nodes()
+                    .map(|element| self.type_of(element))
+                    .collect();
+
+                match node.child_nodes().any(|element| element.kind == SyntaxKind::Unpack) {
+                    true => Type::Unknown,
+                    false => Type::Tuple(elements)
+                }
+            }
+
+            SyntaxKind::DictLiteral => self.dictionary(node),
+
+            // The elements of an unpacked iterable, i.e. `*xs`, are handled by whatever contains it.
+            SyntaxKind::Unpack => match node.child_nodes().next() {
+                Some(inner) => self.type_of(inner),
+                None => Type::Unknown
+            },
+
+            _ => {
+                for child in node.child_nodes() {
+                    self.type_of(child);
+                }
+                Type::Unknown
+            }
+        }
+    }
+
+
+    ///
+    /// The type of `left <operator> right`, reporting an error at the operator of `node`
+    /// if the operation is not supported.
+    ///
+    fn operation(&mut self, node: &SyntaxNode, operator: &BinaryOperator, left: &Type, right: &Type) -> Type {
+        if let Some(result) = binary(operator, left, right) {
+            return result;
+        }
+
+        let token = node.children.iter().find_map(|element| match element {
+            SyntaxElement::Token(token) if BinaryOperator::from(token).is_some() => Some(token.clone()),
+            _ => None
+        });
+
+        let (span, symbol) = match &token {
+            Some(token) => (Span::of(token), token.lexeme.trim_end_matches('=').to_string()),
+            None => (span_of(node), String::new())
+        };
+
+        self.error(span, format!("Unsupported operand types for {}: '{}' and '{}'", symbol, left, right));
+        Type::Unknown
+    }
+
+
+    ///
+    /// The type of the variable or parameter `symbol` at the point described by `flow`.
+    ///
+    fn local_type(&self, flow: &Flow, symbol: SymbolId) -> Type {
+        match flow.locals.get(&symbol) {
+            Some(type_) => type_.clone(),
+            None => self.declared(symbol)
+        }
+    }
+
+
+    ///
+    /// Record the type of the variable or parameter `symbol` where `token` names it.
+    ///
+    fn record(&mut self, token: &PieToken, symbol: SymbolId) {
+        let type_ = self.local_type(&self.flow, symbol);
+        let key = (token.line, token.column);
+
+        let narrowed = match self.index.symbols[symbol].kind {
+            SymbolKind::Variable => type_ != Type::Unknown,
+            SymbolKind::Parameter | SymbolKind::Constant => {
+                self.flow.locals.contains_key(&symbol) && type_ != self.declared(symbol)
+            }
+            _ => false
+        };
+
+        match narrowed {
+            true => self.types.insert(key, type_),
+            false => self.types.remove(&key)
+        };
+    }
+
+
+    ///
+    /// What is known after either `first` or `second`. A variable which has only been
+    /// assigned to along one of them keeps its type from that one.
+    ///
+    fn join(&self, first: Flow, second: Flow) -> Flow {
+        if !first.reachable {
+            return second;
+        }
+        if !second.reachable {
+            return first;
+        }
+
+        let mut locals = first.locals.clone();
+        for (symbol, type_) in &second.locals {
+            let joined = match first.locals.get(symbol) {
+                Some(other) => Type::union(vec![other.clone(), type_.clone()]),
+                None => self.widen(*symbol, type_)
+            };
+            locals.insert(*symbol, joined);
+        }
+
+        for (symbol, type_) in &first.locals {
+            if !second.locals.contains_key(symbol) {
+                locals.insert(*symbol, self.widen(*symbol, type_));
+            }
+        }
+
+        Flow { locals: locals, reachable: true }
+    }
+
+
+    ///
+    /// The type of `symbol` after joining a path along which it is of type `type_` with
+    /// one along which it is untouched. A parameter, or a declared variable, is then of its
+    /// declared type, whereas any other variable is unassigned, and so cannot be used at all.
+    ///
+    fn widen(&self, symbol: SymbolId, type_: &Type) -> Type {
+        let declared = self.declared(symbol);
+        match self.index.symbols[symbol].kind {
+            SymbolKind::Variable if declared == Type::Unknown => type_.clone(),
+            _ => Type::union(vec![type_.clone(), declared])
+        }
+    }
+
+
+    ///
+    /// Check a `while` loop. Its body is checked over and over again until the types at
+    /// the start of the loop settle, then once more to report type errors.
+    ///
+    fn loop_(&mut self, node: &Rc<SyntaxNode>) {
+        const LIMIT: usize = 8;
+
+        let Some(condition) = node.child_nodes().next() else { return };
+        let entry = self.flow.clone();
+        let errors = self.errors.len();
+
+        let mut head = entry.clone();
+        let mut settled = false;
+        for _ in 0..LIMIT {
+            (self.flow, _) = self.narrow(condition, &head);
+            for statement in node.child_nodes().skip(1) {
+                self.statement(statement);
+            }
+
+            let body = std::mem::take(&mut self.flow);
+            let next = self.join(head.clone(), body);
+            if next == head {
+                settled = true;
+                break;
+            }
+            head = next;
+        }
+
+        // Give up on the variables whose types keep growing.
+        if !settled {
+            for (symbol, type_) in head.locals.iter_mut() {
+                if entry.locals.get(symbol) != Some(type_) {
+                    *type_ = Type::Unknown;
+                }
+            }
+        }
+
+        self.errors.truncate(errors);
+        self.flow = head;
+        self.type_of(condition);
+
+        let (body, otherwise) = self.narrow(condition, &self.flow.clone());
+        self.flow = body;
+        for statement in node.child_nodes().skip(1) {
+            self.statement(statement);
+        }
+
+        self.flow = otherwise;
+    }
+
+
+    ///
+    /// Check a `match` statement. Each case starts from what is known before the statement,
+    /// with the names bound by its pattern assigned, and the subject narrowed to what the
+    /// pattern matches if it is a name. Unless a case matches anything, none may be run.
+    ///
+    fn match_(&mut self, node: &Rc<SyntaxNode>) {
+        let mut children = node.child_nodes();
+        let Some(subject) = children.next() else { return };
+        let subject_type = self.type_of(subject);
+
+        let entry = self.flow.clone();
+        let mut exit: Option<Flow> = None;
+        let mut exhaustive = false;
+
+        for case in children {
+            self.flow = entry.clone();
+            let mut parts = case.child_nodes();
+            let Some(pattern) = parts.next() else { continue };
+
+            let matched = self.pattern(pattern, &subject_type);
+            if let (Some(symbol), Some(matched)) = (self.narrowable(subject), matched) {
+                self.flow = self.narrowed(&self.flow, symbol, matched);
+            }
+
+            let guarded = case.children.iter().any(|element| {
+                matches!(element, SyntaxElement::Token(token) if token.type_ == TokenType::If)
+            });
+
+            if guarded && let Some(guard) = parts.next() {
+                self.type_of(guard);
+                (self.flow, _) = self.narrow(guard, &self.flow.clone());
+            }
+
+            for statement in parts {
+                self.statement(statement);
+            }
+
+            let after = std::mem::take(&mut self.flow);
+            exit = Some(match exit {
+                Some(exit) => self.join(exit, after),
+                None => after
+            });
+            exhaustive |= !guarded && matches!(pattern.kind, SyntaxKind::WildcardPattern | SyntaxKind::CapturePattern);
+        }
+
+        self.flow = match exit {
+            Some(exit) if exhaustive => exit,
+            Some(exit) => self.join(entry, exit),
+            None => entry
+        };
+    }
+
+
+    ///
+    /// Check the pattern `node` against a subject of type `subject`, assigning the names it
+    /// binds, and reporting a pattern that no such subject could ever match.
+    ///
+    /// # Returns
+    /// - The type of the values the pattern matches, if it is narrower than `subject`.
+    ///
+    fn pattern(&mut self, node: &Rc<SyntaxNode>, subject: &Type) -> Option<Type> {
+        let matched = match node.kind {
+            SyntaxKind::CapturePattern => {
+                if let Some(name) = node.first_token() {
+                    self.assign_name(&name, subject, node);
+                }
+                return None;
+            }
+
+            SyntaxKind::LiteralPattern | SyntaxKind::ValuePattern => {
+                let value = node.child_nodes().next()?;
+                match self.type_of(value) {
+                    // A variant with a payload, without any patterns for its fields.
+                    Type::Class { symbol, .. } => self.instance(symbol),
+                    type_ => type_
+                }
+            }
+
+            SyntaxKind::ClassPattern => self.class_pattern(node)?,
+
+            SyntaxKind::SequencePattern => {
+                let element = subject.element();
+                for child in node.child_nodes() {
+                    if child.kind != SyntaxKind::StarPattern {
+                        self.pattern(child, &element);
+                    } else if let Some(name) = child.last_token().filter(|name| name.lexeme != "_") {
+                        self.assign_name(&name, &Type::List(Box::new(element.clone())), child);
+                    }
+                }
+                return None;
+            }
+
+            _ => return None
+        };
+
+        if matched == Type::Unknown {
+            return None;
+        }
+
+        if !accepts(subject, &matched) && !accepts(&matched, subject) {
+            let message = format!("A pattern of type '{}' never matches a value of type '{}'", matched, subject);
+            self.error(span_of(node), message);
+        }
+        Some(matched)
+    }
+
+
+    ///
+    /// Check the class pattern `node`, e.g. `Point(0, y=height)`, whose positional patterns
+    /// are matched against the fields of the struct or variant in the order they are declared.
+    ///
+    /// # Returns
+    /// - The type of the instances of the class, if it is known.
+    ///
+    fn class_pattern(&mut self, node: &Rc<SyntaxNode>) -> Option<Type> {
+        let mut parts = node.child_nodes();
+        let class = parts.next()?;
+
+        let (instance, fields) = match self.type_of(class) {
+            Type::Class { symbol, .. } => {
+                let definition = &self.index.symbols[symbol];
+                let names: Vec<String> = match definition.node.ast.as_deref() {
+                    Some(AstNode::StructDefinition(structure)) => structure.fields.iter().map(|field| field.name.clone()).collect(),
+                    _ => self.variant_fields(symbol).unwrap_or_default().iter().map(|field| field.name.clone()).collect()
+                };
+
+                let fields: Vec<Type> = names
+                    .iter()
+                    .map(|name| match definition.body.and_then(|body| self.index.scopes[body].names.get(name)) {
+                        Some(&field) => self.declared(field),
+                        None => Type::Unknown
+                    })
+                    .collect();
+
+                (self.instance(symbol), Some(fields))
+            }
+
+            // E.g. `int(n)`, which matches an `int` and binds it to `n`.
+            Type::Builtin(name) if is_builtin_type(&name) => {
+                let instance = builtin_type(&name, &[]);
+                (instance.clone(), Some(vec![instance]))
+            }
+
+            _ => (Type::Unknown, None)
+        };
+
+        let (positional, keywords): (Vec<&Rc<SyntaxNode>>, Vec<&Rc<SyntaxNode>>) =
+            parts.partition(|part| part.kind != SyntaxKind::KeywordPattern);
+
+        if let Some(fields) = &fields && positional.len() > fields.len() {
+            let message = format!("'{}' accepts {} positional patterns, found {}", instance, fields.len(), positional.len());
+            self.error(span_of(node), message);
+        }
+
+        for (i, pattern) in positional.into_iter().enumerate() {
+            let field = fields.as_ref().and_then(|fields| fields.get(i)).cloned().unwrap_or(Type::Unknown);
+            self.pattern(pattern, &field);
+        }
+
+        for keyword in keywords {
+            let Some(name) = keyword.first_token() else { continue };
+            let field = self.member(&instance, &name);
+            if let Some(pattern) = keyword.child_nodes().next() {
+                self.pattern(pattern, &field);
+            }
+        }
+
+        Some(instance)
+    }
+
+
+    ///
+    /// What is known when `condition` is true, and when it is false, starting from `flow`.
+    /// Comparisons with `None`, `isinstance` calls and the truthiness of a name narrow the
+    /// type of the variable or parameter they are about.
+    ///
+    fn narrow(&self, condition: &SyntaxNode, flow: &Flow) -> (Flow, Flow) {
+        let unchanged = (flow.clone(), flow.clone());
+        let operands: Vec<&Rc<SyntaxNode>> = condition.child_nodes().collect();
+
+        match (condition.kind, condition.ast.as_deref()) {
+            (SyntaxKind::Parenthesized, _) => match operands.first() {
+                Some(inner) => self.narrow(inner, flow),
+                None => unchanged
+            },
+
+            (SyntaxKind::UnaryOperation, Some(AstNode::UnaryOperation(operation))) => {
+                match (&operation.operator, operands.first()) {
+                    (UnaryOperator::LogicalNot, Some(operand)) => {
+                        let (then, otherwise) = self.narrow(operand, flow);
+                        (otherwise, then)
+                    }
+                    _ => unchanged
+                }
+            }
+
+            (SyntaxKind::BinaryOperation, Some(AstNode::BinaryOperation(operation))) => {
+                let [left, right] = operands[..] else { return unchanged };
+
+                match operation.operator {
+                    BinaryOperator::And => {
+                        let (left_then, left_otherwise) = self.narrow(left, flow);
+                        let (then, right_otherwise) = self.narrow(right, &left_then);
+                        (then, self.join(left_otherwise, right_otherwise))
+                    }
+
+                    BinaryOperator::Or => {
+                        let (left_then, left_otherwise) = self.narrow(left, flow);
+                        let (right_then, otherwise) = self.narrow(right, &left_otherwise);
+                        (self.join(left_then, right_then), otherwise)
+                    }
+
+                    BinaryOperator::EqualEqual | BinaryOperator::BangEqual => {
+                        let subject = match (self.narrowable(left), self.narrowable(right)) {
+                            (Some(symbol), _) if is_none(right) => symbol,
+                            (_, Some(symbol)) if is_none(left) => symbol,
+                            _ => return unchanged
+                        };
+
+                        let type_ = self.local_type(flow, subject);
+                        let equal = self.narrowed(flow, subject, Type::None);
+                        let unequal = match type_.filter(|member| *member != Type::None) {
+                            Some(rest) => self.narrowed(flow, subject, rest),
+                            None => flow.clone()
+                        };
+
+                        match operation.operator {
+                            BinaryOperator::EqualEqual => (equal, unequal),
+                            _ => (unequal, equal)
+                        }
+                    }
+
+                    _ => unchanged
+                }
+            }
+
+            (SyntaxKind::Name, _) => {
+                let Some(subject) = self.narrowable(condition) else { return unchanged };
+                match self.local_type(flow, subject).filter(|member| *member != Type::None) {
+                    Some(rest) => (self.narrowed(flow, subject, rest), flow.clone()),
+                    None => unchanged
+                }
+            }
+
+            (SyntaxKind::FunctionCall, _) => {
+                let Some(callee) = operands.first() else { return unchanged };
+                if callee.kind != SyntaxKind::Name || callee.first_token().is_none_or(|token| token.lexeme != "isinstance") {
+                    return unchanged;
+                }
+
+                let [_, subject, class] = operands[..] else { return unchanged };
+                let (Some(subject), Some(class)) = (self.narrowable(subject), self.class_type(class)) else {
+                    return unchanged
+                };
+
+                let type_ = self.local_type(flow, subject);
+                let then = match type_ {
+                    Type::Unknown => class.clone(),
+                    _ => type_.filter(|member| accepts(&class, member)).unwrap_or(class.clone())
+                };
+
+                let otherwise = match type_.filter(|member| !accepts(&class, member)) {
+                    Some(rest) => self.narrowed(flow, subject, rest),
+                    None => flow.clone()
+                };
+
+                (self.narrowed(flow, subject, then), otherwise)
+            }
+
+            _ => unchanged
+        }
+    }
+
+
+    ///
+    /// The variable or parameter which `node` names, if it is a name of one.
+    ///
+    fn narrowable(&self, node: &SyntaxNode) -> Option<SymbolId> {
+        if node.kind != SyntaxKind::Name {
+            return None;
+        }
+
+        let symbol = self.symbol_at(&*node.first_token()?)?;
+        match self.index.symbols[symbol].kind {
+            SymbolKind::Variable | SymbolKind::Constant | SymbolKind::Parameter => Some(symbol),
+            _ => None
+        }
+    }
+
+
+    fn narrowed(&self, flow: &Flow, symbol: SymbolId, type_: Type) -> Flow {
+        let mut flow = flow.clone();
+        flow.locals.insert(symbol, type_);
+        flow
+    }
+
+
+    ///
+    /// The type of the instances of the class, or tuple of classes, `node`, as given to
+    /// `isinstance`.
+    ///
+    fn class_type(&self, node: &SyntaxNode) -> Option<Type> {
+        match node.kind {
+            SyntaxKind::Name => {
+                let token = node.first_token()?;
+                match self.symbol_at(&token) {
+                    Some(symbol) => match self.index.symbols[symbol].kind {
+                        SymbolKind::Struct | SymbolKind::Enum => Some(self.instance(symbol)),
+                        _ => None
+                    },
+                    None if is_builtin_type(&token.lexeme) => Some(builtin_type(&token.lexeme, &[])).filter(|type_| *type_ != Type::Unknown),
+                    None => None
+                }
+            }
+
+            SyntaxKind::Parenthesized => self.class_type(node.child_nodes().next()?),
+
+            SyntaxKind::TupleLiteral => {
+                let classes: Option<Vec<Type>> = node.child_nodes().map(|class| self.class_type(class)).collect();
+                Some(Type::union(classes?))
+            }
+
+            _ => None
+        }
+    }
+
+
+    ///
+    /// The type of the value of the symbol `symbol` where it is used.
+    ///
+    fn symbol_type(&self, symbol: SymbolId) -> Type {
+        let definition = &self.index.symbols[symbol];
+
+        match definition.kind {
+            SymbolKind::Variable | SymbolKind::Constant | SymbolKind::Parameter => self.local_type(&self.flow, symbol),
+            SymbolKind::Field => self.declared(symbol),
+            SymbolKind::Function | SymbolKind::Method => Type::Function { symbol: symbol, bound: false, arguments: vec![] },
+            SymbolKind::Struct | SymbolKind::Enum => Type::Class { name: definition.name.clone(), symbol: symbol },
+
+            // A variant with a payload is constructed like a struct.
+            SymbolKind::Variant if definition.body.is_some() => Type::Class { name: definition.name.clone(), symbol: symbol },
+
+            SymbolKind::Variant => match self.index.owner_of(definition.scope) {
+                Some(owner) => self.instance(owner),
+                None => Type::Unknown
+            },
+
+            SymbolKind::Interface | SymbolKind::TypeParameter | SymbolKind::Import => Type::Unknown
+        }
+    }
+
+
+    ///
+    /// The declared type of a parameter, field, or variable or constant declared with
+    /// `let` or `const`, or `Unknown` if it is not annotated.
+    ///
+    fn declared(&self, symbol: SymbolId) -> Type {
+        let definition = &self.index.symbols[symbol];
+        if let Some(AstNode::Declaration(declaration)) = definition.node.ast.as_deref() {
+            return match &declaration.annotation {
+                Some(annotation) => self.annotation(annotation, definition.scope),
+                None => Type::Unknown
+            };
+        }
+
+        let Some(owner) = self.index.owner_of(definition.scope) else { return Type::Unknown };
+
+        match (definition.kind, self.index.symbols[owner].node.ast.as_deref()) {
+            (SymbolKind::Parameter, Some(AstNode::FunctionDefinition(function))) => function.param_list
+                .iter()
+                .find(|parameter| parameter.name == definition.name)
+                .map(|parameter| self.parameter_type(owner, parameter))
+                .unwrap_or(Type::Unknown),
+
+            (SymbolKind::Field, Some(AstNode::StructDefinition(structure))) => structure.fields
+                .iter()
+                .find(|field| field.name == definition.name)
+                .map(|field| self.annotation(&field.annotation, definition.scope))
+                .unwrap_or(Type::Unknown),
+
+            (SymbolKind::Field, None) => self.variant_fields(owner)
+                .and_then(|fields| fields.iter().find(|field| field.name == definition.name))
+                .map(|field| self.annotation(&field.annotation, definition.scope))
+                .unwrap_or(Type::Unknown),
+
+            _ => Type::Unknown
+        }
+    }
+
+
+    ///
+    /// The declared type of `parameter` of the function `function`. The first parameter of
+    /// a method is an instance of its struct unless it is annotated otherwise.
+    ///
+    fn parameter_type(&self, function: SymbolId, parameter: &Parameter) -> Type {
+        let function = &self.index.symbols[function];
+
+        if let Some(annotation) = &parameter.annotation {
+            let type_ = self.annotation(annotation, self.annotation_scope(function));
+            return match parameter.kind {
+                ParameterKind::VarKeyword => Type::Dict(Box::new(Type::Str), Box::new(type_)),
+                ParameterKind::VarPositional => Type::Unknown,
+                _ => type_
+            };
+        }
+
+        let body = function.body.map(|body| &self.index.scopes[body]);
+        let instance_of = body
+            .and_then(|body| body.names.get(&parameter.name))
+            .and_then(|&symbol| self.index.symbols[symbol].instance_of);
+
+        match instance_of {
+            Some(owner) => self.instance(owner),
+            None => Type::Unknown
+        }
+    }
+
+
+    ///
+    /// The type described by the annotation `node`, which is within `scope`. Annotations
+    /// that are not understood are `Unknown`, so whatever they describe is not checked.
+    ///
+    fn annotation(&self, node: &AstNode, scope: ScopeId) -> Type {
+        match node {
+            AstNode::Identifier(name) => self.named_type(name, &[], scope),
+
+            // A forward reference, e.g. `"Node"`.
+            AstNode::StringLiteral(name) => self.named_type(name, &[], scope),
+
+            // E.g. `typing.Optional`, which is not looked up within the module.
+            AstNode::MemberAccess(access) => match access.child.as_ref() {
+                AstNode::Identifier(name) => builtin_type(name, &[]),
+                _ => Type::Unknown
+            },
+
+            AstNode::Subscript(subscript) => {
+                let arguments: Vec<Type> = match subscript.index.as_ref() {
+                    AstNode::TupleLiteral(elements) => elements
+                        .iter()
+                        .map(|element| self.annotation(element, scope))
+                        .collect(),
+                    index => vec![self.annotation(index, scope)]
+                };
+
+                match subscript.value.as_ref() {
+                    AstNode::Identifier(name) => self.named_type(name, &arguments, scope),
+                    AstNode::MemberAccess(access) => match access.child.as_ref() {
+                        AstNode::Identifier(name) => builtin_type(name, &arguments),
+                        _ => Type::Unknown
+                    },
+                    _ => Type::Unknown
+                }
+            }
+
+            _ => Type::Unknown
+        }
+    }
+
+
+    ///
+    /// The type named `name` within `scope`, given the type arguments `arguments`. Names
+    /// imported from elsewhere, e.g. from `typing`, are known by the name of the item.
+    ///
+    fn named_type(&self, name: &str, arguments: &[Type], scope: ScopeId) -> Type {
+        // As in Python, the names within `typing`, e.g. `Optional`, must be imported.
+        let Some(symbol) = self.index.lookup(scope, name) else {
+            if TYPING_TYPES.contains(&name) {
+                return Type::Unknown;
+            }
+            return builtin_type(name, arguments);
+        };
+
+        let definition = &self.index.symbols[symbol];
+        match definition.kind {
+            SymbolKind::Struct => Type::Struct {
+                name: definition.name.clone(),
+                symbol: symbol,
+                arguments: arguments.to_vec()
+            },
+
+            SymbolKind::Interface | SymbolKind::Enum => self.instance(symbol),
+            SymbolKind::TypeParameter => self.type_param(symbol),
+
+            SymbolKind::Import => match definition.import.as_ref().and_then(|import| import.item.as_ref()) {
+                Some(item) => builtin_type(item, arguments),
+                None => Type::Unknown
+            },
+
+            _ => Type::Unknown
+        }
+    }
+
+
+    ///
+    /// The type of an instance of the struct, interface or enum `symbol`, as seen from
+    /// within its definition, i.e. with its own type parameters as its type arguments.
+    ///
+    fn instance(&self, symbol: SymbolId) -> Type {
+        let definition = &self.index.symbols[symbol];
+        match definition.kind {
+            SymbolKind::Struct => Type::Struct {
+                name: definition.name.clone(),
+                symbol: symbol,
+                arguments: self.type_params(symbol)
+            },
+            SymbolKind::Enum => Type::Enum { name: definition.name.clone(), symbol: symbol },
+
+            SymbolKind::Variant => match self.index.owner_of(definition.scope) {
+                Some(owner) => Type::Variant {
+                    name: format!("{}::{}", self.index.symbols[owner].name, definition.name),
+                    symbol: symbol,
+                    owner: owner
+                },
+                None => Type::Unknown
+            },
+
+            SymbolKind::Interface => Type::Interface {
+                name: definition.name.clone(),
+                symbol: symbol,
+                implementors: (0..self.index.symbols.len())
+                    .filter(|&structure| self.index.symbols[structure].kind == SymbolKind::Struct)
+                    .filter(|&structure| self.index.interfaces(structure).contains(&symbol))
+                    .collect()
+            },
+
+            _ => Type::Unknown
+        }
+    }
+
+
+    ///
+    /// The fields of the variant with a payload `symbol`, as declared by its enum.
+    ///
+    fn variant_fields(&self, symbol: SymbolId) -> Option<&[Field]> {
+        let definition = &self.index.symbols[symbol];
+        let owner = self.index.owner_of(definition.scope)?;
+        let Some(AstNode::EnumDefinition(enumeration)) = self.index.symbols[owner].node.ast.as_deref() else { return None };
+
+        enumeration.variants
+            .iter()
+            .find(|variant| variant.name == definition.name)?
+            .fields
+            .as_deref()
+    }
+
+
+    ///
+    /// The type parameters of the generic function or struct `symbol`, in the order they
+    /// are declared.
+    ///
+    fn type_params(&self, symbol: SymbolId) -> Vec<Type> {
+        let Some(body) = self.index.symbols[symbol].body else { return vec![] };
+        let generic = self.index.scopes[body].parent.filter(|&parent| self.index.scopes[parent].kind == ScopeKind::TypeParameters);
+        let Some(generic) = generic else { return vec![] };
+
+        // Symbols are numbered in the order they are bound.
+        let mut params: Vec<SymbolId> = self.index.scopes[generic].names.values().copied().collect();
+        params.sort();
+        params.into_iter().map(|param| self.type_param(param)).collect()
+    }
+
+
+    ///
+    /// The type of the type parameter `symbol`, e.g. `N` in `def largest[N: float](...)`.
+    ///
+    fn type_param(&self, symbol: SymbolId) -> Type {
+        let definition = &self.index.symbols[symbol];
+
+        // The only node of a type parameter is its bound. Any one of its constraints, e.g.
+        // `T: (int, float)`, may be given for it, much like a bound of `int | float`.
+        let bound = match definition.node.child_nodes().next().and_then(|bound| bound.ast.as_deref()) {
+            Some(AstNode::TupleLiteral(constraints)) => Type::union(constraints
+                .iter()
+                .map(|constraint| self.annotation(constraint, definition.scope))
+                .collect()),
+            Some(bound) => self.annotation(bound, definition.scope),
+            None => Type::Unknown
+        };
+
+        Type::Parameter {
+            name: definition.name.clone(),
+            symbol: symbol,
+            bound: Box::new(bound)
+        }
+    }
+
+
+    ///
+    /// The scope that the annotations of the function `function` are within, which is that
+    /// of its type parameters if it is generic.
+    ///
+    fn annotation_scope(&self, function: &Symbol) -> ScopeId {
+        function.body
+            .and_then(|body| self.index.scopes[body].parent)
+            .unwrap_or(function.scope)
+    }
+
+
+    fn owner_name(&self, symbol: SymbolId) -> String {
+        self.index
+            .owner_of(self.index.symbols[symbol].scope)
+            .map(|owner| self.index.symbols[owner].name.clone())
+            .unwrap_or_default()
+    }
+
+
+    ///
+    /// The symbol bound by the definition `node`, i.e. that of its name.
+    ///
+    fn definition(&self, node: &SyntaxNode) -> Option<SymbolId> {
+        let name = node.children.iter().find_map(|element| match element {
+            SyntaxElement::Token(token) if token.type_ == TokenType::Identifier => Some(token.clone()),
+            _ => None
+        })?;
+        self.symbol_at(&name)
+    }
+
+
+    ///
+    /// The signature of the function, method or struct `symbol`.
+    ///
+    fn signature(&self, symbol: SymbolId) -> Option<Signature> {
+        let definition = &self.index.symbols[symbol];
+
+        if definition.kind == SymbolKind::Variant {
+            let owner = self.index.owner_of(definition.scope)?;
+            return Some(Signature {
+                name: format!("{}::{}", self.index.symbols[owner].name, definition.name),
+                params: self.variant_fields(symbol)?
+                    .iter()
+                    .map(|field| (
+                        field.name.clone(),
+                        ParameterKind::Normal,
+                        self.annotation(&field.annotation, definition.body.unwrap_or(definition.scope)),
+                        field.default.is_some()
+                    ))
+                    .collect(),
+                returns: self.instance(symbol)
+            });
+        }
+
+        match definition.node.ast.as_deref()? {
+            AstNode::FunctionDefinition(function) => Some(Signature {
+                name: definition.name.clone(),
+                params: function.param_list
+                    .iter()
+                    .map(|parameter| (
+                        parameter.name.clone(),
+                        parameter.kind.clone(),
+                        self.parameter_type(symbol, parameter),
+                        parameter.default.is_some()
+                    ))
+                    .collect(),
+                returns: self.annotation(&function.return_type, self.annotation_scope(definition))
+            }),
+
+            AstNode::StructDefinition(structure) => Some(Signature {
+                name: definition.name.clone(),
+                params: structure.fields
+                    .iter()
+                    .map(|field| (
+                        field.name.clone(),
+                        ParameterKind::Normal,
+                        self.annotation(&field.annotation, definition.body.unwrap_or(definition.scope)),
+                        field.default.is_some()
+                    ))
+                    .collect(),
+                returns: self.instance(symbol)
+            }),
+
+            _ => None
+        }
+    }
+
+
+    fn call(&mut self, node: &Rc<SyntaxNode>) -> Type {
+        let mut children = node.child_nodes();
+        let Some(callee_node) = children.next() else { return Type::Unknown };
+        let callee = self.type_of(callee_node);
+
+        let mut arguments = vec![];
+        for argument in children {
+            let (name, value) = match argument.kind {
+                SyntaxKind::KeywordArgument => (argument.first_token(), argument.child_nodes().nth(1)),
+                _ => (None, Some(argument))
+            };
+            let Some(value) = value else { continue };
+
+            arguments.push(Argument {
+                name: name,
+                type_: self.type_of(value),
+                node: value,
+                unpacked: argument.kind == SyntaxKind::Unpack
+            });
+        }
+
+        match callee {
+            Type::Function { symbol, bound, arguments: receiver } => match self.signature(symbol) {
+                Some(signature) => {
+                    let signature = self.instantiate(symbol, signature, bound, &receiver, &arguments, callee_node);
+                    self.check_arguments(&signature, bound, &arguments, callee_node);
+                    signature.returns
+                }
+                None => Type::Unknown
+            },
+
+            Type::Class { symbol, .. } => match self.signature(symbol) {
+                Some(signature) => {
+                    let signature = self.instantiate(symbol, signature, false, &[], &arguments, callee_node);
+                    self.check_arguments(&signature, false, &arguments, callee_node);
+                    signature.returns
+                }
+                None => self.instance(symbol)
+            },
+
+            Type::Builtin(name) => builtin_call(&name, &arguments),
+
+            Type::None | Type::Bool | Type::Int | Type::Float | Type::Str
+            | Type::List(_) | Type::Set(_) | Type::Dict(..) | Type::Tuple(_) => {
+                self.error(span_of(callee_node), format!("'{}' is not callable", callee));
+                Type::Unknown
+            }
+
+            _ => Type::Unknown
+        }
+    }
+
+
+    ///
+    /// The signature of a call to the function or struct `symbol`, with the types given for
+    /// its type parameters filled in. Those of the struct a method was accessed through are
+    /// its `receiver`'s type arguments, while the others are inferred from `arguments`, and
+    /// must be accepted by their bounds. Any that cannot be inferred are `Unknown`.
+    ///
+    fn instantiate(
+        &mut self,
+        symbol: SymbolId,
+        signature: Signature,
+        bound: bool,
+        receiver: &[Type],
+        arguments: &[Argument],
+        callee: &SyntaxNode
+    ) -> Signature {
+        let definition = &self.index.symbols[symbol];
+        let owner = match definition.kind {
+            SymbolKind::Method => self.index.owner_of(definition.scope),
+            _ => None
+        };
+
+        let mut bindings = HashMap::new();
+        let mut params = self.type_params(symbol);
+        if let Some(owner) = owner {
+            let owner_params = self.type_params(owner);
+            if owner_params.len() == receiver.len() {
+                for (param, argument) in owner_params.iter().zip(receiver) {
+                    if let Type::Parameter { symbol, .. } = param {
+                        bindings.insert(*symbol, argument.clone());
+                    }
+                }
+            } else {
+                params.extend(owner_params);
+            }
+        }
+
+        if params.is_empty() && bindings.is_empty() {
+            return signature;
+        }
+
+        let mut found = HashMap::new();
+        for (expected, argument) in self.matched(&signature, bound, arguments) {
+            unify(expected, &argument.type_, &mut found);
+        }
+
+        for param in params {
+            let Type::Parameter { name, symbol, bound } = param else { continue };
+            let inferred = found.remove(&symbol).map(Type::union).unwrap_or(Type::Unknown);
+            self.expect(&bound, &inferred, callee, || format!("type parameter '{}' of '{}'", name, signature.name));
+            bindings.insert(symbol, inferred);
+        }
+
+        Signature {
+            name: signature.name,
+            params: signature.params
+                .into_iter()
+                .map(|(name, kind, type_, default)| (name, kind, type_.substitute(&bindings), default))
+                .collect(),
+            returns: signature.returns.substitute(&bindings)
+        }
+    }
+
+
+    ///
+    /// The declared type of the parameter of `signature` that each of `arguments` is passed
+    /// to, if it is known.
+    ///
+    fn matched<'b, 'c>(&self, signature: &'b Signature, bound: bool, arguments: &'c [Argument]) -> Vec<(&'b Type, &'c Argument<'c>)> {
+        let skipped = usize::from(bound && signature.params.first().is_some_and(|(_, kind, ..)| *kind == ParameterKind::Normal));
+        let params = &signature.params[skipped..];
+
+        let positional = params
+            .iter()
+            .take_while(|(_, kind, ..)| !matches!(kind, ParameterKind::VarPositional | ParameterKind::KeywordOnlyMarker))
+            .filter(|(_, kind, ..)| *kind == ParameterKind::Normal);
+
+        let positional_arguments = arguments.iter().take_while(|argument| argument.name.is_none() && !argument.unpacked);
+        let mut matched: Vec<_> = positional.map(|(_, _, type_, _)| type_).zip(positional_arguments).collect();
+
+        for argument in arguments {
+            let Some(name) = &argument.name else { continue };
+            let parameter = params
+                .iter()
+                .find(|(parameter, kind, ..)| *parameter == name.lexeme && *kind == ParameterKind::Normal);
+
+            if let Some((_, _, type_, _)) = parameter {
+                matched.push((type_, argument));
+            }
+        }
+
+        matched
+    }
+
+
+    ///
+    /// Check the arguments of a call against the parameters of `signature`. When `bound`,
+    /// the first parameter is already filled by the instance the function was accessed through.
+    ///
+    fn check_arguments(&mut self, signature: &Signature, bound: bool, arguments: &[Argument], callee: &SyntaxNode) {
+        let skipped = usize::from(bound && signature.params.first().is_some_and(|(_, kind, ..)| *kind == ParameterKind::Normal));
+        let params = &signature.params[skipped..];
+
+        // Parameters after `*` or `*args` can only be passed by keyword.
+        let positional: Vec<_> = params
+            .iter()
+            .take_while(|(_, kind, ..)| !matches!(kind, ParameterKind::VarPositional | ParameterKind::KeywordOnlyMarker))
+            .filter(|(_, kind, ..)| *kind == ParameterKind::Normal)
+            .collect();
+
+        let var_positional = params.iter().any(|(_, kind, ..)| *kind == ParameterKind::VarPositional);
+        let var_keyword = params.iter().any(|(_, kind, ..)| *kind == ParameterKind::VarKeyword);
+        let unpacked = arguments.iter().any(|argument| argument.unpacked);
+
+        let mut filled = HashSet::new();
+        let positional_arguments = arguments.iter().filter(|argument| argument.name.is_none() && !argument.unpacked);
+
+        for (i, argument) in positional_arguments.enumerate() {
+            match positional.get(i) {
+                Some((name, _, expected, _)) => {
+                    self.expect(expected, &argument.type_, argument.node, || {
+                        format!("parameter '{}' of '{}'", name, signature.name)
+                    });
+                    filled.insert(name.as_str());
+                }
+
+                None if !var_positional && !unpacked => {
+                    let message = format!(
+                        "'{}' takes {} positional argument(s), found {}",
+                        signature.name,
+                        positional.len(),
+                        arguments.iter().filter(|argument| argument.name.is_none()).count()
+                    );
+                    self.error(span_of(argument.node), message);
+                    break;
+                }
+
+                None => break
+            }
+        }
+
+        for argument in arguments {
+            let Some(name) = &argument.name else { continue };
+            let parameter = params
+                .iter()
+                .find(|(parameter, kind, ..)| *parameter == name.lexeme && *kind == ParameterKind::Normal);
+
+            match parameter {
+                Some((parameter, _, expected, _)) => {
+                    self.expect(expected, &argument.type_, argument.node, || {
+                        format!("parameter '{}' of '{}'", parameter, signature.name)
+                    });
+                    filled.insert(parameter.as_str());
+                }
+
+                None if !var_keyword => {
+                    let message = format!("'{}' has no parameter named '{}'", signature.name, name.lexeme);
+                    self.error(Span::of(name), message);
+                }
+
+                None => {}
+            }
+        }
+
+        // Any parameter may be filled by unpacking, e.g. `f(*args)`.
+        if unpacked {
+            return;
+        }
+
+        for (name, kind, _, default) in params {
+            if *kind == ParameterKind::Normal && !default && !filled.contains(name.as_str()) {
+                self.error(span_of(callee), format!("Missing argument '{}' of '{}'", name, signature.name));
+            }
+        }
+    }
+
+
+    ///
+    /// The type of the member `member` of a value of type `parent`, i.e. `parent.member`.
+    ///
+    fn member(&mut self, parent: &Type, member: &PieToken) -> Type {
+        let name = member.lexeme.as_str();
+
+        match parent {
+            Type::Struct { name: struct_name, symbol, .. }
+            | Type::Interface { name: struct_name, symbol, .. }
+            | Type::Variant { name: struct_name, symbol, .. }
+            | Type::Class { name: struct_name, symbol } => {
+                let body = self.index.symbols[*symbol].body;
+                let found = body.and_then(|body| self.index.scopes[body].names.get(name).copied());
+                let instance = matches!(parent, Type::Struct { .. } | Type::Interface { .. } | Type::Variant { .. });
+
+                // The types given for the struct's type parameters, if it is generic.
+                let arguments = match parent {
+                    Type::Struct { arguments, .. } => arguments.clone(),
+                    _ => vec![]
+                };
+                let bindings: HashMap<SymbolId, Type> = self.type_params(*symbol)
+                    .into_iter()
+                    .zip(arguments.iter().cloned())
+                    .filter_map(|(param, argument)| match param {
+                        Type::Parameter { symbol, .. } => Some((symbol, argument)),
+                        _ => None
+                    })
+                    .collect();
+
+                match found.map(|found| (found, self.index.symbols[found].kind)) {
+                    Some((found, SymbolKind::Field)) if instance => self.declared(found).substitute(&bindings),
+                    Some((found, SymbolKind::Method)) => Type::Function { symbol: found, bound: instance, arguments: arguments },
+                    Some((found, SymbolKind::Variant)) => self.symbol_type(found),
+                    Some(_) => Type::Unknown,
+
+                    // Every object has the attributes of `object`, e.g. `__class__`.
+                    None if name.starts_with("__") => Type::Unknown,
+                    None if !instance && self.index.symbols[*symbol].kind == SymbolKind::Enum => Type::Unknown,
+
+                    None => {
+                        self.error(Span::of(member), format!("'{}' has no attribute '{}'", struct_name, name));
+                        Type::Unknown
+                    }
+                }
+            }
+
+            Type::Enum { .. } => match name {
+                "name" => Type::Str,
+                _ => Type::Unknown
+            },
+
+            Type::Parameter { bound, .. } => self.member(bound, member),
+
+            Type::None => {
+                self.error(Span::of(member), format!("'None' has no attribute '{}'", name));
+                Type::Unknown
+            }
+
+            Type::Union(members) => {
+                let types = members.iter().map(|parent| self.member(parent, member)).collect();
+                Type::union(types)
+            }
+
+            _ => Type::Unknown
+        }
+    }
+
+
+    ///
+    /// The type of `parent::member`, which is a variant if `parent` is an enum. A variant
+    /// with a payload is called to construct it.
+    ///
+    fn variant(&mut self, parent: &Type, member: &PieToken) -> Type {
+        let Type::Class { name, symbol } = parent else { return Type::Unknown };
+        let definition = &self.index.symbols[*symbol];
+        if definition.kind != SymbolKind::Enum {
+            return Type::Unknown;
+        }
+
+        let found = definition.body.and_then(|body| self.index.scopes[body].names.get(&member.lexeme).copied());
+        match found {
+            Some(variant) => self.symbol_type(variant),
+            None => {
+                self.error(Span::of(member), format!("'{}' has no variant '{}'", name, member.lexeme));
+                Type::Unknown
+            }
+        }
+    }
+
+
+    ///
+    /// The type of the subscript `node`, e.g. `items[0]`, along with the type of the value
+    /// being subscripted.
+    ///
+    fn subscript(&mut self, node: &Rc<SyntaxNode>) -> (Type, Type) {
+        let mut children = node.child_nodes();
+        let Some(value) = children.next() else { return (Type::Unknown, Type::Unknown) };
+        let container = self.type_of(value);
+
+        let mut indices = vec![];
+        for index in children {
+            if index.kind == SyntaxKind::Slice {
+                for bound in index.child_nodes() {
+                    self.type_of(bound);
+                }
+                indices.push((index, None));
+            } else {
+                indices.push((index, Some(self.type_of(index))));
+            }
+        }
+
+        // Several indices, e.g. `grid[i, j]`, are only used by other libraries.
+        let [(index_node, index)] = &indices[..] else { return (Type::Unknown, container) };
+
+        let what = || format!("an index of '{}'", container);
+        let element = match (&container, index) {
+            (Type::List(_) | Type::Str | Type::Tuple(_), None) => match &container {
+                Type::Tuple(_) => Type::Unknown,
+                _ => container.clone()
+            },
+
+            (Type::List(element), Some(index)) => {
+                self.expect(&Type::Int, index, index_node, what);
+                element.as_ref().clone()
+            }
+
+            (Type::Str, Some(index)) => {
+                self.expect(&Type::Int, index, index_node, what);
+                Type::Str
+            }
+
+            (Type::Tuple(elements), Some(index)) => {
+                self.expect(&Type::Int, index, index_node, what);
+                match constant_index(index_node) {
+                    Some(i) if i >= 0 => elements.get(i as usize).cloned().unwrap_or(Type::Unknown),
+                    Some(i) => elements.len().checked_sub(i.unsigned_abs() as usize)
+                        .and_then(|i| elements.get(i).cloned())
+                        .unwrap_or(Type::Unknown),
+                    None => Type::union(elements.clone())
+                }
+            }
+
+            (Type::Dict(key, value), Some(index)) => {
+                self.expect(key, index, index_node, || format!("a key of '{}'", container));
+                value.as_ref().clone()
+            }
+
+            (Type::None | Type::Bool | Type::Int | Type::Float, _) => {
+                self.error(span_of(value), format!("'{}' is not subscriptable", container));
+                Type::Unknown
+            }
+
+            _ => Type::Unknown
+        };
+
+        (element, container)
+    }
+
+
+    ///
+    /// The type of the elements of a list or set literal.
+    ///
+    fn elements(&mut self, node: &Rc<SyntaxNode>) -> Type {
+        let types: Vec<Type> = node
+            .child_nodes()
+            .map(|element| match element.kind {
+                SyntaxKind::Unpack => self.type_of(element).element(),
+                _ => self.type_of(element)
+            })
+            .collect();
+
+        Type::union(types)
+    }
+
+
+    fn dictionary(&mut self, node: &Rc<SyntaxNode>) -> Type {
+        let Some(AstNode::DictLiteral(entries)) = node.ast.as_deref() else { return Type::Unknown };
+        let mut nodes = node.child_nodes();
+        let mut keys = vec![];
+        let mut values = vec![];
+
+        for entry in entries {
+            match entry {
+                DictEntry::KeyValue { .. } => {
+                    let (Some(key), Some(value)) = (nodes.next(), nodes.next()) else { break };
+                    keys.push(self.type_of(key));
+                    values.push(self.type_of(value));
+                }
+
+                DictEntry::Unpack(_) => {
+                    let Some(mapping) = nodes.next() else { break };
+                    match self.type_of(mapping) {
+                        Type::Dict(key, value) => {
+                            keys.push(*key);
+                            values.push(*value);
+                        }
+                        _ => {
+                            keys.push(Type::Unknown);
+                            values.push(Type::Unknown);
+                        }
+                    }
+                }
+            }
+        }
+
+        Type::Dict(Box::new(Type::union(keys)), Box::new(Type::union(values)))
+    }
+}
+
+
+///
+/// The type of `-operand`, or `None` if it cannot be negated.
+///
+fn negate(operand: &Type) -> Option<Type> {
+    match operand {
+        Type::Unknown => Some(Type::Unknown),
+        Type::Bool | Type::Int => Some(Type::Int),
+        Type::Float => Some(Type::Float),
+        Type::Union(members) => members.iter().map(negate).collect::<Option<Vec<_>>>().map(Type::union),
+        _ => None
+    }
+}
+
+
+///
+/// The value of an index which is an integer literal, possibly negated, e.g. `-1`.
+///
+fn constant_index(node: &SyntaxNode) -> Option<i64> {
+    match node.ast.as_deref()? {
+        AstNode::IntegerLiteral(i) => Some(*i),
+        AstNode::UnaryOperation(operation) if matches!(operation.operator, UnaryOperator::Minus) => {
+            match operation.operand.as_ref() {
+                AstNode::IntegerLiteral(i) => Some(-i),
+                _ => None
+            }
+        }
+        _ => None
+    }
+}
+
+
+///
+/// Whether `node` is the name `None`.
+///
+fn is_none(node: &SyntaxNode) -> bool {
+    node.kind == SyntaxKind::Name && node.first_token().is_some_and(|token| token.lexeme == "None")
+}
+
+
+/// The types [`builtin_type`] knows that are only available once imported from `typing`.
+const TYPING_TYPES: [&str; 6] = ["List", "Set", "Dict", "Tuple", "Optional", "Union"];
+
+
+fn is_builtin_type(name: &str) -> bool {
+    let types = ["int", "float", "str", "bool", "list", "dict", "set", "tuple", "object", "type", "bytes", "frozenset"];
+    types.contains(&name) || name.starts_with(char::is_uppercase)
+}
+
+
+///
+/// The type named `name` by Python or `typing`, given the type arguments `arguments`.
+///
+fn builtin_type(name: &str, arguments: &[Type]) -> Type {
+    let argument = |i: usize| Box::new(arguments.get(i).cloned().unwrap_or(Type::Unknown));
+
+    match name {
+        "int" => Type::Int,
+        "float" => Type::Float,
+        "str" => Type::Str,
+        "bool" => Type::Bool,
+        "None" => Type::None,
+        "list" | "List" => Type::List(argument(0)),
+        "set" | "Set" => Type::Set(argument(0)),
+        "dict" | "Dict" => Type::Dict(argument(0), argument(1)),
+        "tuple" | "Tuple" if !arguments.is_empty() => Type::Tuple(arguments.to_vec()),
+        "Optional" => Type::union(vec![*argument(0), Type::None]),
+        "Union" if !arguments.is_empty() => Type::union(arguments.to_vec()),
+        _ => Type::Unknown
+    }
+}
+
+
+///
+/// The type of the value returned by calling the builtin `name` with `arguments`.
+///
+fn builtin_call(name: &str, arguments: &[Argument]) -> Type {
+    let first = arguments.first().map(|argument| argument.type_.clone()).unwrap_or(Type::Unknown);
+
+    match name {
+        "int" | "len" | "ord" | "hash" | "id" => Type::Int,
+        "float" => Type::Float,
+        "str" | "repr" | "input" | "chr" | "hex" | "oct" | "bin" | "ascii" | "format" => Type::Str,
+        "bool" | "isinstance" | "issubclass" | "callable" | "hasattr" | "any" | "all" => Type::Bool,
+        "print" => Type::None,
+        "abs" if first.is_numeric() => negate(&first).unwrap_or(Type::Unknown),
+        "round" if arguments.len() == 1 => Type::Int,
+        "list" | "sorted" => Type::List(Box::new(first.element())),
+        "set" => Type::Set(Box::new(first.element())),
+        _ => Type::Unknown
+    }
+}
+
+
+///
+/// Where the node is, which is the whole node if it is on a single line, or otherwise
+/// its first token.
+///
+fn span_of(node: &SyntaxNode) -> Span {
+    let (Some(first), Some(last)) = (node.first_token(), node.last_token()) else {
+        return Span { line: 0, column: 0, length: 0 };
+    };
+
+    let mut span = Span::of(&first);
+    if first.line == last.line {
+        span.length = last.column + last.lexeme.chars().count() as u32 - first.column;
+    }
+    span
+}
@@ -0,0 +1,168 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    process::{Child, Command},
+    thread,
+    time::{Duration, SystemTime}
+};
+use color_eyre::eyre;
+use crate::modules::BuildTarget;
+
+
+/// 
+/// Options for `nadra watch`.
+/// 
+/// # Fields
+/// - `run` - Whether to run the program with `python3` after every successful build.
+/// - `interval` - How long to wait between checks for changes.
+/// 
+pub struct WatchOptions {
+    pub run: bool,
+    pub interval: Duration
+}
+
+
+impl Default for WatchOptions {
+    fn default() -> Self {
+        Self {
+            run: false,
+            interval: Duration::from_millis(500)
+        }
+    }
+}
+
+
+/// 
+/// The modification time and size of every `.ndr` file in a project. The project is
+/// rebuilt whenever this changes, including when a file is added or removed.
+/// 
+type Snapshot = BTreeMap<PathBuf, (SystemTime, u64)>;
+
+
+/// 
+/// Poll the source files of `target`, rebuilding it every time one of them changes. Since
+/// builds are incremental, only the modules affected by a change are rebuilt. This never
+/// returns.
+/// 
+pub fn watch(target: &BuildTarget, options: &WatchOptions) -> eyre::Result<()> {
+    let mut previous: Option<Snapshot> = None;
+    let mut program: Option<Child> = None;
+
+    println!("Watching {} for changes", target.root.display());
+
+    loop {
+        let snapshot = take_snapshot(&target.root, &target.out_dir);
+
+        if previous.as_ref() != Some(&snapshot) {
+            previous = Some(snapshot);
+
+            if rebuild(target) && options.run {
+                program = restart(target, program.take());
+            }
+        }
+
+        thread::sleep(options.interval);
+    }
+}
+
+
+/// 
+/// Rebuild `target`, printing which modules were rebuilt or any error that occurred.
+/// 
+/// # Returns
+/// - Whether the build succeeded.
+/// 
+fn rebuild(target: &BuildTarget) -> bool {
+    match target.build() {
+        Ok(report) => {
+            for warning in &report.warnings {
+                eprintln!("[watch] {}", warning);
+            }
+            for module in &report.rebuilt {
+                println!("[watch] Rebuilt {}", module);
+            }
+            println!(
+                "[watch] Build succeeded ({} rebuilt, {} up to date)",
+                report.rebuilt.len(),
+                report.module_count - report.rebuilt.len()
+            );
+            true
+        }
+
+        Err(e) => {
+            eprintln!("[watch] Build failed: {}", e);
+            false
+        }
+    }
+}
+
+
+/// 
+/// Stop the previous run of the program if it is still going, then run it again.
+/// 
+fn restart(target: &BuildTarget, previous: Option<Child>) -> Option<Child> {
+    if let Some(mut previous) = previous {
+        // Killing fails if the program already exited, which is fine.
+        let _ = previous.kill();
+        let _ = previous.wait();
+    }
+
+    let entry = match target.entry_output() {
+        Ok(entry) => entry,
+        Err(e) => {
+            eprintln!("[watch] {}", e);
+            return None;
+        }
+    };
+
+    println!("[watch] Running {}", entry.display());
+    match Command::new("python3").arg(&entry).spawn() {
+        Ok(child) => Some(child),
+        Err(e) => {
+            eprintln!("[watch] Failed to run python3: {}", e);
+            None
+        }
+    }
+}
+
+
+/// 
+/// Record every `.ndr` file within `root`, skipping `out_dir` and hidden directories.
+/// Directories and files deleted while the snapshot is taken are left out of it.
+/// 
+fn take_snapshot(root: &Path, out_dir: &Path) -> Snapshot {
+    let mut snapshot = Snapshot::new();
+    let out_dir = out_dir.canonicalize().unwrap_or(out_dir.to_path_buf());
+    let mut directories = vec![root.to_path_buf()];
+
+    while let Some(directory) = directories.pop() {
+        let Ok(entries) = fs::read_dir(&directory) else {
+            continue;
+        };
+
+        for entry in entries.flatten() {
+            let path = entry.path();
+
+            let is_hidden = path
+                .file_name()
+                .is_some_and(|name| name.to_string_lossy().starts_with('.'));
+
+            if is_hidden || path == out_dir {
+                continue;
+            }
+
+            if path.is_dir() {
+                directories.push(path);
+            } else if path.extension().is_some_and(|extension| extension == "ndr") {
+                // The file may be deleted between listing and reading it.
+                if let Ok(metadata) = fs::metadata(&path) {
+                    let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+                    snapshot.insert(path, (modified, metadata.len()));
+                }
+            }
+        }
+    }
+
+    snapshot
+}
@@ -0,0 +1,675 @@
+//! Builds projects with `nadra build` and runs the generated code. These tests need
+//! `python3` to be installed.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::Command
+};
+
+
+struct Output {
+    success: bool,
+    stdout: String,
+    stderr: String
+}
+
+
+///
+/// A fresh directory for a project, containing `files`.
+///
+fn project(name: &str, files: &[(&str, &str)]) -> PathBuf {
+    let root = std::env::temp_dir().join(format!("nadra-build-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&root);
+    fs::create_dir_all(&root).unwrap();
+
+    for (path, text) in files {
+        write(&root, path, text);
+    }
+
+    root.canonicalize().unwrap()
+}
+
+
+fn write(root: &Path, path: &str, text: &str) {
+    let path = root.join(path);
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, text).unwrap();
+}
+
+
+///
+/// Run `nadra` with `arguments` within `root`.
+///
+fn nadra(root: &Path, arguments: &[&str]) -> Output {
+    let output = Command::new(env!("CARGO_BIN_EXE_nadra"))
+        .args(arguments)
+        .current_dir(root)
+        .output()
+        .expect("failed to start nadra");
+
+    Output {
+        success: output.status.success(),
+        stdout: String::from_utf8(output.stdout).unwrap(),
+        stderr: String::from_utf8(output.stderr).unwrap()
+    }
+}
+
+
+///
+/// Build the project in `root` from `main.ndr`, which must succeed.
+///
+fn build(root: &Path) -> Output {
+    let output = nadra(root, &["build", "main.ndr"]);
+    assert!(output.success, "the build failed: {}", output.stderr);
+    output
+}
+
+
+///
+/// Run the entry module of the project in `root` once built, returning what it prints.
+///
+fn run(root: &Path) -> String {
+    let output = Command::new("python3")
+        .arg(root.join("build").join("main.py"))
+        .output()
+        .expect("failed to start python3");
+
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(output.status.success(), "the program failed: {}", stderr);
+    String::from_utf8(output.stdout).unwrap()
+}
+
+
+#[test]
+fn builds_modules_into_packages() {
+    let root = project("packages", &[
+        ("main.ndr", "use utils.strings\nuse utils::helpers\nuse utils.deep.leaf::value\n\
+            print(utils.strings.shout(\"hi\"))\nprint(helpers.twice(value))\n"),
+        ("utils/strings.ndr", "def shout(s: str) -> str\n    return s.upper()\nenddef\n"),
+        ("utils/helpers.ndr", "use .strings::shout\ndef twice(x: int) -> int\n    return len(shout(\"ab\")) * x\nenddef\n"),
+        ("utils/deep/leaf.ndr", "value = 7\n")
+    ]);
+
+    build(&root);
+
+    let out = root.join("build");
+    for file in ["main.py", "utils/__init__.py", "utils/strings.py", "utils/helpers.py", "utils/deep/__init__.py", "utils/deep/leaf.py"] {
+        assert!(out.join(file).is_file(), "{} was not written", file);
+    }
+    assert!(!out.join("__init__.py").exists());
+    assert_eq!(run(&root), "HI\n14\n");
+}
+
+
+#[test]
+fn leaves_python_modules_to_python() {
+    let root = project("python_modules", &[
+        ("main.ndr", "use os.path\nuse json::dumps\nprint(dumps([1]), os.path.basename(\"/a/b\"))\n")
+    ]);
+
+    build(&root);
+    assert_eq!(run(&root), "[1] b\n");
+}
+
+
+#[test]
+fn runs_while_loops() {
+    let root = project("while_loops", &[
+        ("main.ndr", "use util::triangle\ni = 0\nwhile i < 2 do\n    print(i)\n    i += 1\ndone\nprint(triangle(4))\n"),
+        ("util.ndr", "def triangle(n: int) -> int\n    total = 0\n    while n > 0 do\n        if n % 2 == 0 then\n            total += n\n        endif\n        total += n\n        n -= 1\n    done\n    return total\nenddef\n")
+    ]);
+
+    build(&root);
+    assert_eq!(run(&root), "0\n1\n16\n");
+}
+
+
+#[test]
+fn reports_import_cycles() {
+    let root = project("cycle", &[
+        ("main.ndr", "use a\n"),
+        ("a.ndr", "use b\n"),
+        ("b.ndr", "use a\n")
+    ]);
+
+    let output = nadra(&root, &["build", "main.ndr"]);
+    assert!(!output.success);
+    assert!(output.stderr.contains("Import cycle detected: a -> b -> a"), "stderr: {}", output.stderr);
+
+    let root = project("self_import", &[("main.ndr", "use main\n")]);
+    let output = nadra(&root, &["build", "main.ndr"]);
+    assert!(output.stderr.contains("Import cycle detected: main -> main"), "stderr: {}", output.stderr);
+}
+
+
+#[test]
+fn scaffolds_and_builds_new_projects() {
+    let root = project("new", &[]);
+    let output = nadra(&root, &["new", "hello"]);
+    assert!(output.success, "nadra new failed: {}", output.stderr);
+
+    let hello = root.join("hello");
+    for file in ["nadra.toml", "src/main.ndr", ".gitignore"] {
+        assert!(hello.join(file).is_file(), "{} was not created", file);
+    }
+
+    let output = nadra(&hello, &["build"]);
+    assert!(output.success, "the build failed: {}", output.stderr);
+    assert!(output.stdout.contains("Built 1 module(s)"), "stdout: {}", output.stdout);
+    assert_eq!(run(&hello), "Hello, World!\n");
+
+    assert!(!nadra(&root, &["new", "hello"]).success);
+    assert!(nadra(&root, &["new", "two words"]).stderr.contains("Invalid project name 'two words'"));
+}
+
+
+#[test]
+fn builds_the_entry_module_named_by_the_manifest() {
+    let root = project("manifest", &[
+        ("nadra.toml", "[project]\nname = \"app\"\nentry = \"app.start\"\nsource = \"code\"\noutput = \"out\"\n"),
+        ("code/app/start.ndr", "use .config::NAME\nprint(NAME)\n"),
+        ("code/app/config.ndr", "NAME = \"app\"\n")
+    ]);
+
+    let output = nadra(&root.join("code"), &["build"]);
+    assert!(output.success, "the build failed: {}", output.stderr);
+    assert!(root.join("out/app/start.py").is_file());
+    assert!(root.join("out/app/__init__.py").is_file());
+}
+
+
+#[test]
+fn reports_invalid_manifests() {
+    let cases = [
+        ("[project]\nentry = \"main\"\n", "missing field `name`"),
+        ("[project]\nname = \"app\"\nversion = \"1\"\n", "unknown field `version`"),
+        ("[project]\nname = \"my app\"\n", "Invalid project name 'my app'"),
+        ("[project]\nname = \"app\"\npython = \"3\"\n", "Invalid Python version '3'"),
+        ("[project]\nname = \"app\"\npython = \"2.7\"\n", "Only Python 3 is supported"),
+        ("[project]\nname = \"app\"\n[format]\nindent = 2\n", "unknown field `indent`"),
+        ("[project]\nname = \"app\"\n[lint]\nunused_things = \"warn\"\n", "Unknown lint 'unused_things'"),
+        ("[project]\nname = \"app\"\n[lint]\nundefined_names = \"loud\"\n", "unknown variant `loud`"),
+        ("[project\nname = \"app\"\n", "TOML parse error")
+    ];
+
+    for (i, (manifest, message)) in cases.iter().enumerate() {
+        let root = project(&format!("invalid_manifest_{}", i), &[
+            ("nadra.toml", manifest),
+            ("src/main.ndr", "print(1)\n")
+        ]);
+
+        let output = nadra(&root, &["build"]);
+        assert!(!output.success, "{} was accepted", manifest);
+        assert!(output.stderr.contains("nadra.toml"), "{}", output.stderr);
+        assert!(output.stderr.contains(message), "{}", output.stderr);
+    }
+}
+
+
+///
+/// How many modules a build rebuilt and found up to date, as reported by `nadra build`.
+///
+fn rebuilt(output: &Output) -> (usize, usize) {
+    let summary = output.stdout
+        .lines()
+        .find_map(|line| line.strip_prefix("Built "))
+        .unwrap_or_else(|| panic!("no build summary in: {}", output.stdout));
+
+    let numbers: Vec<usize> = summary
+        .split(' ')
+        .filter_map(|word| word.trim_end_matches(',').parse().ok())
+        .collect();
+    (numbers[0], numbers[1])
+}
+
+
+#[test]
+fn skips_modules_that_have_not_changed() {
+    let root = project("cache_unchanged", &[
+        ("main.ndr", "use util::double\nprint(double(2))\n"),
+        ("util.ndr", "def double(x: int) -> int\n    return x * 2\nenddef\n")
+    ]);
+
+    assert_eq!(rebuilt(&build(&root)), (2, 0));
+    assert_eq!(rebuilt(&build(&root)), (0, 2));
+
+    // Only the module whose body changed is rebuilt.
+    write(&root, "util.ndr", "def double(x: int) -> int\n    return x + x\nenddef\n");
+    assert_eq!(rebuilt(&build(&root)), (1, 1));
+
+    // Lost output is written again.
+    fs::remove_file(root.join("build/main.py")).unwrap();
+    assert_eq!(rebuilt(&build(&root)), (1, 1));
+    assert_eq!(run(&root), "4\n");
+}
+
+
+#[test]
+fn rebuilds_importers_when_an_interface_changes() {
+    let root = project("cache_interfaces", &[
+        ("main.ndr", "use util::*\nprint(1)\n"),
+        ("util.ndr", "def double(x: int) -> int\n    return x * 2\nenddef\n")
+    ]);
+    build(&root);
+
+    let interfaces = [
+        "def double(x: int, y: int = 0) -> int\n    return x * 2\nenddef\n",
+        "def double(x: int, y: int = 0) -> int\n    return x * 2\nenddef\nlet limit: int = 1\n",
+        "def double(x: int, y: int = 0) -> int\n    return x * 2\nenddef\nlet limit: str = \"1\"\n",
+        "def double(x: int, y: int = 0) -> int\n    return x * 2\nenddef\nconst limit: str = \"1\"\n",
+        "struct Point\n    x: int\nendstruct\n",
+        "struct Point\n    x: int\n    y: int = 0\nendstruct\n",
+        "interface Named\n    def name(self) -> str\nendinterface\n",
+        "interface Named\n    def name(self, short: bool) -> str\nendinterface\n",
+        "enum Shape\n    Circle(radius: int)\nendenum\n",
+        "enum Shape\n    Circle(radius: int),\n    Empty()\nendenum\n"
+    ];
+
+    for interface in interfaces {
+        write(&root, "util.ndr", interface);
+        assert_eq!(rebuilt(&build(&root)), (2, 0), "{}", interface);
+    }
+
+    // The value of a constant is not part of the interface.
+    write(&root, "util.ndr", "const limit: str = \"2\"\n");
+    build(&root);
+    write(&root, "util.ndr", "const limit: str = \"3\"\n");
+    assert_eq!(rebuilt(&build(&root)), (1, 1));
+}
+
+
+#[test]
+fn discards_the_cache_when_the_compiler_or_options_change() {
+    let root = project("cache_invalidation", &[
+        ("nadra.toml", "[project]\nname = \"app\"\nsource = \".\"\npython = \"3.10\"\n"),
+        ("main.ndr", "use util\nprint(util.VALUE)\n"),
+        ("util.ndr", "VALUE = 1\n")
+    ]);
+
+    assert_eq!(rebuilt(&nadra(&root, &["build"])), (2, 0));
+    assert_eq!(rebuilt(&nadra(&root, &["build"])), (0, 2));
+
+    write(&root, "nadra.toml", "[project]\nname = \"app\"\nsource = \".\"\npython = \"3.11\"\n");
+    assert_eq!(rebuilt(&nadra(&root, &["build"])), (2, 0));
+
+    write(&root, "nadra.toml", "[project]\nname = \"app\"\nsource = \".\"\npython = \"3.11\"\nannotate = true\n");
+    assert_eq!(rebuilt(&nadra(&root, &["build"])), (2, 0));
+    assert_eq!(rebuilt(&nadra(&root, &["build"])), (0, 2));
+
+    // A cache written by another version of the compiler.
+    let cache = root.join("build/.nadra-cache");
+    let contents = fs::read_to_string(&cache).unwrap();
+    let (fingerprint, entries) = contents.split_once('\n').unwrap();
+    let (_, options) = fingerprint.split_once(' ').unwrap();
+    fs::write(&cache, format!("0.0.0 {}\n{}", options, entries)).unwrap();
+    assert_eq!(rebuilt(&nadra(&root, &["build"])), (2, 0));
+    assert_eq!(rebuilt(&nadra(&root, &["build"])), (0, 2));
+
+    // Another build of the same version of the compiler, whose executable differs.
+    let compiler = root.join("nadra-rebuilt");
+    let mut executable = fs::read(env!("CARGO_BIN_EXE_nadra")).unwrap();
+    executable.extend_from_slice(b"rebuilt");
+    fs::write(&compiler, executable).unwrap();
+    fs::set_permissions(&compiler, fs::metadata(env!("CARGO_BIN_EXE_nadra")).unwrap().permissions()).unwrap();
+
+    // Other tests starting processes may briefly hold the new executable open for writing.
+    let output = (0..10)
+        .find_map(|_| {
+            let output = Command::new(&compiler).arg("build").current_dir(&root).output();
+            if output.is_err() {
+                std::thread::sleep(std::time::Duration::from_millis(50));
+            }
+            output.ok()
+        })
+        .expect("failed to start the rebuilt nadra");
+
+    let output = Output {
+        success: output.status.success(),
+        stdout: String::from_utf8(output.stdout).unwrap(),
+        stderr: String::from_utf8(output.stderr).unwrap()
+    };
+    assert_eq!(rebuilt(&output), (2, 0));
+}
+
+
+#[test]
+fn reuses_the_lints_of_modules_that_have_not_changed() {
+    let manifest = "[project]\nname = \"app\"\nsource = \".\"\n";
+    let root = project("cache_lints", &[
+        ("nadra.toml", manifest),
+        ("main.ndr", "use util\nprint(util.double(2))\n"),
+        ("util.ndr", "def double(x: int) -> int\n    unused = 1\n    return x * 2\nenddef\n")
+    ]);
+
+    let output = nadra(&root, &["build"]);
+    assert!(output.stderr.contains("util.ndr:2:5: warning:"), "stderr: {}", output.stderr);
+    assert!(output.stderr.contains("[unused_variables]"), "stderr: {}", output.stderr);
+
+    // The warnings of an unchanged module are read from the cache rather than found again.
+    let cache = root.join("build/.nadra-cache");
+    let contents = fs::read_to_string(&cache).unwrap();
+    assert!(contents.contains("unused_variables"), "cache: {}", contents);
+    fs::write(&cache, contents.replace("unused_variables", "unused_imports")).unwrap();
+    let output = nadra(&root, &["build"]);
+    assert!(output.stderr.contains("[unused_imports]"), "stderr: {}", output.stderr);
+
+    // Changing the level of a lint lints every module again.
+    write(&root, "nadra.toml", &format!("{}\n[lint]\nunused_variables = \"error\"\n", manifest));
+    let output = nadra(&root, &["build"]);
+    assert!(!output.success);
+    assert!(output.stderr.contains("util.ndr:2:5: error:"), "stderr: {}", output.stderr);
+    assert!(output.stderr.contains("[unused_variables]"), "stderr: {}", output.stderr);
+
+    write(&root, "nadra.toml", &format!("{}\n[lint]\nunused_variables = \"allow\"\n", manifest));
+    let output = nadra(&root, &["build"]);
+    assert!(output.success, "stderr: {}", output.stderr);
+    assert_eq!(output.stderr, "");
+
+    // As does changing the module itself.
+    write(&root, "nadra.toml", manifest);
+    write(&root, "util.ndr", "def double(x: int) -> int\n    return x * 2\nenddef\n");
+    let output = nadra(&root, &["build"]);
+    assert_eq!(output.stderr, "");
+    assert_eq!(rebuilt(&output), (1, 1));
+}
+
+
+///
+/// Build `source` as the entry module of a project targeting `python`, returning the
+/// generated code and what it prints. The code is only run if the installed Python is at
+/// least as new as `python`.
+///
+fn build_for(name: &str, python: &str, source: &str) -> (String, Option<String>) {
+    let manifest = format!("[project]\nname = \"app\"\nsource = \".\"\npython = \"{}\"\n", python);
+    let root = project(&format!("{}_{}", name, python), &[("nadra.toml", &manifest), ("main.ndr", source)]);
+
+    let output = nadra(&root, &["build"]);
+    assert!(output.success, "the build failed: {}", output.stderr);
+    let code = fs::read_to_string(root.join("build/main.py")).unwrap();
+
+    let check = format!("import sys; sys.exit(sys.version_info < ({},))", python.replace('.', ", "));
+    let supported = Command::new("python3")
+        .args(["-c", &check])
+        .status()
+        .expect("failed to start python3")
+        .success();
+
+    (code, supported.then(|| run(&root)))
+}
+
+
+#[test]
+fn declares_type_parameters_for_each_python_version() {
+    let source = "\
+def largest[T: float](a: T, b: T) -> T
+    if a > b then
+        return a
+    endif
+    return b
+enddef
+
+def first[T](xs: list[T]) -> T
+    return xs[0]
+enddef
+
+def last[T](xs: list[T]) -> T
+    return xs[-1]
+enddef
+
+struct Box[T]
+    value: T
+
+    def get(self) -> T
+        return self.value
+    enddef
+endstruct
+
+def biggest[T: (int, str)](a: T, b: T) -> T
+    if a > b then
+        return a
+    endif
+    return b
+enddef
+
+print(first([1, 2]), last([1, 2]), Box(3).get(), largest(1.5, 2.5), biggest(\"a\", \"b\"))
+";
+
+    // Each bound has a `TypeVar` of its own, so the parameters of `first` are unbounded.
+    for python in ["3.8", "3.11"] {
+        let (code, output) = build_for("generics", python, source);
+        assert_eq!(code.matches("T = typing.TypeVar(\"T\")").count(), 1, "{}", code);
+        assert_eq!(code.matches("T_float = typing.TypeVar(\"T_float\", bound=\"float\")").count(), 1, "{}", code);
+        assert_eq!(code.matches("T_int_str = typing.TypeVar(\"T_int_str\", \"int\", \"str\")").count(), 1, "{}", code);
+        assert!(code.contains("def largest(a: T_float, b: T_float) -> T_float:"), "{}", code);
+        assert!(code.contains("def first(xs: list[T]) -> T:"), "{}", code);
+        assert!(code.contains("def biggest(a: T_int_str, b: T_int_str) -> T_int_str:"), "{}", code);
+        assert!(code.contains("class Box(typing.Generic[T]):"), "{}", code);
+        assert_eq!(output.as_deref(), Some("1 2 3 2.5 b\n"));
+    }
+
+    let (code, output) = build_for("generics", "3.12", source);
+    assert!(!code.contains("TypeVar"), "{}", code);
+    assert!(code.contains("def first[T](xs: list[T]) -> T:"), "{}", code);
+    assert!(code.contains("class Box[T]:"), "{}", code);
+    assert!(code.contains("def largest[T: float](a: T, b: T) -> T:"), "{}", code);
+    assert!(code.contains("def biggest[T: (int, str)](a: T, b: T) -> T:"), "{}", code);
+    if let Some(output) = output {
+        assert_eq!(output, "1 2 3 2.5 b\n");
+    }
+}
+
+
+#[test]
+fn keeps_the_variants_of_each_enum_apart() {
+    let shapes = "enum Shape\n    Circle(radius: float),\n    Empty\nendenum\n";
+    let main = "\
+use shapes
+use shapes::Shape
+use shapes::Shape as S
+
+enum Tree
+    Leaf(value: int),
+    Empty()
+endenum
+
+struct Circle
+    x: int
+endstruct
+
+def describe(value) -> str
+    match value with
+        case Tree::Empty() then
+            return \"empty tree\"
+        case shapes.Shape::Empty then
+            return \"empty shape\"
+        case S::Circle(r) then
+            return \"circle \" + str(r)
+        case Circle(x) then
+            return \"struct \" + str(x)
+        case _ then
+            return \"something else\"
+    endmatch
+enddef
+
+print(Shape::Circle(2.0))
+print(Circle(1))
+print(shapes.Shape::Empty)
+print(isinstance(S::Circle(1.0), Shape), isinstance(Tree::Empty(), Shape))
+print(describe(shapes::Shape::Empty))
+print(describe(Tree::Empty()))
+print(describe(S::Circle(1.5)))
+print(describe(Circle(3)))
+print(describe(Tree::Leaf(1)))
+";
+
+    // Both Python's own `match` and the chain of `if` statements it is lowered to otherwise.
+    for python in ["3.8", "3.10"] {
+        let manifest = format!("[project]\nname = \"app\"\nsource = \".\"\npython = \"{}\"\n", python);
+        let root = project(&format!("enums_{}", python), &[
+            ("nadra.toml", &manifest),
+            ("shapes.ndr", shapes),
+            ("main.ndr", main)
+        ]);
+
+        let output = nadra(&root, &["build"]);
+        assert!(output.success, "the build failed: {}", output.stderr);
+        assert_eq!(run(&root), "\
+Shape_Circle(radius=2.0)
+Circle(x=1)
+Shape_Empty()
+True False
+empty shape
+empty tree
+circle 1.5
+struct 3
+something else
+");
+    }
+}
+
+
+#[test]
+fn warns_about_matches_over_enums_from_other_modules() {
+    let root = project("imported_matches", &[
+        ("nadra.toml", "[project]\nname = \"app\"\nsource = \".\"\n"),
+        ("shapes.ndr", "enum Shape\n    Circle(radius: float),\n    Empty\nendenum\n"),
+        ("main.ndr", "use shapes::Shape\nmatch Shape::Empty with\n    case Shape::Circle(r) then\n        print(r)\nendmatch\n")
+    ]);
+
+    let output = nadra(&root, &["build"]);
+    assert!(output.success, "the build failed: {}", output.stderr);
+    assert!(
+        output.stderr.contains("main.ndr:2:1: warning: Match over 'Shape' does not handle 'Shape::Empty'"),
+        "stderr: {}",
+        output.stderr
+    );
+}
+
+
+#[test]
+fn matches_the_same_way_for_each_python_version() {
+    let source = "\
+enum Color
+    Red,
+    Green
+endenum
+
+struct Point
+    x: int
+    y: int
+endstruct
+
+def describe(value) -> str
+    match value with
+        case 0 then
+            return \"zero\"
+        case true then
+            return \"true\"
+        case Color::Red then
+            return \"red\"
+        case Point(0, y) then
+            return \"on the y axis at \" + str(y)
+        case Point(x=x, y=0) if x > 5 then
+            return \"far along the x axis at \" + str(x)
+        case Point(x, y) then
+            return \"point \" + str(x) + \" \" + str(y)
+        case \"ab\" then
+            return \"the string ab\"
+        case [first, *middle, last] then
+            return str(first) + \" \" + str(middle) + \" \" + str(last)
+        case [] then
+            return \"empty\"
+        case _ then
+            return \"something else\"
+    endmatch
+enddef
+
+def first_of(values: list[int]) -> int
+    match values[0:1] with
+        case [x] then
+            return x
+        case _ then
+            return -1
+    endmatch
+enddef
+
+print(describe(0))
+print(describe(true))
+print(describe(Color::Red))
+print(describe(Color::Green))
+print(describe(Point(0, 3)))
+print(describe(Point(7, 0)))
+print(describe(Point(2, 0)))
+print(describe(\"ab\"))
+print(describe([1, 2, 3, 4]))
+print(describe([1]))
+print(describe([]))
+print(first_of([5, 6]), first_of([]))
+";
+
+    let expected = "\
+zero
+true
+red
+something else
+on the y axis at 3
+far along the x axis at 7
+point 2 0
+the string ab
+1 [2, 3] 4
+something else
+empty
+5 -1
+";
+
+    let (code, output) = build_for("match", "3.8", source);
+    assert!(!code.contains("match "), "{}", code);
+    assert!(code.contains("dataclasses.fields("), "{}", code);
+    assert_eq!(output.as_deref(), Some(expected));
+
+    let (code, output) = build_for("match", "3.10", source);
+    assert!(code.contains("match value:"), "{}", code);
+    assert!(!code.contains("dataclasses.fields("), "{}", code);
+    assert_eq!(output.as_deref(), Some(expected));
+}
+
+
+#[test]
+fn annotates_variables_with_their_inferred_types() {
+    let root = project("annotate", &[
+        ("nadra.toml", "[project]\nname = \"app\"\nsource = \".\"\npython = \"3.8\"\nannotate = true\n"),
+        ("main.ndr", "\
+use typing::Optional
+
+def pick(flag: bool) -> Optional[int]
+    if flag then
+        return 1
+    endif
+    return None
+enddef
+
+x = 0
+if pick(True) == None then
+    x = \"none\"
+endif
+xs = [1, 2]
+p = pick(False)
+x = 3
+print(x, xs, p)
+")
+    ]);
+
+    // The options of the project are only read from its manifest without an entry module.
+    let output = nadra(&root, &["build"]);
+    assert!(output.success, "the build failed: {}", output.stderr);
+    let code = fs::read_to_string(root.join("build/main.py")).unwrap();
+
+    // Only the first assignment to each variable is annotated, and the annotations are not
+    // evaluated, since Python 3.8 does not support `int | str`.
+    assert!(code.starts_with("from __future__ import annotations\n"), "{}", code);
+    for line in ["x: int | str = 0", "xs: list[int] = [1, 2]", "p: int | None = pick(False)", "\nx = 3\n"] {
+        assert!(code.contains(line), "{} is missing from:\n{}", line, code);
+    }
+    assert_eq!(run(&root), "3 [1, 2] None\n");
+}
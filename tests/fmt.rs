@@ -0,0 +1,191 @@
+//! Formats files with `nadra fmt`.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::Command
+};
+
+
+struct Output {
+    success: bool,
+    stdout: String,
+    stderr: String
+}
+
+
+///
+/// A fresh directory containing `files`.
+///
+fn directory(name: &str, files: &[(&str, &str)]) -> PathBuf {
+    let root = std::env::temp_dir().join(format!("nadra-fmt-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&root);
+    fs::create_dir_all(&root).unwrap();
+
+    for (path, text) in files {
+        let path = root.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, text).unwrap();
+    }
+
+    root.canonicalize().unwrap()
+}
+
+
+///
+/// Run `nadra fmt` with `arguments` within `root`.
+///
+fn fmt(root: &Path, arguments: &[&str]) -> Output {
+    let output = Command::new(env!("CARGO_BIN_EXE_nadra"))
+        .arg("fmt")
+        .args(arguments)
+        .current_dir(root)
+        .output()
+        .expect("failed to start nadra");
+
+    Output {
+        success: output.status.success(),
+        stdout: String::from_utf8(output.stdout).unwrap(),
+        stderr: String::from_utf8(output.stderr).unwrap()
+    }
+}
+
+
+///
+/// Format `source` with the default settings.
+///
+fn format(name: &str, source: &str) -> String {
+    let root = directory(name, &[("main.ndr", source)]);
+    let output = fmt(&root, &["main.ndr"]);
+    assert!(output.success, "formatting failed: {}", output.stderr);
+    fs::read_to_string(root.join("main.ndr")).unwrap()
+}
+
+
+const UNFORMATTED: &str = "\
+use os
+
+
+
+def  f(a,b = 1,*args,c: int=2)->int   # note
+  x=a+b*-c
+  if x>1 then
+        return f( x-1 ,c=c )
+  endif
+  xs=[1,2,3][1:2]
+  return x
+enddef
+struct P implements Q
+x: int
+def m(self)->int
+return 1
+enddef
+endstruct
+struct Box[T:float , U]
+def first[V :T](xs: list[V])->V
+return xs[0:1][0]
+enddef
+endstruct
+match 1 with
+case 1 if true then
+print(1)
+case _ then
+print(2)
+endmatch";
+
+
+const FORMATTED: &str = "\
+use os
+
+
+def f(a, b=1, *args, c: int = 2) -> int  # note
+    x = a + b * -c
+    if x > 1 then
+        return f(x - 1, c=c)
+    endif
+    xs = [1, 2, 3][1:2]
+    return x
+enddef
+struct P implements Q
+    x: int
+    def m(self) -> int
+        return 1
+    enddef
+endstruct
+struct Box[T: float, U]
+    def first[V: T](xs: list[V]) -> V
+        return xs[0:1][0]
+    enddef
+endstruct
+match 1 with
+    case 1 if true then
+        print(1)
+    case _ then
+        print(2)
+endmatch
+";
+
+
+#[test]
+fn normalises_indentation_spacing_and_blank_lines() {
+    assert_eq!(format("normalises", UNFORMATTED), FORMATTED);
+}
+
+
+#[test]
+fn formatting_is_idempotent() {
+    let sources = [
+        ("formatted", FORMATTED),
+        ("document", include_str!("../scripts/document.ndr")),
+        ("once_formatted", &format("unformatted", UNFORMATTED))
+    ];
+
+    for (name, source) in sources {
+        let once = format(name, source);
+        assert_eq!(format(&format!("{}_again", name), &once), once, "{}", name);
+    }
+}
+
+
+#[test]
+fn fails_the_check_without_changing_unformatted_files() {
+    let root = directory("check", &[("a.ndr", UNFORMATTED), ("b.ndr", FORMATTED)]);
+
+    let output = fmt(&root, &["--check", "a.ndr", "b.ndr"]);
+    assert!(!output.success);
+    assert_eq!(output.stdout, "Would reformat a.ndr\n");
+    assert!(output.stderr.contains("1 file(s) would be reformatted"), "stderr: {}", output.stderr);
+    assert_eq!(fs::read_to_string(root.join("a.ndr")).unwrap(), UNFORMATTED);
+
+    let output = fmt(&root, &["--check", "b.ndr"]);
+    assert!(output.success, "stderr: {}", output.stderr);
+    assert_eq!(output.stdout, "");
+}
+
+
+#[test]
+fn formats_every_file_of_the_project_with_its_settings() {
+    let root = directory("project", &[
+        ("nadra.toml", "[project]\nname = \"app\"\n\n[format]\nindent_width = 2\nmax_blank_lines = 1\n"),
+        ("src/main.ndr", "x = 1\n\n\n\nif x then\nprint(x)\nendif\n"),
+        ("src/util/strings.ndr", "def f() -> int\nreturn 1\nenddef\n")
+    ]);
+
+    let output = fmt(&root, &[]);
+    assert!(output.success, "stderr: {}", output.stderr);
+    assert_eq!(fs::read_to_string(root.join("src/main.ndr")).unwrap(), "x = 1\n\nif x then\n  print(x)\nendif\n");
+    assert_eq!(fs::read_to_string(root.join("src/util/strings.ndr")).unwrap(), "def f() -> int\n  return 1\nenddef\n");
+
+    assert!(fmt(&root, &["--check"]).success);
+}
+
+
+#[test]
+fn leaves_files_that_do_not_parse_untouched() {
+    let root = directory("invalid", &[("main.ndr", "def f(\n")]);
+
+    let output = fmt(&root, &["main.ndr"]);
+    assert!(!output.success);
+    assert!(output.stderr.contains("Could not format 1 file(s)"), "stderr: {}", output.stderr);
+    assert_eq!(fs::read_to_string(root.join("main.ndr")).unwrap(), "def f(\n");
+}
@@ -0,0 +1,1300 @@
+//! Drives `nadra lsp` with a scripted JSON-RPC client.
+
+use std::{
+    fs,
+    io::{BufRead, BufReader, Read, Write},
+    path::{Path, PathBuf},
+    process::{Child, ChildStdin, ChildStdout, Command, ExitStatus, Stdio}
+};
+use serde_json::{json, Value};
+
+
+const URI: &str = "file:///project/main.ndr";
+
+
+struct Client {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+    next_id: i64,
+    notifications: Vec<Value>,
+    capabilities: Value,
+    uri: String
+}
+
+
+impl Client {
+    ///
+    /// Start the server and initialize it, offering `encodings` as position encodings.
+    ///
+    fn start(encodings: &[&str]) -> Self {
+        Self::start_in(encodings, None)
+    }
+
+
+    ///
+    /// Start the server with `root` as the root of its workspace, if there is one.
+    ///
+    fn start_in(encodings: &[&str], root: Option<&Path>) -> Self {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_nadra"))
+            .arg("lsp")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .expect("failed to start nadra lsp");
+
+        let mut client = Self {
+            stdin: child.stdin.take().unwrap(),
+            stdout: BufReader::new(child.stdout.take().unwrap()),
+            child: child,
+            next_id: 1,
+            notifications: vec![],
+            capabilities: Value::Null,
+            uri: URI.to_string()
+        };
+
+        let result = client.request("initialize", json!({
+            "rootUri": root.map(uri_of),
+            "capabilities": { "general": { "positionEncodings": encodings } }
+        }));
+        client.capabilities = result["capabilities"].clone();
+        client.notify("initialized", json!({}));
+        client
+    }
+
+
+    fn send(&mut self, message: Value) {
+        let body = message.to_string();
+        write!(self.stdin, "Content-Length: {}\r\n\r\n{}", body.len(), body).unwrap();
+        self.stdin.flush().unwrap();
+    }
+
+
+    fn receive(&mut self) -> Value {
+        let mut length = 0;
+        loop {
+            let mut header = String::new();
+            assert!(self.stdout.read_line(&mut header).unwrap() > 0, "the server closed its output");
+            let header = header.trim_end();
+            if header.is_empty() {
+                break;
+            }
+            if let Some(value) = header.strip_prefix("Content-Length: ") {
+                length = value.parse().unwrap();
+            }
+        }
+
+        let mut body = vec![0; length];
+        self.stdout.read_exact(&mut body).unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+
+    fn notify(&mut self, method: &str, params: Value) {
+        self.send(json!({ "jsonrpc": "2.0", "method": method, "params": params }));
+    }
+
+
+    ///
+    /// Send a request and wait for its response, keeping any notifications received
+    /// in the meantime.
+    ///
+    fn request_raw(&mut self, method: &str, params: Value) -> Value {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.send(json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params }));
+
+        loop {
+            let message = self.receive();
+            if message["id"] == id {
+                return message;
+            }
+            self.notifications.push(message);
+        }
+    }
+
+
+    fn request(&mut self, method: &str, params: Value) -> Value {
+        let response = self.request_raw(method, params);
+        assert!(response.get("error").is_none(), "{} failed: {}", method, response);
+        response["result"].clone()
+    }
+
+
+    fn open(&mut self, text: &str) {
+        self.notify("textDocument/didOpen", json!({
+            "textDocument": { "uri": self.uri, "languageId": "nadra", "version": 1, "text": text }
+        }));
+    }
+
+
+    fn change(&mut self, version: i64, text: &str) {
+        self.notify("textDocument/didChange", json!({
+            "textDocument": { "uri": self.uri, "version": version },
+            "contentChanges": [{ "text": text }]
+        }));
+    }
+
+
+    ///
+    /// The diagnostics published next.
+    ///
+    fn diagnostics(&mut self) -> Vec<Value> {
+        let message = match self.notifications.is_empty() {
+            true => self.receive(),
+            false => self.notifications.remove(0)
+        };
+
+        assert_eq!(message["method"], "textDocument/publishDiagnostics");
+        assert_eq!(message["params"]["uri"], self.uri.as_str());
+        message["params"]["diagnostics"].as_array().unwrap().clone()
+    }
+
+
+    fn document_request(&mut self, method: &str) -> Value {
+        self.request(method, json!({ "textDocument": { "uri": self.uri } }))
+    }
+
+
+    fn position_request(&mut self, method: &str, line: u64, character: u64, extra: Value) -> Value {
+        let mut params = json!({ "textDocument": { "uri": self.uri }, "position": position(line, character) });
+        params.as_object_mut().unwrap().extend(extra.as_object().unwrap().clone());
+        self.request_raw(method, params)
+    }
+
+
+    fn shutdown(mut self) -> ExitStatus {
+        self.request("shutdown", Value::Null);
+        self.notify("exit", Value::Null);
+        self.child.wait().unwrap()
+    }
+}
+
+
+fn position(line: u64, character: u64) -> Value {
+    json!({ "line": line, "character": character })
+}
+
+
+fn range(line: u64, start: u64, end: u64) -> Value {
+    json!({ "start": position(line, start), "end": position(line, end) })
+}
+
+
+fn uri_of(path: &Path) -> String {
+    format!("file://{}", path.display())
+}
+
+
+///
+/// A fresh directory for a workspace, containing `files`.
+///
+fn workspace(name: &str, files: &[(&str, &str)]) -> PathBuf {
+    let root = std::env::temp_dir().join(format!("nadra-lsp-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&root);
+
+    for (path, text) in files {
+        let path = root.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, text).unwrap();
+    }
+
+    root.canonicalize().unwrap()
+}
+
+
+///
+/// The edits of a workspace edit, as the file name, line, start and end of each.
+///
+fn edits(result: &Value) -> Vec<(String, u64, u64, u64)> {
+    let mut edits = vec![];
+    for (uri, changes) in result["changes"].as_object().unwrap() {
+        let file = uri.rsplit('/').next().unwrap().to_string();
+        for change in changes.as_array().unwrap() {
+            let start = &change["range"]["start"];
+            edits.push((
+                file.clone(),
+                start["line"].as_u64().unwrap(),
+                start["character"].as_u64().unwrap(),
+                change["range"]["end"]["character"].as_u64().unwrap()
+            ));
+        }
+    }
+    edits.sort();
+    edits
+}
+
+
+#[test]
+fn publishes_parser_errors_with_their_position() {
+    let mut client = Client::start(&[]);
+    client.open("x = 1\ny = )\n");
+
+    let diagnostics = client.diagnostics();
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0]["message"], "Unexpected token: )");
+    assert_eq!(diagnostics[0]["severity"], 1);
+    assert_eq!(diagnostics[0]["range"]["start"], position(1, 4));
+    assert_eq!(diagnostics[0]["range"]["end"], position(1, 5));
+
+    assert!(client.shutdown().success());
+}
+
+
+#[test]
+fn publishes_lexer_errors_with_their_position() {
+    let mut client = Client::start(&[]);
+    client.open("def f() -> int\n    return $\nenddef\n");
+
+    let diagnostics = client.diagnostics();
+    assert_eq!(diagnostics[0]["message"], "Unexpected: $");
+    assert_eq!(diagnostics[0]["range"]["start"], position(1, 11));
+
+    client.shutdown();
+}
+
+
+#[test]
+fn clears_diagnostics_once_the_error_is_fixed() {
+    let mut client = Client::start(&[]);
+    client.open("x = 1\nif x then\n");
+    assert_eq!(client.diagnostics().len(), 1);
+
+    client.change(2, "x = 1\nif x then\n    print(x)\nendif\n");
+    assert_eq!(client.diagnostics(), Vec::<Value>::new());
+
+    client.shutdown();
+}
+
+
+#[test]
+fn counts_positions_in_utf16_unless_utf32_is_supported() {
+    let source = "s = \"😀\" )\n";
+
+    let mut client = Client::start(&["utf-16"]);
+    client.open(source);
+    assert_eq!(client.diagnostics()[0]["range"]["start"], position(0, 9));
+    client.shutdown();
+
+    let mut client = Client::start(&["utf-32", "utf-16"]);
+    client.open(source);
+    assert_eq!(client.diagnostics()[0]["range"]["start"], position(0, 8));
+    client.shutdown();
+}
+
+
+#[test]
+fn lists_document_symbols() {
+    let mut client = Client::start(&[]);
+    client.open("\
+def main() -> None
+    print(1)
+enddef
+
+struct Point
+    x: int
+    y: int
+
+    def norm(self) -> float
+        return self.x
+    enddef
+endstruct
+
+enum Color
+    Red,
+    Green
+endenum
+");
+    client.diagnostics();
+
+    let symbols = client.document_request("textDocument/documentSymbol");
+    let summary = |symbol: &Value| (symbol["name"].as_str().unwrap().to_string(), symbol["kind"].as_u64().unwrap());
+
+    let top_level: Vec<_> = symbols.as_array().unwrap().iter().map(summary).collect();
+    assert_eq!(top_level, [
+        ("main".to_string(), 12),
+        ("Point".to_string(), 23),
+        ("Color".to_string(), 10)
+    ]);
+
+    let members: Vec<_> = symbols[1]["children"].as_array().unwrap().iter().map(summary).collect();
+    assert_eq!(members, [("x".to_string(), 8), ("y".to_string(), 8), ("norm".to_string(), 6)]);
+
+    let variants: Vec<_> = symbols[2]["children"].as_array().unwrap().iter().map(summary).collect();
+    assert_eq!(variants, [("Red".to_string(), 22), ("Green".to_string(), 22)]);
+
+    assert_eq!(symbols[0]["range"]["start"], position(0, 0));
+    assert_eq!(symbols[0]["range"]["end"], position(2, 6));
+    assert_eq!(symbols[0]["selectionRange"]["start"], position(0, 4));
+    assert_eq!(symbols[0]["selectionRange"]["end"], position(0, 8));
+
+    client.shutdown();
+}
+
+
+#[test]
+fn publishes_lint_warnings() {
+    let mut client = Client::start(&[]);
+    client.open("\
+use os
+
+def f(n: int) -> None
+    unused = n
+    pritn(n)
+enddef
+");
+
+    let diagnostics: Vec<_> = client
+        .diagnostics()
+        .iter()
+        .map(|diagnostic| (
+            diagnostic["code"].as_str().unwrap().to_string(),
+            diagnostic["severity"].as_u64().unwrap(),
+            diagnostic["range"]["start"].clone()
+        ))
+        .collect();
+
+    assert_eq!(diagnostics, [
+        ("unused_imports".to_string(), 2, position(0, 4)),
+        ("unused_variables".to_string(), 2, position(3, 4)),
+        ("undefined_names".to_string(), 2, position(4, 4))
+    ]);
+
+    client.shutdown();
+}
+
+
+#[test]
+fn reads_lint_levels_from_the_manifest() {
+    let root = workspace("lints", &[
+        ("nadra.toml", "[project]\nname = \"app\"\n\n[lint]\nundefined_names = \"error\"\nunused_imports = \"allow\"\n"),
+        ("src/helpers.ndr", "def greet() -> None\n    pass\nenddef\n")
+    ]);
+
+    let mut client = Client::start_in(&[], Some(&root));
+    client.uri = uri_of(&root.join("src/main.ndr"));
+
+    // Names from `use module::*` are known when the module is in the workspace.
+    client.open("use os\nuse .helpers::*\n\ngreet()\ngret()\n");
+
+    let diagnostics = client.diagnostics();
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0]["message"], "Undefined name 'gret'");
+    assert_eq!(diagnostics[0]["severity"], 1);
+
+    client.shutdown();
+    fs::remove_dir_all(root).unwrap();
+}
+
+
+#[test]
+fn reports_invalid_declarations_as_errors() {
+    let mut client = Client::start(&[]);
+    client.open("\
+const LIMIT = 10
+let count: int = 0
+let count = 1
+LIMIT += 1
+print(count, LIMIT)
+");
+
+    // These are errors whatever the lint levels are, so they have no lint name.
+    let diagnostics = client.diagnostics();
+    let messages: Vec<&str> = diagnostics.iter().map(|diagnostic| diagnostic["message"].as_str().unwrap()).collect();
+    assert_eq!(messages, [
+        "'count' is already declared on line 2",
+        "Cannot assign to constant 'LIMIT'"
+    ]);
+    assert_eq!(diagnostics[0]["range"], range(2, 4, 9));
+    assert_eq!(diagnostics[0]["severity"], 1);
+    assert_eq!(diagnostics[0].get("code"), None);
+
+    client.shutdown();
+}
+
+
+#[test]
+fn checks_types_once_enabled_by_the_manifest() {
+    let source = "\
+def area(w: int, h: int) -> int
+    return w * h
+enddef
+
+def label(n) -> str
+    return n + 1
+enddef
+
+def name() -> int
+    return \"a\" + \"b\"
+enddef
+
+x = \"a\" + 1
+y = area(2, \"3\")
+";
+
+    // Type errors are not reported unless the project asks for them.
+    let mut client = Client::start(&[]);
+    client.open(source);
+    assert!(client.diagnostics().is_empty());
+    client.shutdown();
+
+    let root = workspace("types", &[
+        ("nadra.toml", "[project]\nname = \"app\"\n\n[lint]\ntype_errors = \"error\"\n")
+    ]);
+
+    let mut client = Client::start_in(&[], Some(&root));
+    client.uri = uri_of(&root.join("src/main.ndr"));
+    client.open(source);
+
+    // `label` is not annotated, so its body is not checked.
+    let diagnostics = client.diagnostics();
+    let messages: Vec<&str> = diagnostics.iter().map(|diagnostic| diagnostic["message"].as_str().unwrap()).collect();
+    assert_eq!(messages, [
+        "Expected 'name' to return 'int', found 'str'",
+        "Unsupported operand types for +: 'str' and 'int'",
+        "Expected 'int' for parameter 'h' of 'area', found 'str'"
+    ]);
+    assert_eq!(diagnostics[1]["range"], range(12, 8, 9));
+    assert_eq!(diagnostics[1]["code"], "type_errors");
+    assert_eq!(diagnostics[1]["severity"], 1);
+
+    client.shutdown();
+    fs::remove_dir_all(root).unwrap();
+}
+
+
+#[test]
+fn checks_generic_functions_and_structs() {
+    let root = workspace("generics", &[
+        ("nadra.toml", "[project]\nname = \"app\"\n\n[lint]\ntype_errors = \"error\"\n")
+    ]);
+
+    let mut client = Client::start_in(&[], Some(&root));
+    client.uri = uri_of(&root.join("src/main.ndr"));
+    client.open("\
+struct Box[T]
+    value: T
+
+    def get(self) -> T
+        return self.value
+    enddef
+endstruct
+
+def first[T](xs: list[T]) -> T
+    return xs[0]
+enddef
+
+def largest[N: float](a: N, b: N) -> N
+    if a > b then
+        return a
+    endif
+    return b
+enddef
+
+def biggest[N: (int, str)](a: N, b: N) -> N
+    return a
+enddef
+
+let a: int = first([1, 2])
+let b: str = first([1, 2])
+let c: str = Box(\"x\").get()
+let d: int = Box(\"x\").value
+print(a, b, c, d, largest(1, 2.5), largest(\"a\", \"b\"))
+print(biggest(1, 2), biggest(\"a\", \"b\"), biggest(1.5, 2.5))
+");
+
+    // The type parameters are bound where they are declared, so none are undefined.
+    let diagnostics = client.diagnostics();
+    let messages: Vec<&str> = diagnostics.iter().map(|diagnostic| diagnostic["message"].as_str().unwrap()).collect();
+    assert_eq!(messages, [
+        "Expected 'str' for variable 'b', found 'int'",
+        "Expected 'int' for variable 'd', found 'str'",
+        "Expected 'float' for type parameter 'N' of 'largest', found 'str'",
+        "Expected 'int | str' for type parameter 'N' of 'biggest', found 'float'"
+    ]);
+
+    client.shutdown();
+    fs::remove_dir_all(root).unwrap();
+}
+
+
+#[test]
+fn understands_typing_annotations_once_imported() {
+    let root = workspace("typing", &[
+        ("nadra.toml", "[project]\nname = \"app\"\n\n[lint]\ntype_errors = \"error\"\n")
+    ]);
+
+    let mut client = Client::start_in(&[], Some(&root));
+    client.uri = uri_of(&root.join("src/main.ndr"));
+    client.open("\
+use typing::Optional
+
+def h(v: Optional[int]) -> int
+    return v
+enddef
+
+def g(v: Union[int, str]) -> int
+    return v
+enddef
+");
+
+    // Without its import, `Union` is undefined and the annotation is not understood.
+    let diagnostics = client.diagnostics();
+    let messages: Vec<&str> = diagnostics.iter().map(|diagnostic| diagnostic["message"].as_str().unwrap()).collect();
+    assert_eq!(messages, [
+        "Expected 'h' to return 'int', found 'int | None'",
+        "Undefined name 'Union'"
+    ]);
+
+    client.shutdown();
+    fs::remove_dir_all(root).unwrap();
+}
+
+
+#[test]
+fn checks_that_structs_implement_their_interfaces() {
+    let root = workspace("interfaces", &[
+        ("nadra.toml", "[project]\nname = \"app\"\n\n[lint]\ntype_errors = \"error\"\n")
+    ]);
+
+    let mut client = Client::start_in(&[], Some(&root));
+    client.uri = uri_of(&root.join("src/main.ndr"));
+    client.open("\
+interface Shape
+    def area(self) -> float
+    def name(self) -> str
+endinterface
+
+struct Square implements Shape
+    side: float
+
+    def area(self) -> float
+        return self.side ** 2
+    enddef
+
+    def name(self) -> str
+        return \"square\"
+    enddef
+endstruct
+
+struct Circle implements Shape
+    radius: float
+
+    def area(self, precise: bool) -> float
+        return 3.14 * self.radius ** 2
+    enddef
+endstruct
+
+struct Point implements Square
+    x: int
+endstruct
+
+def describe(shape: Shape) -> str
+    return shape.name() + shape.size
+enddef
+
+print(describe(Square(1.0)), describe(Point(1)))
+");
+
+    let diagnostics = client.diagnostics();
+    let messages: Vec<&str> = diagnostics.iter().map(|diagnostic| diagnostic["message"].as_str().unwrap()).collect();
+    assert_eq!(messages, [
+        "'Circle' does not implement 'name' of 'Shape'",
+        "'area' of 'Circle' does not match its signature in 'Shape'",
+        "'Square' is not an interface",
+        "'Shape' has no attribute 'size'",
+        "Expected 'Shape' for parameter 'shape' of 'describe', found 'Point'"
+    ]);
+    assert_eq!(diagnostics[0]["range"], range(17, 25, 30));
+    assert_eq!(diagnostics[0].get("code"), None);
+
+    client.shutdown();
+    fs::remove_dir_all(root).unwrap();
+}
+
+
+#[test]
+fn checks_enums_with_payloads() {
+    let root = workspace("payloads", &[
+        ("nadra.toml", "[project]\nname = \"app\"\n\n[lint]\ntype_errors = \"error\"\n")
+    ]);
+
+    let mut client = Client::start_in(&[], Some(&root));
+    client.uri = uri_of(&root.join("src/main.ndr"));
+    client.open("\
+enum Shape
+    Circle(radius: float),
+    Rect(w: float, h: float = 1.0)
+endenum
+
+def describe(shape: Shape) -> str
+    return \"shape\"
+enddef
+
+let circle = Shape::Circle(2.0)
+let radius: float = circle.radius
+let name: str = circle.radius
+print(describe(circle), describe(Shape::Rect(1.0)), describe(3))
+print(Shape::Circle(\"big\"), Shape::Rect(), circle.diameter)
+");
+
+    let diagnostics = client.diagnostics();
+    let messages: Vec<&str> = diagnostics.iter().map(|diagnostic| diagnostic["message"].as_str().unwrap()).collect();
+    assert_eq!(messages, [
+        "Expected 'str' for variable 'name', found 'float'",
+        "Expected 'Shape' for parameter 'shape' of 'describe', found 'int'",
+        "Expected 'float' for parameter 'radius' of 'Shape::Circle', found 'str'",
+        "Missing argument 'w' of 'Shape::Rect'",
+        "'Shape::Circle' has no attribute 'diameter'"
+    ]);
+
+    client.shutdown();
+    fs::remove_dir_all(root).unwrap();
+}
+
+
+#[test]
+fn checks_match_statements() {
+    let root = workspace("matches", &[
+        ("nadra.toml", "[project]\nname = \"app\"\n\n[lint]\ntype_errors = \"error\"\n")
+    ]);
+
+    let mut client = Client::start_in(&[], Some(&root));
+    client.uri = uri_of(&root.join("src/main.ndr"));
+    client.open("\
+enum Shape
+    Circle(radius: float),
+    Rect(w: float, h: float),
+    Empty()
+endenum
+
+def area(shape: Shape) -> float
+    match shape with
+        case Shape::Circle(r) if r > 0.0 then
+            return 3.14 * r * r
+        case Shape::Rect(w, h) then
+            let name: str = w
+            print(name)
+            return w * h
+        case Shape::Circle(radius=r, size=_) then
+            return r
+        case \"square\" then
+            return 0.0
+    endmatch
+    return 0.0
+enddef
+
+def describe(n: int) -> str
+    match n with
+        case 0 then
+            return \"zero\"
+        case Shape::Rect(1.0) then
+            return \"rect\"
+        case _ then
+            return \"other\"
+    endmatch
+enddef
+");
+
+    let diagnostics = client.diagnostics();
+    let messages: Vec<&str> = diagnostics.iter().map(|diagnostic| diagnostic["message"].as_str().unwrap()).collect();
+    assert_eq!(messages, [
+        "Match over 'Shape' does not handle 'Shape::Empty'",
+        "Expected 'str' for variable 'name', found 'float'",
+        "'Shape::Circle' has no attribute 'size'",
+        "A pattern of type 'str' never matches a value of type 'Shape'",
+        "A pattern of type 'Shape::Rect' never matches a value of type 'int'"
+    ]);
+    assert_eq!(diagnostics[0]["range"], range(7, 4, 9));
+    assert_eq!(diagnostics[0]["code"], "non_exhaustive_matches");
+    assert_eq!(diagnostics[0]["severity"], 2);
+
+    client.shutdown();
+    fs::remove_dir_all(root).unwrap();
+}
+
+
+#[test]
+fn checks_matches_over_enums_from_other_modules() {
+    let root = workspace("imported_matches", &[
+        ("nadra.toml", "[project]\nname = \"app\"\n"),
+        ("src/shapes.ndr", "enum Shape\n    Circle(radius: float),\n    Rect(w: float, h: float),\n    Empty\nendenum\n")
+    ]);
+
+    let mut client = Client::start_in(&[], Some(&root));
+    client.uri = uri_of(&root.join("src/main.ndr"));
+    client.open("\
+use shapes
+use shapes::Shape
+use shapes::Shape as S
+
+def area(shape: Shape) -> float
+    match shape with
+        case Shape::Circle(r) then
+            return 3.14 * r * r
+        case Shape::Empty then
+            return 0.0
+    endmatch
+    match shape with
+        case S::Rect(w, h) then
+            return w * h
+        case S::Circle(_) then
+            return 0.0
+        case S::Empty then
+            return 0.0
+    endmatch
+    match shape with
+        case shapes.Shape::Rect(w, h) then
+            return w * h
+    endmatch
+    return 0.0
+enddef
+");
+
+    let diagnostics = client.diagnostics();
+    let messages: Vec<&str> = diagnostics.iter().map(|diagnostic| diagnostic["message"].as_str().unwrap()).collect();
+    assert_eq!(messages, [
+        "Match over 'Shape' does not handle 'Shape::Rect'",
+        "Match over 'Shape' does not handle 'Shape::Circle', 'Shape::Empty'"
+    ]);
+    assert_eq!(diagnostics[0]["range"], range(5, 4, 9));
+    assert_eq!(diagnostics[1]["range"], range(19, 4, 9));
+
+    client.shutdown();
+    fs::remove_dir_all(root).unwrap();
+}
+
+
+#[test]
+fn keeps_symbols_while_the_document_does_not_parse() {
+    let mut client = Client::start(&[]);
+    client.open("def f() -> None\n    pass\nenddef\n");
+    client.diagnostics();
+
+    client.change(2, "def f() -> None\n    pass\nenddef\nif\n");
+    assert_eq!(client.diagnostics().len(), 1);
+
+    let symbols = client.document_request("textDocument/documentSymbol");
+    assert_eq!(symbols[0]["name"], "f");
+
+    client.shutdown();
+}
+
+
+#[test]
+fn folds_blocks() {
+    let mut client = Client::start(&[]);
+    client.open("\
+def f(n) -> int
+    while n > 0 do
+        if n == 3 then
+            return n
+        endif
+        n -= 1
+    done
+    return n
+enddef
+");
+    client.diagnostics();
+
+    let ranges = client.document_request("textDocument/foldingRange");
+    let mut ranges: Vec<_> = ranges
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|range| (range["startLine"].as_u64().unwrap(), range["endLine"].as_u64().unwrap()))
+        .collect();
+    ranges.sort();
+
+    assert_eq!(ranges, [(0, 7), (1, 5), (2, 3)]);
+    client.shutdown();
+}
+
+
+#[test]
+fn folds_matches_and_their_cases() {
+    let mut client = Client::start(&[]);
+    client.open("\
+match n with
+    case 0 then
+        print(\"zero\")
+        print(\"none\")
+    case x if x > 0 then
+        print(\"positive\")
+        print(x)
+    case _ then
+        print(\"negative\")
+endmatch
+");
+    client.diagnostics();
+
+    let ranges = client.document_request("textDocument/foldingRange");
+    let mut ranges: Vec<_> = ranges
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|range| (range["startLine"].as_u64().unwrap(), range["endLine"].as_u64().unwrap()))
+        .collect();
+    ranges.sort();
+
+    assert_eq!(ranges, [(0, 8), (1, 3), (4, 6), (7, 8)]);
+    client.shutdown();
+}
+
+
+#[test]
+fn provides_semantic_tokens() {
+    let mut client = Client::start(&[]);
+    let legend = client.capabilities["semanticTokensProvider"]["legend"]["tokenTypes"].clone();
+
+    client.open("def f() -> int\n    return 1  # one\nenddef\n");
+    client.diagnostics();
+
+    let data: Vec<u64> = client.document_request("textDocument/semanticTokens/full")["data"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|value| value.as_u64().unwrap())
+        .collect();
+
+    let tokens: Vec<_> = data
+        .chunks(5)
+        .map(|token| (token[0], token[1], token[2], legend[token[3] as usize].as_str().unwrap()))
+        .collect();
+
+    assert_eq!(tokens, [
+        (0, 0, 3, "keyword"),   // def
+        (0, 4, 1, "function"),  // f
+        (0, 7, 3, "variable"),  // int
+        (1, 4, 6, "keyword"),   // return
+        (0, 7, 1, "number"),    // 1
+        (0, 3, 5, "comment"),   // # one
+        (1, 0, 6, "keyword")    // enddef
+    ]);
+
+    client.shutdown();
+}
+
+
+#[test]
+fn goes_to_definitions() {
+    let mut client = Client::start(&[]);
+    client.open("\
+def main() -> None
+    p = Point(1)
+    print(helper(p.x))
+enddef
+
+def helper(n: int) -> int
+    return n
+enddef
+
+struct Point
+    x: int
+
+    def get(self) -> int
+        return self.x
+    enddef
+endstruct
+");
+    client.diagnostics();
+
+    // `helper` is used before it is defined.
+    let definition = client.position_request("textDocument/definition", 2, 11, json!({}));
+    assert_eq!(definition["result"]["range"], range(5, 4, 10));
+
+    let definition = client.position_request("textDocument/definition", 13, 21, json!({}));
+    assert_eq!(definition["result"]["range"], range(10, 4, 5));
+
+    // Builtins are not defined anywhere.
+    let definition = client.position_request("textDocument/definition", 2, 5, json!({}));
+    assert_eq!(definition["result"], Value::Null);
+
+    client.shutdown();
+}
+
+
+#[test]
+fn finds_references() {
+    let mut client = Client::start(&[]);
+    client.open("\
+total = 0
+
+def add(n: int) -> None
+    total = n
+    print(total)
+enddef
+
+print(total)
+total += 1
+");
+    client.diagnostics();
+
+    let references = client.position_request("textDocument/references", 7, 6, json!({
+        "context": { "includeDeclaration": false }
+    }));
+    let ranges: Vec<_> = references["result"].as_array().unwrap().iter().map(|location| location["range"].clone()).collect();
+
+    // `total` within `add` is a local variable of its own.
+    assert_eq!(ranges, [range(7, 6, 11), range(8, 0, 5)]);
+    client.shutdown();
+}
+
+
+#[test]
+fn renames_symbols() {
+    let mut client = Client::start(&[]);
+    client.open("\
+def area(w: int, h: int) -> int
+    return w * h
+enddef
+
+print(area(2, h=3))
+");
+    client.diagnostics();
+
+    // Keyword arguments are renamed along with the parameter.
+    let result = client.position_request("textDocument/rename", 0, 17, json!({ "newName": "height" }))["result"].clone();
+    assert_eq!(edits(&result), [
+        ("main.ndr".to_string(), 0, 17, 18),
+        ("main.ndr".to_string(), 1, 15, 16),
+        ("main.ndr".to_string(), 4, 14, 15)
+    ]);
+
+    let result = client.position_request("textDocument/rename", 4, 7, json!({ "newName": "size" }))["result"].clone();
+    assert_eq!(edits(&result), [
+        ("main.ndr".to_string(), 0, 4, 8),
+        ("main.ndr".to_string(), 4, 6, 10)
+    ]);
+
+    client.shutdown();
+}
+
+
+#[test]
+fn refuses_renames_that_change_what_names_refer_to() {
+    let mut client = Client::start(&[]);
+    client.open("\
+limit = 10
+
+def check(n: int) -> bool
+    count = n
+    return count < limit
+enddef
+");
+    client.diagnostics();
+
+    let refuse = |client: &mut Client, line, character, new_name: &str| {
+        let response = client.position_request("textDocument/rename", line, character, json!({ "newName": new_name }));
+        assert_eq!(response["error"]["code"], -32803, "{}", response);
+        response["error"]["message"].as_str().unwrap().to_string()
+    };
+
+    // `count` would shadow `limit` within `check`.
+    let message = refuse(&mut client, 0, 0, "count");
+    assert!(message.contains("shadow"), "{}", message);
+
+    // `limit` within `check` would refer to the renamed `count` instead.
+    let message = refuse(&mut client, 3, 4, "limit");
+    assert!(message.contains("would refer to"), "{}", message);
+
+    refuse(&mut client, 3, 4, "n");
+    refuse(&mut client, 3, 4, "while");
+    client.shutdown();
+}
+
+
+#[test]
+fn renames_across_the_workspace() {
+    let root = workspace("rename", &[
+        ("nadra.toml", "[project]\nname = \"app\"\n"),
+        ("src/main.ndr", "use utils::join\nuse utils\nuse .strings::*\n\nprint(join(utils.join(1)))\nprint(shout(1))\n"),
+        ("src/utils.ndr", "def join(x) -> int\n    return x\nenddef\n"),
+        ("src/strings.ndr", "use .utils::join as j\n\ndef shout(x) -> int\n    return j(x)\nenddef\n")
+    ]);
+
+    let mut client = Client::start_in(&[], Some(&root));
+    client.uri = uri_of(&root.join("src/utils.ndr"));
+    client.open(&fs::read_to_string(root.join("src/utils.ndr")).unwrap());
+    client.diagnostics();
+
+    let result = client.position_request("textDocument/rename", 0, 5, json!({ "newName": "concat" }))["result"].clone();
+    assert_eq!(edits(&result), [
+        ("main.ndr".to_string(), 0, 11, 15),
+        ("main.ndr".to_string(), 4, 6, 10),
+        ("main.ndr".to_string(), 4, 17, 21),
+        ("strings.ndr".to_string(), 0, 12, 16),
+        ("utils.ndr".to_string(), 0, 4, 8)
+    ]);
+
+    // `shout` is imported into `main` with `use .strings::*`.
+    client.uri = uri_of(&root.join("src/strings.ndr"));
+    client.open(&fs::read_to_string(root.join("src/strings.ndr")).unwrap());
+    client.diagnostics();
+
+    let result = client.position_request("textDocument/rename", 2, 4, json!({ "newName": "yell" }));
+    assert_eq!(edits(&result["result"]), [
+        ("main.ndr".to_string(), 5, 6, 11),
+        ("strings.ndr".to_string(), 2, 4, 9)
+    ]);
+
+    // `print` within `main` would then refer to `shout`.
+    let response = client.position_request("textDocument/rename", 2, 4, json!({ "newName": "print" }));
+    assert_eq!(response["error"]["code"], -32803);
+
+    let definition = client.position_request("textDocument/definition", 3, 11, json!({}));
+    assert_eq!(definition["result"]["uri"], uri_of(&root.join("src/utils.ndr")));
+    assert_eq!(definition["result"]["range"], range(0, 4, 8));
+
+    client.uri = uri_of(&root.join("src/main.ndr"));
+    client.open(&fs::read_to_string(root.join("src/main.ndr")).unwrap());
+    client.diagnostics();
+
+    let response = client.position_request("textDocument/rename", 1, 5, json!({ "newName": "helpers" }));
+    assert_eq!(response["error"]["code"], -32803);
+
+    client.shutdown();
+    fs::remove_dir_all(root).unwrap();
+}
+
+
+///
+/// The labels of a list of completion items.
+///
+fn labels(items: &Value) -> Vec<String> {
+    items
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|item| item["label"].as_str().unwrap().to_string())
+        .collect()
+}
+
+
+#[test]
+fn completes_names_in_scope() {
+    let mut client = Client::start(&[]);
+    client.open("\
+counter = 0
+
+def compute(value: int) -> int
+    converted = value
+    return co
+enddef
+
+def convert() -> None
+    pass
+enddef
+");
+    client.diagnostics();
+
+    let items = client.position_request("textDocument/completion", 4, 13, json!({}))["result"].clone();
+    assert_eq!(labels(&items), ["compute", "const", "continue", "convert", "converted", "counter"]);
+
+    let convert = items.as_array().unwrap().iter().find(|item| item["label"] == "convert").unwrap();
+    assert_eq!(convert["kind"], 3);
+    assert_eq!(convert["detail"], "def convert() -> None");
+
+    // Keywords come from the lexer.
+    let items = client.position_request("textDocument/completion", 8, 4, json!({}))["result"].clone();
+    for keyword in ["def", "endstruct", "while", "use"] {
+        assert!(labels(&items).contains(&keyword.to_string()), "{}", keyword);
+    }
+
+    client.shutdown();
+}
+
+
+#[test]
+fn completes_members() {
+    let mut client = Client::start(&[]);
+    let source = "\
+enum Color
+    Red,
+    Green
+endenum
+
+struct Point
+    x: int
+    y: int
+
+    def norm(self) -> int
+        return self.x
+    enddef
+endstruct
+";
+    client.open(source);
+    client.diagnostics();
+
+    // The document does not parse while the member is being typed.
+    client.change(2, &source.replace("self.x", "self.").replace("endstruct\n", "endstruct\nc = Color::\n"));
+    assert_eq!(client.diagnostics().len(), 1);
+
+    let items = client.position_request("textDocument/completion", 10, 20, json!({}))["result"].clone();
+    assert_eq!(labels(&items), ["norm", "x", "y"]);
+
+    let items = client.position_request("textDocument/completion", 13, 11, json!({}))["result"].clone();
+    assert_eq!(labels(&items), ["Green", "Red"]);
+    assert_eq!(items[1]["kind"], 20);
+
+    client.shutdown();
+}
+
+
+#[test]
+fn completes_the_members_of_workspace_modules() {
+    let root = workspace("completion", &[
+        ("main.ndr", "use utils\n\nutils.\n"),
+        ("utils.ndr", "def join(x) -> str\n    return x\nenddef\n\nSEPARATOR = \",\"\n")
+    ]);
+
+    let mut client = Client::start_in(&[], Some(&root));
+    client.uri = uri_of(&root.join("main.ndr"));
+    client.open("use utils\n\nutils.j\n");
+    client.diagnostics();
+
+    let items = client.position_request("textDocument/completion", 2, 7, json!({}))["result"].clone();
+    assert_eq!(labels(&items), ["join"]);
+    assert_eq!(items[0]["detail"], "def join(x) -> str");
+
+    client.shutdown();
+    fs::remove_dir_all(root).unwrap();
+}
+
+
+#[test]
+fn reads_workspace_files_again_once_they_change() {
+    let root = workspace("changes", &[
+        ("main.ndr", "use utils\n\nutils.x\n"),
+        ("utils.ndr", "def join(x) -> str\n    return x\nenddef\n")
+    ]);
+    let utils = root.join("utils.ndr");
+
+    let mut client = Client::start_in(&[], Some(&root));
+    client.uri = uri_of(&root.join("main.ndr"));
+    client.open("use utils\n\nutils.x\n");
+    client.diagnostics();
+
+    // Files are not read again until the client says that they have changed.
+    fs::write(&utils, "def split(x) -> str\n    return x\nenddef\n").unwrap();
+    let items = client.position_request("textDocument/completion", 2, 6, json!({}))["result"].clone();
+    assert_eq!(labels(&items), ["join"]);
+
+    client.notify("workspace/didChangeWatchedFiles", json!({
+        "changes": [{ "uri": uri_of(&utils), "type": 2 }]
+    }));
+    let items = client.position_request("textDocument/completion", 2, 6, json!({}))["result"].clone();
+    assert_eq!(labels(&items), ["split"]);
+
+    // Saving an open file updates it for every other file once it is closed.
+    client.uri = uri_of(&utils);
+    client.open("def split(x) -> str\n    return x\nenddef\n");
+    client.diagnostics();
+    fs::write(&utils, "def strip(x) -> str\n    return x\nenddef\n").unwrap();
+    client.notify("textDocument/didSave", json!({ "textDocument": { "uri": client.uri } }));
+    client.notify("textDocument/didClose", json!({ "textDocument": { "uri": client.uri } }));
+    client.diagnostics();
+
+    client.uri = uri_of(&root.join("main.ndr"));
+    let items = client.position_request("textDocument/completion", 2, 6, json!({}))["result"].clone();
+    assert_eq!(labels(&items), ["strip"]);
+
+    client.shutdown();
+    fs::remove_dir_all(root).unwrap();
+}
+
+
+#[test]
+fn describes_symbols_on_hover() {
+    let mut client = Client::start(&[]);
+    client.open("\
+# Not part of the doc comment.
+
+# The area of a rectangle.
+# Both sides must be positive.
+def area(
+    w: int,
+    h: int = 1
+) -> int
+    return w * h
+enddef
+
+enum Shape
+    Square
+endenum
+
+print(area(2), Shape::Square)
+");
+    client.diagnostics();
+
+    let hover = client.position_request("textDocument/hover", 15, 7, json!({}))["result"].clone();
+    assert_eq!(hover["contents"]["kind"], "markdown");
+    assert_eq!(
+        hover["contents"]["value"],
+        "```nadra\ndef area(w: int, h: int = 1) -> int\n```\n\n---\n\nThe area of a rectangle.\nBoth sides must be positive."
+    );
+    assert_eq!(hover["range"], range(15, 6, 10));
+
+    let hover = client.position_request("textDocument/hover", 15, 24, json!({}))["result"].clone();
+    assert_eq!(hover["contents"]["value"], "```nadra\nShape::Square\n```");
+
+    let hover = client.position_request("textDocument/hover", 8, 11, json!({}))["result"].clone();
+    assert_eq!(hover["contents"]["value"], "```nadra\nw: int\n```");
+
+    let hover = client.position_request("textDocument/hover", 15, 0, json!({}))["result"].clone();
+    assert_eq!(hover, Value::Null);
+
+    client.shutdown();
+}
+
+
+#[test]
+fn describes_inferred_types_on_hover() {
+    let mut client = Client::start(&[]);
+    client.open("\
+use typing::Optional
+def describe(n: Optional[int], flag: bool) -> str
+    count = 0
+    if flag then
+        count = \"many\"
+    endif
+    if n == None then
+        return \"none\"
+    endif
+    return str(n + 1) + str(count)
+enddef
+");
+    client.diagnostics();
+
+    let hover = |client: &mut Client, line, column| {
+        let hover = client.position_request("textDocument/hover", line, column, json!({}))["result"].clone();
+        hover["contents"]["value"].as_str().unwrap().to_string()
+    };
+
+    assert_eq!(hover(&mut client, 2, 4), "```nadra\ncount: int\n```");
+    assert_eq!(hover(&mut client, 9, 31), "```nadra\ncount: int | str\n```");
+
+    // `n` is only `None` along the path which returns early.
+    assert_eq!(hover(&mut client, 6, 7), "```nadra\nn: Optional[int]\n```");
+    assert_eq!(hover(&mut client, 9, 15), "```nadra\nn: int\n```");
+
+    client.shutdown();
+}
+
+
+#[test]
+fn reports_unknown_methods() {
+    let mut client = Client::start(&[]);
+    let response = client.request_raw("textDocument/unknown", json!({}));
+    assert_eq!(response["error"]["code"], -32601);
+    client.shutdown();
+}
+
+
+#[test]
+fn fails_when_exiting_without_shutting_down() {
+    let mut client = Client::start(&[]);
+    client.notify("exit", Value::Null);
+    assert!(!client.child.wait().unwrap().success());
+}
@@ -0,0 +1,226 @@
+//! Drives the REPL over piped stdin. These tests need `python3` to be installed.
+
+use std::{
+    io::Write,
+    process::{Command, Stdio}
+};
+
+
+struct Output {
+    stdout: String,
+    stderr: String
+}
+
+
+fn run_repl(input: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_nadra"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("failed to start nadra");
+
+    child.stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .unwrap();
+
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success(), "the REPL exited with {}", output.status);
+
+    Output {
+        stdout: String::from_utf8(output.stdout).unwrap(),
+        stderr: String::from_utf8(output.stderr).unwrap()
+    }
+}
+
+
+fn lines(text: &str) -> Vec<&str> {
+    text.lines().collect()
+}
+
+
+#[test]
+fn prints_the_values_of_expressions() {
+    let output = run_repl("1 + 2\n\"a\" + \"b\"\n");
+    assert_eq!(lines(&output.stdout), ["3", "'ab'"]);
+}
+
+
+#[test]
+fn keeps_definitions_between_inputs() {
+    let output = run_repl("x = 20\ny = x + 1\ny * 2\n");
+    assert_eq!(lines(&output.stdout), ["42"]);
+}
+
+
+#[test]
+fn continues_open_blocks_onto_the_next_line() {
+    let output = run_repl("def double(x) -> int\n    return x * 2\nenddef\ndouble(4)\n");
+    assert_eq!(lines(&output.stdout), ["8"]);
+    assert_eq!(output.stderr, "");
+}
+
+
+#[test]
+fn runs_while_loops() {
+    let output = run_repl("i = 0\nwhile i < 3 do\n    print(i)\n    i += 1\ndone\ni\n");
+    assert_eq!(lines(&output.stdout), ["0", "1", "2", "3"]);
+    assert_eq!(output.stderr, "");
+}
+
+
+#[test]
+fn continues_unclosed_brackets_and_strings_onto_the_next_line() {
+    let output = run_repl("xs = [1,\n2]\nxs\ns = \"a\nb\"\nlen(s)\n");
+    assert_eq!(lines(&output.stdout), ["[1, 2]", "3"]);
+}
+
+
+#[test]
+fn recovers_from_lexer_errors() {
+    let output = run_repl("x = $\n1 + 1\n");
+    assert!(output.stderr.contains("Unexpected: $"), "stderr: {}", output.stderr);
+    assert_eq!(lines(&output.stdout), ["2"]);
+}
+
+
+#[test]
+fn recovers_from_integer_literals_that_are_too_large() {
+    let output = run_repl("99999999999999999999999\n7\n");
+    assert!(output.stderr.contains("Integer literal is too large"), "stderr: {}", output.stderr);
+    assert_eq!(lines(&output.stdout), ["7"]);
+}
+
+
+#[test]
+fn discards_input_after_a_parse_error() {
+    let output = run_repl("x = )\ny = 5\ny\n");
+    assert!(output.stderr.contains("Unexpected token: )"), "stderr: {}", output.stderr);
+    assert_eq!(lines(&output.stdout), ["5"]);
+}
+
+
+#[test]
+fn handles_non_ascii_input() {
+    let output = run_repl("é = \"ü\"\né * 2\n");
+    assert_eq!(lines(&output.stdout), ["'üü'"]);
+}
+
+
+#[test]
+fn prints_python_exceptions_and_continues() {
+    let output = run_repl("1 / 0\n2\n");
+    assert!(output.stderr.contains("ZeroDivisionError"), "stderr: {}", output.stderr);
+    assert_eq!(lines(&output.stdout), ["2"]);
+}
+
+
+#[test]
+fn restarts_python_after_it_exits() {
+    let output = run_repl("x = 1\nexit()\nx\n3\n");
+    assert!(output.stderr.contains("The Python process exited"), "stderr: {}", output.stderr);
+    assert!(output.stderr.contains("NameError"), "stderr: {}", output.stderr);
+    assert_eq!(lines(&output.stdout), ["3"]);
+}
+
+
+#[test]
+fn reset_forgets_definitions() {
+    let output = run_repl("x = 1\n.reset\nx\n");
+    assert_eq!(lines(&output.stdout), ["Reset the Python process"]);
+    assert!(output.stderr.contains("NameError"), "stderr: {}", output.stderr);
+}
+
+
+#[test]
+fn py_shows_the_generated_code() {
+    let output = run_repl(".py\nprint(1 + 1)\n.py\nprint(3)\n");
+    assert_eq!(lines(&output.stdout), [
+        "Started showing the generated Python code",
+        "print(1 + 1)",
+        "2",
+        "Stopped showing the generated Python code",
+        "3"
+    ]);
+}
+
+
+#[test]
+fn tokens_and_ast_show_each_stage() {
+    let output = run_repl(".tokens\n.ast\n1 + 2\n");
+    let stdout = lines(&output.stdout);
+
+    assert!(stdout.iter().any(|line| line.contains("Integer \"1\"")), "stdout: {:?}", stdout);
+    assert!(stdout.contains(&"(Plus IntegerLiteral(1) IntegerLiteral(2))"), "stdout: {:?}", stdout);
+    assert_eq!(stdout.last(), Some(&"3"));
+}
+
+
+#[test]
+fn reports_unknown_commands() {
+    let output = run_repl(".bogus\n1\n");
+    assert!(output.stderr.contains("Unknown command .bogus"), "stderr: {}", output.stderr);
+    assert_eq!(lines(&output.stdout), ["1"]);
+}
+
+
+#[test]
+fn quit_ends_the_session() {
+    let output = run_repl("1\n.quit\n2\n");
+    assert_eq!(lines(&output.stdout), ["1"]);
+}
+
+
+#[test]
+fn exits_cleanly_with_incomplete_input() {
+    let output = run_repl("def f() -> None\n");
+    assert_eq!(output.stdout, "");
+}
+
+
+#[test]
+fn ends_interfaces_at_endinterface() {
+    let input = "interface Named\n    def name(self) -> str\nendinterface\n\
+        struct Cat implements Named\n    def name(self) -> str\n        return \"cat\"\n    enddef\nendstruct\n\
+        Cat().name()\n";
+    let output = run_repl(input);
+    assert_eq!(lines(&output.stdout), ["'cat'"]);
+    assert_eq!(output.stderr, "");
+}
+
+
+#[test]
+fn constructs_variants_of_enums_declared_in_earlier_inputs() {
+    let input = "enum Shape\n    Circle(radius: int),\n    Square(side: int)\nendenum\n\
+        Shape::Circle(2)\nShape::Square(side=3).side\n";
+    let output = run_repl(input);
+    assert_eq!(lines(&output.stdout), ["Shape_Circle(radius=2)", "3"]);
+    assert_eq!(output.stderr, "");
+}
+
+
+#[test]
+fn matches_the_constants_true_false_and_none() {
+    let input = "def describe(flag) -> str\n    match flag with\n        case True then\n            return \"yes\"\n\
+        case False then\n            return \"no\"\n        case None then\n            return \"unknown\"\n\
+        case other then\n            return str(other)\n    endmatch\nenddef\n\
+        print(describe(True), describe(False), describe(None), describe(3))\n";
+    let output = run_repl(input);
+    assert_eq!(lines(&output.stdout), ["yes no unknown 3"]);
+    assert_eq!(output.stderr, "");
+}
+
+
+#[test]
+fn matches_on_the_variants_of_enums() {
+    let input = "enum Shape\n    Circle(radius: int),\n    Square(side: int)\nendenum\n\
+        def area(shape: Shape) -> int\n    match shape with\n        case Shape::Circle(r) if r > 10 then\n            return -1\n\
+        case Shape::Circle(r) then\n            return 3 * r * r\n        case Shape::Square(side=s) then\n            return s * s\n\
+        endmatch\nenddef\n\
+        area(Shape::Circle(2))\narea(Shape::Circle(20))\narea(Shape::Square(3))\n";
+    let output = run_repl(input);
+    assert_eq!(lines(&output.stdout), ["12", "-1", "9"]);
+    assert_eq!(output.stderr, "");
+}
@@ -0,0 +1,239 @@
+//! Transpiles single `.ndr` files with `nadra <file>` and checks the generated Python.
+
+use std::{
+    fs,
+    path::PathBuf,
+    process::Command
+};
+
+
+struct Output {
+    success: bool,
+    stdout: String,
+    stderr: String
+}
+
+
+///
+/// Transpile `source`, written to a fresh file whose name is based on `name`.
+///
+fn transpile(name: &str, source: &str) -> Output {
+    let path = source_file(name);
+    fs::write(&path, source).unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_nadra"))
+        .arg(&path)
+        .output()
+        .expect("failed to start nadra");
+
+    Output {
+        success: output.status.success(),
+        stdout: String::from_utf8(output.stdout).unwrap(),
+        stderr: String::from_utf8(output.stderr).unwrap()
+    }
+}
+
+
+fn source_file(name: &str) -> PathBuf {
+    let directory = std::env::temp_dir().join(format!("nadra-transpile-{}", std::process::id()));
+    fs::create_dir_all(&directory).unwrap();
+    directory.join(format!("{}.ndr", name))
+}
+
+
+///
+/// The Python code generated for `source`, which must transpile.
+///
+fn python(name: &str, source: &str) -> String {
+    let output = transpile(name, source);
+    assert!(output.success, "transpiling failed: {}", output.stderr);
+    output.stdout
+}
+
+
+///
+/// The error reported for `source`, which must fail to transpile.
+///
+fn error(name: &str, source: &str) -> String {
+    let output = transpile(name, source);
+    assert!(!output.success, "transpiling succeeded: {}", output.stdout);
+    output.stderr
+}
+
+
+#[test]
+fn chains_calls_attributes_and_subscripts_on_any_expression() {
+    let source = "x = obj.method(x)(y)[0].name\ny = (a + b).real\nz = (-a).b\nw = f(1)[2](3)\n";
+    assert_eq!(
+        python("postfix_chains", source),
+        "x = obj.method(x)(y)[0].name\ny = (a + b).real\nz = (-a).b\nw = f(1)[2](3)\n"
+    );
+}
+
+
+#[test]
+fn starts_a_new_statement_with_brackets_on_the_next_line() {
+    let source = "w = f\n(1, 2)\nv = xs\n[0]\nu = g(\n    1\n)[\n    0\n]\n";
+    assert_eq!(python("next_line_brackets", source), "w = f\n(1, 2)\nv = xs\n[0]\nu = g(1)[0]\n");
+}
+
+
+#[test]
+fn transpiles_collection_literals() {
+    let source = "xs = [1, 2, 3]\nt = (1, \"a\")\none = (1,)\nnone = ()\nd = {\"a\": 1, \"b\": 2}\ns = {1, 2}\nempty = {}\n";
+    assert_eq!(
+        python("collections", source),
+        "xs = [1, 2, 3]\nt = (1, \"a\")\none = (1,)\nnone = ()\nd = {\"a\": 1, \"b\": 2}\ns = {1, 2}\nempty = {}\n"
+    );
+}
+
+
+#[test]
+fn accepts_trailing_commas_and_nesting_in_collection_literals() {
+    let source = "xs = [1, 2, 3,]\nd = {\"a\": 1,}\ns = {1,}\nnested = [\n    [1, 2],\n    {\"k\": (3, 4)},\n]\n";
+    assert_eq!(
+        python("trailing_commas", source),
+        "xs = [1, 2, 3]\nd = {\"a\": 1}\ns = {1}\nnested = [[1, 2], {\"k\": (3, 4)}]\n"
+    );
+}
+
+
+#[test]
+fn unpacks_within_collection_literals() {
+    let source = "xs = [1]\nd = {}\nys = [0, *xs]\nt = (*xs, 5)\ns = {*xs, 4}\nm = {**d, \"c\": 3}\n";
+    assert_eq!(
+        python("unpacking", source),
+        "xs = [1]\nd = {}\nys = [0, *xs]\nt = (*xs, 5)\ns = {*xs, 4}\nm = {**d, \"c\": 3}\n"
+    );
+}
+
+
+#[test]
+fn rejects_misplaced_unpacking_and_unclosed_literals() {
+    assert!(error("double_star_in_list", "d = {}\nxs = [**d]\n").contains("Unexpected token: **"));
+    assert!(error("mixed_dict_and_set", "d = {1: 2, 3}\n").contains("Expected Colon"));
+    assert!(error("unclosed_list", "xs = [1, 2\n").contains("end of token stream"));
+}
+
+
+#[test]
+fn transpiles_subscripts_and_slices() {
+    let source = "xs = [1]\nd = {}\na = xs[0]\nb = d[\"key\"]\nc = xs[1:-1:2]\ne = xs[:]\nf = xs[::2]\n\
+        g = xs[a::2]\nm = xs[0][1]\nn = d[1, 2]\n";
+    assert_eq!(
+        python("slices", source),
+        "xs = [1]\nd = {}\na = xs[0]\nb = d[\"key\"]\nc = xs[1:-1:2]\ne = xs[:]\nf = xs[::2]\n\
+        g = xs[a::2]\nm = xs[0][1]\nn = d[1, 2]\n"
+    );
+}
+
+
+#[test]
+fn accepts_ranges_as_slices() {
+    let source = "xs = [1]\na = xs[1..3]\nb = xs[..3]\nc = xs[1..]\n";
+    assert_eq!(python("range_slices", source), "xs = [1]\na = xs[1:3]\nb = xs[:3]\nc = xs[1:]\n");
+
+    assert!(error("range_slice_with_step", "xs = [1]\ny = xs[1..2:3]\n").contains("Expected RightBracket"));
+    assert!(error("chained_range_slice", "xs = [1]\ny = xs[1..2..3]\n").contains("Expected RightBracket"));
+}
+
+
+#[test]
+fn assigns_to_subscripts_and_slices() {
+    let source = "xs = [1]\nd = {}\nxs[0] = 5\nxs[1:2] = [3]\nxs[1..3] = [2]\nd[\"k\"] += 1\n";
+    assert_eq!(
+        python("subscript_targets", source),
+        "xs = [1]\nd = {}\nxs[0] = 5\nxs[1:2] = [3]\nxs[1:3] = [2]\nd[\"k\"] += 1\n"
+    );
+
+    assert!(error("call_target", "xs = [1]\n# length\nlen( xs ) = 1\n").contains("Cannot assign to 'len( xs )'"));
+    assert!(error("empty_subscript", "xs = [1]\ny = xs[]\n").contains("Unexpected token: ]"));
+}
+
+
+#[test]
+fn transpiles_parameters_and_keyword_arguments() {
+    let source = "def f(a, b=1, *args, c, d=2, **kw) -> None\n    print(a, b, args, c, d, kw)\nenddef\n\
+        def g(a: int, /, b: str = \"x\", *, c: int = 0) -> None\n    print(a)\nenddef\n\
+        f(1, 2, 3, c=4, e=5)\nprint(1, end=\"\")\ng(*[1], **{})\n";
+    assert_eq!(
+        python("parameters", source),
+        "def f(a, b=1, *args, c, d=2, **kw) -> None:\n\tprint(a, b, args, c, d, kw)\n\n\
+        def g(a: int, /, b: str = \"x\", *, c: int = 0) -> None:\n\tprint(a)\n\n\
+        f(1, 2, 3, c=4, e=5)\nprint(1, end=\"\")\ng(*[1], **{})\n"
+    );
+}
+
+
+#[test]
+fn rejects_parameters_in_the_wrong_order() {
+    let cases = [
+        ("def f(a=1, b) -> None", "Parameter 'b' without a default follows a parameter with a default"),
+        ("def f(**kw, a) -> None", "'**kw' must be the last parameter"),
+        ("def f(*a, *b) -> None", "'*' may only appear once"),
+        ("def f(a, a) -> None", "Duplicate parameter 'a'"),
+        ("def f(/, a) -> None", "'/' must follow at least one parameter"),
+        ("def f(a, *, /) -> None", "Named parameters must follow a bare '*'"),
+        ("def f(*) -> None", "Named parameters must follow a bare '*'")
+    ];
+
+    for (i, (header, message)) in cases.iter().enumerate() {
+        let source = format!("{}\n    print(1)\nenddef\n", header);
+        let error = error(&format!("parameter_order_{}", i), &source);
+        assert!(error.contains(message), "{}: {}", header, error);
+    }
+}
+
+
+#[test]
+fn rejects_arguments_in_the_wrong_order() {
+    assert!(error("positional_after_keyword", "print(end=\"\", 1 + 2)\n").contains("Positional argument '1 + 2' follows a keyword argument"));
+    assert!(error("repeated_keyword", "print(x=1, x=2)\n").contains("Keyword argument 'x' is repeated"));
+}
+
+
+#[test]
+fn rejects_invalid_type_parameters() {
+    let cases = [
+        ("def f[T, T](a: T) -> T", "Duplicate type parameter 'T'"),
+        ("def f[](a: int) -> int", "Expected at least one type parameter"),
+        ("def f[T: (int,)](a: T) -> T", "Type parameter 'T' needs at least two constraints")
+    ];
+
+    for (i, (header, message)) in cases.iter().enumerate() {
+        let source = format!("{}\n    return a\nenddef\n", header);
+        let error = error(&format!("type_parameters_{}", i), &source);
+        assert!(error.contains(message), "{}: {}", header, error);
+    }
+}
+
+
+#[test]
+fn transpiles_each_form_of_use_statement() {
+    let cases = [
+        ("use os", "import os"),
+        ("use os.path", "import os.path"),
+        ("use numpy as np", "import numpy as np"),
+        ("use collections::OrderedDict", "from collections import OrderedDict"),
+        ("use collections::OrderedDict as OD", "from collections import OrderedDict as OD"),
+        ("use typing::{List, Dict as D}", "from typing import List, Dict as D"),
+        ("use typing::{\n    Any,\n    Optional,\n}", "from typing import Any, Optional"),
+        ("use os::*", "from os import *"),
+        ("use .utils::*", "from .utils import *"),
+        ("use ..pkg.module::thing", "from ..pkg.module import thing"),
+        ("use .sibling", "from . import sibling")
+    ];
+
+    for (i, (statement, import)) in cases.iter().enumerate() {
+        let python = python(&format!("use_{}", i), &format!("{}\n", statement));
+        assert_eq!(python, format!("{}\n", import), "{}", statement);
+    }
+}
+
+
+#[test]
+fn rejects_malformed_use_statements() {
+    assert!(error("empty_group", "use a::{}\n").contains("Expected at least one item"));
+    assert!(error("aliased_wildcard", "use a::* as b\n").contains("Unexpected token: as"));
+    assert!(error("bare_relative", "use .\n").contains("Expected a module name after '.'"));
+}
@@ -0,0 +1,142 @@
+//! Runs `nadra watch` on a project while changing its files.
+
+use std::{
+    fs,
+    io::{BufRead, BufReader, Read},
+    path::{Path, PathBuf},
+    process::{Child, Command, Stdio},
+    sync::mpsc::{self, Receiver},
+    thread,
+    time::{Duration, Instant}
+};
+
+
+///
+/// A running `nadra watch`, which is stopped once dropped.
+///
+struct Watcher {
+    child: Child,
+    lines: Receiver<String>
+}
+
+
+impl Watcher {
+    fn start(root: &Path) -> Self {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_nadra"))
+            .arg("watch")
+            .current_dir(root)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .expect("failed to start nadra");
+
+        // Build errors are printed to stderr, everything else to stdout.
+        let (sender, lines) = mpsc::channel();
+        let stdout: Box<dyn Read + Send> = Box::new(child.stdout.take().unwrap());
+        let stderr: Box<dyn Read + Send> = Box::new(child.stderr.take().unwrap());
+        for output in [stdout, stderr] {
+            let sender = sender.clone();
+            thread::spawn(move || {
+                for line in BufReader::new(output).lines().map_while(Result::ok) {
+                    if sender.send(line).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+
+        Self { child: child, lines: lines }
+    }
+
+
+    ///
+    /// Wait for the next build to finish, returning what it printed.
+    ///
+    fn next_build(&self) -> Vec<String> {
+        let mut printed = vec![];
+
+        loop {
+            let line = self.lines
+                .recv_timeout(Duration::from_secs(10))
+                .unwrap_or_else(|_| panic!("nadra watch stopped building after: {:?}", printed));
+
+            let finished = line.starts_with("[watch] Build");
+            printed.push(line);
+            if finished {
+                return printed;
+            }
+        }
+    }
+
+
+    fn is_running(&mut self) -> bool {
+        self.child.try_wait().unwrap().is_none()
+    }
+}
+
+
+impl Drop for Watcher {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+
+fn project(name: &str, files: &[(&str, &str)]) -> PathBuf {
+    let root = std::env::temp_dir().join(format!("nadra-watch-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&root);
+    fs::create_dir_all(&root).unwrap();
+
+    for (path, text) in files {
+        fs::write(root.join(path), text).unwrap();
+    }
+
+    root.canonicalize().unwrap()
+}
+
+
+#[test]
+fn rebuilds_when_files_change() {
+    let root = project("changes", &[
+        ("nadra.toml", "[project]\nname = \"app\"\nsource = \".\"\n"),
+        ("main.ndr", "print(1)\n")
+    ]);
+
+    let mut watcher = Watcher::start(&root);
+    assert!(watcher.next_build().contains(&"[watch] Rebuilt main".to_string()));
+
+    fs::write(root.join("main.ndr"), "print(1 + 1)\n").unwrap();
+    assert!(watcher.next_build().contains(&"[watch] Rebuilt main".to_string()));
+
+    fs::write(root.join("main.ndr"), "print(\n").unwrap();
+    assert!(watcher.next_build().last().unwrap().starts_with("[watch] Build failed"));
+    assert!(watcher.is_running());
+}
+
+
+#[test]
+fn keeps_watching_when_directories_vanish() {
+    let root = project("vanishing", &[
+        ("nadra.toml", "[project]\nname = \"app\"\nsource = \".\"\n"),
+        ("main.ndr", "print(1)\n")
+    ]);
+
+    let mut watcher = Watcher::start(&root);
+    watcher.next_build();
+
+    // Remove a large tree of directories over and over, so some of them vanish while the
+    // watcher is reading them.
+    let scratch = root.join("scratch");
+    let start = Instant::now();
+    while start.elapsed() < Duration::from_secs(4) {
+        for i in 0..2000 {
+            fs::create_dir_all(scratch.join(format!("{}/{}", i % 50, i))).unwrap();
+        }
+        fs::remove_dir_all(&scratch).unwrap();
+    }
+
+    fs::write(root.join("main.ndr"), "print(3)\n").unwrap();
+    while !watcher.next_build().contains(&"[watch] Rebuilt main".to_string()) {}
+    assert!(watcher.is_running());
+}